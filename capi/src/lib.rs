@@ -0,0 +1,5 @@
+/*!
+ C shared library (`liblastlog`) exporting the `ffi` module of the lastlog
+ crate, built separately so the crate itself stays usable without `std`.
+*/
+pub use lastlog_core::ffi::*;
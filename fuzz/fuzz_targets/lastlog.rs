@@ -0,0 +1,18 @@
+/*
+ *  Fuzz the lastlog readers with arbitrary files
+ */
+#![no_main]
+
+use lastlog::{MemorySource, SourceReader};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let mut source = MemorySource::new();
+    source.insert("passwd", "root:x:0:0::/root:/bin/sh\nfoo:x:1000:1000::/:/bin/sh\n");
+    source.insert("lastlog", data);
+    let Ok(reader) = SourceReader::new(source).with_passwd("passwd") else {
+        return;
+    };
+    let _ = reader.iter_accounts("lastlog");
+    let _ = reader.logged_in("lastlog");
+});
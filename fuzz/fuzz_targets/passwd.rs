@@ -0,0 +1,14 @@
+/*
+ *  Fuzz the passwd parser used to resolve usernames and user-ids
+ */
+#![no_main]
+
+use lastlog::UserMap;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let users = UserMap::from_passwd(data);
+    for (name, uid) in users.names() {
+        let _ = (users.uid(name), users.name(*uid));
+    }
+});
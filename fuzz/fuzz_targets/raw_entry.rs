@@ -0,0 +1,19 @@
+/*
+ *  Fuzz raw entry decoding, checking encoded entries always decode again
+ */
+#![no_main]
+
+use lastlog::raw::{LastlogEntry, UtmpEntry};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: (UtmpEntry, LastlogEntry)| {
+    let (utmp, lastlog) = input;
+    let bytes = utmp.encode();
+    if let Ok(decoded) = UtmpEntry::decode(&bytes) {
+        assert_eq!(decoded.encode(), bytes);
+    }
+    let bytes = lastlog.encode();
+    if let Ok(decoded) = LastlogEntry::decode(&bytes) {
+        assert_eq!(decoded.encode(), bytes);
+    }
+});
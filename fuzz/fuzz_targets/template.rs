@@ -0,0 +1,14 @@
+/*
+ *  Fuzz template parsing and rendering of arbitrary records
+ */
+#![no_main]
+
+use lastlog::{Record, Template};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: (&str, Record)| {
+    let (template, record) = input;
+    if let Ok(template) = Template::new(template) {
+        let _ = template.render(&record);
+    }
+});
@@ -0,0 +1,25 @@
+/*
+ *  Fuzz the utmp/wtmp/btmp readers and session pairing with arbitrary files
+ */
+#![no_main]
+
+use std::collections::HashMap;
+use std::io::Cursor;
+
+use lastlog::{MemorySource, Sessions, SourceReader, UtmpReader, UtmpRevReader};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let users = HashMap::from([("root".to_owned(), 0)]);
+    for record in UtmpReader::with_users(Cursor::new(data), users.clone()) {
+        let _ = record.map(|r| r.to_string());
+    }
+    if let Ok(reader) = UtmpRevReader::with_users(Cursor::new(data), users) {
+        for session in Sessions::new(reader.filter_map(|r| r.ok())) {
+            let _ = session.duration();
+        }
+    }
+    let mut source = MemorySource::new();
+    source.insert("wtmp", data);
+    let _ = SourceReader::new(source).search_username("root", "wtmp");
+});
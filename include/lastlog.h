@@ -0,0 +1,143 @@
+/* C bindings of the lastlog crate, built with `--features ffi` */
+
+#ifndef LASTLOG_H
+#define LASTLOG_H
+
+/* Generated by cbindgen, do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Opaque login record owned by the caller
+//
+// Must be released with `lastlog_record_free`, except when borrowed
+// from a `LastlogRecordList`.
+typedef struct LastlogRecord LastlogRecord;
+
+// Opaque list of login records owned by the caller
+//
+// Must be released with `lastlog_list_free`.
+typedef struct LastlogRecordList LastlogRecordList;
+
+// Find the latest login of the given user-id with an auto-selected database
+//
+// Returns `0` and stores a new record in `out` on success, otherwise an
+// errno value such as `ENOENT` (`EINVAL` for parsing failures or unknown
+// users, `EIO` for internal errors) and stores `NULL`.
+//
+// # Safety
+//
+// `out` must be a valid pointer to writable memory.
+int lastlog_search_uid(uint32_t uid, struct LastlogRecord **out);
+
+// Find the latest login of the given username with an auto-selected database
+//
+// Returns a status like `lastlog_search_uid`.
+//
+// # Safety
+//
+// `username` must be a valid nul terminated string and `out` a valid
+// pointer to writable memory.
+int lastlog_search_username(const char *username, struct LastlogRecord **out);
+
+// Collect the latest login of every user with an auto-selected database
+//
+// Returns `0` and stores a new list in `out` on success, otherwise an
+// errno value and stores `NULL`.
+//
+// # Safety
+//
+// `out` must be a valid pointer to writable memory.
+int lastlog_iter_accounts(struct LastlogRecordList **out);
+
+// Retrieve the number of records within the list
+//
+// # Safety
+//
+// `list` must be a list returned by this library or `NULL`.
+size_t lastlog_list_len(const struct LastlogRecordList *list);
+
+// Borrow the record at the given index, `NULL` when out of bounds
+//
+// The record remains owned by the list and must not be freed.
+//
+// # Safety
+//
+// `list` must be a list returned by this library or `NULL`.
+const struct LastlogRecord *lastlog_list_get(const struct LastlogRecordList *list, size_t index);
+
+// Release a list and every record it holds
+//
+// # Safety
+//
+// `list` must be a list returned by this library or `NULL`, and is
+// invalid afterwards.
+void lastlog_list_free(struct LastlogRecordList *list);
+
+// Release a record returned by one of the search functions
+//
+// # Safety
+//
+// `record` must be an owned record returned by this library or `NULL`,
+// and is invalid afterwards.
+void lastlog_record_free(struct LastlogRecord *record);
+
+// Retrieve the utmp(5) record-type (`ut_type`) of the record
+//
+// # Safety
+//
+// `record` must be a valid record returned by this library.
+int lastlog_record_type(const struct LastlogRecord *record);
+
+// Retrieve the user-id of the record, `-1` when unknown
+//
+// # Safety
+//
+// `record` must be a valid record returned by this library.
+int64_t lastlog_record_uid(const struct LastlogRecord *record);
+
+// Retrieve the process-id of the record, `-1` when unknown
+//
+// # Safety
+//
+// `record` must be a valid record returned by this library.
+int32_t lastlog_record_pid(const struct LastlogRecord *record);
+
+// Retrieve the login-time as a unix timestamp, `0` if never logged in
+//
+// # Safety
+//
+// `record` must be a valid record returned by this library.
+int64_t lastlog_record_time(const struct LastlogRecord *record);
+
+// Borrow the username of the record
+//
+// The string remains owned by the record.
+//
+// # Safety
+//
+// `record` must be a valid record returned by this library.
+const char *lastlog_record_name(const struct LastlogRecord *record);
+
+// Borrow the tty of the record
+//
+// The string remains owned by the record.
+//
+// # Safety
+//
+// `record` must be a valid record returned by this library.
+const char *lastlog_record_tty(const struct LastlogRecord *record);
+
+// Borrow the remote host of the record
+//
+// The string remains owned by the record.
+//
+// # Safety
+//
+// `record` must be a valid record returned by this library.
+const char *lastlog_record_host(const struct LastlogRecord *record);
+
+#endif  /* LASTLOG_H */
@@ -0,0 +1,218 @@
+/*
+ *  Combined per-account reports joining passwd, shadow, lastlog and btmp
+ */
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Result};
+use std::{panic, thread};
+
+use super::common::*;
+use super::utmp::UtmpReader;
+use super::{detect_module, get_module};
+
+/* Variables */
+
+static SHADOW: &str = "/etc/shadow";
+static BTMP: &str = "/var/log/btmp";
+
+/* Types */
+
+/// Locations of the databases joined into an account report
+///
+/// Missing or unreadable optional databases (shadow, btmp) are skipped
+/// and leave their related fields empty instead of failing the report.
+#[derive(Debug, Clone)]
+pub struct AccountSources {
+    /// passwd(5) formatted account list
+    pub passwd: String,
+    /// shadow(5) formatted password database used for the locked status
+    pub shadow: String,
+    /// login database, auto-selected like [`get_module`] when `None`
+    pub lastlog: Option<String>,
+    /// btmp database of failed logins
+    pub btmp: String,
+}
+
+impl Default for AccountSources {
+    fn default() -> Self {
+        Self {
+            passwd: PASSWD.to_owned(),
+            shadow: SHADOW.to_owned(),
+            lastlog: None,
+            btmp: BTMP.to_owned(),
+        }
+    }
+}
+
+/// Single user account combined from every supported database
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Account {
+    pub uid: u32,
+    pub gid: u32,
+    pub name: String,
+    pub gecos: String,
+    pub home: String,
+    pub shell: String,
+    /// most recent successful login
+    pub last_login: LoginTime,
+    /// most recent failed login attempt recorded in btmp
+    pub last_failed: LoginTime,
+    /// number of failed login attempts recorded in btmp
+    pub failed_count: usize,
+    /// whether the password is locked, `None` when shadow is unreadable
+    pub locked: Option<bool>,
+}
+
+/* Functions */
+
+// parse passwd entries into accounts without any login information
+pub fn read_accounts(fname: &str) -> Result<Vec<Account>> {
+    read_accounts_from(File::open(fname)?)
+}
+
+// parse passwd entries from any reader into accounts without login information
+pub fn read_accounts_from<R: Read>(reader: R) -> Result<Vec<Account>> {
+    let mut accounts = vec![];
+    for line in BufReader::new(reader).lines() {
+        let line = line?;
+        let fields: Vec<&str> = line.splitn(7, ':').collect();
+        if fields.len() < 7 {
+            continue;
+        }
+        let (Ok(uid), Ok(gid)) = (fields[2].parse(), fields[3].parse()) else {
+            continue;
+        };
+        accounts.push(Account {
+            uid,
+            gid,
+            name: fields[0].to_owned(),
+            gecos: fields[4].to_owned(),
+            home: fields[5].to_owned(),
+            shell: fields[6].to_owned(),
+            last_login: LoginTime::Never,
+            last_failed: LoginTime::Never,
+            failed_count: 0,
+            locked: None,
+        });
+    }
+    Ok(accounts)
+}
+
+// parse the locked status of every account listed within shadow
+fn read_locked(fname: &str) -> Result<HashMap<String, bool>> {
+    let f = File::open(fname)?;
+    let mut locked = HashMap::new();
+    for line in BufReader::new(f).lines() {
+        let line = line?;
+        let mut fields = line.splitn(3, ':');
+        let (Some(name), Some(password)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        locked.insert(name.to_owned(), password.starts_with('!'));
+    }
+    Ok(locked)
+}
+
+// collect the latest failure time and failure count for each username
+fn read_failed(fname: &str) -> Result<HashMap<String, (LoginTime, usize)>> {
+    let mut failed: HashMap<String, (LoginTime, usize)> = HashMap::new();
+    for record in UtmpReader::open(fname)?.filter_map(|r| r.ok()) {
+        if record.name.is_empty() {
+            continue;
+        }
+        let entry = failed.entry(record.name).or_insert((LoginTime::Never, 0));
+        entry.0 = entry.0.clone().max(record.last_login);
+        entry.1 += 1;
+    }
+    Ok(failed)
+}
+
+/// Build a combined report of every account using the given databases
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{accounts_from, AccountSources};
+///
+/// let sources = AccountSources {
+///     btmp: "/var/log/btmp.1".to_owned(),
+///     ..Default::default()
+/// };
+/// if let Ok(accounts) = accounts_from(&sources) {
+///     for account in accounts {
+///         println!("{} {:?}", account.name, account.locked);
+///     }
+/// }
+/// ```
+pub fn accounts_from(sources: &AccountSources) -> Result<Vec<Account>> {
+    let (module, path) = match &sources.lastlog {
+        Some(path) => (detect_module(path)?, path.to_owned()),
+        None => get_module()?,
+    };
+    // the passwd file is parsed once for both the report and the login lookup
+    let accounts = read_accounts(&sources.passwd)?;
+    let users = UserMap::from_names(accounts.iter().map(|a| (a.name.clone(), a.uid)).collect());
+    join_accounts(sources, accounts, || {
+        module.iter_accounts_with_users(&path, &users)
+    })
+}
+
+// join the passwd, shadow and btmp sources with the latest login records,
+// reading shadow and btmp on a separate thread while the logins are read
+pub fn join_accounts<F>(
+    sources: &AccountSources,
+    mut accounts: Vec<Account>,
+    logins: F,
+) -> Result<Vec<Account>>
+where
+    F: FnOnce() -> Result<Vec<Record>>,
+{
+    let (logins, (locked, failed)) = thread::scope(|s| {
+        let optional = s.spawn(|| {
+            let locked = read_locked(&sources.shadow).ok();
+            let failed = read_failed(&sources.btmp).unwrap_or_default();
+            (locked, failed)
+        });
+        let logins = logins();
+        let optional = optional.join().unwrap_or_else(|e| panic::resume_unwind(e));
+        (logins, optional)
+    });
+    let logins: HashMap<String, LoginTime> = logins?
+        .into_iter()
+        .map(|r| (r.name, r.last_login))
+        .collect();
+    for account in accounts.iter_mut() {
+        if let Some(last) = logins.get(&account.name) {
+            account.last_login = last.clone();
+        }
+        if let Some((last, count)) = failed.get(&account.name) {
+            account.last_failed = last.clone();
+            account.failed_count = *count;
+        }
+        account.locked = locked.as_ref().map(|l| l.get(&account.name) == Some(&true));
+    }
+    Ok(accounts)
+}
+
+/// Build a combined report of every account using the system databases
+///
+/// Similar to `lslogins(1)`, joining `/etc/passwd`, `/etc/shadow`, the
+/// auto-selected login database and `/var/log/btmp`.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// if let Ok(accounts) = lastlog::accounts() {
+///     for account in accounts.iter().filter(|a| a.failed_count > 0) {
+///         println!("{}: {} failed logins", account.name, account.failed_count);
+///     }
+/// }
+/// ```
+pub fn accounts() -> Result<Vec<Account>> {
+    accounts_from(&AccountSources::default())
+}
@@ -0,0 +1,127 @@
+/*
+ *  Record layouts of historical IRIX and HP-UX wtmp databases
+ */
+use super::schema::{Endian, FieldKind, RecordSchema};
+
+/* Implementation */
+
+/// Historical layouts for forensic images of legacy unix systems
+///
+/// Both platforms ran on big-endian hardware (MIPS and PA-RISC/Itanium)
+/// and use the System V record-types, which swap the meaning of `3` and
+/// `4` (`OLD_TIME` and `NEW_TIME`) compared to Linux. Their `time_t` is
+/// 32 bits wide, so none of these layouts can store logins past 2038.
+impl RecordSchema {
+    /// `/var/adm/wtmp` of IRIX, 36 byte System V `struct utmp` entries
+    ///
+    /// | Offset | Size | Field     |
+    /// | ------ | ---- | --------- |
+    /// | 0      | 8    | `ut_user` |
+    /// | 8      | 4    | `ut_id`   |
+    /// | 12     | 12   | `ut_line` |
+    /// | 24     | 2    | `ut_pid`  |
+    /// | 26     | 2    | `ut_type` |
+    /// | 28     | 4    | `ut_exit` |
+    /// | 32     | 4    | `ut_time` |
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use lastlog::RecordSchema;
+    ///
+    /// if let Ok(reader) = RecordSchema::irix_utmp().open("/mnt/irix/var/adm/wtmp") {
+    ///     let logins: Vec<_> = reader.filter_map(|r| r.ok()).collect();
+    /// }
+    /// ```
+    pub fn irix_utmp() -> Self {
+        Self::new("IRIX utmp", 36)
+            .endian(Endian::Big)
+            .field(FieldKind::User, 0, 8)
+            .field(FieldKind::Line, 12, 12)
+            .field(FieldKind::Pid, 24, 2)
+            .field(FieldKind::Type, 26, 2)
+            .field(FieldKind::Time, 32, 4)
+    }
+
+    /// `/var/adm/wtmpx` of IRIX, 372 byte System V `struct utmpx` entries
+    ///
+    /// | Offset | Size | Field                 |
+    /// | ------ | ---- | --------------------- |
+    /// | 0      | 32   | `ut_user`             |
+    /// | 32     | 4    | `ut_id`               |
+    /// | 36     | 32   | `ut_line`             |
+    /// | 68     | 4    | `ut_pid`              |
+    /// | 72     | 2    | `ut_type`             |
+    /// | 74     | 4    | `ut_exit`             |
+    /// | 80     | 8    | `ut_tv`               |
+    /// | 88     | 4    | `ut_session`          |
+    /// | 92     | 20   | padding               |
+    /// | 112    | 2    | `ut_syslen`           |
+    /// | 114    | 257  | `ut_host`             |
+    ///
+    /// The same layout is written by other System V derivatives, such as
+    /// 32-bit Solaris.
+    pub fn irix_utmpx() -> Self {
+        Self::new("IRIX utmpx", 372)
+            .endian(Endian::Big)
+            .field(FieldKind::User, 0, 32)
+            .field(FieldKind::Line, 36, 32)
+            .field(FieldKind::Pid, 68, 4)
+            .field(FieldKind::Type, 72, 2)
+            .field(FieldKind::Time, 80, 4)
+            .field(FieldKind::Usec, 84, 4)
+            .field(FieldKind::Host, 114, 257)
+    }
+
+    /// `/var/adm/wtmp` of HP-UX 10 and 11, 60 byte `struct utmp` entries
+    ///
+    /// | Offset | Size | Field          |
+    /// | ------ | ---- | -------------- |
+    /// | 0      | 8    | `ut_user`      |
+    /// | 8      | 4    | `ut_id`        |
+    /// | 12     | 12   | `ut_line`      |
+    /// | 24     | 4    | `ut_pid`       |
+    /// | 28     | 2    | `ut_type`      |
+    /// | 30     | 4    | `ut_exit`      |
+    /// | 34     | 2    | `ut_reserved1` |
+    /// | 36     | 4    | `ut_time`      |
+    /// | 40     | 16   | `ut_host`      |
+    /// | 56     | 4    | `ut_addr`      |
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use lastlog::{RecordSchema, RecordType, UserMap};
+    ///
+    /// let mut entry = [0u8; 60];
+    /// entry[..4].copy_from_slice(b"root");
+    /// entry[12..17].copy_from_slice(b"pty/0");
+    /// entry[29] = 7;
+    /// entry[36..40].copy_from_slice(&852076800u32.to_be_bytes());
+    /// entry[40..48].copy_from_slice(b"hp-term1");
+    ///
+    /// let record = RecordSchema::hpux_utmp().decode(&entry, 0, &UserMap::default()).unwrap();
+    /// assert_eq!(record.rtype, RecordType::User);
+    /// assert_eq!((record.name.as_str(), record.host.as_str()), ("root", "hp-term1"));
+    /// assert_eq!(record.last_login.unix_seconds(), Some(852076800));
+    /// ```
+    pub fn hpux_utmp() -> Self {
+        Self::new("HP-UX utmp", 60)
+            .endian(Endian::Big)
+            .field(FieldKind::User, 0, 8)
+            .field(FieldKind::Line, 12, 12)
+            .field(FieldKind::Pid, 24, 4)
+            .field(FieldKind::Type, 28, 2)
+            .field(FieldKind::Time, 36, 4)
+            .field(FieldKind::Host, 40, 16)
+    }
+
+    /// Every historical layout available, for trying each against an image
+    pub fn legacy() -> Vec<Self> {
+        vec![Self::irix_utmp(), Self::irix_utmpx(), Self::hpux_utmp()]
+    }
+}
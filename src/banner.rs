@@ -0,0 +1,103 @@
+/*
+ *  "Last login" banners for MOTD and SSH login messages
+ */
+use std::io::Result;
+
+use super::common::*;
+use super::format::LAST_FULL_TIME;
+use super::search_username;
+use super::utmp::UtmpReader;
+
+/* Variables */
+
+static BTMP: &str = "/var/log/btmp";
+
+/* Functions */
+
+// describe where the login came from as ` from <host> on <tty>`
+fn origin(record: &Record) -> String {
+    let mut origin = String::new();
+    if !record.host.is_empty() {
+        origin.push_str(&format!(" from {}", record.host));
+    }
+    if !record.tty.is_empty() {
+        origin.push_str(&format!(" on {}", record.tty));
+    }
+    origin
+}
+
+/// Format the classic login banner for a login record and failed attempts
+///
+/// Produces the `Last login: ...` line printed by `login(1)` and `sshd`,
+/// preceded by a summary of any failed attempts newer than the last login
+/// in the style of `pam_lastlog`. Returns `None` if the user never logged
+/// in and had no failed attempts.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use std::time::{Duration, UNIX_EPOCH};
+/// use lastlog::{format_banner, Record};
+///
+/// let record = Record::builder()
+///     .name("root")
+///     .tty("pts/1")
+///     .host("10.0.0.5")
+///     .last_login(UNIX_EPOCH + Duration::from_secs(1709634731))
+///     .build();
+/// let banner = format_banner(&record, &[]).unwrap();
+/// assert!(banner.starts_with("Last login: "));
+/// assert!(banner.ends_with(" from 10.0.0.5 on pts/1"));
+/// ```
+pub fn format_banner(record: &Record, failed: &[Record]) -> Option<String> {
+    let mut lines = vec![];
+    let failed: Vec<&Record> = failed
+        .iter()
+        .filter(|f| f.name == record.name && f.last_login > record.last_login)
+        .collect();
+    if let Some(latest) = failed.iter().max_by(|a, b| a.last_login.cmp(&b.last_login)) {
+        let time = latest.last_login.format_local(LAST_FULL_TIME)?;
+        lines.push(format!("Last failed login: {time}{}", origin(latest)));
+        lines.push(match failed.len() {
+            1 => "There was 1 failed login attempt since the last successful login.".to_owned(),
+            n => format!("There were {n} failed login attempts since the last successful login."),
+        });
+    }
+    if let Some(time) = record.last_login.format_local(LAST_FULL_TIME) {
+        lines.push(format!("Last login: {time}{}", origin(record)));
+    }
+    match lines.is_empty() {
+        true => None,
+        false => Some(lines.join("\n")),
+    }
+}
+
+/// Format the login banner of the given user from the system databases
+///
+/// The previous login is looked up using the auto-selected database and
+/// failed attempts are counted from `/var/log/btmp` when it is readable
+/// (usually root only). Call this before recording the new login, as
+/// otherwise the current login is reported as the previous one.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// if let Ok(Some(banner)) = lastlog::format_last_login_banner("root") {
+///     println!("{banner}");
+/// }
+/// ```
+pub fn format_last_login_banner(user: &str) -> Result<Option<String>> {
+    let record = search_username(user)?;
+    let failed: Vec<Record> = match UtmpReader::open(BTMP) {
+        Ok(reader) => reader
+            .filter_map(|r| r.ok())
+            .filter(|r| r.name == user)
+            .collect(),
+        Err(_) => vec![],
+    };
+    Ok(format_banner(&record, &failed))
+}
@@ -0,0 +1,269 @@
+/*
+ *  Learned login behavior of users persisted across restarts
+ */
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{rename, File};
+use std::io::{BufRead, BufReader, BufWriter, Error, ErrorKind, Read, Result, Write};
+
+use super::common::*;
+use super::finding::{Finding, FindingKind, Severity};
+use super::suppress::Suppressions;
+
+/* Variables */
+
+static HEADER: &str = "# lastlog baseline 1";
+
+// logins required before a user's behavior is considered learned
+static DEFAULT_MIN_LOGINS: u32 = 10;
+
+static HOUR: u64 = 60 * 60;
+
+/* Types */
+
+// learned behavior of a single user
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Profile {
+    hours: [u32; 24],
+    hosts: BTreeSet<String>,
+}
+
+/// Typical login hours and known hosts of every user for anomaly detection
+///
+/// Logins are learned from `USER_PROCESS` records, and once a user logged
+/// in often enough, logins from hosts they never used before or at hours
+/// (UTC) far from their usual ones are reported as [`FindingKind::Anomaly`]
+/// findings. The baseline can be saved to a state file and loaded again,
+/// so detection keeps working across restarts instead of learning anew.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use std::time::{Duration, UNIX_EPOCH};
+/// use lastlog::{LoginBaseline, Record, RecordType};
+///
+/// let login = |hour: u64, host: &str| {
+///     let time = UNIX_EPOCH + Duration::from_secs(hour * 60 * 60);
+///     Record::builder().rtype(RecordType::User).name("alice").host(host).last_login(time).build()
+/// };
+/// let mut baseline = LoginBaseline::new().min_logins(3);
+/// for day in 0..5 {
+///     baseline.learn(&login(day * 24 + 9, "10.0.0.5"));
+/// }
+/// assert!(baseline.check(&login(5 * 24 + 10, "10.0.0.5")).is_empty());
+/// let findings = baseline.check(&login(5 * 24 + 3, "203.0.113.7"));
+/// assert_eq!(findings.len(), 2);
+///
+/// let path = std::env::temp_dir().join(format!("lastlog-baseline-{}", std::process::id()));
+/// let path = path.to_str().unwrap();
+/// baseline.save(path).unwrap();
+/// assert_eq!(LoginBaseline::load(path).unwrap().min_logins(3), baseline);
+/// std::fs::remove_file(path).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoginBaseline {
+    users: BTreeMap<String, Profile>,
+    min_logins: u32,
+    suppress: Suppressions,
+}
+
+/* Functions */
+
+// check the value can be stored within the tab separated state file
+fn is_storable(value: &str) -> bool {
+    !value.is_empty() && !value.contains(['\t', '\n', '\r'])
+}
+
+// build the error reported for a malformed state file
+fn invalid(line: usize, message: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("invalid baseline at line {line}: {message}"),
+    )
+}
+
+/* Implementation */
+
+impl Profile {
+    // total number of logins learned
+    fn logins(&self) -> u32 {
+        self.hours.iter().sum()
+    }
+
+    // check if the user logged in around the given hour before
+    fn is_usual_hour(&self, hour: usize) -> bool {
+        [23, 0, 1]
+            .iter()
+            .any(|offset| self.hours[(hour + offset) % 24] > 0)
+    }
+}
+
+impl LoginBaseline {
+    /// Start an empty baseline learning users after 10 logins
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how many logins of a user are learned before reporting anomalies
+    pub fn min_logins(mut self, min_logins: u32) -> Self {
+        self.min_logins = min_logins;
+        self
+    }
+
+    /// Drop findings suppressed by any of the rules
+    pub fn suppress(mut self, rules: Suppressions) -> Self {
+        self.suppress = rules;
+        self
+    }
+
+    // retrieve the hour of the day a login record happened at
+    fn login_hour(record: &Record) -> Option<usize> {
+        if record.rtype != RecordType::User || !is_storable(&record.name) {
+            return None;
+        }
+        let seconds = record.last_login.unix_seconds()?;
+        Some((seconds / HOUR % 24) as usize)
+    }
+
+    /// Learn the login hour and host of a login record
+    pub fn learn(&mut self, record: &Record) {
+        let Some(hour) = Self::login_hour(record) else {
+            return;
+        };
+        let profile = self.users.entry(record.name.clone()).or_default();
+        profile.hours[hour] = profile.hours[hour].saturating_add(1);
+        if is_storable(&record.host) {
+            profile.hosts.insert(record.host.clone());
+        }
+    }
+
+    /// Check a login record against the learned behavior of its user
+    pub fn check(&self, record: &Record) -> Vec<Finding> {
+        let Some(hour) = Self::login_hour(record) else {
+            return vec![];
+        };
+        let Some(profile) = self.users.get(&record.name) else {
+            return vec![];
+        };
+        if profile.logins() < self.min_logins {
+            return vec![];
+        }
+        let mut findings = vec![];
+        if !record.host.is_empty() && !profile.hosts.contains(&record.host) {
+            let finding = Finding::new(FindingKind::Anomaly, Severity::Medium, &record.name)
+                .description(format!("login from unknown host {}", record.host))
+                .evidence(vec![record.clone()]);
+            findings.push(finding);
+        }
+        if !profile.is_usual_hour(hour) {
+            let finding = Finding::new(FindingKind::Anomaly, Severity::Low, &record.name)
+                .description(format!(
+                    "login at {hour:02}:00 UTC outside of the usual hours"
+                ))
+                .evidence(vec![record.clone()]);
+            findings.push(finding);
+        }
+        self.suppress.apply(findings)
+    }
+
+    /// Check a login record and learn it afterwards
+    pub fn observe(&mut self, record: &Record) -> Vec<Finding> {
+        let findings = self.check(record);
+        self.learn(record);
+        findings
+    }
+
+    /// Hosts the user is known to log in from
+    pub fn known_hosts(&self, user: &str) -> Vec<&str> {
+        match self.users.get(user) {
+            Some(profile) => profile.hosts.iter().map(|h| h.as_str()).collect(),
+            None => vec![],
+        }
+    }
+
+    /// Read a baseline written by [`LoginBaseline::write`]
+    pub fn read(reader: impl Read) -> Result<Self> {
+        let mut baseline = Self::new();
+        for (n, line) in BufReader::new(reader).lines().enumerate() {
+            let line = line?;
+            if n == 0 && line != HEADER {
+                return Err(invalid(1, "missing header"));
+            }
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.split('\t');
+            let name = fields.next().unwrap_or_default().to_owned();
+            let counts = fields
+                .next()
+                .ok_or_else(|| invalid(n + 1, "missing hours"))?;
+            let mut profile = Profile::default();
+            let mut hours = counts.split(' ');
+            for count in profile.hours.iter_mut() {
+                let value = hours
+                    .next()
+                    .ok_or_else(|| invalid(n + 1, "missing hours"))?;
+                *count = value
+                    .parse()
+                    .map_err(|_| invalid(n + 1, "invalid login count"))?;
+            }
+            profile.hosts = fields.map(|h| h.to_owned()).collect();
+            baseline.users.insert(name, profile);
+        }
+        Ok(baseline)
+    }
+
+    /// Write the baseline as a tab separated text state
+    pub fn write(&self, writer: impl Write) -> Result<()> {
+        let mut writer = BufWriter::new(writer);
+        writeln!(writer, "{HEADER}")?;
+        for (name, profile) in self.users.iter() {
+            let hours: Vec<String> = profile.hours.iter().map(|c| c.to_string()).collect();
+            write!(writer, "{name}\t{}", hours.join(" "))?;
+            for host in profile.hosts.iter() {
+                write!(writer, "\t{host}")?;
+            }
+            writeln!(writer)?;
+        }
+        writer.flush()
+    }
+
+    /// Load a baseline from a state file, starting empty when it is missing
+    ///
+    /// The loaded baseline reports anomalies after the default number of
+    /// logins without any suppressions, since the configuration itself is
+    /// not part of the state.
+    pub fn load(path: &str) -> Result<Self> {
+        match File::open(path) {
+            Ok(file) => Self::read(file),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(Self::new()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Save the baseline into a state file, replacing it atomically
+    pub fn save(&self, path: &str) -> Result<()> {
+        let temp = format!("{path}.tmp-{}", std::process::id());
+        let result = (|| {
+            let file = File::create(&temp)?;
+            self.write(&file)?;
+            file.sync_all()?;
+            rename(&temp, path)
+        })();
+        if result.is_err() {
+            let _ = std::fs::remove_file(&temp);
+        }
+        result
+    }
+}
+
+impl Default for LoginBaseline {
+    fn default() -> Self {
+        Self {
+            users: BTreeMap::new(),
+            min_logins: DEFAULT_MIN_LOGINS,
+            suppress: Suppressions::new(),
+        }
+    }
+}
@@ -0,0 +1,64 @@
+/*!
+ D-Bus broker serving last-login queries to unprivileged clients
+ ---
+
+ Invoked as `lastlog-dbus [--system | --session]`. Usually started as root
+ on the system bus (with a matching bus policy allowing clients to call
+ `io.github.imgurbot12.LastLog1`) so the databases stay root readable.
+*/
+use std::env;
+use std::process::exit;
+use std::thread;
+
+use lastlog::{LastLogService, DBUS_NAME, DBUS_PATH};
+use zbus::blocking::connection::Builder;
+
+/* Variables */
+
+static NAME: &str = "lastlog-dbus";
+
+static USAGE: &str = "Usage: lastlog-dbus [options]
+
+Options:
+      --system    serve on the system bus (default)
+      --session   serve on the session bus
+  -h, --help      display this help and exit";
+
+/* Functions */
+
+// claim the bus name and serve the interface until terminated
+fn serve(session: bool) -> zbus::Result<()> {
+    let builder = match session {
+        true => Builder::session()?,
+        false => Builder::system()?,
+    };
+    let _conn = builder
+        .name(DBUS_NAME)?
+        .serve_at(DBUS_PATH, LastLogService::default())?
+        .build()?;
+    loop {
+        thread::park();
+    }
+}
+
+fn main() {
+    let mut session = false;
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--system" => session = false,
+            "--session" => session = true,
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return;
+            }
+            arg => {
+                eprintln!("{NAME}: unrecognized option: {arg}\n{USAGE}");
+                exit(1);
+            }
+        }
+    }
+    if let Err(err) = serve(session) {
+        eprintln!("{NAME}: {err}");
+        exit(1);
+    }
+}
@@ -0,0 +1,115 @@
+/*
+ *  Minimal getopt-style command line parsing
+ */
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+/* Types */
+
+/// Single parsed command-line argument
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Arg {
+    Short(char),
+    Long(String),
+    Number(usize),
+    Positional(String),
+}
+
+/// Queue of command-line arguments supporting clustered short flags
+/// (`-xF`), attached values (`-n5`, `--file=wtmp`) and `--` separators
+pub struct Args {
+    args: VecDeque<String>,
+    cluster: VecDeque<char>,
+    value: Option<String>,
+    positional: bool,
+}
+
+impl Args {
+    pub fn new(args: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            args: args.into_iter().collect(),
+            cluster: VecDeque::new(),
+            value: None,
+            positional: false,
+        }
+    }
+
+    /// Peek at the next raw argument without consuming it
+    pub fn peek(&self) -> Option<&str> {
+        self.args.front().map(|s| s.as_str())
+    }
+
+    /// Retrieve the value attached to or following the given flag
+    pub fn value(&mut self, flag: &Arg) -> Result<String, String> {
+        if let Some(value) = self.value.take() {
+            return Ok(value);
+        }
+        if !self.cluster.is_empty() {
+            return Ok(self.cluster.drain(..).collect());
+        }
+        self.args
+            .pop_front()
+            .ok_or_else(|| format!("option {} requires an argument", flag))
+    }
+
+    /// Parse the value attached to or following the given flag
+    pub fn parse<T: FromStr>(&mut self, flag: &Arg) -> Result<T, String> {
+        let value = self.value(flag)?;
+        value
+            .parse()
+            .map_err(|_| format!("invalid argument {value:?} for option {flag}"))
+    }
+}
+
+impl Iterator for Args {
+    type Item = Arg;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(c) = self.cluster.pop_front() {
+            return Some(Arg::Short(c));
+        }
+        let arg = self.args.pop_front()?;
+        if self.positional || arg == "-" || !arg.starts_with('-') {
+            return Some(Arg::Positional(arg));
+        }
+        if arg == "--" {
+            self.positional = true;
+            return self.next();
+        }
+        if let Some(long) = arg.strip_prefix("--") {
+            return Some(match long.split_once('=') {
+                Some((name, value)) => {
+                    self.value = Some(value.to_owned());
+                    Arg::Long(name.to_owned())
+                }
+                None => Arg::Long(long.to_owned()),
+            });
+        }
+        let short = &arg[1..];
+        if let Ok(n) = short.parse::<usize>() {
+            return Some(Arg::Number(n));
+        }
+        self.cluster.extend(short.chars());
+        self.next()
+    }
+}
+
+impl std::fmt::Display for Arg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Short(c) => write!(f, "-{c}"),
+            Self::Long(name) => write!(f, "--{name}"),
+            Self::Number(n) => write!(f, "-{n}"),
+            Self::Positional(arg) => write!(f, "{arg}"),
+        }
+    }
+}
+
+/// Check if the argument matches either the given short or long flag
+pub fn is_flag(arg: &Arg, short: char, long: &str) -> bool {
+    match arg {
+        Arg::Short(c) => *c == short,
+        Arg::Long(name) => name == long,
+        _ => false,
+    }
+}
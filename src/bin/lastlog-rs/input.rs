@@ -0,0 +1,30 @@
+/*
+ *  Database input shared between command-line modes
+ */
+use std::fs::File;
+use std::io::{stdin, Cursor, Read};
+
+use super::Error;
+
+/* Variables */
+
+/// Path used to request reading a database from stdin
+pub static STDIN: &str = "-";
+
+/* Functions */
+
+/// Open the given database path for reading, treating `-` as stdin
+pub fn open(path: &str) -> Result<Box<dyn Read>, Error> {
+    if path == STDIN {
+        return Ok(Box::new(stdin().lock()));
+    }
+    let f = File::open(path).map_err(|e| format!("cannot open {path}: {e}"))?;
+    Ok(Box::new(f))
+}
+
+/// Buffer the whole of stdin so it can be read backwards
+pub fn buffer_stdin() -> Result<Cursor<Vec<u8>>, Error> {
+    let mut buffer = vec![];
+    stdin().lock().read_to_end(&mut buffer)?;
+    Ok(Cursor::new(buffer))
+}
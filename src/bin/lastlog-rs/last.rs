@@ -0,0 +1,235 @@
+/*
+ *  last(1) compatible session listing mode
+ */
+use std::io::{stdout, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use lastlog::{normalize_tty, Bundle, Filter, Session, SessionEnd, Sessions};
+use lastlog::{parse_time, sessions_from, sessions_from_reader, LoginTime, Record, RecordType};
+use lastlog::{LAST_FULL_TIME, LAST_TIME};
+
+use super::args::{is_flag, Arg, Args};
+use super::input;
+use super::output::Output;
+use super::Error;
+
+/* Variables */
+
+static WTMP: &str = "/var/log/wtmp";
+
+static USAGE: &str = "Usage: lastlog-rs last [options] [<username>...] [<tty>...]
+
+Options:
+  -<number>            how many lines to show
+  -B, --bundle PATH    read wtmp from a support bundle directory or archive
+  -f, --file FILE      use a specific file instead of /var/log/wtmp
+                       (- reads from stdin)
+  -F, --fulltimes      print full login and logout times and dates
+      --csv            print sessions as CSV
+      --json           print sessions as a JSON array
+      --jsonl          print sessions as JSON-lines
+      --format FMT     print sessions using a template such as
+                       {user}\\t{time:%F %T}
+  -h, --help           display this help message and exit
+  -n, --limit NUMBER   how many lines to show
+  -R, --nohostname     don't display the hostname field
+  -s, --since TIME     display the lines since the specified time
+  -t, --until TIME     display the lines until the specified time
+  -w, --where EXPR     display only sessions matching a filter expression
+                       such as 'user != root && host =~ 10.0.'
+  -x, --system         display system shutdown entries and run level changes";
+
+/* Types */
+
+#[derive(Default)]
+struct Options {
+    full: bool,
+    hostname: bool,
+    system: bool,
+    limit: Option<usize>,
+    since: Option<SystemTime>,
+    until: Option<SystemTime>,
+    filters: Vec<String>,
+    filter: Option<Filter>,
+    output: Output,
+}
+
+/* Functions */
+
+// format the length of a session as `(hh:mm)` or `(d+hh:mm)`
+fn format_duration(duration: Duration) -> String {
+    let mins = duration.as_secs() / 60;
+    let (days, hours, mins) = (mins / 1440, mins % 1440 / 60, mins % 60);
+    match days {
+        0 => format!("({hours:02}:{mins:02})"),
+        _ => format!("({days}+{hours:02}:{mins:02})"),
+    }
+}
+
+// format a point in time using the short or full last(1) time format
+fn format_time(time: SystemTime, full: bool, short: &str) -> String {
+    let fmt = if full { LAST_FULL_TIME } else { short };
+    LoginTime::from(time).format_local(fmt).unwrap_or_default()
+}
+
+// render the user and line columns of a session
+fn columns(session: &Session) -> (String, String) {
+    let record = &session.record;
+    match record.rtype {
+        RecordType::BootTime => ("reboot".to_owned(), "system boot".to_owned()),
+        RecordType::RunLvl if record.name == "shutdown" => {
+            ("shutdown".to_owned(), "system down".to_owned())
+        }
+        RecordType::RunLvl => {
+            let level = record.pid.map(|p| (p % 256) as u8 as char).unwrap_or('?');
+            ("runlevel".to_owned(), format!("(to lvl {level})"))
+        }
+        _ => (record.name.clone(), record.tty.clone()),
+    }
+}
+
+// render a single session in the last(1) column format
+fn format_session(session: &Session, opts: &Options) -> String {
+    let (user, line) = columns(session);
+    let start = session.start().unwrap_or(SystemTime::UNIX_EPOCH);
+    let login = format_time(start, opts.full, LAST_TIME);
+    let length = session.duration().map(format_duration).unwrap_or_default();
+    let width = if opts.full { 26 } else { 7 };
+    let logout = match session.end {
+        SessionEnd::Active if session.record.rtype == RecordType::User => {
+            "  still logged in".to_owned()
+        }
+        SessionEnd::Active => "  still running".to_owned(),
+        SessionEnd::Logout(t) => format!("- {}", format_time(t, opts.full, "%H:%M")),
+        // the shutdown ending a boot is shown by its time, as by last(1)
+        SessionEnd::Down(t) if session.record.rtype == RecordType::BootTime => {
+            format!("- {}", format_time(t, opts.full, "%H:%M"))
+        }
+        SessionEnd::Down(_) => "- down".to_owned(),
+        SessionEnd::Crash(_) => "- crash".to_owned(),
+    };
+    let tail = match session.end {
+        SessionEnd::Active => logout,
+        _ => format!("{logout:<width$} {length}"),
+    };
+    let host = match opts.hostname {
+        true => format!(" {:<16.16}", session.record.host),
+        false => String::new(),
+    };
+    format!("{user:<8.8} {line:<12.12}{host} {login} {tail}")
+        .trim_end()
+        .to_owned()
+}
+
+// check if the session passes all of the configured filters
+fn selected(session: &Session, opts: &Options) -> bool {
+    if session.is_system() && session.record.rtype != RecordType::BootTime && !opts.system {
+        return false;
+    }
+    let start = session.start();
+    if opts.since.is_some_and(|since| start < Some(since)) {
+        return false;
+    }
+    if opts.until.is_some_and(|until| start > Some(until)) {
+        return false;
+    }
+    if opts
+        .filter
+        .as_ref()
+        .is_some_and(|f| !f.matches(&session.record))
+    {
+        return false;
+    }
+    if opts.filters.is_empty() {
+        return true;
+    }
+    let (user, line) = columns(session);
+    opts.filters.iter().any(|f| {
+        let tty = normalize_tty(f);
+        *f == user || tty == line || tty == normalize_tty(&session.record.tty)
+    })
+}
+
+// write every selected session followed by the beginning of the history
+fn report<I>(mut sessions: Sessions<I>, name: &str, opts: &Options) -> Result<(), Error>
+where
+    I: Iterator<Item = Record>,
+{
+    let mut out = stdout().lock();
+    let mut shown = 0;
+    let mut collected = vec![];
+    for session in sessions.by_ref() {
+        if opts.limit.is_some_and(|limit| shown >= limit) {
+            break;
+        }
+        if !selected(&session, opts) {
+            continue;
+        }
+        shown += 1;
+        match opts.output.is_text() {
+            true => writeln!(out, "{}", format_session(&session, opts))?,
+            false => collected.push(session),
+        }
+    }
+    if !opts.output.is_text() {
+        return opts.output.write(out, &collected);
+    }
+    // report the oldest entry read, matching last(1) when stopping early
+    let begins = sessions.begins().unwrap_or_else(SystemTime::now);
+    let begins = format_time(begins, true, LAST_FULL_TIME);
+    writeln!(out, "\n{name} begins {begins}")?;
+    Ok(())
+}
+
+// parse a time argument reporting invalid values
+fn time_arg(args: &mut Args, flag: &Arg) -> Result<SystemTime, Error> {
+    let value = args.value(flag)?;
+    parse_time(&value).ok_or_else(|| format!("invalid time value {value:?}").into())
+}
+
+pub fn run(mut args: Args) -> Result<(), Error> {
+    let mut file = WTMP.to_owned();
+    let mut bundle: Option<String> = None;
+    let mut opts = Options {
+        hostname: true,
+        ..Default::default()
+    };
+    while let Some(arg) = args.next() {
+        match &arg {
+            Arg::Number(n) => opts.limit = Some(*n),
+            a if is_flag(a, 'n', "limit") => opts.limit = Some(args.parse(a)?),
+            a if is_flag(a, 'f', "file") => file = args.value(a)?,
+            a if is_flag(a, 'B', "bundle") => bundle = Some(args.value(a)?),
+            a if is_flag(a, 'F', "fulltimes") => opts.full = true,
+            a if is_flag(a, 'R', "nohostname") => opts.hostname = false,
+            a if is_flag(a, 's', "since") => opts.since = Some(time_arg(&mut args, a)?),
+            a if is_flag(a, 't', "until") => opts.until = Some(time_arg(&mut args, a)?),
+            a if is_flag(a, 'w', "where") => opts.filter = Some(args.value(a)?.parse()?),
+            a if is_flag(a, 'x', "system") => opts.system = true,
+            a if is_flag(a, 'h', "help") => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            Arg::Positional(name) => opts.filters.push(name.to_owned()),
+            a => match Output::from_flag(a, &mut args)? {
+                Some(format) => opts.output = format,
+                None => return Err(format!("unrecognized option: {a}\n\n{USAGE}").into()),
+            },
+        }
+    }
+    if let Some(path) = bundle {
+        let bundle = Bundle::open(&path).map_err(|e| format!("{path}: {e}"))?;
+        return report(bundle.sessions()?, "wtmp", &opts);
+    }
+    if file == input::STDIN {
+        let sessions = sessions_from_reader(input::buffer_stdin()?)?;
+        return report(sessions, "stdin", &opts);
+    }
+    let sessions = sessions_from(&file).map_err(|e| format!("cannot open {file}: {e}"))?;
+    let name = Path::new(&file)
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or(file.clone());
+    report(sessions, &name, &opts)
+}
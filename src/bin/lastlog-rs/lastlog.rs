@@ -0,0 +1,197 @@
+/*
+ *  lastlog(8) compatible reporting mode
+ */
+use std::io::{stdin, stdout, Write};
+use std::time::{Duration, SystemTime};
+
+use lastlog::{detect_module, get_module, sort_records, Bundle, LastLog, LoginDB, Order, Record};
+use lastlog::{Filter, LoginTime, RecordType, SortBy, Utmp};
+
+use super::args::{is_flag, Arg, Args};
+use super::input;
+use super::output::Output;
+use super::Error;
+
+/* Variables */
+
+static USAGE: &str = "Usage: lastlog-rs [lastlog] [options]
+
+Options:
+  -b, --before DAYS   print only lastlog records older than DAYS
+  -B, --bundle PATH   read records from a support bundle directory or archive
+  -C, --clear         clear lastlog record of a user (usable only with -u)
+  -f, --file FILE     read login records from FILE
+                      (- reads a utmp/wtmp stream from stdin)
+      --csv           print records as CSV
+      --json          print records as a JSON array
+      --jsonl         print records as JSON-lines
+      --format FMT    print records using a template such as
+                      {user}\\t{time:%F %T}
+  -h, --help          display this help message and exit
+  -t, --time DAYS     print only lastlog records more recent than DAYS
+  -u, --user LOGIN    print lastlog record of the specified LOGIN
+                      (a username, uid or uid range such as 1000-2000)
+  -w, --where EXPR    print only records matching a filter expression
+                      such as 'uid >= 1000 && age > 30d'";
+
+/* Types */
+
+// user selection given via `-u`
+enum UserSpec {
+    Name(String),
+    Range(Option<u32>, Option<u32>),
+}
+
+impl UserSpec {
+    fn parse(value: &str) -> Result<Self, String> {
+        let invalid = || format!("unknown user or range: {value}");
+        if let Ok(uid) = value.parse::<u32>() {
+            return Ok(Self::Range(Some(uid), Some(uid)));
+        }
+        let Some((min, max)) = value.split_once('-') else {
+            return Ok(Self::Name(value.to_owned()));
+        };
+        let bound = |s: &str| match s.is_empty() {
+            true => Ok(None),
+            false => s.parse::<u32>().map(Some).map_err(|_| invalid()),
+        };
+        match (bound(min), bound(max)) {
+            (Ok(min), Ok(max)) => Ok(Self::Range(min, max)),
+            // usernames are allowed to contain dashes
+            _ => Ok(Self::Name(value.to_owned())),
+        }
+    }
+
+    fn contains(&self, uid: Option<u32>) -> bool {
+        let Self::Range(min, max) = self else {
+            return false;
+        };
+        let Some(uid) = uid else { return false };
+        min.is_none_or(|min| uid >= min) && max.is_none_or(|max| uid <= max)
+    }
+}
+
+/* Functions */
+
+// convert a number of days into the matching point in time, rejecting
+// values reaching past what the system clock can represent
+fn days_ago(days: u64) -> Result<SystemTime, Error> {
+    days.checked_mul(86400)
+        .and_then(|secs| SystemTime::now().checked_sub(Duration::from_secs(secs)))
+        .ok_or_else(|| format!("invalid number of days: {days}").into())
+}
+
+// collect the records selected by the user specification
+fn select(module: &dyn LoginDB, path: &str, user: &Option<UserSpec>) -> Result<Vec<Record>, Error> {
+    let records = match user {
+        Some(UserSpec::Name(name)) => vec![module
+            .search_username(name, path)
+            .map_err(|_| format!("unknown user or range: {name}"))?],
+        Some(spec) => module
+            .iter_accounts(path)?
+            .into_iter()
+            .filter(|r| spec.contains(r.uid))
+            .collect(),
+        None => module.iter_accounts(path)?,
+    };
+    Ok(records)
+}
+
+// collect the records selected by the user specification from pre-read records
+fn select_from(records: Vec<Record>, user: &Option<UserSpec>) -> Result<Vec<Record>, Error> {
+    let mut records = records
+        .into_iter()
+        .filter(|r| r.rtype == RecordType::User && r.uid.is_some());
+    Ok(match user {
+        Some(UserSpec::Name(name)) => vec![records
+            .find(|r| r.name == *name)
+            .ok_or_else(|| format!("unknown user or range: {name}"))?],
+        Some(spec) => records.filter(|r| spec.contains(r.uid)).collect(),
+        None => records.collect(),
+    })
+}
+
+pub fn run(mut args: Args) -> Result<(), Error> {
+    let mut before = None;
+    let mut after = None;
+    let mut clear = false;
+    let mut file: Option<String> = None;
+    let mut bundle: Option<String> = None;
+    let mut user = None;
+    let mut filter: Option<Filter> = None;
+    let mut output = Output::default();
+    while let Some(arg) = args.next() {
+        match &arg {
+            a if is_flag(a, 'b', "before") => before = Some(days_ago(args.parse(a)?)?),
+            a if is_flag(a, 't', "time") => after = Some(days_ago(args.parse(a)?)?),
+            a if is_flag(a, 'C', "clear") => clear = true,
+            a if is_flag(a, 'f', "file") => file = Some(args.value(a)?),
+            a if is_flag(a, 'B', "bundle") => bundle = Some(args.value(a)?),
+            a if is_flag(a, 'u', "user") => user = Some(UserSpec::parse(&args.value(a)?)?),
+            a if is_flag(a, 'w', "where") => filter = Some(args.value(a)?.parse()?),
+            a if is_flag(a, 'h', "help") => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            Arg::Positional(arg) => {
+                return Err(format!("unexpected argument: {arg}\n\n{USAGE}").into())
+            }
+            a => match Output::from_flag(a, &mut args)? {
+                Some(format) => output = format,
+                None => return Err(format!("unrecognized option: {a}\n\n{USAGE}").into()),
+            },
+        }
+    }
+    // clearing records only applies to the lastlog database itself
+    if clear {
+        if user.is_none() {
+            return Err("option -C requires -u".to_owned().into());
+        }
+        let llog = LastLog {};
+        let path = match file {
+            Some(path) => path,
+            None => llog.primary_file()?.to_owned(),
+        };
+        for record in select(&llog, &path, &user)? {
+            let Some(uid) = record.uid else { continue };
+            llog.clear(uid, &path)
+                .map_err(|e| format!("failed to clear {}: {e}", record.name))?;
+        }
+        return Ok(());
+    }
+    let records = match (bundle, file) {
+        (Some(bundle), _) => {
+            let bundle = Bundle::open(&bundle).map_err(|e| format!("{bundle}: {e}"))?;
+            select_from(bundle.iter_accounts()?, &user)?
+        }
+        (None, Some(path)) if path == input::STDIN => {
+            select_from((Utmp {}).read_stream(stdin().lock())?, &user)?
+        }
+        (None, Some(path)) => {
+            let module = detect_module(&path).map_err(|e| format!("{path}: {e}"))?;
+            select(module.as_ref(), &path, &user)?
+        }
+        (None, None) => {
+            let (module, path) = get_module()?;
+            select(module.as_ref(), &path, &user)?
+        }
+    };
+    let mut records: Vec<_> = records
+        .into_iter()
+        .filter(|r| before.is_none_or(|t| r.last_login < LoginTime::from(t)))
+        .filter(|r| after.is_none_or(|t| r.last_login > LoginTime::from(t)))
+        .filter(|r| filter.as_ref().is_none_or(|f| f.matches(r)))
+        .collect();
+    sort_records(&mut records, SortBy::Uid, Order::Ascending);
+    let mut out = stdout().lock();
+    if !output.is_text() {
+        return output.write(out, &records);
+    }
+    if !records.is_empty() {
+        writeln!(out, "{}", Record::HEADER)?;
+    }
+    for record in records.iter() {
+        writeln!(out, "{record}")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,127 @@
+/*
+ *  lslogins(1) style combined account report mode
+ */
+use std::io::{stdout, Write};
+
+use lastlog::{accounts_from, Account, AccountSources, Bundle, LoginTime};
+
+use super::args::{is_flag, Arg, Args};
+use super::output::Output;
+use super::Error;
+
+/* Variables */
+
+// first and last uid of regular user accounts (see login.defs(5))
+static UID_MIN: u32 = 1000;
+static UID_MAX: u32 = 60000;
+
+static REPORT_TIME: &str = "%Y-%m-%d %H:%M";
+
+static USAGE: &str = "Usage: lastlog-rs lslogins [options] [<username>]
+
+Options:
+  -B, --bundle PATH        read every database from a support bundle
+  -s, --system-accs        display system accounts
+  -u, --user-accs          display user accounts
+  -l, --logins LIST        display only users from LIST (comma separated)
+      --btmp-file PATH     set an alternate path for btmp
+      --lastlog PATH       set an alternate path for the login database
+      --passwd-file PATH   set an alternate path for passwd
+      --shadow-file PATH   set an alternate path for shadow
+      --csv                print accounts as CSV
+      --json               print accounts as a JSON array
+      --jsonl              print accounts as JSON-lines
+      --format FMT         print accounts using a template such as
+                           {user}\\t{time:%F %T}
+  -h, --help               display this help message and exit";
+
+/* Functions */
+
+// format a login time as a fixed-width report column
+fn format_time(time: &LoginTime) -> String {
+    time.format_local(REPORT_TIME).unwrap_or_default()
+}
+
+// render a single account row
+fn format_account(account: &Account) -> String {
+    let locked = match account.locked {
+        Some(true) => "1",
+        Some(false) => "0",
+        None => "",
+    };
+    let failed = match account.failed_count {
+        0 => String::new(),
+        n => format!("{} ({n})", format_time(&account.last_failed)),
+    };
+    let row = format!(
+        "{:>5} {:<16} {:>8} {:<16} {:<21} {}",
+        account.uid,
+        account.name,
+        locked,
+        format_time(&account.last_login),
+        failed,
+        account.gecos,
+    );
+    row.trim_end().to_owned()
+}
+
+// check if the account is a regular user account
+fn is_user(account: &Account) -> bool {
+    (UID_MIN..=UID_MAX).contains(&account.uid)
+}
+
+pub fn run(mut args: Args) -> Result<(), Error> {
+    let mut sources = AccountSources::default();
+    let (mut users, mut system) = (false, false);
+    let mut logins: Vec<String> = vec![];
+    let mut output = Output::default();
+    let mut bundle: Option<String> = None;
+    while let Some(arg) = args.next() {
+        match &arg {
+            a if is_flag(a, 'B', "bundle") => bundle = Some(args.value(a)?),
+            a if is_flag(a, 's', "system-accs") => system = true,
+            a if is_flag(a, 'u', "user-accs") => users = true,
+            a if is_flag(a, 'l', "logins") => {
+                let list = args.value(a)?;
+                logins.extend(list.split(',').map(|s| s.to_owned()));
+            }
+            a @ Arg::Long(l) if l == "btmp-file" => sources.btmp = args.value(a)?,
+            a @ Arg::Long(l) if l == "lastlog" => sources.lastlog = Some(args.value(a)?),
+            a @ Arg::Long(l) if l == "passwd-file" => sources.passwd = args.value(a)?,
+            a @ Arg::Long(l) if l == "shadow-file" => sources.shadow = args.value(a)?,
+            a if is_flag(a, 'h', "help") => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            Arg::Positional(name) => logins.push(name.to_owned()),
+            a => match Output::from_flag(a, &mut args)? {
+                Some(format) => output = format,
+                None => return Err(format!("unrecognized option: {a}\n\n{USAGE}").into()),
+            },
+        }
+    }
+    let accounts = match bundle {
+        Some(path) => Bundle::open(&path)
+            .and_then(|b| b.accounts())
+            .map_err(|e| format!("{path}: {e}"))?,
+        None => accounts_from(&sources)?,
+    };
+    let accounts: Vec<Account> = accounts
+        .into_iter()
+        .filter(|a| logins.is_empty() || logins.contains(&a.name))
+        .filter(|a| users == system || users == is_user(a))
+        .collect();
+    let mut out = stdout().lock();
+    if !output.is_text() {
+        return output.write(out, &accounts);
+    }
+    writeln!(
+        out,
+        "{:>5} {:<16} {:>8} {:<16} {:<21} GECOS",
+        "UID", "USER", "PWD-LOCK", "LAST-LOGIN", "FAILED-LOGIN"
+    )?;
+    for account in accounts.iter() {
+        writeln!(out, "{}", format_account(account))?;
+    }
+    Ok(())
+}
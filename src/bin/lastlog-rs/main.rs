@@ -0,0 +1,108 @@
+/*!
+ Command line interface mirroring the standard login-record utilities
+ ---
+
+ Invoked as `lastlog-rs [mode] [options]` where mode defaults to `lastlog`.
+ When installed (or symlinked) under the name of a mode such as `last`,
+ that mode is selected automatically.
+*/
+use std::env;
+use std::fmt;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+use std::process::exit;
+
+mod args;
+mod input;
+mod last;
+mod lastlog;
+mod lslogins;
+mod migrate;
+mod osquery;
+mod output;
+mod who;
+
+use args::Args;
+
+/* Variables */
+
+static NAME: &str = "lastlog-rs";
+
+static MODES: [&str; 6] = ["lastlog", "last", "lslogins", "migrate", "osquery", "who"];
+
+/* Types */
+
+/// Failure of a command-line mode
+pub enum Error {
+    Msg(String),
+    Io(io::Error),
+}
+
+impl From<String> for Error {
+    fn from(value: String) -> Self {
+        Self::Msg(value)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Msg(msg) => write!(f, "{msg}"),
+            Self::Io(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+/* Functions */
+
+// run the command-line mode of the given name
+fn run_mode(mode: &str, args: Args) -> Option<Result<(), Error>> {
+    match mode {
+        "lastlog" => Some(lastlog::run(args)),
+        "last" => Some(last::run(args)),
+        "lslogins" => Some(lslogins::run(args)),
+        "migrate" => Some(migrate::run(args)),
+        "osquery" => Some(osquery::run(args)),
+        "who" => Some(who::run(args)),
+        _ => None,
+    }
+}
+
+fn main() {
+    let mut args = env::args();
+    let program = args.next().unwrap_or_default();
+    let mut args = Args::new(args);
+    // select the mode based on the program name or the first argument
+    let program = Path::new(&program)
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mode = match args.peek() {
+        Some(mode) if program == NAME && MODES.contains(&mode) => {
+            let mode = mode.to_owned();
+            args.next();
+            mode
+        }
+        _ if program == NAME => "lastlog".to_owned(),
+        _ => program,
+    };
+    let result = match run_mode(&mode, args) {
+        Some(result) => result,
+        None => Err(Error::Msg(format!("unknown mode: {mode}"))),
+    };
+    match result {
+        // output being piped into `head` and similar is not an error
+        Err(Error::Io(err)) if err.kind() == ErrorKind::BrokenPipe => {}
+        Err(err) => {
+            eprintln!("{NAME}: {err}");
+            exit(1);
+        }
+        Ok(()) => {}
+    }
+}
@@ -0,0 +1,58 @@
+/*
+ *  Migration mode moving legacy lastlog and wtmp into lastlog2 and wtmpdb
+ */
+use lastlog::{migrate_lastlog2, migrate_wtmpdb, LASTLOG2_DB, WTMPDB_DB};
+
+use super::args::{is_flag, Arg, Args};
+use super::Error;
+
+/* Variables */
+
+static LASTLOG: &str = "/var/log/lastlog";
+static WTMP: &str = "/var/log/wtmp";
+
+static USAGE: &str = "Usage: lastlog-rs migrate [options]
+
+Options:
+      --lastlog PATH    read the legacy lastlog from PATH
+      --wtmp PATH       read the legacy wtmp from PATH
+      --lastlog2 PATH   write lastlog2 entries into PATH
+      --wtmpdb PATH     write wtmpdb entries into PATH
+      --no-lastlog      do not migrate the lastlog database
+      --no-wtmp         do not migrate the wtmp database
+  -h, --help            display this help message and exit
+
+Defaults are /var/log/lastlog and /var/log/wtmp, migrated into
+/var/lib/lastlog/lastlog2.db and /var/lib/wtmpdb/wtmp.db.";
+
+/* Functions */
+
+pub fn run(mut args: Args) -> Result<(), Error> {
+    let (mut lastlog, mut wtmp) = (Some(LASTLOG.to_owned()), Some(WTMP.to_owned()));
+    let (mut lastlog2, mut wtmpdb) = (LASTLOG2_DB.to_owned(), WTMPDB_DB.to_owned());
+    while let Some(arg) = args.next() {
+        match &arg {
+            Arg::Long(name) if name == "lastlog" => lastlog = Some(args.value(&arg)?),
+            Arg::Long(name) if name == "wtmp" => wtmp = Some(args.value(&arg)?),
+            Arg::Long(name) if name == "lastlog2" => lastlog2 = args.value(&arg)?,
+            Arg::Long(name) if name == "wtmpdb" => wtmpdb = args.value(&arg)?,
+            Arg::Long(name) if name == "no-lastlog" => lastlog = None,
+            Arg::Long(name) if name == "no-wtmp" => wtmp = None,
+            a if is_flag(a, 'h', "help") => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            a => return Err(format!("unrecognized option: {a}\n\n{USAGE}").into()),
+        }
+    }
+    if let Some(lastlog) = lastlog {
+        let migrated =
+            migrate_lastlog2(&lastlog, &lastlog2).map_err(|e| format!("{lastlog}: {e}"))?;
+        println!("{lastlog}: migrated {migrated} accounts into {lastlog2}");
+    }
+    if let Some(wtmp) = wtmp {
+        let migrated = migrate_wtmpdb(&wtmp, &wtmpdb).map_err(|e| format!("{wtmp}: {e}"))?;
+        println!("{wtmp}: migrated {migrated} sessions into {wtmpdb}");
+    }
+    Ok(())
+}
@@ -0,0 +1,100 @@
+/*
+ *  osquery compatible table output mode
+ */
+use std::io::{stdout, BufReader, Write};
+
+use lastlog::{detect_module, get_module, OsqueryTable, Record, UtmpReader};
+
+use super::args::{is_flag, Arg, Args};
+use super::input;
+use super::Error;
+
+/* Variables */
+
+static WTMP: &str = "/var/log/wtmp";
+static UTMP: &str = "/var/run/utmp";
+
+static USAGE: &str = "Usage: lastlog-rs osquery [options] <table>...
+
+Print login records shaped like osquery's tables, where a table is one of
+last (wtmp), lastlog or logged_in_users (utmp).
+
+Options:
+  -f, --file FILE      read a specific file instead of the table's default
+                       (- reads wtmp or utmp from stdin)
+      --jsonl          print rows as JSON-lines instead of a JSON array
+      --schema         print the columns and types of the table
+      --atc PATH       print an osquery ATC configuration serving the
+                       tables from the SQLite archive at PATH
+  -h, --help           display this help message and exit";
+
+/* Functions */
+
+// read the records of the table from the given or default database
+fn read(table: OsqueryTable, file: Option<&str>) -> Result<Vec<Record>, Error> {
+    let default = match table {
+        OsqueryTable::Last => WTMP,
+        OsqueryTable::LoggedInUsers => UTMP,
+        OsqueryTable::Lastlog => {
+            let (module, path) = match file {
+                Some(path) => {
+                    let module = detect_module(path).map_err(|e| format!("{path}: {e}"))?;
+                    (module, path.to_owned())
+                }
+                None => get_module()?,
+            };
+            return Ok(module.iter_accounts(&path)?);
+        }
+    };
+    let reader = input::open(file.unwrap_or(default))?;
+    let records = UtmpReader::new(BufReader::new(reader)).filter_map(|r| r.ok());
+    Ok(records.collect())
+}
+
+pub fn run(mut args: Args) -> Result<(), Error> {
+    let (mut file, mut atc) = (None, None);
+    let (mut jsonl, mut schema) = (false, false);
+    let mut tables = vec![];
+    while let Some(arg) = args.next() {
+        match &arg {
+            a if is_flag(a, 'f', "file") => file = Some(args.value(a)?),
+            Arg::Long(name) if name == "jsonl" => jsonl = true,
+            Arg::Long(name) if name == "schema" => schema = true,
+            Arg::Long(name) if name == "atc" => atc = Some(args.value(&arg)?),
+            a if is_flag(a, 'h', "help") => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            Arg::Positional(name) => tables.push(name.parse::<OsqueryTable>()?),
+            a => return Err(format!("unrecognized option: {a}\n\n{USAGE}").into()),
+        }
+    }
+    let mut out = stdout().lock();
+    if let Some(archive) = atc {
+        if tables.is_empty() {
+            tables = vec![
+                OsqueryTable::Last,
+                OsqueryTable::Lastlog,
+                OsqueryTable::LoggedInUsers,
+            ];
+        }
+        let config = OsqueryTable::atc_config(&tables, &archive);
+        writeln!(out, "{config:#}")?;
+        return Ok(());
+    }
+    let [table] = tables[..] else {
+        return Err(format!("expected a single table\n\n{USAGE}").into());
+    };
+    if schema {
+        for (name, kind) in table.columns() {
+            writeln!(out, "{name} {kind}")?;
+        }
+        return Ok(());
+    }
+    let records = read(table, file.as_deref())?;
+    match jsonl {
+        true => table.write_jsonl(&records, out)?,
+        false => writeln!(out, "{}", table.to_json(&records)?)?,
+    }
+    Ok(())
+}
@@ -0,0 +1,63 @@
+/*
+ *  Machine readable output shared between command-line modes
+ */
+use std::io::Write;
+
+use lastlog::{Template, TemplateSource, ToCsv, ToJson};
+
+use super::args::{Arg, Args};
+use super::Error;
+
+/* Types */
+
+/// Output encoding selected for a command-line mode
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Output {
+    #[default]
+    Text,
+    Csv,
+    Json,
+    JsonLines,
+    Template(Template),
+}
+
+impl Output {
+    /// Parse the `--csv`, `--json`, `--jsonl` and `--format` output flags
+    pub fn from_flag(arg: &Arg, args: &mut Args) -> Result<Option<Self>, Error> {
+        Ok(match arg {
+            Arg::Long(name) if name == "csv" => Some(Self::Csv),
+            Arg::Long(name) if name == "json" => Some(Self::Json),
+            Arg::Long(name) if name == "jsonl" => Some(Self::JsonLines),
+            Arg::Long(name) if name == "format" => {
+                let template = Template::new(&args.value(arg)?)?;
+                Some(Self::Template(template))
+            }
+            _ => None,
+        })
+    }
+
+    /// Check if the output is plain text columns
+    pub fn is_text(&self) -> bool {
+        *self == Self::Text
+    }
+
+    /// Write the items in the selected machine readable or templated format
+    pub fn write<T, W: Write>(&self, mut out: W, items: &[T]) -> Result<(), Error>
+    where
+        T: TemplateSource,
+        [T]: ToCsv + ToJson,
+    {
+        match self {
+            Self::Text => {}
+            Self::Csv => items.write_csv(out)?,
+            Self::Json => writeln!(out, "{}", items.to_json()?)?,
+            Self::JsonLines => items.write_jsonl(out)?,
+            Self::Template(template) => {
+                for item in items {
+                    writeln!(out, "{}", template.render(item)?)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+/*
+ *  who(1) compatible current session listing mode
+ */
+use std::fs::File;
+use std::io::{stdout, BufReader, Read, Write};
+
+use lastlog::UtmpReader;
+use lastlog::{current_sessions_from_reader, Bundle, LoginTime, Record, RecordType, Runlevel};
+
+use super::args::{is_flag, Arg, Args};
+use super::input;
+use super::output::Output;
+use super::Error;
+
+/* Variables */
+
+static UTMP: &str = "/var/run/utmp";
+
+static WHO_TIME: &str = "%Y-%m-%d %H:%M";
+
+static USAGE: &str = "Usage: lastlog-rs who [options] [FILE]
+
+Options:
+  -b, --boot           time of last system boot
+  -B, --bundle PATH    read utmp from a support bundle directory or archive
+  -H, --heading        print line of column headings
+  -q, --count          all login names and number of users logged on
+  -r, --runlevel       print current runlevel
+      --csv            print records as CSV
+      --json           print records as a JSON array
+      --jsonl          print records as JSON-lines
+      --format FMT     print records using a template such as
+                       {user}\\t{time:%F %T}
+  -h, --help           display this help message and exit
+
+If FILE is not specified, use /var/run/utmp. A FILE of - reads from stdin.";
+
+/* Functions */
+
+// format the login time of a record in the who(1) format
+fn format_time(time: &LoginTime) -> String {
+    time.format_local(WHO_TIME).unwrap_or_default()
+}
+
+// render a single who(1) row with an optional trailing comment
+fn format_row(name: &str, line: &str, time: &LoginTime, comment: &str) -> String {
+    let row = format!("{name:<8} {line:<12} {}", format_time(time));
+    match comment.is_empty() {
+        true => row,
+        false => format!("{row} {comment}"),
+    }
+}
+
+// render the runlevel entry as `run-level N` and the previous level
+fn format_runlevel(record: &Record) -> String {
+    let (level, previous) = match Runlevel::from_record(record) {
+        Some(runlevel) => (runlevel.level, runlevel.previous),
+        None => ('?', None),
+    };
+    let line = format!("run-level {level}");
+    let comment = match previous {
+        Some(previous) => format!("last={previous}"),
+        None => String::new(),
+    };
+    format_row("", &line, &record.last_login, &comment)
+}
+
+// find the most recent system entry of each given type in a single pass
+fn latest(reader: impl Read, rtypes: &[RecordType]) -> Vec<Record> {
+    let mut found: Vec<Option<Record>> = vec![None; rtypes.len()];
+    for record in UtmpReader::new(BufReader::new(reader)).filter_map(|r| r.ok()) {
+        let Some(i) = rtypes.iter().position(|t| *t == record.rtype) else {
+            continue;
+        };
+        if found[i]
+            .as_ref()
+            .is_none_or(|f| f.last_login <= record.last_login)
+        {
+            found[i] = Some(record);
+        }
+    }
+    found.into_iter().flatten().collect()
+}
+
+pub fn run(mut args: Args) -> Result<(), Error> {
+    let mut file = UTMP.to_owned();
+    let (mut boot, mut heading, mut count, mut runlevel) = (false, false, false, false);
+    let mut output = Output::default();
+    let mut bundle: Option<String> = None;
+    while let Some(arg) = args.next() {
+        match &arg {
+            a if is_flag(a, 'b', "boot") => boot = true,
+            a if is_flag(a, 'B', "bundle") => bundle = Some(args.value(a)?),
+            a if is_flag(a, 'H', "heading") => heading = true,
+            a if is_flag(a, 'q', "count") => count = true,
+            a if is_flag(a, 'r', "runlevel") => runlevel = true,
+            a if is_flag(a, 'h', "help") => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            Arg::Positional(path) => file = path.to_owned(),
+            a => match Output::from_flag(a, &mut args)? {
+                Some(format) => output = format,
+                None => return Err(format!("unrecognized option: {a}\n\n{USAGE}").into()),
+            },
+        }
+    }
+    let mut out = stdout().lock();
+    let mut events = vec![];
+    if boot {
+        events.push(RecordType::BootTime);
+    }
+    if runlevel {
+        events.push(RecordType::RunLvl);
+    }
+    let records = match bundle {
+        Some(path) => {
+            let bundle = Bundle::open(&path).map_err(|e| format!("{path}: {e}"))?;
+            match (events.is_empty(), &bundle.paths().utmp) {
+                (true, _) => bundle.current_sessions()?,
+                (false, Some(utmp)) => latest(File::open(utmp)?, &events),
+                (false, None) => return Err(format!("{path}: bundle contains no utmp").into()),
+            }
+        }
+        None => {
+            let reader = input::open(&file)?;
+            match events.is_empty() {
+                true => current_sessions_from_reader(reader)?,
+                false => latest(reader, &events),
+            }
+        }
+    };
+    if !output.is_text() {
+        return output.write(out, &records);
+    }
+    if count {
+        let names: Vec<&str> = records.iter().map(|r| r.name.as_str()).collect();
+        writeln!(out, "{}", names.join(" "))?;
+        writeln!(out, "# users={}", names.len())?;
+        return Ok(());
+    }
+    if heading {
+        writeln!(out, "{:<8} {:<12} {:<16} COMMENT", "NAME", "LINE", "TIME")?;
+    }
+    for record in records {
+        let row = match record.rtype {
+            RecordType::BootTime => format_row("", "system boot", &record.last_login, ""),
+            RecordType::RunLvl => format_runlevel(&record),
+            _ => {
+                let comment = match record.host.is_empty() {
+                    true => String::new(),
+                    false => format!("({})", record.host),
+                };
+                format_row(&record.name, &record.tty, &record.last_login, &comment)
+            }
+        };
+        writeln!(out, "{}", row)?;
+    }
+    Ok(())
+}
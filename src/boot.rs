@@ -0,0 +1,240 @@
+/*
+ *  Boot identifiers correlating logins with the boot they happened in
+ */
+use std::fs;
+use std::io::{Error, Result};
+#[cfg(feature = "journal")]
+use std::io::{ErrorKind, Read};
+#[cfg(feature = "journal")]
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::session::Session;
+
+/* Variables */
+
+static BOOT_ID: &str = "/proc/sys/kernel/random/boot_id";
+static PROC_STAT: &str = "/proc/stat";
+
+// wtmp boot entries are stamped with the kernel start, which precedes the
+// first journal entry of the same boot by the time early boot takes
+static BOOT_SLACK: Duration = Duration::from_secs(5 * 60);
+
+/* Types */
+
+/// Single boot of the machine and the time span it covered
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BootInfo {
+    /// identifier of the boot as reported by `boot_id` and the journal
+    pub id: String,
+    /// time the boot started
+    pub first: SystemTime,
+    /// time of the last activity within the boot, `None` while running
+    pub last: Option<SystemTime>,
+}
+
+/// Known boots of a machine used to attribute logins to a specific boot
+///
+/// The running boot is identified by `/proc/sys/kernel/random/boot_id`,
+/// while past boots are listed by the journal with the `journal` feature.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// if let (Ok(boots), Ok(sessions)) = (lastlog::BootIds::system(), lastlog::sessions()) {
+///     for session in boots.annotate(sessions) {
+///         println!("{} {:?}", session.record.name, session.boot_id);
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BootIds {
+    boots: Vec<BootInfo>,
+}
+
+/* Functions */
+
+/// Retrieve the identifier of the running boot
+///
+/// The identifier is formatted like the boot ids of the journal, as 32
+/// lowercase hex characters without dashes.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// if let Ok(id) = lastlog::current_boot_id() {
+///     assert_eq!(id.len(), 32);
+/// }
+/// ```
+pub fn current_boot_id() -> Result<String> {
+    Ok(fs::read_to_string(BOOT_ID)?.trim().replace('-', ""))
+}
+
+// retrieve the time the running boot started from the kernel
+fn current_boot_time() -> Result<SystemTime> {
+    let stat = fs::read_to_string(PROC_STAT)?;
+    let btime = stat
+        .lines()
+        .find_map(|l| l.strip_prefix("btime "))
+        .and_then(|t| t.trim().parse::<u64>().ok())
+        .ok_or_else(|| Error::other("no boot time in /proc/stat"))?;
+    Ok(UNIX_EPOCH + Duration::from_secs(btime))
+}
+
+// convert the microseconds reported by the journal into a time
+#[cfg(feature = "journal")]
+fn journal_time(value: Option<&serde_json::Value>) -> Option<SystemTime> {
+    let micros = match value? {
+        serde_json::Value::Number(n) => n.as_u64()?,
+        serde_json::Value::String(s) => s.parse().ok()?,
+        _ => return None,
+    };
+    Some(UNIX_EPOCH + Duration::from_micros(micros))
+}
+
+/* Implementation */
+
+impl BootIds {
+    /// Build the boot list from already known boots
+    pub fn new(mut boots: Vec<BootInfo>) -> Self {
+        boots.sort_by_key(|b| b.first);
+        Self { boots }
+    }
+
+    /// Identify only the running boot
+    pub fn current() -> Result<Self> {
+        Ok(Self::new(vec![BootInfo {
+            id: current_boot_id()?,
+            first: current_boot_time()?,
+            last: None,
+        }]))
+    }
+
+    /// Identify the running boot alongside every boot known to the journal
+    pub fn system() -> Result<Self> {
+        let current = Self::current()?;
+        #[cfg(feature = "journal")]
+        if let Ok(mut boots) = Self::from_journal() {
+            // journals without persistent storage may not list the running boot
+            for boot in current.boots {
+                if !boots.boots.iter().any(|b| b.id == boot.id) {
+                    boots.boots.push(boot);
+                }
+            }
+            return Ok(Self::new(boots.boots));
+        }
+        Ok(current)
+    }
+
+    /// List every boot recorded by the system journal
+    ///
+    /// Runs `journalctl --list-boots -o json`, which requires access to
+    /// the system journal (usually root or the `systemd-journal` group).
+    #[cfg(feature = "journal")]
+    pub fn from_journal() -> Result<Self> {
+        let output = Command::new("journalctl")
+            .args(["--list-boots", "-o", "json"])
+            .output()?;
+        if !output.status.success() {
+            let reason = String::from_utf8_lossy(&output.stderr);
+            return Err(Error::other(format!(
+                "journalctl failed: {}",
+                reason.trim()
+            )));
+        }
+        let mut boots = Self::read_journal(output.stdout.as_slice())?;
+        // the running boot has no end even though the journal reports one
+        if let Ok(id) = current_boot_id() {
+            for boot in boots.boots.iter_mut().filter(|b| b.id == id) {
+                boot.last = None;
+            }
+        }
+        Ok(boots)
+    }
+
+    /// Parse the output of `journalctl --list-boots -o json`
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use std::time::{Duration, UNIX_EPOCH};
+    /// use lastlog::BootIds;
+    ///
+    /// let boots = r#"[{"index":0,"boot_id":"2f5c1f8e0c4b4d1c9a3a8d7e6b5c4a3f","first_entry":1709634731000000,"last_entry":1709638331000000}]"#;
+    /// let boots = BootIds::read_journal(boots.as_bytes()).unwrap();
+    /// let login = UNIX_EPOCH + Duration::from_secs(1709635000);
+    /// assert_eq!(boots.boot_id(login), Some("2f5c1f8e0c4b4d1c9a3a8d7e6b5c4a3f"));
+    /// ```
+    #[cfg(feature = "journal")]
+    pub fn read_journal<R: Read>(mut reader: R) -> Result<Self> {
+        let invalid = |e: serde_json::Error| Error::new(ErrorKind::InvalidData, e.to_string());
+        let mut data = String::new();
+        reader.read_to_string(&mut data)?;
+        // older systemd releases print one json object per line
+        let entries: Vec<serde_json::Value> = match data.trim_start().starts_with('[') {
+            true => serde_json::from_str(&data).map_err(invalid)?,
+            false => data
+                .lines()
+                .filter(|l| !l.trim().is_empty())
+                .map(serde_json::from_str)
+                .collect::<std::result::Result<_, _>>()
+                .map_err(invalid)?,
+        };
+        let boots = entries
+            .iter()
+            .filter_map(|entry| {
+                Some(BootInfo {
+                    id: entry.get("boot_id")?.as_str()?.to_owned(),
+                    first: journal_time(entry.get("first_entry"))?,
+                    last: journal_time(entry.get("last_entry")),
+                })
+            })
+            .collect();
+        Ok(Self::new(boots))
+    }
+
+    /// Every known boot from oldest to newest
+    pub fn boots(&self) -> &[BootInfo] {
+        &self.boots
+    }
+
+    /// Find the boot that was running at the given time
+    pub fn find(&self, time: SystemTime) -> Option<&BootInfo> {
+        let within = self
+            .boots
+            .iter()
+            .rev()
+            .find(|b| b.first <= time && b.last.is_none_or(|last| time <= last));
+        within.or_else(|| {
+            // boot entries are written slightly before the journal starts
+            self.boots
+                .iter()
+                .find(|b| b.first > time && b.first <= time + BOOT_SLACK)
+        })
+    }
+
+    /// Find the identifier of the boot that was running at the given time
+    pub fn boot_id(&self, time: SystemTime) -> Option<&str> {
+        self.find(time).map(|b| b.id.as_str())
+    }
+
+    /// Assign every session the boot it started in
+    pub fn annotate<'a, I>(&'a self, sessions: I) -> impl Iterator<Item = Session> + 'a
+    where
+        I: IntoIterator<Item = Session>,
+        I::IntoIter: 'a,
+    {
+        sessions.into_iter().map(|mut session| {
+            if let Some(start) = session.start() {
+                session.boot_id = self.boot_id(start).map(|id| id.to_owned());
+            }
+            session
+        })
+    }
+}
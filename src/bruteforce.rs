@@ -0,0 +1,172 @@
+/*
+ *  Brute-force detection over failed login attempts
+ */
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Result;
+use std::ops::Range;
+use std::time::Duration;
+
+use super::common::*;
+use super::finding::{Finding, FindingKind, Severity};
+use super::suppress::Suppressions;
+use super::utmp::UtmpReader;
+
+/* Variables */
+
+// failed logins within the window reported as an attack by default
+static DEFAULT_THRESHOLD: usize = 5;
+
+static DEFAULT_WINDOW: Duration = Duration::from_secs(5 * 60);
+
+// bursts this many times the threshold are reported as critical
+static CRITICAL_FACTOR: usize = 10;
+
+// subject of attempts without a remote host (e.g. the console)
+static LOCAL: &str = "local";
+
+/* Types */
+
+/// Detection of repeated failed logins from a single source
+///
+/// Failed logins, such as the entries of `/var/log/btmp`, are grouped by
+/// their remote host, and every host reaching the threshold of attempts
+/// within the sliding time window is reported as a
+/// [`FindingKind::BruteForce`] finding holding its largest burst as
+/// evidence. Bursts of ten times the threshold are reported as critical.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use std::time::{Duration, UNIX_EPOCH};
+/// use lastlog::{BruteForceDetector, Record, Severity};
+///
+/// let attempt = |secs: u64, host: &str| {
+///     let time = UNIX_EPOCH + Duration::from_secs(secs);
+///     Record::builder().name("root").host(host).last_login(time).build()
+/// };
+/// let mut failures: Vec<_> = (0..6).map(|n| attempt(n * 10, "203.0.113.7")).collect();
+/// failures.push(attempt(30, "10.0.0.5"));
+///
+/// let findings = BruteForceDetector::new().check(&failures);
+/// assert_eq!(findings.len(), 1);
+/// assert_eq!(findings[0].subject, "203.0.113.7");
+/// assert_eq!(findings[0].severity, Severity::High);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BruteForceDetector {
+    threshold: usize,
+    window: Duration,
+    suppress: Suppressions,
+}
+
+/* Implementation */
+
+impl BruteForceDetector {
+    /// Report 5 failed logins within 5 minutes from the same host
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how many failed logins within the window are reported
+    pub fn threshold(mut self, threshold: usize) -> Self {
+        self.threshold = threshold.max(1);
+        self
+    }
+
+    /// Set the length of the sliding time window
+    pub fn window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Drop findings suppressed by any of the rules
+    pub fn suppress(mut self, rules: Suppressions) -> Self {
+        self.suppress = rules;
+        self
+    }
+
+    // largest run of attempts within the window, given attempts sorted by time
+    fn largest_burst(&self, attempts: &[&Record]) -> Range<usize> {
+        let seconds = |r: &Record| r.last_login.unix_seconds().unwrap_or_default();
+        let (mut best, mut start) = (0..0, 0);
+        for (end, attempt) in attempts.iter().enumerate() {
+            while seconds(attempts[start]) + self.window.as_secs() < seconds(attempt) {
+                start += 1;
+            }
+            if end + 1 - start > best.len() {
+                best = start..end + 1;
+            }
+        }
+        best
+    }
+
+    /// Check failed login attempts for brute-force bursts
+    pub fn check<'a>(&self, failures: impl IntoIterator<Item = &'a Record>) -> Vec<Finding> {
+        let mut hosts: BTreeMap<&str, Vec<&Record>> = BTreeMap::new();
+        for record in failures.into_iter().filter(|r| !r.last_login.is_never()) {
+            let host = match record.host.is_empty() {
+                true => LOCAL,
+                false => record.host.as_str(),
+            };
+            hosts.entry(host).or_default().push(record);
+        }
+        let mut findings = vec![];
+        for (host, mut attempts) in hosts {
+            attempts.sort_by(|a, b| a.last_login.cmp(&b.last_login));
+            let burst = &attempts[self.largest_burst(&attempts)];
+            if burst.len() < self.threshold {
+                continue;
+            }
+            let severity = match burst.len() >= self.threshold * CRITICAL_FACTOR {
+                true => Severity::Critical,
+                false => Severity::High,
+            };
+            let users: BTreeSet<&str> = burst.iter().map(|r| r.name.as_str()).collect();
+            let users: Vec<&str> = users.into_iter().collect();
+            let finding = Finding::new(FindingKind::BruteForce, severity, host)
+                .description(format!(
+                    "{} failed logins within {} seconds against {}",
+                    burst.len(),
+                    self.window.as_secs(),
+                    users.join(", ")
+                ))
+                .evidence(burst.iter().map(|r| (*r).clone()).collect());
+            findings.push(finding);
+        }
+        self.suppress.apply(findings)
+    }
+
+    /// Check the failed logins recorded within a btmp database
+    ///
+    /// Entries that fail to parse are skipped.
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use lastlog::BruteForceDetector;
+    ///
+    /// if let Ok(findings) = BruteForceDetector::new().scan("/var/log/btmp") {
+    ///     for finding in findings {
+    ///         println!("{finding}");
+    ///     }
+    /// }
+    /// ```
+    pub fn scan(&self, btmp: &str) -> Result<Vec<Finding>> {
+        let failures: Vec<Record> = UtmpReader::open(btmp)?.filter_map(|r| r.ok()).collect();
+        Ok(self.check(&failures))
+    }
+}
+
+impl Default for BruteForceDetector {
+    fn default() -> Self {
+        Self {
+            threshold: DEFAULT_THRESHOLD,
+            window: DEFAULT_WINDOW,
+            suppress: Suppressions::new(),
+        }
+    }
+}
@@ -0,0 +1,319 @@
+/*
+ *  Support-bundle (sosreport) ingestion of copied login databases
+ */
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+
+use super::account::{join_accounts, read_accounts, Account, AccountSources};
+use super::common::*;
+use super::lastlog::read_users;
+use super::session::Sessions;
+use super::stats::ReadStats;
+use super::utmp::{read_merged, UtmpReader, UtmpRevReader};
+
+/* Variables */
+
+// deepest directory level searched for databases within a bundle
+static MAX_DEPTH: usize = 6;
+
+// bundle relative locations of each supported database
+static PASSWD: &str = "etc/passwd";
+static SHADOW: &str = "etc/shadow";
+static WTMP: &str = "var/log/wtmp";
+static BTMP: &str = "var/log/btmp";
+static LASTLOG: &str = "var/log/lastlog";
+static UTMP: [&str; 2] = ["var/run/utmp", "run/utmp"];
+
+/* Types */
+
+/// Locations of the databases found within a support bundle
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BundlePaths {
+    pub passwd: Option<PathBuf>,
+    pub shadow: Option<PathBuf>,
+    pub wtmp: Option<PathBuf>,
+    pub btmp: Option<PathBuf>,
+    pub lastlog: Option<PathBuf>,
+    pub utmp: Option<PathBuf>,
+}
+
+/// Login databases copied from another system such as a sosreport
+///
+/// A bundle is either a directory holding copies of `/etc` and `/var/log`
+/// (at any depth, e.g. `sosreport-host-1234/var/log/wtmp`) or, with the
+/// `bundle` feature, a `.tar`, `.tar.gz` or `.tgz` archive of one. Archives
+/// are partially extracted into a temporary directory that is removed
+/// again once the bundle is dropped.
+///
+/// Every query resolves usernames and user-ids using the `passwd` file of
+/// the bundle rather than the local system.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::Bundle;
+///
+/// if let Ok(bundle) = Bundle::open("/tmp/sosreport-host-1234") {
+///     for session in bundle.sessions().unwrap().take(10) {
+///         println!("{} {:?}", session.record.name, session.end);
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Bundle {
+    root: PathBuf,
+    paths: BundlePaths,
+    temporary: bool,
+}
+
+/* Functions */
+
+// search the directory tree for the first file ending in the given relative path
+fn locate(root: &Path, suffix: &str, depth: usize) -> Option<PathBuf> {
+    let candidate = root.join(suffix);
+    if candidate.is_file() {
+        return Some(candidate);
+    }
+    if depth == 0 {
+        return None;
+    }
+    let mut dirs: Vec<PathBuf> = fs::read_dir(root)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_dir()))
+        .map(|e| e.path())
+        .collect();
+    dirs.sort();
+    dirs.iter().find_map(|dir| locate(dir, suffix, depth - 1))
+}
+
+// convert a located path into a string usable by the other readers
+fn path_str(path: &Path) -> Result<&str> {
+    path.to_str()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "non utf-8 bundle path"))
+}
+
+// error returned when a required database is missing from the bundle
+fn missing(name: &str) -> Error {
+    Error::new(ErrorKind::NotFound, format!("bundle contains no {name}"))
+}
+
+// check if the path looks like a supported archive
+fn is_archive(path: &Path) -> bool {
+    let name = path.to_string_lossy();
+    [".tar", ".tar.gz", ".tgz"]
+        .iter()
+        .any(|ext| name.ends_with(ext))
+}
+
+// extract the supported databases from a tar archive into a temporary directory
+#[cfg(feature = "bundle")]
+fn extract(archive: &Path) -> Result<PathBuf> {
+    use std::io::Read;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or_default();
+    let dest = std::env::temp_dir().join(format!("lastlog-bundle-{}-{nanos}", std::process::id()));
+    // fail instead of reusing a directory created by someone else first
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(&dest)?;
+    let f = BufReader::new(File::open(archive)?);
+    let reader: Box<dyn Read> = match archive.to_string_lossy().ends_with(".tar") {
+        true => Box::new(f),
+        false => Box::new(flate2::read::GzDecoder::new(f)),
+    };
+    let wanted = [PASSWD, SHADOW, WTMP, BTMP, LASTLOG, UTMP[0], UTMP[1]];
+    let mut tar = tar::Archive::new(reader);
+    let result = (|| {
+        for entry in tar.entries()? {
+            let mut entry = entry?;
+            // links could point the readers at files outside the bundle
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let path = entry.path()?.into_owned();
+            if wanted.iter().any(|w| path.ends_with(w)) {
+                // unpack_in refuses entries escaping the destination
+                entry.unpack_in(&dest)?;
+            }
+        }
+        Ok(())
+    })();
+    if let Err(err) = result {
+        let _ = fs::remove_dir_all(&dest);
+        return Err(err);
+    }
+    Ok(dest)
+}
+
+#[cfg(not(feature = "bundle"))]
+fn extract(_archive: &Path) -> Result<PathBuf> {
+    Err(Error::new(
+        ErrorKind::Unsupported,
+        "reading bundle archives requires the `bundle` feature",
+    ))
+}
+
+/* Implementation */
+
+impl Bundle {
+    /// Open a bundle directory or archive and locate its databases
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let (root, temporary) = match path.is_dir() {
+            true => (path.to_path_buf(), false),
+            false if is_archive(path) => (extract(path)?, true),
+            false => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "bundle must be a directory or tar archive",
+                ))
+            }
+        };
+        let find = |suffix: &str| locate(&root, suffix, MAX_DEPTH);
+        let paths = BundlePaths {
+            passwd: find(PASSWD),
+            shadow: find(SHADOW),
+            wtmp: find(WTMP),
+            btmp: find(BTMP),
+            lastlog: find(LASTLOG),
+            utmp: UTMP.iter().find_map(|u| find(u)),
+        };
+        Ok(Self {
+            root,
+            paths,
+            temporary,
+        })
+    }
+
+    /// Directory the bundle is read from (a temporary one for archives)
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Locations of every database found within the bundle
+    pub fn paths(&self) -> &BundlePaths {
+        &self.paths
+    }
+
+    /// Map every username within the bundle passwd file to its user-id
+    pub fn users(&self) -> Result<HashMap<String, u32>> {
+        Ok(self
+            .passwd_accounts()?
+            .into_iter()
+            .map(|a| (a.name, a.uid))
+            .collect())
+    }
+
+    // parse every account of the bundle passwd file
+    fn passwd_accounts(&self) -> Result<Vec<Account>> {
+        let passwd = self
+            .paths
+            .passwd
+            .as_ref()
+            .ok_or_else(|| missing("passwd"))?;
+        read_accounts(path_str(passwd)?)
+    }
+
+    /// Iterate every wtmp entry of the bundle in file order
+    pub fn records(&self) -> Result<UtmpReader<BufReader<File>>> {
+        let wtmp = self.paths.wtmp.as_ref().ok_or_else(|| missing("wtmp"))?;
+        let f = BufReader::new(File::open(wtmp)?);
+        Ok(UtmpReader::with_users(f, self.users().unwrap_or_default()))
+    }
+
+    /// Iterate every failed login attempt recorded in the bundle btmp
+    pub fn failed_logins(&self) -> Result<UtmpReader<BufReader<File>>> {
+        let btmp = self.paths.btmp.as_ref().ok_or_else(|| missing("btmp"))?;
+        let f = BufReader::new(File::open(btmp)?);
+        Ok(UtmpReader::with_users(f, self.users().unwrap_or_default()))
+    }
+
+    /// Pair the login sessions recorded in the bundle wtmp, newest first
+    pub fn sessions(&self) -> Result<Sessions<impl Iterator<Item = Record>>> {
+        let wtmp = self.paths.wtmp.as_ref().ok_or_else(|| missing("wtmp"))?;
+        let users = self.users().unwrap_or_default();
+        let reader = UtmpRevReader::with_users(File::open(wtmp)?, users)?;
+        Ok(Sessions::new(reader.filter_map(|r| r.ok())))
+    }
+
+    /// Collect the sessions that were logged in when the bundle was captured
+    pub fn current_sessions(&self) -> Result<Vec<Record>> {
+        let utmp = self.paths.utmp.as_ref().ok_or_else(|| missing("utmp"))?;
+        let reader = UtmpReader::with_users(File::open(utmp)?, self.users().unwrap_or_default());
+        Ok(reader
+            .filter_map(|r| r.ok())
+            .filter(|r| r.rtype == RecordType::User && !r.name.is_empty())
+            .collect())
+    }
+
+    /// Retrieve the latest login of every bundle account
+    ///
+    /// The bundle lastlog database is preferred, falling back to the
+    /// latest entry per user within wtmp.
+    pub fn iter_accounts(&self) -> Result<Vec<Record>> {
+        self.latest_logins(self.users()?)
+    }
+
+    // retrieve the latest login of every account within the given user map
+    fn latest_logins(&self, users: HashMap<String, u32>) -> Result<Vec<Record>> {
+        if let Some(lastlog) = &self.paths.lastlog {
+            let idmap = users.iter().map(|(n, u)| (*u, n.to_owned())).collect();
+            return read_users(path_str(lastlog)?, &idmap, &mut ReadStats::default());
+        }
+        let wtmp = self
+            .paths
+            .wtmp
+            .as_ref()
+            .ok_or_else(|| missing("wtmp or lastlog"))?;
+        Ok(read_merged(
+            &users,
+            &[path_str(wtmp)?.to_owned()],
+            &mut ReadStats::default(),
+        )?
+        .into_iter()
+        .filter(|r| r.rtype == RecordType::User && r.uid.is_some())
+        .collect())
+    }
+
+    /// Build the combined account report of the bundle
+    pub fn accounts(&self) -> Result<Vec<Account>> {
+        let passwd = self
+            .paths
+            .passwd
+            .as_ref()
+            .ok_or_else(|| missing("passwd"))?;
+        let optional = |path: &Option<PathBuf>| {
+            path.as_ref()
+                .and_then(|p| p.to_str())
+                .unwrap_or_default()
+                .to_owned()
+        };
+        let sources = AccountSources {
+            passwd: path_str(passwd)?.to_owned(),
+            shadow: optional(&self.paths.shadow),
+            lastlog: None,
+            btmp: optional(&self.paths.btmp),
+        };
+        let accounts = self.passwd_accounts()?;
+        let users = accounts.iter().map(|a| (a.name.clone(), a.uid)).collect();
+        join_accounts(&sources, accounts, || self.latest_logins(users))
+    }
+}
+
+impl Drop for Bundle {
+    fn drop(&mut self) {
+        if self.temporary {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+}
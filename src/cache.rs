@@ -0,0 +1,153 @@
+/*
+ *  Caching of passwd maps and latest-login answers invalidated on file change
+ */
+use std::collections::HashMap;
+use std::fs::metadata;
+use std::hash::Hash;
+use std::io::Result;
+#[cfg(feature = "cached")]
+use std::sync::LazyLock;
+use std::sync::{Mutex, MutexGuard};
+use std::time::SystemTime;
+
+use super::common::*;
+
+/* Variables */
+
+#[cfg(feature = "cached")]
+static LOGINS: LazyLock<FileCache<(String, Query), Vec<Record>>> = LazyLock::new(FileCache::new);
+
+/* Types */
+
+// identity of a file used to notice it was modified, `None` when missing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileStamp(Option<(SystemTime, u64)>);
+
+// cached value alongside the state of every file it was derived from
+#[derive(Debug)]
+struct Entry<V> {
+    stamps: Vec<(String, FileStamp)>,
+    value: V,
+}
+
+// thread-safe cache whose entries expire when their source files change
+#[derive(Debug)]
+pub struct FileCache<K, V> {
+    entries: Mutex<HashMap<K, Entry<V>>>,
+}
+
+// latest-login question answered from a database
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Query {
+    Uid(u32),
+    Username(String),
+    Accounts,
+}
+
+/* Functions */
+
+// answer a latest-login query, reusing the previous answer while the
+// database and passwd files are unchanged
+pub fn latest_logins<F>(path: &str, query: Query, read: F) -> Result<Vec<Record>>
+where
+    F: FnOnce() -> Result<Vec<Record>>,
+{
+    #[cfg(feature = "cached")]
+    return LOGINS.get_or_try_insert_with((path.to_owned(), query), &[path, PASSWD], read);
+    #[cfg(not(feature = "cached"))]
+    {
+        let _ = (path, query);
+        read()
+    }
+}
+
+/// Drop every cached passwd map and latest-login answer
+///
+/// Cached values are already refreshed once `/etc/passwd` or the database
+/// they were read from changes modification-time or size. This forces a
+/// re-read regardless, e.g. after restoring a database with its original
+/// timestamps.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// let before = lastlog::iter_accounts();
+/// lastlog::invalidate();
+/// let after = lastlog::iter_accounts();
+/// ```
+#[cfg(feature = "cached")]
+pub fn invalidate() {
+    LOGINS.clear();
+    invalidate_passwd();
+}
+
+/* Implementation */
+
+impl FileStamp {
+    fn of(path: &str) -> Self {
+        Self(
+            metadata(path)
+                .ok()
+                .and_then(|m| Some((m.modified().ok()?, m.len()))),
+        )
+    }
+}
+
+impl<K: Eq + Hash, V: Clone> FileCache<K, V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // retrieve the cached value or compute and cache a new one from the given files
+    pub fn get_or_try_insert_with<E, F>(
+        &self,
+        key: K,
+        files: &[&str],
+        f: F,
+    ) -> std::result::Result<V, E>
+    where
+        F: FnOnce() -> std::result::Result<V, E>,
+    {
+        // files are stamped before reading so changes made during the read
+        // invalidate the entry on the next lookup
+        let stamps: Vec<_> = files
+            .iter()
+            .map(|path| (path.to_string(), FileStamp::of(path)))
+            .collect();
+        if let Some(entry) = self.lock().get(&key) {
+            if entry.stamps == stamps {
+                return Ok(entry.value.clone());
+            }
+        }
+        // the lock is released while reading so other lookups are not blocked
+        let value = f()?;
+        self.lock().insert(
+            key,
+            Entry {
+                stamps,
+                value: value.clone(),
+            },
+        );
+        Ok(value)
+    }
+
+    // remove every cached value
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    // lock the entries, recovering them if a reader panicked
+    fn lock(&self) -> MutexGuard<'_, HashMap<K, Entry<V>>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<K, V> Default for FileCache<K, V> {
+    fn default() -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+}
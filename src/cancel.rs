@@ -0,0 +1,85 @@
+/*
+ *  Cancellation and deadlines for long running database scans
+ */
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/* Types */
+
+/// Token used to abort a long running scan from another thread or after a deadline
+///
+/// Clones share the same cancellation state, so a token can be handed to
+/// a scan while a UI thread keeps a clone to cancel it. Cancellable
+/// operations stop at the next entry once the token is cancelled and
+/// return the results gathered so far as a [`Partial`].
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use std::time::Duration;
+/// use lastlog::CancelToken;
+///
+/// let token = CancelToken::with_timeout(Duration::from_millis(500));
+/// assert!(!token.is_cancelled());
+/// token.cancel();
+/// assert!(token.is_cancelled());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+/// Results of a cancellable operation that may have been stopped early
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Partial<T> {
+    /// results gathered before the operation finished or was stopped
+    pub items: T,
+    /// whether the operation was cancelled before reading everything
+    pub truncated: bool,
+}
+
+/* Implementation */
+
+impl CancelToken {
+    /// Create a token that is only cancelled manually
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a token that is cancelled once the given time has passed
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self::with_deadline(Instant::now() + timeout)
+    }
+
+    /// Create a token that is cancelled at the given instant
+    pub fn with_deadline(deadline: Instant) -> Self {
+        Self {
+            cancelled: Arc::default(),
+            deadline: Some(deadline),
+        }
+    }
+
+    /// Cancel every operation using this token or one of its clones
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Check if the token was cancelled or its deadline has passed
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed) || self.deadline.is_some_and(|d| Instant::now() >= d)
+    }
+}
+
+impl<T> Partial<T> {
+    /// Wrap the results of an operation that ran to completion
+    pub fn complete(items: T) -> Self {
+        Self {
+            items,
+            truncated: false,
+        }
+    }
+}
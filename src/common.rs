@@ -3,18 +3,35 @@
  */
 use std::collections::HashMap;
 use std::env;
+use std::fmt;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Read, Result};
-use std::slice;
+use std::io::{BufRead, BufReader, Error, ErrorKind, Read, Result};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{panic, thread};
 
 #[cfg(feature = "cached")]
-use cached::proc_macro::cached;
+use super::cache::FileCache;
+use super::cancel::{CancelToken, Partial};
+#[cfg(feature = "humanize")]
+use super::format::humanize;
+use super::format::{local_offset, strftime, DateTime, LASTLOG_TIME};
+use super::layout::LayoutError;
+use super::pattern::Pattern;
+pub use super::raw::{fixed_bytes, RecordType};
+use super::raw::{DecodeError, Timestamp};
+use super::stats::{timed, ReadStats};
+use super::users::{user_source, UserSource};
 
 /* Variables */
 
-static PASSWD: &str = "/etc/passwd";
+pub static PASSWD: &str = "/etc/passwd";
 static USER_ENV: &str = "USER";
+static HOSTNAME_PROC: &str = "/proc/sys/kernel/hostname";
+static HOSTNAME_FILE: &str = "/etc/hostname";
+
+#[cfg(feature = "cached")]
+static PASSWD_CACHE: std::sync::LazyLock<FileCache<(), Vec<User>>> =
+    std::sync::LazyLock::new(FileCache::new);
 
 /* Types */
 
@@ -24,48 +41,23 @@ struct User {
     pub name: String,
 }
 
-/// Utmp RecordType
-/// (https://man7.org/linux/man-pages/man5/utmp.5.html)
-#[derive(Debug, Clone, PartialEq)]
-pub enum RecordType {
-    Empty,
-    RunLvl,
-    BootTime,
-    NewTime,
-    OldTime,
-    InitProc,
-    LoginProc,
-    User,
-    DeadProc,
-    Accounting,
-}
-
-impl TryFrom<i32> for RecordType {
-    type Error = String;
-    fn try_from(value: i32) -> std::prelude::v1::Result<Self, Self::Error> {
-        match value {
-            0 => Ok(Self::Empty),
-            1 => Ok(Self::RunLvl),
-            2 => Ok(Self::BootTime),
-            3 => Ok(Self::NewTime),
-            4 => Ok(Self::OldTime),
-            5 => Ok(Self::InitProc),
-            6 => Ok(Self::LoginProc),
-            7 => Ok(Self::User),
-            8 => Ok(Self::DeadProc),
-            9 => Ok(Self::Accounting),
-            _ => Err(format!("Invalid RecordType: {value:?}")),
-        }
-    }
-}
-
 /// Simple Enum for declaring last login-time
-#[derive(Debug, Clone)]
+///
+/// Login-times are ordered chronologically with `Never` always sorting
+/// before any recorded login.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum LoginTime {
     Never,
     Last(SystemTime),
 }
 
+#[cfg(feature = "fuzzing")]
+impl<'a> arbitrary::Arbitrary<'a> for LoginTime {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Option::<Timestamp>::arbitrary(u)?.map_or(LoginTime::Never, LoginTime::from))
+    }
+}
+
 impl From<SystemTime> for LoginTime {
     fn from(v: SystemTime) -> Self {
         LoginTime::Last(v)
@@ -81,23 +73,602 @@ impl From<Option<SystemTime>> for LoginTime {
     }
 }
 
-impl Into<Option<SystemTime>> for LoginTime {
-    fn into(self) -> Option<SystemTime> {
-        match self {
+impl From<LoginTime> for Option<SystemTime> {
+    fn from(v: LoginTime) -> Self {
+        match v {
             LoginTime::Never => None,
             LoginTime::Last(time) => Some(time),
         }
     }
 }
 
+#[cfg(feature = "chrono")]
+impl From<LoginTime> for Option<chrono::DateTime<chrono::Utc>> {
+    fn from(v: LoginTime) -> Self {
+        v.to_chrono()
+    }
+}
+
+#[cfg(feature = "time")]
+impl From<LoginTime> for Option<time::OffsetDateTime> {
+    fn from(v: LoginTime) -> Self {
+        v.to_offset_datetime()
+    }
+}
+
+impl LoginTime {
+    /// Convert the login-time into a [`chrono::DateTime`] in UTC
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use std::time::{Duration, UNIX_EPOCH};
+    /// use lastlog::LoginTime;
+    ///
+    /// let login = LoginTime::from(UNIX_EPOCH + Duration::from_secs(1709634731));
+    /// let date = login.to_chrono().unwrap();
+    /// assert_eq!(date.to_rfc3339(), "2024-03-05T10:32:11+00:00");
+    /// ```
+    #[cfg(feature = "chrono")]
+    pub fn to_chrono(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        match self {
+            LoginTime::Never => None,
+            LoginTime::Last(time) => Some(chrono::DateTime::from(*time)),
+        }
+    }
+
+    /// Convert the login-time into a [`time::OffsetDateTime`] in UTC
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use std::time::{Duration, UNIX_EPOCH};
+    /// use lastlog::LoginTime;
+    ///
+    /// let login = LoginTime::from(UNIX_EPOCH + Duration::from_secs(1709634731));
+    /// let date = login.to_offset_datetime().unwrap();
+    /// assert_eq!(date.unix_timestamp(), 1709634731);
+    /// ```
+    #[cfg(feature = "time")]
+    pub fn to_offset_datetime(&self) -> Option<time::OffsetDateTime> {
+        match self {
+            LoginTime::Never => None,
+            LoginTime::Last(time) => Some(time::OffsetDateTime::from(*time)),
+        }
+    }
+
+    /// Format the login-time in UTC using `strftime(3)` style specifiers
+    ///
+    /// Offsets rendered with `%z` are always `+0000`. Returns `None` if
+    /// the user never logged in.
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use std::time::{Duration, UNIX_EPOCH};
+    /// use lastlog::{LoginTime, LASTLOG_TIME};
+    ///
+    /// let login = LoginTime::from(UNIX_EPOCH + Duration::from_secs(1709634731));
+    /// let text = login.format_utc(LASTLOG_TIME).unwrap();
+    /// assert_eq!(text, "Tue Mar  5 10:32:11 +0000 2024");
+    /// ```
+    pub fn format_utc(&self, fmt: &str) -> Option<String> {
+        match self {
+            LoginTime::Never => None,
+            LoginTime::Last(time) => Some(strftime(&DateTime::new(*time, 0), fmt)),
+        }
+    }
+
+    /// Format the login-time in the system local timezone using
+    /// `strftime(3)` style specifiers
+    ///
+    /// The local timezone is named by the `TZ` environment variable and
+    /// defaults to `/etc/localtime`. With the `libc` feature it is resolved
+    /// by `localtime_r(3)`, otherwise the crate reads tzdata zones (from
+    /// `$TZDIR` or `/usr/share/zoneinfo`), TZif files and POSIX rules such
+    /// as `EST5EDT,M3.2.0,M11.1.0` itself. Leap seconds are ignored there,
+    /// and a timezone that cannot be resolved (e.g. a zone name on a system
+    /// without tzdata installed) is treated as UTC, making this identical
+    /// to [`LoginTime::format_utc`].
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use std::time::{Duration, SystemTime, UNIX_EPOCH};
+    /// use lastlog::{LoginTime, LAST_FULL_TIME};
+    ///
+    /// std::env::set_var("TZ", "EST5EDT,M3.2.0,M11.1.0");
+    /// let login = LoginTime::from(SystemTime::now());
+    /// println!("{}", login.format_local(LAST_FULL_TIME).unwrap());
+    ///
+    /// let winter = LoginTime::from(UNIX_EPOCH + Duration::from_secs(1705341600));
+    /// let summer = LoginTime::from(UNIX_EPOCH + Duration::from_secs(1721052000));
+    /// assert_eq!(winter.format_local("%H:%M %z").unwrap(), "13:00 -0500");
+    /// assert_eq!(summer.format_local("%H:%M %z").unwrap(), "10:00 -0400");
+    /// ```
+    pub fn format_local(&self, fmt: &str) -> Option<String> {
+        match self {
+            LoginTime::Never => None,
+            LoginTime::Last(time) => {
+                let offset = local_offset(*time);
+                Some(strftime(&DateTime::new(*time, offset), fmt))
+            }
+        }
+    }
+
+    /// Check if the login-time represents a user that never logged in
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use lastlog::LoginTime;
+    ///
+    /// assert!(LoginTime::Never.is_never());
+    /// assert!(LoginTime::Never < LoginTime::from(std::time::SystemTime::now()));
+    /// ```
+    pub fn is_never(&self) -> bool {
+        matches!(self, LoginTime::Never)
+    }
+
+    /// Retrieve the amount of time passed since the last login
+    ///
+    /// Returns `None` if the user never logged in or if the login-time
+    /// is somehow located in the future.
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use std::time::{Duration, SystemTime};
+    /// use lastlog::LoginTime;
+    ///
+    /// let login = LoginTime::from(SystemTime::now() - Duration::from_secs(60));
+    /// assert!(login.elapsed().unwrap() >= Duration::from_secs(60));
+    /// assert_eq!(LoginTime::Never.elapsed(), None);
+    /// ```
+    pub fn elapsed(&self) -> Option<Duration> {
+        match self {
+            LoginTime::Never => None,
+            LoginTime::Last(time) => time.elapsed().ok(),
+        }
+    }
+
+    /// Retrieve the last login as seconds since the unix epoch
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use std::time::{Duration, UNIX_EPOCH};
+    /// use lastlog::LoginTime;
+    ///
+    /// let login = LoginTime::from(UNIX_EPOCH + Duration::from_secs(1700000000));
+    /// assert_eq!(login.unix_seconds(), Some(1700000000));
+    /// ```
+    pub fn unix_seconds(&self) -> Option<u64> {
+        match self {
+            LoginTime::Never => None,
+            LoginTime::Last(time) => time.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs()),
+        }
+    }
+
+    /// Retrieve the last login as microseconds since the unix epoch
+    ///
+    /// Records read from utmp/wtmp carry sub-second precision which can be
+    /// used to order multiple logins within the same second.
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use std::time::{Duration, UNIX_EPOCH};
+    /// use lastlog::LoginTime;
+    ///
+    /// let login = LoginTime::from(UNIX_EPOCH + Duration::from_micros(1700000000_000123));
+    /// assert_eq!(login.unix_seconds(), Some(1700000000));
+    /// assert_eq!(login.unix_micros(), Some(1700000000_000123));
+    /// ```
+    pub fn unix_micros(&self) -> Option<u128> {
+        match self {
+            LoginTime::Never => None,
+            LoginTime::Last(time) => time.duration_since(UNIX_EPOCH).ok().map(|d| d.as_micros()),
+        }
+    }
+
+    /// Describe the login-time relative to now in plain english
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use std::time::{Duration, SystemTime};
+    /// use lastlog::LoginTime;
+    ///
+    /// let login = LoginTime::from(SystemTime::now() - Duration::from_secs(3 * 86400));
+    /// assert_eq!(login.humanize(), "3 days ago");
+    /// assert_eq!(LoginTime::Never.humanize(), "never");
+    /// ```
+    #[cfg(feature = "humanize")]
+    pub fn humanize(&self) -> String {
+        match self {
+            LoginTime::Never => "never".to_owned(),
+            LoginTime::Last(time) => match time.elapsed() {
+                Ok(elapsed) => humanize(elapsed, false),
+                Err(e) => humanize(e.duration(), true),
+            },
+        }
+    }
+}
+
 /// Single Database Record instance for a given user's latest-login information
-#[derive(Debug)]
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use std::collections::HashSet;
+///
+/// let records: HashSet<_> = lastlog::iter_accounts()
+///     .unwrap_or_default()
+///     .into_iter()
+///     .collect();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+#[non_exhaustive]
 pub struct Record {
     pub rtype: RecordType,
     pub uid: Option<u32>,
     pub name: String,
     pub tty: String,
+    pub host: String,
+    pub pid: Option<i32>,
     pub last_login: LoginTime,
+    pub machine: Option<String>,
+}
+
+impl Record {
+    /// Generate a new empty record of the given type for the specified username
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use lastlog::{LoginTime, Record, RecordType};
+    ///
+    /// let record = Record::new(RecordType::User, "foo");
+    /// assert_eq!(record.last_login, LoginTime::Never);
+    /// ```
+    pub fn new(rtype: RecordType, name: impl Into<String>) -> Self {
+        Self {
+            rtype,
+            uid: None,
+            name: name.into(),
+            tty: "".to_owned(),
+            host: "".to_owned(),
+            pid: None,
+            last_login: LoginTime::Never,
+            machine: None,
+        }
+    }
+
+    /// Build a new record one field at a time
+    ///
+    /// Since `Record` may gain new fields in the future it cannot be
+    /// constructed using a struct literal outside this crate.
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use std::time::SystemTime;
+    /// use lastlog::{Record, RecordType};
+    ///
+    /// let record = Record::builder()
+    ///     .rtype(RecordType::User)
+    ///     .uid(1000)
+    ///     .name("foo")
+    ///     .tty("pts/1")
+    ///     .host("10.0.0.5")
+    ///     .last_login(SystemTime::now())
+    ///     .build();
+    /// ```
+    pub fn builder() -> RecordBuilder {
+        RecordBuilder::default()
+    }
+}
+
+impl Record {
+    /// Column header matching the [`Display`](fmt::Display) output of a record
+    pub const HEADER: &'static str = "Username         Port     From             Latest";
+}
+
+/// Format the record the same way `lastlog(8)` prints a single entry
+///
+/// Login-times are rendered in the system local timezone.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::Record;
+///
+/// let record = Record::builder().name("foo").tty("pts/1").build();
+/// println!("{}", Record::HEADER);
+/// println!("{record}");
+/// assert!(record.to_string().ends_with("**Never logged in**"));
+/// ```
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let latest = self
+            .last_login
+            .format_local(LASTLOG_TIME)
+            .unwrap_or_else(|| "**Never logged in**".to_owned());
+        write!(
+            f,
+            "{:<16} {:<8.8} {:<16} {}",
+            self.name, self.tty, self.host, latest
+        )
+    }
+}
+
+/// Step-by-step constructor for [`Record`] objects
+///
+/// Unset fields default to a `User` record with no uid, no tty, no host,
+/// no pid and no recorded login.
+#[derive(Debug, Clone)]
+pub struct RecordBuilder {
+    record: Record,
+}
+
+impl Default for RecordBuilder {
+    fn default() -> Self {
+        Self {
+            record: Record::new(RecordType::User, ""),
+        }
+    }
+}
+
+impl RecordBuilder {
+    /// Set the record-type of the record
+    pub fn rtype(mut self, rtype: RecordType) -> Self {
+        self.record.rtype = rtype;
+        self
+    }
+
+    /// Set the user-id associated with the record
+    pub fn uid(mut self, uid: impl Into<Option<u32>>) -> Self {
+        self.record.uid = uid.into();
+        self
+    }
+
+    /// Set the username associated with the record
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.record.name = name.into();
+        self
+    }
+
+    /// Set the terminal line the login occurred on
+    pub fn tty(mut self, tty: impl Into<String>) -> Self {
+        self.record.tty = tty.into();
+        self
+    }
+
+    /// Set the remote host the login originated from
+    pub fn host(mut self, host: impl Into<String>) -> Self {
+        self.record.host = host.into();
+        self
+    }
+
+    /// Set the process-id of the login session
+    pub fn pid(mut self, pid: impl Into<Option<i32>>) -> Self {
+        self.record.pid = pid.into();
+        self
+    }
+
+    /// Set the time of the login
+    pub fn last_login(mut self, last_login: impl Into<LoginTime>) -> Self {
+        self.record.last_login = last_login.into();
+        self
+    }
+
+    /// Set the machine the record was read on
+    pub fn machine(mut self, machine: impl Into<Option<String>>) -> Self {
+        self.record.machine = machine.into();
+        self
+    }
+
+    /// Complete the builder and return the finished record
+    pub fn build(self) -> Record {
+        self.record
+    }
+}
+
+/// Username and user-id mappings of the accounts known to a system
+///
+/// Building the map reads `/etc/passwd` (or another passwd file), so an
+/// application issuing several queries can build it once and pass it to
+/// the `*_with_users` methods of [`LoginDB`] instead of having every query
+/// parse passwd again.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{LoginDB, Utmp, UserMap};
+///
+/// let users = UserMap::system();
+/// let utmp = Utmp {};
+/// for name in ["root", "foo"] {
+///     let record = utmp.search_username_with_users(name, "/var/log/wtmp", &users);
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UserMap {
+    names: HashMap<String, u32>,
+    ids: HashMap<u32, String>,
+}
+
+/// Error produced when a single database entry cannot be parsed
+///
+/// Unlike the bulk search functions, record iterators report these
+/// per-entry so that callers can skip corrupt entries and continue.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ParseError {
+    pub offset: u64,
+    pub kind: ErrorKind,
+    pub reason: String,
+}
+
+impl ParseError {
+    /// Generate a new parse-error for the entry located at the given offset
+    pub fn new(offset: u64, kind: ErrorKind, reason: impl Into<String>) -> Self {
+        Self {
+            offset,
+            kind,
+            reason: reason.into(),
+        }
+    }
+
+    /// Generate a new parse-error from an existing io-error
+    pub fn from_io(offset: u64, err: Error) -> Self {
+        Self::new(offset, err.kind(), err.to_string())
+    }
+}
+
+impl UserMap {
+    /// Read the accounts of the installed [`UserSource`], which is the
+    /// system passwd database by default
+    pub fn system() -> Self {
+        Self::from_names(read_passwd_nmap())
+    }
+
+    /// Build a map from the entries of a passwd(5) formatted file
+    ///
+    /// Malformed entries, such as those without a numeric user-id, are
+    /// skipped.
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use lastlog::UserMap;
+    ///
+    /// let passwd = "root:x:0:0:root:/root:/bin/sh\nbroken\nfoo:x:1000:1000::/home/foo:/bin/sh\n";
+    /// let users = UserMap::from_passwd(passwd.as_bytes());
+    /// assert_eq!(users.uid("foo"), Some(1000));
+    /// assert_eq!(users.name(0), Some("root"));
+    /// ```
+    pub fn from_passwd<R: Read>(reader: R) -> Self {
+        let users = parse_passwd(reader);
+        Self::from_names(users.into_iter().map(|u| (u.name, u.uid)).collect())
+    }
+
+    /// Build a map from pairs of usernames and user-ids
+    pub fn from_names(names: HashMap<String, u32>) -> Self {
+        let ids = names.iter().map(|(n, u)| (*u, n.to_owned())).collect();
+        Self { names, ids }
+    }
+
+    /// Build a map from the accounts already loaded by the `uzers` crate
+    ///
+    /// Applications holding a [`uzers::UsersSnapshot`] (or any other
+    /// [`uzers::AllUsers`] provider) can reuse it instead of having
+    /// passwd parsed a second time.
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use lastlog::{LoginDB, UserMap, Utmp};
+    /// use uzers::UsersSnapshot;
+    ///
+    /// // safety: no other thread is enumerating users at the same time
+    /// let snapshot = unsafe { UsersSnapshot::new() };
+    /// let users = UserMap::from_uzers(&snapshot);
+    /// let records = Utmp {}.iter_accounts_with_users("/var/log/wtmp", &users);
+    /// ```
+    #[cfg(feature = "uzers")]
+    pub fn from_uzers<A: uzers::AllUsers>(source: &A) -> Self {
+        let names = source
+            .get_all_users()
+            .map(|u| (u.name().to_string_lossy().into_owned(), u.uid()))
+            .collect();
+        Self::from_names(names)
+    }
+
+    /// Map of every username to its user-id
+    pub fn names(&self) -> &HashMap<String, u32> {
+        &self.names
+    }
+
+    /// Map of every user-id to its username
+    pub fn ids(&self) -> &HashMap<u32, String> {
+        &self.ids
+    }
+
+    /// Retrieve the user-id of the given username
+    pub fn uid(&self, name: &str) -> Option<u32> {
+        self.names.get(name).copied()
+    }
+
+    /// Retrieve the username of the given user-id
+    pub fn name(&self, uid: u32) -> Option<&str> {
+        self.ids.get(&uid).map(|n| n.as_str())
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at offset {})", self.reason, self.offset)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<Timestamp> for LoginTime {
+    fn from(value: Timestamp) -> Self {
+        unix_timestamp(value.sec, value.usec)
+    }
+}
+
+impl From<LayoutError> for Error {
+    fn from(value: LayoutError) -> Self {
+        Error::new(ErrorKind::Unsupported, value.to_string())
+    }
+}
+
+impl From<DecodeError> for Error {
+    fn from(value: DecodeError) -> Self {
+        Error::new(ErrorKind::InvalidData, value.to_string())
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(value: ParseError) -> Self {
+        Error::new(value.kind, value)
+    }
 }
 
 /// Public Trait for specific linux database search implementations
@@ -111,89 +682,246 @@ pub trait LoginDB {
     fn iter_accounts(&self, fname: &str) -> Result<Vec<Record>>;
     fn search_uid(&self, uid: u32, fname: &str) -> Result<Record>;
     fn search_username(&self, username: &str, fname: &str) -> Result<Record>;
+
+    /// Iterate logins for every user account using a pre-built user map
+    ///
+    /// The default implementation ignores the map and calls `iter_accounts`.
+    fn iter_accounts_with_users(&self, fname: &str, users: &UserMap) -> Result<Vec<Record>> {
+        let _ = users;
+        self.iter_accounts(fname)
+    }
+
+    /// Search for the latest login of a user-id using a pre-built user map
+    ///
+    /// The default implementation ignores the map and calls `search_uid`.
+    fn search_uid_with_users(&self, uid: u32, fname: &str, users: &UserMap) -> Result<Record> {
+        let _ = users;
+        self.search_uid(uid, fname)
+    }
+
+    /// Search for the latest login of a username using a pre-built user map
+    ///
+    /// The default implementation ignores the map and calls `search_username`.
+    fn search_username_with_users(
+        &self,
+        username: &str,
+        fname: &str,
+        users: &UserMap,
+    ) -> Result<Record> {
+        let _ = users;
+        self.search_username(username, fname)
+    }
+
+    /// Iterate logins for every user account alongside read statistics
+    ///
+    /// The default implementation only measures the duration and counts
+    /// the returned records as parsed.
+    fn iter_accounts_with_stats(&self, fname: &str) -> Result<(Vec<Record>, ReadStats)> {
+        let mut stats = ReadStats::default();
+        let records = timed(&mut stats, |_| self.iter_accounts(fname))?;
+        stats.records_parsed = records.len();
+        Ok((records, stats))
+    }
+
+    /// Iterate logins for every user account until the token is cancelled
+    ///
+    /// The default implementation only checks the token before reading,
+    /// which suits databases that are small enough to read at once.
+    fn iter_accounts_cancellable(
+        &self,
+        fname: &str,
+        cancel: &CancelToken,
+    ) -> Result<Partial<Vec<Record>>> {
+        match cancel.is_cancelled() {
+            true => Ok(Partial {
+                items: vec![],
+                truncated: true,
+            }),
+            false => Ok(Partial::complete(self.iter_accounts(fname)?)),
+        }
+    }
+
+    /// Search for the latest logins of every account matching the pattern
+    fn search_matching(&self, pattern: &Pattern, fname: &str) -> Result<Vec<Record>> {
+        let records = self.iter_accounts(fname)?;
+        Ok(records
+            .into_iter()
+            .filter(|r| pattern.matches(&r.name))
+            .collect())
+    }
+
+    /// Search for the latest logins of several user-ids at once
+    ///
+    /// Records are returned in the same order as the requested user-ids.
+    fn search_uids(&self, uids: &[u32], fname: &str) -> Result<Vec<Record>> {
+        uids.iter()
+            .map(|uid| self.search_uid(*uid, fname))
+            .collect()
+    }
+
+    /// Search for the latest logins of several usernames at once
+    ///
+    /// Records are returned in the same order as the requested usernames.
+    fn search_usernames(&self, usernames: &[&str], fname: &str) -> Result<Vec<Record>> {
+        usernames
+            .iter()
+            .map(|name| self.search_username(name, fname))
+            .collect()
+    }
 }
 
 /* Functions */
 
-// convert unix-timestamp to system-time object (when applicable)
+// convert unix-timestamp (with optional microseconds) to system-time object (when applicable)
 #[inline]
-pub fn unix_timestamp(ts: u32) -> LoginTime {
+pub fn unix_timestamp(ts: u32, usec: u32) -> LoginTime {
     if ts > 0 {
-        return LoginTime::Last(UNIX_EPOCH + Duration::from_secs(ts as u64));
+        let usec = if usec < 1_000_000 { usec } else { 0 };
+        let since = Duration::from_secs(ts as u64) + Duration::from_micros(usec as u64);
+        return LoginTime::Last(UNIX_EPOCH + since);
     }
     LoginTime::Never
 }
 
-// read serialized C struct into object
-pub fn read_struct<T, R: Read>(mut read: R) -> Result<T> {
-    let num_bytes = ::std::mem::size_of::<T>();
-    unsafe {
-        let mut s = ::std::mem::zeroed();
-        let buffer = slice::from_raw_parts_mut(&mut s as *mut T as *mut u8, num_bytes);
-        match read.read_exact(buffer) {
-            Ok(()) => Ok(s),
-            Err(e) => {
-                ::std::mem::forget(s);
-                Err(e)
-            }
-        }
+// generate empty user record for the given uid/name
+pub fn new_record(uid: u32, name: String) -> Record {
+    Record::builder().uid(uid).name(name).build()
+}
+
+// run the operation on every item, spread over one thread per item when
+// more than a single cpu is available, returning the results in order
+pub fn parallel_map<T, R, F>(items: &[T], op: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let cpus = thread::available_parallelism().map_or(1, |n| n.get());
+    if cpus <= 1 || items.len() <= 1 {
+        return items.iter().map(op).collect();
     }
+    thread::scope(|s| {
+        let op = &op;
+        let workers: Vec<_> = items.iter().map(|item| s.spawn(move || op(item))).collect();
+        workers
+            .into_iter()
+            .map(|w| w.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+            .collect()
+    })
 }
 
-// generate empty user record for the given uid/name
-pub fn new_record(uid: u32, name: String) -> Record {
-    Record {
-        rtype: RecordType::User,
-        uid: Some(uid),
-        name,
-        tty: "".to_owned(),
-        last_login: LoginTime::Never,
+/// Retrieve the hostname of the local machine for tagging records
+///
+/// Uses `gethostname(2)` with the `libc` feature, otherwise the kernel
+/// hostname exposed in procfs.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// let machine = lastlog::local_machine();
+/// let mut records = lastlog::iter_accounts().unwrap_or_default();
+/// for record in records.iter_mut() {
+///     record.machine = machine.clone();
+/// }
+/// ```
+pub fn local_machine() -> Option<String> {
+    #[cfg(feature = "libc")]
+    {
+        let mut buffer = [0u8; 256];
+        let result = unsafe { libc::gethostname(buffer.as_mut_ptr().cast(), buffer.len()) };
+        if result == 0 {
+            let end = buffer.iter().position(|b| *b == 0).unwrap_or(buffer.len());
+            return Some(String::from_utf8_lossy(&buffer[..end]).into_owned());
+        }
     }
+    [HOSTNAME_PROC, HOSTNAME_FILE]
+        .iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .map(|name| name.trim().to_owned())
+        .find(|name| !name.is_empty())
 }
 
-// parse /etc/passwd for users and uids on system
+// parse /etc/passwd for users and uids on system, cached until it changes,
+// unless another user source was installed
 fn read_passwd() -> Vec<User> {
-    let f = File::open(&PASSWD).expect("unable to read /etc/passwd");
+    if let Some(source) = user_source() {
+        return read_source(&*source);
+    }
+    #[cfg(feature = "cached")]
+    return PASSWD_CACHE
+        .get_or_try_insert_with((), &[PASSWD], || Ok::<_, ()>(read_passwd_file()))
+        .unwrap_or_default();
+    #[cfg(not(feature = "cached"))]
+    read_passwd_file()
+}
+
+// drop the cached passwd entries
+#[cfg(feature = "cached")]
+pub fn invalidate_passwd() {
+    PASSWD_CACHE.clear();
+}
+
+// retrieve the accounts of an installed user source, empty when it fails
+fn read_source(source: &dyn UserSource) -> Vec<User> {
+    match source.users() {
+        Ok(users) => users
+            .names
+            .into_iter()
+            .map(|(name, uid)| User { uid, name })
+            .collect(),
+        Err(_e) => {
+            warn_event!(error = %_e, "failed to read user source");
+            vec![]
+        }
+    }
+}
+
+// parse /etc/passwd for users and uids on system, empty when unreadable
+fn read_passwd_file() -> Vec<User> {
+    match File::open(PASSWD) {
+        Ok(f) => parse_passwd(f),
+        Err(_e) => {
+            warn_event!(error = %_e, path = PASSWD, "failed to read passwd");
+            vec![]
+        }
+    }
+}
+
+// parse users and uids of passwd(5) entries, skipping malformed lines
+fn parse_passwd<R: Read>(reader: R) -> Vec<User> {
     let mut users = vec![];
-    for rline in BufReader::new(f).lines() {
+    for rline in BufReader::new(reader).lines() {
         let Ok(line) = rline else { continue };
-        if line.trim().len() == 0 {
+        if line.trim().is_empty() {
             continue;
         };
         let mut temp = line.splitn(4, ':');
-        let name = temp.next().expect("Invalid /etc/passwd Entry");
-        temp.next();
-        let raw_uid = temp.next().expect("Invalid /etc/passwd UID");
+        let (Some(name), _, Some(raw_uid)) = (temp.next(), temp.next(), temp.next()) else {
+            warn_event!(entry = %line, "skipping passwd entry without a uid");
+            continue;
+        };
+        let Ok(uid) = raw_uid.parse::<u32>() else {
+            warn_event!(entry = %line, "skipping passwd entry with an invalid uid");
+            continue;
+        };
         users.push(User {
             name: name.to_owned(),
-            uid: raw_uid.parse::<u32>().expect("Invalid user UID"),
+            uid,
         });
     }
     users
 }
 
-#[cfg(not(feature = "cached"))]
 pub fn read_passwd_nmap() -> HashMap<String, u32> {
     read_passwd().into_iter().map(|r| (r.name, r.uid)).collect()
 }
 
-#[cfg(not(feature = "cached"))]
 pub fn read_passwd_idmap() -> HashMap<u32, String> {
     read_passwd().into_iter().map(|r| (r.uid, r.name)).collect()
 }
 
-#[cfg(feature = "cached")]
-#[cached]
-pub fn read_passwd_idmap() -> HashMap<u32, String> {
-    read_passwd().into_iter().map(|r| (r.uid, r.name)).collect()
-}
-
-#[cfg(feature = "cached")]
-#[cached]
-pub fn read_passwd_nmap() -> HashMap<String, u32> {
-    read_passwd().into_iter().map(|r| (r.name, r.uid)).collect()
-}
-
 // retrieve best guess for user id from system
 pub fn guess_uid() -> u32 {
     let mut uid = 0;
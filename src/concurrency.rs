@@ -0,0 +1,89 @@
+/*
+ *  Per-user peaks of simultaneous sessions within a time window
+ */
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+use super::session::Session;
+
+/* Types */
+
+/// Highest number of simultaneous sessions a user had within a window
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use std::time::{Duration, SystemTime};
+///
+/// let month = Duration::from_secs(30 * 24 * 60 * 60);
+/// let now = SystemTime::now();
+/// if let Ok(sessions) = lastlog::sessions() {
+///     for usage in lastlog::peak_concurrency(sessions, now - month, now) {
+///         println!("{} had {} sessions at once", usage.user, usage.peak);
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Concurrency {
+    /// username the sessions belong to
+    pub user: String,
+    /// highest number of sessions open at the same time
+    pub peak: usize,
+    /// first moment the peak was reached
+    pub at: SystemTime,
+    /// number of sessions overlapping the window
+    pub sessions: usize,
+}
+
+/* Functions */
+
+/// Compute the peak of simultaneous sessions of every user within a window
+///
+/// Only user logins are counted, and sessions still open are treated as
+/// lasting until the end of the window. Users are returned with the
+/// highest peak first, so shared accounts and license overuse stand out.
+pub fn peak_concurrency<I>(sessions: I, since: SystemTime, until: SystemTime) -> Vec<Concurrency>
+where
+    I: IntoIterator<Item = Session>,
+{
+    // collect the start (+1) and end (-1) events of every user
+    let mut events: HashMap<String, Vec<(SystemTime, i32)>> = HashMap::new();
+    for session in sessions.into_iter().filter(|s| !s.is_system()) {
+        let Some(start) = session.start() else {
+            continue;
+        };
+        let end = session.end.time().unwrap_or(until).min(until);
+        if start > until || end < since {
+            continue;
+        }
+        let user = events.entry(session.record.name).or_default();
+        user.push((start.max(since), 1));
+        user.push((end, -1));
+    }
+    let mut peaks: Vec<_> = events
+        .into_iter()
+        .map(|(user, mut events)| {
+            // sessions ending at the same moment another starts do not overlap
+            events.sort();
+            let sessions = events.len() / 2;
+            let (mut open, mut peak, mut at) = (0i32, 0, since);
+            for (time, change) in events {
+                open += change;
+                if open as usize > peak {
+                    (peak, at) = (open as usize, time);
+                }
+            }
+            Concurrency {
+                user,
+                peak,
+                at,
+                sessions,
+            }
+        })
+        .collect();
+    peaks.sort_by(|a, b| (Reverse(a.peak), &a.user).cmp(&(Reverse(b.peak), &b.user)));
+    peaks
+}
@@ -0,0 +1,205 @@
+/*
+ *  CSV export of records, sessions and account reports
+ */
+use std::io::{Error, ErrorKind, Result, Write};
+
+use super::account::Account;
+use super::common::*;
+use super::format::RFC3339_TIME;
+use super::session::Session;
+use super::sink::RecordSink;
+
+/* Variables */
+
+static RECORD_COLUMNS: [&str; 7] = ["type", "uid", "user", "tty", "host", "pid", "last_login"];
+
+static SESSION_COLUMNS: [&str; 9] = [
+    "type", "uid", "user", "tty", "host", "pid", "login", "logout", "end",
+];
+
+static ACCOUNT_COLUMNS: [&str; 10] = [
+    "uid",
+    "gid",
+    "user",
+    "gecos",
+    "home",
+    "shell",
+    "last_login",
+    "last_failed",
+    "failed_count",
+    "locked",
+];
+
+/* Types */
+
+/// Encode collections of records, sessions or accounts as CSV
+///
+/// Every collection starts with a header row and uses a fixed column
+/// order, so exports remain comparable between releases. Login-times are
+/// written as RFC 3339 UTC timestamps and left empty when missing.
+///
+/// | Collection     | Columns                                                  |
+/// | -------------- | -------------------------------------------------------- |
+/// | `[Record]`     | type, uid, user, tty, host, pid, last_login              |
+/// | `[Session]`    | type, uid, user, tty, host, pid, login, logout, end      |
+/// | `[Account]`    | uid, gid, user, gecos, home, shell, last_login, last_failed, failed_count, locked |
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{Record, RecordType, ToCsv};
+///
+/// let records = vec![Record::new(RecordType::User, "foo")];
+/// let csv = records.to_csv().unwrap();
+/// assert_eq!(csv, "type,uid,user,tty,host,pid,last_login\nUSER_PROCESS,,foo,,,,\n");
+/// ```
+pub trait ToCsv {
+    /// Encode the collection as a CSV document
+    fn to_csv(&self) -> Result<String> {
+        let mut buffer = vec![];
+        self.write_csv(&mut buffer)?;
+        String::from_utf8(buffer).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+    }
+
+    /// Stream the collection as CSV into the given writer
+    fn write_csv<W: Write>(&self, writer: W) -> Result<()>;
+}
+
+/// Sink streaming records as CSV rows with the `[Record]` columns of [`ToCsv`]
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{CsvSink, Record, RecordSink};
+///
+/// let mut csv = vec![];
+/// let mut sink = CsvSink::new(&mut csv).unwrap();
+/// sink.write(&Record::builder().name("foo").build()).unwrap();
+/// sink.finish().unwrap();
+/// drop(sink);
+/// assert_eq!(csv, b"type,uid,user,tty,host,pid,last_login\nUSER_PROCESS,,foo,,,,\n");
+/// ```
+pub struct CsvSink<W: Write> {
+    writer: ::csv::Writer<W>,
+}
+
+/* Functions */
+
+// convert an optional value into a csv field
+#[inline]
+fn field<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+// render the record-type by name, falling back to its raw value
+#[inline]
+fn rtype_field(rtype: &RecordType) -> String {
+    match rtype.name() {
+        Some(name) => name.to_owned(),
+        None => i32::from(*rtype).to_string(),
+    }
+}
+
+// render a login-time column
+#[inline]
+fn time_field(time: &LoginTime) -> String {
+    time.format_utc(RFC3339_TIME).unwrap_or_default()
+}
+
+// write the header and every row using the csv writer
+fn write_rows<W, I>(writer: W, columns: &[&str], rows: I) -> Result<()>
+where
+    W: Write,
+    I: Iterator<Item = Vec<String>>,
+{
+    let mut writer = ::csv::Writer::from_writer(writer);
+    writer.write_record(columns)?;
+    for row in rows {
+        writer.write_record(&row)?;
+    }
+    writer.flush()
+}
+
+// render the shared leading columns of records and sessions
+fn record_row(record: &Record) -> Vec<String> {
+    vec![
+        rtype_field(&record.rtype),
+        field(record.uid),
+        record.name.clone(),
+        record.tty.clone(),
+        record.host.clone(),
+        field(record.pid),
+    ]
+}
+
+/* Implementation */
+
+impl ToCsv for [Record] {
+    fn write_csv<W: Write>(&self, writer: W) -> Result<()> {
+        let rows = self.iter().map(|record| {
+            let mut row = record_row(record);
+            row.push(time_field(&record.last_login));
+            row
+        });
+        write_rows(writer, &RECORD_COLUMNS, rows)
+    }
+}
+
+impl ToCsv for [Session] {
+    fn write_csv<W: Write>(&self, writer: W) -> Result<()> {
+        let rows = self.iter().map(|session| {
+            let mut row = record_row(&session.record);
+            let logout = session.end.time().map_or(LoginTime::Never, LoginTime::from);
+            row.push(time_field(&session.record.last_login));
+            row.push(time_field(&logout));
+            row.push(session.end.kind().to_owned());
+            row
+        });
+        write_rows(writer, &SESSION_COLUMNS, rows)
+    }
+}
+
+impl ToCsv for [Account] {
+    fn write_csv<W: Write>(&self, writer: W) -> Result<()> {
+        let rows = self.iter().map(|account| {
+            vec![
+                account.uid.to_string(),
+                account.gid.to_string(),
+                account.name.clone(),
+                account.gecos.clone(),
+                account.home.clone(),
+                account.shell.clone(),
+                time_field(&account.last_login),
+                time_field(&account.last_failed),
+                account.failed_count.to_string(),
+                field(account.locked),
+            ]
+        });
+        write_rows(writer, &ACCOUNT_COLUMNS, rows)
+    }
+}
+
+impl<W: Write> CsvSink<W> {
+    /// Start streaming into the given writer, writing the header row
+    pub fn new(writer: W) -> Result<Self> {
+        let mut writer = ::csv::Writer::from_writer(writer);
+        writer.write_record(RECORD_COLUMNS)?;
+        Ok(Self { writer })
+    }
+}
+
+impl<W: Write> RecordSink for CsvSink<W> {
+    fn write(&mut self, record: &Record) -> Result<()> {
+        let mut row = record_row(record);
+        row.push(time_field(&record.last_login));
+        Ok(self.writer.write_record(&row)?)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.writer.flush()
+    }
+}
@@ -0,0 +1,160 @@
+/*
+ *  D-Bus interface exposing last-login queries to unprivileged clients
+ */
+use zbus::fdo;
+use zbus::interface;
+
+use super::common::*;
+use super::search_username;
+use super::session::{current_sessions_from, sessions_from, Session, SessionEnd};
+
+/* Variables */
+
+/// Well-known bus name claimed by the `lastlog-dbus` broker
+pub static DBUS_NAME: &str = "io.github.imgurbot12.LastLog1";
+
+/// Object path the [`LastLogService`] interface is served at
+pub static DBUS_PATH: &str = "/io/github/imgurbot12/LastLog1";
+
+static UTMP: &str = "/var/run/utmp";
+static WTMP: &str = "/var/log/wtmp";
+
+/* Types */
+
+/// Login as transferred over D-Bus: `(user, tty, host, login-time)`
+///
+/// Times are unix timestamps in seconds, with `0` meaning never.
+pub type DbusLogin = (String, String, String, u64);
+
+/// Session as transferred over D-Bus:
+/// `(user, tty, host, login-time, logout-time, end)`
+///
+/// The end is one of `active`, `logout`, `down` or `crash` (see
+/// [`SessionEnd::kind`](super::SessionEnd::kind)).
+pub type DbusSession = (String, String, String, u64, u64, String);
+
+/// D-Bus interface `io.github.imgurbot12.LastLog1`
+///
+/// Meant to be served by a privileged broker (see the `lastlog-dbus`
+/// binary) so desktop applets and other non-root services can query login
+/// data without read access to the databases themselves.
+///
+/// | Method                     | Signature            |
+/// | -------------------------- | -------------------- |
+/// | `GetLastLogin(username)`   | `s -> (ssst)`        |
+/// | `ListSessions()`           | ` -> a(ssstts)`      |
+/// | `ListHistory(limit)`       | `u -> a(ssstts)`     |
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```no_run
+/// use lastlog::{LastLogService, DBUS_NAME, DBUS_PATH};
+///
+/// let _conn = zbus::blocking::connection::Builder::session()
+///     .unwrap()
+///     .name(DBUS_NAME)
+///     .unwrap()
+///     .serve_at(DBUS_PATH, LastLogService::default())
+///     .unwrap()
+///     .build()
+///     .unwrap();
+/// loop {
+///     std::thread::park();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct LastLogService {
+    /// utmp database the current sessions are read from
+    pub utmp: String,
+    /// wtmp database the session history is read from
+    pub wtmp: String,
+}
+
+impl Default for LastLogService {
+    fn default() -> Self {
+        Self {
+            utmp: UTMP.to_owned(),
+            wtmp: WTMP.to_owned(),
+        }
+    }
+}
+
+/* Functions */
+
+// convert a login-time into unix seconds with zero meaning never
+fn seconds(time: &LoginTime) -> u64 {
+    time.unix_seconds().unwrap_or_default()
+}
+
+// convert an io error into a generic d-bus failure
+fn failed(err: std::io::Error) -> fdo::Error {
+    fdo::Error::Failed(err.to_string())
+}
+
+// flatten a record into its d-bus representation
+fn to_login(record: &Record) -> DbusLogin {
+    (
+        record.name.clone(),
+        record.tty.clone(),
+        record.host.clone(),
+        seconds(&record.last_login),
+    )
+}
+
+// flatten a session into its d-bus representation
+fn to_session(session: &Session) -> DbusSession {
+    let (user, tty, host, login) = to_login(&session.record);
+    let logout = LoginTime::from(session.end.time());
+    (
+        user,
+        tty,
+        host,
+        login,
+        seconds(&logout),
+        session.end.kind().to_owned(),
+    )
+}
+
+/* Implementation */
+
+#[interface(name = "io.github.imgurbot12.LastLog1")]
+impl LastLogService {
+    /// Retrieve the latest login of the given username
+    fn get_last_login(&self, username: &str) -> fdo::Result<DbusLogin> {
+        let record = search_username(username).map_err(failed)?;
+        Ok(to_login(&record))
+    }
+
+    /// List the sessions that are currently logged in
+    fn list_sessions(&self) -> fdo::Result<Vec<DbusSession>> {
+        let records = current_sessions_from(&self.utmp).map_err(failed)?;
+        Ok(records
+            .into_iter()
+            .map(|record| {
+                to_session(&Session {
+                    record,
+                    end: SessionEnd::Active,
+                    boot_id: None,
+                })
+            })
+            .collect())
+    }
+
+    /// List up to `limit` user sessions from wtmp history, newest first
+    ///
+    /// A limit of zero returns the entire history.
+    fn list_history(&self, limit: u32) -> fdo::Result<Vec<DbusSession>> {
+        let limit = match limit {
+            0 => usize::MAX,
+            n => n as usize,
+        };
+        Ok(sessions_from(&self.wtmp)
+            .map_err(failed)?
+            .filter(|s| !s.is_system())
+            .take(limit)
+            .map(|s| to_session(&s))
+            .collect())
+    }
+}
@@ -0,0 +1,281 @@
+/*
+ *  C ABI bindings for embedding the crate into C/C++ programs
+ */
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::io::Error;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+use super::common::*;
+use super::{iter_accounts, search_uid, search_username};
+
+/* Variables */
+
+// errno reported for failures that do not originate from the os
+const EINVAL: c_int = 22;
+
+// errno reported when a lookup panicked instead of unwinding into c
+const EIO: c_int = 5;
+
+/* Types */
+
+/// Opaque login record owned by the caller
+///
+/// Must be released with `lastlog_record_free`, except when borrowed
+/// from a `LastlogRecordList`.
+pub struct LastlogRecord {
+    record: Record,
+    name: CString,
+    tty: CString,
+    host: CString,
+}
+
+/// Opaque list of login records owned by the caller
+///
+/// Must be released with `lastlog_list_free`.
+pub struct LastlogRecordList {
+    records: Vec<LastlogRecord>,
+}
+
+/* Functions */
+
+// convert a string into a c-string, dropping any interior nul bytes
+fn c_string(value: &str) -> CString {
+    CString::new(value.replace('\0', "")).unwrap_or_default()
+}
+
+// convert an error into the errno style status returned to c callers
+fn status(err: Error) -> c_int {
+    err.raw_os_error().unwrap_or(EINVAL)
+}
+
+// wrap a record together with the c-strings handed out by the accessors
+fn wrap(record: Record) -> LastlogRecord {
+    LastlogRecord {
+        name: c_string(&record.name),
+        tty: c_string(&record.tty),
+        host: c_string(&record.host),
+        record,
+    }
+}
+
+// run a lookup storing its result in `out`, which is reset to `NULL` and
+// reported as `EIO` when the lookup panics
+unsafe fn guard<T>(out: *mut *mut T, lookup: impl FnOnce() -> c_int) -> c_int {
+    if out.is_null() {
+        return EINVAL;
+    }
+    catch_unwind(AssertUnwindSafe(lookup)).unwrap_or_else(|_| {
+        *out = ptr::null_mut();
+        EIO
+    })
+}
+
+// store a found record in the output pointer and return its status
+unsafe fn store(result: std::io::Result<Record>, out: *mut *mut LastlogRecord) -> c_int {
+    if out.is_null() {
+        return EINVAL;
+    }
+    match result {
+        Ok(record) => {
+            *out = Box::into_raw(Box::new(wrap(record)));
+            0
+        }
+        Err(err) => {
+            *out = ptr::null_mut();
+            status(err)
+        }
+    }
+}
+
+/// Find the latest login of the given user-id with an auto-selected database
+///
+/// Returns `0` and stores a new record in `out` on success, otherwise an
+/// errno value such as `ENOENT` (`EINVAL` for parsing failures or unknown
+/// users, `EIO` for internal errors) and stores `NULL`.
+///
+/// # Safety
+///
+/// `out` must be a valid pointer to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn lastlog_search_uid(uid: u32, out: *mut *mut LastlogRecord) -> c_int {
+    guard(out, || store(search_uid(uid), out))
+}
+
+/// Find the latest login of the given username with an auto-selected database
+///
+/// Returns a status like `lastlog_search_uid`.
+///
+/// # Safety
+///
+/// `username` must be a valid nul terminated string and `out` a valid
+/// pointer to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn lastlog_search_username(
+    username: *const c_char,
+    out: *mut *mut LastlogRecord,
+) -> c_int {
+    if username.is_null() {
+        return EINVAL;
+    }
+    let Ok(username) = CStr::from_ptr(username).to_str() else {
+        return EINVAL;
+    };
+    guard(out, || store(search_username(username), out))
+}
+
+/// Collect the latest login of every user with an auto-selected database
+///
+/// Returns `0` and stores a new list in `out` on success, otherwise an
+/// errno value and stores `NULL`.
+///
+/// # Safety
+///
+/// `out` must be a valid pointer to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn lastlog_iter_accounts(out: *mut *mut LastlogRecordList) -> c_int {
+    guard(out, || match iter_accounts() {
+        Ok(records) => {
+            let records = records.into_iter().map(wrap).collect();
+            *out = Box::into_raw(Box::new(LastlogRecordList { records }));
+            0
+        }
+        Err(err) => {
+            *out = ptr::null_mut();
+            status(err)
+        }
+    })
+}
+
+/// Retrieve the number of records within the list
+///
+/// # Safety
+///
+/// `list` must be a list returned by this library or `NULL`.
+#[no_mangle]
+pub unsafe extern "C" fn lastlog_list_len(list: *const LastlogRecordList) -> usize {
+    list.as_ref().map_or(0, |l| l.records.len())
+}
+
+/// Borrow the record at the given index, `NULL` when out of bounds
+///
+/// The record remains owned by the list and must not be freed.
+///
+/// # Safety
+///
+/// `list` must be a list returned by this library or `NULL`.
+#[no_mangle]
+pub unsafe extern "C" fn lastlog_list_get(
+    list: *const LastlogRecordList,
+    index: usize,
+) -> *const LastlogRecord {
+    list.as_ref()
+        .and_then(|l| l.records.get(index))
+        .map_or(ptr::null(), |r| r as *const LastlogRecord)
+}
+
+/// Release a list and every record it holds
+///
+/// # Safety
+///
+/// `list` must be a list returned by this library or `NULL`, and is
+/// invalid afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn lastlog_list_free(list: *mut LastlogRecordList) {
+    if !list.is_null() {
+        drop(Box::from_raw(list));
+    }
+}
+
+/// Release a record returned by one of the search functions
+///
+/// # Safety
+///
+/// `record` must be an owned record returned by this library or `NULL`,
+/// and is invalid afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn lastlog_record_free(record: *mut LastlogRecord) {
+    if !record.is_null() {
+        drop(Box::from_raw(record));
+    }
+}
+
+/// Retrieve the utmp(5) record-type (`ut_type`) of the record
+///
+/// # Safety
+///
+/// `record` must be a valid record returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn lastlog_record_type(record: *const LastlogRecord) -> c_int {
+    i32::from((*record).record.rtype)
+}
+
+/// Retrieve the user-id of the record, `-1` when unknown
+///
+/// # Safety
+///
+/// `record` must be a valid record returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn lastlog_record_uid(record: *const LastlogRecord) -> i64 {
+    (*record).record.uid.map_or(-1, i64::from)
+}
+
+/// Retrieve the process-id of the record, `-1` when unknown
+///
+/// # Safety
+///
+/// `record` must be a valid record returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn lastlog_record_pid(record: *const LastlogRecord) -> i32 {
+    (*record).record.pid.unwrap_or(-1)
+}
+
+/// Retrieve the login-time as a unix timestamp, `0` if never logged in
+///
+/// # Safety
+///
+/// `record` must be a valid record returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn lastlog_record_time(record: *const LastlogRecord) -> i64 {
+    (*record)
+        .record
+        .last_login
+        .unix_seconds()
+        .unwrap_or_default() as i64
+}
+
+/// Borrow the username of the record
+///
+/// The string remains owned by the record.
+///
+/// # Safety
+///
+/// `record` must be a valid record returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn lastlog_record_name(record: *const LastlogRecord) -> *const c_char {
+    (*record).name.as_ptr()
+}
+
+/// Borrow the tty of the record
+///
+/// The string remains owned by the record.
+///
+/// # Safety
+///
+/// `record` must be a valid record returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn lastlog_record_tty(record: *const LastlogRecord) -> *const c_char {
+    (*record).tty.as_ptr()
+}
+
+/// Borrow the remote host of the record
+///
+/// The string remains owned by the record.
+///
+/// # Safety
+///
+/// `record` must be a valid record returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn lastlog_record_host(record: *const LastlogRecord) -> *const c_char {
+    (*record).host.as_ptr()
+}
@@ -0,0 +1,482 @@
+/*
+ *  Small expression language used to filter record streams at runtime
+ */
+use std::io::{Error, ErrorKind, Result};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+use super::common::*;
+use super::format::{parse_duration, parse_time};
+use super::tty::normalize_tty;
+
+/* Types */
+
+// single lexical token of an expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Text(String),
+    Symbol(&'static str),
+}
+
+// record field referenced by a comparison
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    User,
+    Tty,
+    Host,
+    Type,
+    Machine,
+    Origin,
+    Uid,
+    Pid,
+    Age,
+    Time,
+}
+
+// comparison operator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Match,
+    NotMatch,
+}
+
+// value compared against, already converted to the type of its field
+#[derive(Debug, Clone)]
+enum Value {
+    Text(String),
+    Number(i64),
+    Duration(Duration),
+    Time(SystemTime),
+    #[cfg(not(feature = "regex"))]
+    Contains(String),
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+}
+
+// parsed expression tree
+#[derive(Debug, Clone)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Test(Field, Op, Value),
+}
+
+// recursive descent parser over the token list
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    end: usize,
+}
+
+/// Filter expression such as `user != "root" && age > 30d && tty =~ "pts"`
+///
+/// Comparisons are combined with `&&`, `||`, `!` and parentheses, where
+/// `&&` binds tighter than `||`. Text values are quoted or written as a
+/// bare word, and lengths of time are written as a number followed by a
+/// unit (`s`, `m`, `h`, `d`, `w`).
+///
+/// | Field                                              | Kind     | Operators                   |
+/// | -------------------------------------------------- | -------- | --------------------------- |
+/// | `user`, `tty`, `host`, `type`, `machine`, `origin` | text     | `==` `!=` `=~` `!~`         |
+/// | `uid`, `pid`                                       | number   | `==` `!=` `<` `<=` `>` `>=` |
+/// | `age`                                              | duration | `==` `!=` `<` `<=` `>` `>=` |
+/// | `time`                                             | time     | `==` `!=` `<` `<=` `>` `>=` |
+///
+/// `=~` matches a regular expression with the `regex` feature and checks
+/// for a substring otherwise. `time` accepts anything [`parse_time`]
+/// does, and `age` is the time since the last login, which is infinite
+/// for records that never logged in. Comparisons against fields the
+/// record does not have, such as a missing `uid`, only pass for `!=`.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{Filter, Record};
+///
+/// let filter = Filter::parse(r#"user != "root" && age > 30d && tty =~ "pts""#).unwrap();
+/// let root = Record::builder().name("root").tty("pts/0").build();
+/// let user = Record::builder().name("foo").tty("pts/1").build();
+/// assert!(!filter.matches(&root));
+/// assert!(filter.matches(&user));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Filter {
+    expr: Expr,
+}
+
+/* Functions */
+
+// build the error reported for invalid expressions
+fn invalid(pos: usize, message: impl std::fmt::Display) -> Error {
+    Error::new(
+        ErrorKind::InvalidInput,
+        format!("invalid filter at {pos}: {message}"),
+    )
+}
+
+// split the expression into tokens alongside their offsets
+fn tokenize(text: &str) -> Result<Vec<(usize, Token)>> {
+    static SYMBOLS: [&str; 15] = [
+        "&&", "||", "==", "!=", "<=", ">=", "=~", "!~", "<", ">", "!", "(", ")", "=", "~",
+    ];
+    let mut tokens = vec![];
+    let mut chars = text.char_indices().peekable();
+    while let Some(&(pos, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        if c == '"' || c == '\'' {
+            chars.next();
+            let mut value = String::new();
+            loop {
+                match chars.next() {
+                    Some((_, q)) if q == c => break,
+                    Some((_, '\\')) => match chars.next() {
+                        Some((_, e)) => value.push(e),
+                        None => return Err(invalid(pos, "unterminated string")),
+                    },
+                    Some((_, v)) => value.push(v),
+                    None => return Err(invalid(pos, "unterminated string")),
+                }
+            }
+            tokens.push((pos, Token::Text(value)));
+            continue;
+        }
+        if let Some(symbol) = SYMBOLS.iter().find(|s| text[pos..].starts_with(**s)) {
+            // single `=` and `~` are only accepted as part of a longer operator
+            if matches!(*symbol, "=" | "~") {
+                return Err(invalid(pos, format!("unexpected {c:?}")));
+            }
+            for _ in 0..symbol.len() {
+                chars.next();
+            }
+            tokens.push((pos, Token::Symbol(symbol)));
+            continue;
+        }
+        let mut word = String::new();
+        while let Some(&(_, c)) = chars.peek() {
+            if c.is_whitespace() || c == '"' || c == '\'' || "&|=!<>~()".contains(c) {
+                break;
+            }
+            word.push(c);
+            chars.next();
+        }
+        if word.is_empty() {
+            return Err(invalid(pos, format!("unexpected {c:?}")));
+        }
+        tokens.push((pos, Token::Word(word)));
+    }
+    Ok(tokens)
+}
+
+// compare two ordered values using the given operator
+fn compare<T: PartialOrd>(op: Op, a: T, b: T) -> bool {
+    match op {
+        Op::Eq => a == b,
+        Op::Ne => a != b,
+        Op::Lt => a < b,
+        Op::Le => a <= b,
+        Op::Gt => a > b,
+        Op::Ge => a >= b,
+        Op::Match | Op::NotMatch => false,
+    }
+}
+
+// evaluate a single comparison against the record
+fn test(field: Field, op: Op, value: &Value, record: &Record, now: SystemTime) -> bool {
+    let passed = match (field, value) {
+        (Field::Tty, Value::Text(tty)) => field
+            .text(record)
+            .map(|t| compare(op, t.as_str(), normalize_tty(tty).as_str())),
+        (Field::Type, Value::Text(name)) => field
+            .text(record)
+            .map(|t| t.eq_ignore_ascii_case(name) == (op == Op::Eq)),
+        (_, Value::Text(text)) => field.text(record).map(|t| compare(op, &t, text)),
+        #[cfg(not(feature = "regex"))]
+        (_, Value::Contains(part)) => field
+            .text(record)
+            .map(|t| t.contains(part.as_str()) == (op == Op::Match)),
+        #[cfg(feature = "regex")]
+        (_, Value::Regex(regex)) => field
+            .text(record)
+            .map(|t| regex.is_match(&t) == (op == Op::Match)),
+        (Field::Uid, Value::Number(n)) => record.uid.map(|uid| compare(op, uid as i64, *n)),
+        (Field::Pid, Value::Number(n)) => record.pid.map(|pid| compare(op, pid as i64, *n)),
+        (_, Value::Duration(limit)) => {
+            let age = match &record.last_login {
+                LoginTime::Last(time) => now.duration_since(*time).unwrap_or_default(),
+                LoginTime::Never => Duration::MAX,
+            };
+            Some(compare(op, age, *limit))
+        }
+        (_, Value::Time(time)) => match &record.last_login {
+            LoginTime::Last(last) => Some(compare(op, last, time)),
+            LoginTime::Never => None,
+        },
+        (_, Value::Number(_)) => None,
+    };
+    // missing values only differ from anything they are compared with
+    passed.unwrap_or(op == Op::Ne)
+}
+
+/* Implementation */
+
+impl Parser {
+    // offset of the next token, or the end of the expression
+    fn offset(&self) -> usize {
+        self.tokens.get(self.pos).map_or(self.end, |(p, _)| *p)
+    }
+
+    // consume the next token if it is the given symbol
+    fn eat(&mut self, symbol: &str) -> bool {
+        match self.tokens.get(self.pos) {
+            Some((_, Token::Symbol(s))) if *s == symbol => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|(_, t)| t.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        let mut expr = self.and()?;
+        while self.eat("||") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut expr = self.unary()?;
+        while self.eat("&&") {
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        if self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.eat("(") {
+            let expr = self.or()?;
+            if !self.eat(")") {
+                return Err(invalid(self.offset(), "expected `)`"));
+            }
+            return Ok(expr);
+        }
+        self.test()
+    }
+
+    fn test(&mut self) -> Result<Expr> {
+        let pos = self.offset();
+        let field = match self.next() {
+            Some(Token::Word(name)) => Field::from_name(&name)
+                .ok_or_else(|| invalid(pos, format!("unknown field {name:?}")))?,
+            Some(_) => return Err(invalid(pos, "expected a field name")),
+            None => return Err(invalid(pos, "unexpected end of filter")),
+        };
+        let pos = self.offset();
+        let op = match self.next() {
+            Some(Token::Symbol(symbol)) => Op::from_symbol(symbol),
+            _ => None,
+        };
+        let op = op.ok_or_else(|| invalid(pos, "expected a comparison operator"))?;
+        let pos = self.offset();
+        let value = match self.next() {
+            Some(Token::Word(value)) | Some(Token::Text(value)) => value,
+            _ => return Err(invalid(pos, "expected a value")),
+        };
+        let value = field
+            .value(op, value)
+            .map_err(|message| invalid(pos, message))?;
+        Ok(Expr::Test(field, op, value))
+    }
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "user" | "name" => Self::User,
+            "tty" | "line" => Self::Tty,
+            "host" => Self::Host,
+            "type" => Self::Type,
+            "machine" => Self::Machine,
+            "origin" => Self::Origin,
+            "uid" => Self::Uid,
+            "pid" => Self::Pid,
+            "age" => Self::Age,
+            "time" => Self::Time,
+            _ => return None,
+        })
+    }
+
+    // check the operator and convert the value into the kind of the field
+    fn value(&self, op: Op, value: String) -> std::result::Result<Value, String> {
+        let text = matches!(
+            self,
+            Self::User | Self::Tty | Self::Host | Self::Type | Self::Machine | Self::Origin
+        );
+        match (text, op) {
+            (true, Op::Match | Op::NotMatch) => {
+                #[cfg(feature = "regex")]
+                return regex::Regex::new(&value)
+                    .map(Value::Regex)
+                    .map_err(|e| e.to_string());
+                #[cfg(not(feature = "regex"))]
+                return Ok(Value::Contains(value));
+            }
+            (true, Op::Eq | Op::Ne) => return Ok(Value::Text(value)),
+            (true, _) => return Err("text fields only support == != =~ !~".to_owned()),
+            (false, Op::Match | Op::NotMatch) => {
+                return Err("=~ and !~ only apply to text fields".to_owned())
+            }
+            (false, _) => {}
+        }
+        match self {
+            Self::Uid | Self::Pid => value
+                .parse()
+                .map(Value::Number)
+                .map_err(|_| format!("invalid number {value:?}")),
+            Self::Age => parse_duration(&value)
+                .map(Value::Duration)
+                .ok_or_else(|| format!("invalid duration {value:?}")),
+            _ => parse_time(&value)
+                .map(Value::Time)
+                .ok_or_else(|| format!("invalid time {value:?}")),
+        }
+    }
+
+    // retrieve the text value of the field from the record
+    fn text(&self, record: &Record) -> Option<String> {
+        Some(match self {
+            Self::User => record.name.clone(),
+            Self::Tty => normalize_tty(&record.tty),
+            Self::Host => record.host.clone(),
+            Self::Type => match record.rtype.name() {
+                Some(name) => name.to_owned(),
+                None => i32::from(record.rtype).to_string(),
+            },
+            Self::Machine => record.machine.clone()?,
+            Self::Origin => record.origin().name().to_owned(),
+            _ => return None,
+        })
+    }
+}
+
+impl Op {
+    fn from_symbol(symbol: &str) -> Option<Self> {
+        Some(match symbol {
+            "==" => Self::Eq,
+            "!=" => Self::Ne,
+            "<" => Self::Lt,
+            "<=" => Self::Le,
+            ">" => Self::Gt,
+            ">=" => Self::Ge,
+            "=~" => Self::Match,
+            "!~" => Self::NotMatch,
+            _ => return None,
+        })
+    }
+}
+
+impl Expr {
+    fn eval(&self, record: &Record, now: SystemTime) -> bool {
+        match self {
+            Self::Or(a, b) => a.eval(record, now) || b.eval(record, now),
+            Self::And(a, b) => a.eval(record, now) && b.eval(record, now),
+            Self::Not(expr) => !expr.eval(record, now),
+            Self::Test(field, op, value) => test(*field, *op, value, record, now),
+        }
+    }
+}
+
+impl Filter {
+    /// Parse a filter expression, reporting the position of any error
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use lastlog::Filter;
+    ///
+    /// assert!(Filter::parse("uid >= 1000 && (host == \"\" || origin == ssh)").is_ok());
+    /// assert!(Filter::parse("uid > ten").is_err());
+    /// assert!(Filter::parse("shell == bash").is_err());
+    /// ```
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut parser = Parser {
+            tokens: tokenize(text)?,
+            pos: 0,
+            end: text.len(),
+        };
+        let expr = parser.or()?;
+        if parser.pos < parser.tokens.len() {
+            return Err(invalid(parser.offset(), "unexpected trailing input"));
+        }
+        Ok(Self { expr })
+    }
+
+    /// Check if the record passes the filter
+    pub fn matches(&self, record: &Record) -> bool {
+        self.matches_at(record, SystemTime::now())
+    }
+
+    /// Check if the record passes the filter, measuring ages from `now`
+    pub fn matches_at(&self, record: &Record, now: SystemTime) -> bool {
+        self.expr.eval(record, now)
+    }
+
+    /// Keep only the records of the stream passing the filter
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use lastlog::Filter;
+    ///
+    /// let filter = Filter::parse("uid >= 1000 && age < 90d").unwrap();
+    /// if let Ok(records) = lastlog::iter_accounts() {
+    ///     for record in filter.apply(records) {
+    ///         println!("{} {}", record.name, record.host);
+    ///     }
+    /// }
+    /// ```
+    pub fn apply<'a, I>(&'a self, records: I) -> impl Iterator<Item = Record> + 'a
+    where
+        I: IntoIterator<Item = Record>,
+        I::IntoIter: 'a,
+    {
+        let now = SystemTime::now();
+        records
+            .into_iter()
+            .filter(move |record| self.matches_at(record, now))
+    }
+}
+
+impl FromStr for Filter {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
@@ -0,0 +1,163 @@
+/*
+ *  Structured findings shared by every audit analysis
+ */
+use std::fmt;
+use std::io::{Error, ErrorKind, Result};
+use std::str::FromStr;
+
+use super::common::*;
+
+/* Types */
+
+/// How urgently a [`Finding`] should be looked at, ordered by urgency
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// noteworthy without requiring any action
+    Info,
+    /// worth reviewing during the next audit
+    Low,
+    /// likely policy violation that should be resolved
+    Medium,
+    /// probable attack or compromise
+    High,
+    /// ongoing compromise requiring immediate action
+    Critical,
+}
+
+/// Analysis a [`Finding`] was reported by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FindingKind {
+    /// account idle for longer than a [`crate::DormancyPolicy`] allows
+    Dormant,
+    /// repeated failed logins found by a [`crate::BruteForceDetector`]
+    BruteForce,
+    /// login unlike the behavior learned by a [`crate::LoginBaseline`]
+    Anomaly,
+    /// records removed, altered or forged found by a [`crate::TamperDetector`]
+    Tampering,
+}
+
+/// Single result of an audit analysis alongside the records proving it
+///
+/// Every analysis reports its results as findings, so alerts, reports and
+/// suppression rules handle them uniformly regardless of their source.
+/// Custom analyses can build findings of their own using [`Finding::new`].
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{Finding, FindingKind, Record, Severity};
+///
+/// let attempt = Record::builder().name("root").host("10.0.0.5").build();
+/// let finding = Finding::new(FindingKind::BruteForce, Severity::High, "10.0.0.5")
+///     .description("50 failed logins within 5 minutes")
+///     .evidence(vec![attempt]);
+/// assert!(finding.severity >= Severity::Medium);
+/// assert_eq!(finding.to_string(), "high brute-force 10.0.0.5: 50 failed logins within 5 minutes");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// how urgently the finding should be looked at
+    pub severity: Severity,
+    /// analysis the finding was reported by
+    pub kind: FindingKind,
+    /// username, host or file the finding concerns
+    pub subject: String,
+    /// records the finding is based on
+    pub evidence: Vec<Record>,
+    /// human readable explanation of the finding
+    pub description: String,
+}
+
+/* Implementation */
+
+impl Severity {
+    /// Lowercase name of the severity
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+            Self::Critical => "critical",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FindingKind {
+    /// Lowercase name of the analysis
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Dormant => "dormant",
+            Self::BruteForce => "brute-force",
+            Self::Anomaly => "anomaly",
+            Self::Tampering => "tampering",
+        }
+    }
+}
+
+impl FromStr for FindingKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "dormant" => Ok(Self::Dormant),
+            "brute-force" => Ok(Self::BruteForce),
+            "anomaly" => Ok(Self::Anomaly),
+            "tampering" => Ok(Self::Tampering),
+            _ => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("unknown finding kind: {s}"),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for FindingKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl Finding {
+    /// Report a finding about the given subject without any evidence
+    pub fn new(kind: FindingKind, severity: Severity, subject: impl Into<String>) -> Self {
+        Self {
+            severity,
+            kind,
+            subject: subject.into(),
+            evidence: vec![],
+            description: String::new(),
+        }
+    }
+
+    /// Set the human readable explanation of the finding
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    /// Set the records the finding is based on
+    pub fn evidence(mut self, evidence: Vec<Record>) -> Self {
+        self.evidence = evidence;
+        self
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.severity, self.kind, self.subject)?;
+        if !self.description.is_empty() {
+            write!(f, ": {}", self.description)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,366 @@
+/*
+ *  Minimal strftime-style formatting for login-times
+ */
+use std::fmt::Write;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/* Variables */
+
+/// Time format used by `lastlog(8)` for the `Latest` column
+pub static LASTLOG_TIME: &str = "%a %b %e %H:%M:%S %z %Y";
+
+/// Time format used by `last(1)` for session start-times
+pub static LAST_TIME: &str = "%a %b %e %H:%M";
+
+/// Time format used by `last(1)` when passed `-F` for full times
+pub static LAST_FULL_TIME: &str = "%a %b %e %H:%M:%S %Y";
+
+/// RFC 3339 time format used when exporting login-times (in UTC)
+pub static RFC3339_TIME: &str = "%Y-%m-%dT%H:%M:%SZ";
+
+static WEEKDAYS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+
+static MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+#[cfg(feature = "humanize")]
+static UNITS: [(&str, u64); 6] = [
+    ("year", 365 * 86400),
+    ("month", 30 * 86400),
+    ("week", 7 * 86400),
+    ("day", 86400),
+    ("hour", 3600),
+    ("minute", 60),
+];
+
+/* Types */
+
+/// Broken-down calendar representation of a timestamp
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    pub weekday: u32,
+    pub yearday: u32,
+    pub offset: i32,
+    pub timestamp: i64,
+}
+
+impl DateTime {
+    /// Convert a system-time into its calendar representation at the given
+    /// utc-offset (in seconds)
+    pub fn new(time: SystemTime, offset: i32) -> Self {
+        let timestamp = match time.duration_since(UNIX_EPOCH) {
+            Ok(d) => d.as_secs() as i64,
+            Err(e) => -(e.duration().as_secs_f64().ceil() as i64),
+        };
+        let local = timestamp + offset as i64;
+        let days = local.div_euclid(86400);
+        let secs = local.rem_euclid(86400) as u32;
+        let (year, month, day) = civil_from_days(days);
+        let yearday = (days - days_from_civil(year, 1, 1)) as u32;
+        Self {
+            year,
+            month,
+            day,
+            hour: secs / 3600,
+            minute: secs % 3600 / 60,
+            second: secs % 60,
+            weekday: (days + 4).rem_euclid(7) as u32,
+            yearday,
+            offset,
+            timestamp,
+        }
+    }
+}
+
+/* Functions */
+
+/// Retrieve the utc-offset (in seconds) of the system local timezone
+/// at the given time
+///
+/// The `TZ` environment variable is honored through `localtime_r(3)` when
+/// the `libc` feature is enabled, otherwise the timezone is resolved from
+/// `TZ` and the system tzdata by the crate itself.
+#[cfg(feature = "libc")]
+pub fn local_offset(time: SystemTime) -> i32 {
+    let ts = DateTime::new(time, 0).timestamp as libc::time_t;
+    unsafe {
+        let mut tm: libc::tm = std::mem::zeroed();
+        if libc::localtime_r(&ts, &mut tm).is_null() {
+            return 0;
+        }
+        tm.tm_gmtoff as i32
+    }
+}
+
+#[cfg(not(feature = "libc"))]
+pub fn local_offset(time: SystemTime) -> i32 {
+    super::zone::local_offset(DateTime::new(time, 0).timestamp)
+}
+
+// convert days since unix-epoch into (year, month, day)
+// (http://howardhinnant.github.io/date_algorithms.html#civil_from_days)
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+// convert (year, month, day) into days since unix-epoch
+pub(crate) fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let month = month as i64;
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+// write utc-offset in `+hhmm` form
+fn write_offset(out: &mut String, offset: i32, colon: bool) -> std::fmt::Result {
+    let sign = if offset < 0 { '-' } else { '+' };
+    let offset = offset.unsigned_abs();
+    let sep = if colon { ":" } else { "" };
+    write!(
+        out,
+        "{sign}{:02}{sep}{:02}",
+        offset / 3600,
+        offset % 3600 / 60
+    )
+}
+
+/// Format a calendar time using a subset of `strftime(3)` specifiers
+///
+/// Supported specifiers are `%a %A %b %h %B %c %C %d %D %e %F %H %I %j %k %l
+/// %m %M %n %p %R %s %S %t %T %u %w %y %Y %z %:z %Z %%`. Unsupported
+/// specifiers are written out unchanged.
+pub fn strftime(dt: &DateTime, fmt: &str) -> String {
+    let mut out = String::with_capacity(fmt.len() * 2);
+    let mut chars = fmt.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        let hour12 = match dt.hour % 12 {
+            0 => 12,
+            h => h,
+        };
+        let _ = match chars.next() {
+            Some('a') => write!(out, "{}", &WEEKDAYS[dt.weekday as usize][..3]),
+            Some('A') => write!(out, "{}", WEEKDAYS[dt.weekday as usize]),
+            Some('b') | Some('h') => write!(out, "{}", &MONTHS[dt.month as usize - 1][..3]),
+            Some('B') => write!(out, "{}", MONTHS[dt.month as usize - 1]),
+            Some('c') => write!(out, "{}", strftime(dt, "%a %b %e %H:%M:%S %Y")),
+            Some('C') => write!(out, "{:02}", dt.year.div_euclid(100)),
+            Some('d') => write!(out, "{:02}", dt.day),
+            Some('D') => write!(out, "{}", strftime(dt, "%m/%d/%y")),
+            Some('e') => write!(out, "{:>2}", dt.day),
+            Some('F') => write!(out, "{}", strftime(dt, "%Y-%m-%d")),
+            Some('H') => write!(out, "{:02}", dt.hour),
+            Some('I') => write!(out, "{:02}", hour12),
+            Some('j') => write!(out, "{:03}", dt.yearday + 1),
+            Some('k') => write!(out, "{:>2}", dt.hour),
+            Some('l') => write!(out, "{:>2}", hour12),
+            Some('m') => write!(out, "{:02}", dt.month),
+            Some('M') => write!(out, "{:02}", dt.minute),
+            Some('n') => out.write_char('\n'),
+            Some('p') => write!(out, "{}", if dt.hour < 12 { "AM" } else { "PM" }),
+            Some('R') => write!(out, "{}", strftime(dt, "%H:%M")),
+            Some('s') => write!(out, "{}", dt.timestamp),
+            Some('S') => write!(out, "{:02}", dt.second),
+            Some('t') => out.write_char('\t'),
+            Some('T') => write!(out, "{}", strftime(dt, "%H:%M:%S")),
+            Some('u') => write!(out, "{}", if dt.weekday == 0 { 7 } else { dt.weekday }),
+            Some('w') => write!(out, "{}", dt.weekday),
+            Some('y') => write!(out, "{:02}", dt.year.rem_euclid(100)),
+            Some('Y') => write!(out, "{}", dt.year),
+            Some('z') => write_offset(&mut out, dt.offset, false),
+            Some(':') => match chars.next() {
+                Some('z') => write_offset(&mut out, dt.offset, true),
+                Some(c) => write!(out, "%:{c}"),
+                None => write!(out, "%:"),
+            },
+            Some('Z') if dt.offset == 0 => write!(out, "UTC"),
+            Some('Z') => write_offset(&mut out, dt.offset, false),
+            Some('%') => write!(out, "%"),
+            Some(c) => write!(out, "%{c}"),
+            None => write!(out, "%"),
+        };
+    }
+    out
+}
+
+/// Describe a duration relative to now using its largest whole unit
+///
+/// Durations shorter than a minute are described as "just now".
+#[cfg(feature = "humanize")]
+pub fn humanize(duration: Duration, future: bool) -> String {
+    let secs = duration.as_secs();
+    let Some((unit, size)) = UNITS.iter().find(|(_, size)| secs >= *size) else {
+        return "just now".to_owned();
+    };
+    let count = secs / size;
+    let plural = if count == 1 { "" } else { "s" };
+    match future {
+        true => format!("in {count} {unit}{plural}"),
+        false => format!("{count} {unit}{plural} ago"),
+    }
+}
+
+// build a system-time from a calendar date/time interpreted as local time
+fn from_local(year: i64, month: u32, day: u32, secs: u32) -> Option<SystemTime> {
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || secs >= 86400 {
+        return None;
+    }
+    let utc = days_from_civil(year, month, day) * 86400 + secs as i64;
+    let guess = UNIX_EPOCH.checked_add(Duration::from_secs(utc.max(0) as u64))?;
+    let ts = utc - local_offset(guess) as i64;
+    UNIX_EPOCH.checked_add(Duration::from_secs(u64::try_from(ts).ok()?))
+}
+
+// parse `hh:mm[:ss]` into seconds since midnight
+fn parse_clock(text: &str) -> Option<u32> {
+    let mut parts = text.split(':').map(|p| p.parse::<u32>().ok());
+    let hour = parts.next()??;
+    let minute = parts.next()??;
+    let second = parts.next().unwrap_or(Some(0))?;
+    if parts.next().is_some() || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+    Some(hour * 3600 + minute * 60 + second)
+}
+
+// parse a `N unit` length of time such as `5days` or `2h`
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let split = text.find(|c: char| !c.is_ascii_digit())?;
+    let count: u64 = text[..split].parse().ok()?;
+    let unit = match text[split..].trim() {
+        "s" | "sec" | "secs" | "second" | "seconds" => 1,
+        "m" | "min" | "mins" | "minute" | "minutes" => 60,
+        "h" | "hour" | "hours" => 3600,
+        "d" | "day" | "days" => 86400,
+        "w" | "week" | "weeks" => 7 * 86400,
+        _ => return None,
+    };
+    Some(Duration::from_secs(count.checked_mul(unit)?))
+}
+
+// parse a relative `[+-]N unit` offset such as `-5days` or `+2h`
+fn parse_relative(text: &str, now: SystemTime) -> Option<SystemTime> {
+    let (sign, rest) = match text.as_bytes().first()? {
+        b'+' => (1, &text[1..]),
+        b'-' => (-1, &text[1..]),
+        _ => return None,
+    };
+    let offset = parse_duration(rest)?;
+    match sign {
+        1 => now.checked_add(offset),
+        _ => now.checked_sub(offset),
+    }
+}
+
+/// Parse a point in time in any of the formats accepted by `last(1)`
+///
+/// Supports `YYYYMMDDhhmmss`, `YYYY-MM-DD hh:mm[:ss]`, `YYYY-MM-DD`,
+/// `hh:mm[:ss]` (today), `now`, `today`, `yesterday` and relative
+/// offsets such as `-5days` or `+2hours`. Calendar times are interpreted
+/// in the system local timezone.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// let time = lastlog::parse_time("2024-03-05 10:32:11").unwrap();
+/// let recent = lastlog::parse_time("-2days").unwrap();
+/// assert!(time < recent);
+/// ```
+pub fn parse_time(text: &str) -> Option<SystemTime> {
+    let text = text.trim();
+    let now = SystemTime::now();
+    let today = || {
+        let dt = DateTime::new(now, local_offset(now));
+        (dt.year, dt.month, dt.day)
+    };
+    match text {
+        "now" => return Some(now),
+        "today" => {
+            let (y, m, d) = today();
+            return from_local(y, m, d, 0);
+        }
+        "yesterday" => {
+            let (y, m, d) = today();
+            return from_local(y, m, d, 0)?.checked_sub(Duration::from_secs(86400));
+        }
+        _ => {}
+    }
+    if let Some(time) = parse_relative(text, now) {
+        return Some(time);
+    }
+    // compact `YYYYMMDDhhmmss` form
+    if text.len() == 14 && text.bytes().all(|c| c.is_ascii_digit()) {
+        let num = |r: std::ops::Range<usize>| text[r].parse::<u32>().ok();
+        let clock = format!("{}:{}:{}", &text[8..10], &text[10..12], &text[12..14]);
+        return from_local(
+            num(0..4)? as i64,
+            num(4..6)?,
+            num(6..8)?,
+            parse_clock(&clock)?,
+        );
+    }
+    // `YYYY-MM-DD [hh:mm[:ss]]` or a plain clock time for today
+    let (date, clock) = match text.split_once([' ', 'T']) {
+        Some((date, clock)) => (Some(date), Some(clock)),
+        None if text.contains('-') => (Some(text), None),
+        None => (None, Some(text)),
+    };
+    let secs = match clock {
+        Some(clock) => parse_clock(clock)?,
+        None => 0,
+    };
+    let (year, month, day) = match date {
+        Some(date) => {
+            let mut parts = date.splitn(3, '-');
+            let year = parts.next()?.parse::<i64>().ok()?;
+            let month = parts.next()?.parse::<u32>().ok()?;
+            let day = parts.next()?.parse::<u32>().ok()?;
+            (year, month, day)
+        }
+        None => today(),
+    };
+    from_local(year, month, day, secs)
+}
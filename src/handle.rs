@@ -0,0 +1,182 @@
+/*
+ *  Thread-safe shared handle answering many queries from one database
+ */
+use std::fs::File;
+use std::io::{Error, ErrorKind, Result};
+use std::sync::Arc;
+
+use super::cache::{FileCache, Query};
+use super::common::*;
+use super::lastlog::LastLog;
+use super::users::user_source;
+use super::utmp::Utmp;
+use super::{get_module, verify_layout};
+
+/* Types */
+
+// database format the handle was opened with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Backend {
+    Utmp,
+    LastLog,
+}
+
+// state shared between every clone of a handle
+#[derive(Debug)]
+struct Inner {
+    backend: Backend,
+    path: String,
+    users: FileCache<(), Arc<UserMap>>,
+    logins: FileCache<Query, Vec<Record>>,
+}
+
+/// Shared handle to the selected login database
+///
+/// Module discovery happens once when the handle is opened, and the passwd
+/// map and latest-login answers are kept in memory until `/etc/passwd` or
+/// the database change modification-time or size. Clones share the same
+/// state behind an [`Arc`], so a single handle can serve every request of
+/// a multi-threaded service.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use std::thread;
+/// use lastlog::Lastlog;
+///
+/// if let Ok(handle) = Lastlog::open() {
+///     let workers: Vec<_> = (0..4)
+///         .map(|_| {
+///             let handle = handle.clone();
+///             thread::spawn(move || handle.search_uid(0).ok())
+///         })
+///         .collect();
+///     for worker in workers {
+///         println!("{:?}", worker.join().unwrap());
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Lastlog {
+    inner: Arc<Inner>,
+}
+
+/* Implementation */
+
+impl Backend {
+    // match the format of the given database against the supported modules
+    fn detect(path: &str) -> Result<Self> {
+        let mut f = File::open(path)?;
+        if (Utmp {}).is_valid(&mut f) {
+            return Ok(Self::Utmp);
+        }
+        if (LastLog {}).is_valid(&mut f) {
+            return Ok(Self::LastLog);
+        }
+        Err(Error::new(
+            ErrorKind::InvalidData,
+            "unsupported database format",
+        ))
+    }
+}
+
+impl Lastlog {
+    /// Open a handle to the auto-selected database like [`get_module`]
+    ///
+    /// [`get_module`]: crate::get_module
+    pub fn open() -> Result<Self> {
+        let (_, path) = get_module()?;
+        Self::with_path(&path)
+    }
+
+    /// Open a handle to the given database like [`detect_module`]
+    ///
+    /// [`detect_module`]: crate::detect_module
+    pub fn with_path(path: &str) -> Result<Self> {
+        verify_layout()?;
+        Ok(Self {
+            inner: Arc::new(Inner {
+                backend: Backend::detect(path)?,
+                path: path.to_owned(),
+                users: FileCache::new(),
+                logins: FileCache::new(),
+            }),
+        })
+    }
+
+    /// Path of the database the handle reads from
+    pub fn path(&self) -> &str {
+        &self.inner.path
+    }
+
+    // retrieve the passwd map, re-reading it only once passwd changed
+    fn users(&self) -> Arc<UserMap> {
+        // installed user sources are not backed by passwd and do their own caching
+        if user_source().is_some() {
+            return Arc::new(UserMap::system());
+        }
+        let read = || Ok::<_, Error>(Arc::new(UserMap::system()));
+        self.inner
+            .users
+            .get_or_try_insert_with((), &[PASSWD], read)
+            .unwrap_or_default()
+    }
+
+    // module reading the database format of the handle
+    fn module(&self) -> &'static dyn LoginDB {
+        match self.inner.backend {
+            Backend::Utmp => &Utmp {},
+            Backend::LastLog => &LastLog {},
+        }
+    }
+
+    // answer a query from the cache, reading the database on a miss
+    fn query<F>(&self, query: Query, read: F) -> Result<Vec<Record>>
+    where
+        F: FnOnce(&dyn LoginDB, &str, &UserMap) -> Result<Vec<Record>>,
+    {
+        let users = self.users();
+        let path = self.path();
+        self.inner
+            .logins
+            .get_or_try_insert_with(query, &[path, PASSWD], || read(self.module(), path, &users))
+    }
+
+    /// Find the last login for the given user-id
+    pub fn search_uid(&self, uid: u32) -> Result<Record> {
+        let records = self.query(Query::Uid(uid), |module, path, users| {
+            Ok(vec![module.search_uid_with_users(uid, path, users)?])
+        })?;
+        Ok(records[0].clone())
+    }
+
+    /// Find the last login for the given username
+    pub fn search_username(&self, username: &str) -> Result<Record> {
+        let query = Query::Username(username.to_owned());
+        let records = self.query(query, |module, path, users| {
+            Ok(vec![
+                module.search_username_with_users(username, path, users)?
+            ])
+        })?;
+        Ok(records[0].clone())
+    }
+
+    /// Iterate the latest logins of every user account
+    pub fn iter_accounts(&self) -> Result<Vec<Record>> {
+        self.query(Query::Accounts, |module, path, users| {
+            module.iter_accounts_with_users(path, users)
+        })
+    }
+
+    /// Drop every cached passwd entry and latest-login answer of the handle
+    ///
+    /// Changes to passwd or the database are already noticed using their
+    /// modification-time and size, so this is only needed when a file is
+    /// replaced while keeping both.
+    pub fn invalidate(&self) {
+        self.inner.users.clear();
+        self.inner.logins.clear();
+    }
+}
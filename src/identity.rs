@@ -0,0 +1,54 @@
+/*
+ *  Identity of the user running the current process
+ */
+use super::common::*;
+
+/* Types */
+
+/// Account the current process is running as
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// let me = lastlog::whoami();
+/// println!("uid={} name={:?}", me.uid, me.name);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Identity {
+    /// real user-id of the current process
+    pub uid: u32,
+    /// username of the user-id, `None` when it has no passwd entry
+    pub name: Option<String>,
+}
+
+/// Latest login of the current user alongside the identity it was resolved for
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SelfLogin {
+    /// account the search was made for
+    pub identity: Identity,
+    /// latest login of the account
+    pub record: Record,
+}
+
+/* Functions */
+
+// retrieve the real user-id of the current process
+#[cfg(feature = "libc")]
+fn current_uid() -> u32 {
+    unsafe { libc::getuid() }
+}
+
+// retrieve the real user-id of the current process without linking libc
+#[cfg(all(feature = "rustix", not(feature = "libc")))]
+fn current_uid() -> u32 {
+    rustix::process::getuid().as_raw()
+}
+
+/// Resolve the user-id and username the current process is running as
+pub fn whoami() -> Identity {
+    let uid = current_uid();
+    let name = UserMap::system().name(uid).map(|n| n.to_owned());
+    Identity { uid, name }
+}
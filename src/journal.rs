@@ -0,0 +1,253 @@
+/*
+ *  Login event extraction from `journalctl -o json` exports
+ */
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, ErrorKind, Result};
+
+use serde_json::{Map, Value};
+
+use super::common::*;
+
+/* Variables */
+
+// pam services whose sessions are interactive logins (unlike cron or sudo)
+static LOGIN_SERVICES: [&str; 7] = [
+    "login",
+    "sshd",
+    "gdm-password",
+    "gdm-autologin",
+    "lightdm",
+    "sddm",
+    "xrdp-sesman",
+];
+
+/* Types */
+
+/// Reader extracting login events from a `journalctl -o json` export
+///
+/// Every line of the export is a single journal entry. Entries that do not
+/// describe a login are skipped, while the supported messages are mapped
+/// into records:
+///
+/// | Journal message                                     | Record-type  |
+/// | --------------------------------------------------- | ------------ |
+/// | sshd `Accepted <method> for <user> from <host>`     | `User`       |
+/// | `pam_unix(login:session): session opened ...`       | `User`       |
+/// | sshd `Failed <method> for [invalid user] <user> ...`| `LoginProc`  |
+/// | `pam_unix(<service>:session): session closed ...`   | `DeadProc`   |
+///
+/// Only pam sessions of interactive login services (`login`, `sshd` and
+/// common display managers) are reported, so `cron` or `sudo` sessions
+/// are never mistaken for logins.
+///
+/// The login-time is taken from `__REALTIME_TIMESTAMP` and the pid from
+/// `_PID`, and logins on a `getty@<tty>` unit are assigned that tty.
+/// Failed logins use the same record-type as entries in btmp.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{JournalReader, RecordType};
+///
+/// let export = r#"{"__REALTIME_TIMESTAMP":"1709634731000000","_PID":"812","MESSAGE":"Accepted publickey for root from 10.0.0.5 port 50514 ssh2: ED25519"}"#;
+/// let records: Vec<_> = JournalReader::new(export.as_bytes())
+///     .filter_map(|r| r.ok())
+///     .collect();
+/// assert_eq!(records[0].rtype, RecordType::User);
+/// assert_eq!(records[0].host, "10.0.0.5");
+/// ```
+pub struct JournalReader<R: BufRead> {
+    reader: R,
+    users: HashMap<String, u32>,
+    line: String,
+    offset: u64,
+    done: bool,
+}
+
+/* Functions */
+
+// retrieve a journal field as text, joining byte-array encoded values
+fn field(entry: &Map<String, Value>, name: &str) -> Option<String> {
+    match entry.get(name)? {
+        Value::String(s) => Some(s.to_owned()),
+        Value::Array(bytes) => {
+            let bytes: Vec<u8> = bytes
+                .iter()
+                .filter_map(|b| b.as_u64())
+                .map(|b| b as u8)
+                .collect();
+            Some(String::from_utf8_lossy(&bytes).into_owned())
+        }
+        _ => None,
+    }
+}
+
+// retrieve the word directly following the given marker
+fn word_after<'a>(message: &'a str, marker: &str) -> Option<&'a str> {
+    let (_, rest) = message.split_once(marker)?;
+    rest.split_whitespace().next()
+}
+
+// parse `<user> from <host>` login messages written by sshd
+fn parse_sshd(message: &str) -> Option<(RecordType, String, String)> {
+    let (rtype, rest) = if let Some(rest) = message.strip_prefix("Accepted ") {
+        (RecordType::User, rest)
+    } else if let Some(rest) = message.strip_prefix("Failed ") {
+        (RecordType::LoginProc, rest)
+    } else {
+        return None;
+    };
+    let (_, rest) = rest.split_once(" for ")?;
+    let rest = rest.strip_prefix("invalid user ").unwrap_or(rest);
+    let (user, rest) = rest.split_once(" from ")?;
+    let host = rest.split_whitespace().next()?;
+    Some((rtype, user.to_owned(), host.to_owned()))
+}
+
+// parse `session opened/closed for user <name>` messages written by pam
+fn parse_pam(message: &str) -> Option<(RecordType, String, Option<u32>)> {
+    let service = word_after(message, "pam_unix(")?.split(':').next()?;
+    if !LOGIN_SERVICES.contains(&service) {
+        return None;
+    }
+    let rtype = if message.contains("session opened for user ") {
+        // sshd logins are already reported by their `Accepted` message
+        if service == "sshd" {
+            return None;
+        }
+        RecordType::User
+    } else if message.contains("session closed for user ") {
+        RecordType::DeadProc
+    } else {
+        return None;
+    };
+    let user = word_after(message, "for user ")?;
+    // newer versions of pam append the uid as `name(uid=1000)`
+    let (name, uid) = match user.split_once("(uid=") {
+        Some((name, uid)) => (name, uid.trim_end_matches(')').parse().ok()),
+        None => (user, None),
+    };
+    Some((rtype, name.to_owned(), uid))
+}
+
+/* Implementation */
+
+impl JournalReader<BufReader<File>> {
+    /// Open the given journal export for reading
+    pub fn open(fname: &str) -> Result<Self> {
+        let f = File::open(fname)?;
+        Ok(Self::new(BufReader::new(f)))
+    }
+}
+
+impl<R: BufRead> JournalReader<R> {
+    /// Read login events from any journal export using the system passwd database
+    pub fn new(reader: R) -> Self {
+        Self::with_users(reader, read_passwd_nmap())
+    }
+
+    /// Read login events using a pre-built username to uid map
+    pub fn with_users(reader: R, users: HashMap<String, u32>) -> Self {
+        Self {
+            reader,
+            users,
+            line: String::new(),
+            offset: 0,
+            done: false,
+        }
+    }
+
+    // map a single parsed journal entry into a login record
+    fn map_entry(&self, entry: &Map<String, Value>) -> Option<Record> {
+        let message = field(entry, "MESSAGE")?;
+        let pid = field(entry, "_PID").and_then(|p| p.parse().ok());
+        let usec: u64 = field(entry, "__REALTIME_TIMESTAMP")?.parse().ok()?;
+        let time = unix_timestamp((usec / 1_000_000) as u32, (usec % 1_000_000) as u32);
+        let (rtype, name, host, uid) = match parse_sshd(&message) {
+            Some((rtype, name, host)) => (rtype, name, host, None),
+            None => {
+                let (rtype, name, uid) = parse_pam(&message)?;
+                let host = word_after(&message, "rhost=").unwrap_or_default();
+                (rtype, name, host.to_owned(), uid)
+            }
+        };
+        let uid = uid.or_else(|| self.users.get(&name).copied());
+        let tty = field(entry, "_SYSTEMD_UNIT")
+            .filter(|u| u.starts_with("getty@"))
+            .map(|u| u[6..].trim_end_matches(".service").to_owned())
+            .unwrap_or_default();
+        Some(
+            Record::builder()
+                .rtype(rtype)
+                .uid(uid)
+                .name(name)
+                .tty(tty)
+                .host(host)
+                .pid(pid)
+                .last_login(time)
+                .build(),
+        )
+    }
+}
+
+impl<R: BufRead> Iterator for JournalReader<R> {
+    type Item = std::result::Result<Record, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            self.line.clear();
+            let offset = self.offset;
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => self.done = true,
+                Ok(n) => {
+                    self.offset += n as u64;
+                    if self.line.trim().is_empty() {
+                        continue;
+                    }
+                    let entry: Map<String, Value> = match serde_json::from_str(&self.line) {
+                        Ok(entry) => entry,
+                        Err(e) => {
+                            let reason = format!("invalid journal entry: {e}");
+                            warn_event!(offset, "{reason}");
+                            return Some(Err(ParseError::new(
+                                offset,
+                                ErrorKind::InvalidData,
+                                reason,
+                            )));
+                        }
+                    };
+                    if let Some(record) = self.map_entry(&entry) {
+                        return Some(Ok(record));
+                    }
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(ParseError::from_io(offset, e)));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Extract every login event contained within a `journalctl -o json` export
+///
+/// Entries that fail to parse are skipped.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// if let Ok(records) = lastlog::read_journal("/tmp/journal.json") {
+///     for record in records {
+///         println!("{record}");
+///     }
+/// }
+/// ```
+pub fn read_journal(fname: &str) -> Result<Vec<Record>> {
+    Ok(JournalReader::open(fname)?.filter_map(|r| r.ok()).collect())
+}
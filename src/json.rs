@@ -0,0 +1,142 @@
+/*
+ *  JSON and JSON-lines encoding of result collections
+ */
+use std::io::{Error, ErrorKind, Result, Write};
+
+use serde::Serialize;
+
+use super::common::Record;
+use super::sink::RecordSink;
+
+/* Types */
+
+/// Encode collections of records, sessions or accounts as JSON
+///
+/// Implemented for any slice of serializable items, so `Vec<Record>`,
+/// `Vec<Session>` and `Vec<Account>` results can be encoded directly.
+/// See the `serde` feature for the layout of each item.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{Record, RecordType, ToJson};
+///
+/// let records = vec![Record::new(RecordType::User, "foo")];
+/// let json = records.to_json().unwrap();
+/// assert!(json.starts_with("[{\"type\":\"USER_PROCESS\""));
+/// assert_eq!(records.to_jsonl().unwrap().lines().count(), 1);
+/// ```
+pub trait ToJson {
+    /// Encode the collection as a single JSON array
+    fn to_json(&self) -> Result<String>;
+
+    /// Encode the collection as JSON-lines with one object per line
+    fn to_jsonl(&self) -> Result<String>;
+
+    /// Stream the collection as JSON-lines into the given writer
+    fn write_jsonl<W: Write>(&self, writer: W) -> Result<()>;
+}
+
+/// Sink streaming records as JSON-lines or as a single JSON array
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{JsonSink, Record, RecordSink};
+///
+/// let mut json = vec![];
+/// let mut sink = JsonSink::array(&mut json);
+/// sink.write(&Record::builder().name("foo").build()).unwrap();
+/// sink.write(&Record::builder().name("bar").build()).unwrap();
+/// sink.finish().unwrap();
+/// drop(sink);
+/// let records: Vec<serde_json::Value> = serde_json::from_slice(&json).unwrap();
+/// assert_eq!(records.len(), 2);
+/// ```
+pub struct JsonSink<W: Write> {
+    writer: W,
+    array: bool,
+    written: usize,
+}
+
+/* Functions */
+
+// convert json encoding failures into io errors
+#[inline]
+fn json_error(err: serde_json::Error) -> Error {
+    Error::new(ErrorKind::InvalidData, err)
+}
+
+/* Implementation */
+
+impl<T: Serialize> ToJson for [T] {
+    fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(json_error)
+    }
+
+    fn to_jsonl(&self) -> Result<String> {
+        let mut buffer = vec![];
+        self.write_jsonl(&mut buffer)?;
+        String::from_utf8(buffer).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+    }
+
+    fn write_jsonl<W: Write>(&self, mut writer: W) -> Result<()> {
+        for item in self {
+            serde_json::to_writer(&mut writer, item).map_err(json_error)?;
+            writer.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> JsonSink<W> {
+    /// Write every record as a JSON object on its own line
+    pub fn lines(writer: W) -> Self {
+        Self {
+            writer,
+            array: false,
+            written: 0,
+        }
+    }
+
+    /// Write every record into a single JSON array, closed on finish
+    pub fn array(writer: W) -> Self {
+        Self {
+            writer,
+            array: true,
+            written: 0,
+        }
+    }
+
+    /// Retrieve the underlying writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write> RecordSink for JsonSink<W> {
+    fn write(&mut self, record: &Record) -> Result<()> {
+        if self.array {
+            let sep: &[u8] = if self.written == 0 { b"[" } else { b"," };
+            self.writer.write_all(sep)?;
+        }
+        serde_json::to_writer(&mut self.writer, record).map_err(json_error)?;
+        if !self.array {
+            self.writer.write_all(b"\n")?;
+        }
+        self.written += 1;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        if self.array {
+            let end: &[u8] = if self.written == 0 { b"[]\n" } else { b"]\n" };
+            self.writer.write_all(end)?;
+        }
+        self.writer.flush()
+    }
+}
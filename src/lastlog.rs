@@ -1,46 +1,135 @@
 /*
  *  Linux `/var/log/lastlog` db reader
  */
-use std::fs::{metadata, File};
-use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom};
+use std::collections::HashMap;
+use std::fs::{metadata, File, OpenOptions};
+use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom, Write};
 
 use super::common::*;
+use super::layout::GLIBC_TIME32;
+use super::raw::{LastlogEntry, LASTLOG_SIZE};
+use super::stats::{timed, ReadStats};
+use super::validate::{invalid_record, Violation};
 
 /* Variables */
 
-static ST_SIZE: usize = std::mem::size_of::<RStruct>();
+static ST_SIZE: usize = LASTLOG_SIZE;
 
-/* Type */
-
-#[repr(C, packed)]
-#[derive(Debug, Copy, Clone)]
-struct RStruct(u32, [u8; 32], [u8; 256]);
+// largest prefix of the database read at once, entries of higher user-ids
+// (e.g. `nobody` at 65534 in a sparse file) are read individually instead
+static BULK_LIMIT: u64 = 4 * 1024 * 1024;
 
 /* Function */
 
-// map rstruct object into public record object
-fn map_record(name: &str, uid: u32, st: RStruct) -> Result<Record> {
-    let tty = std::str::from_utf8(&st.1).map_err(|_| ErrorKind::InvalidData)?;
+// map raw entry into public record object
+fn map_record(name: &str, uid: u32, st: LastlogEntry) -> Result<Record> {
     Ok(Record {
         rtype: RecordType::User,
         uid: Some(uid),
         name: name.to_owned(),
-        tty: tty.trim_matches('\0').to_owned(),
-        last_login: unix_timestamp(st.0),
+        tty: st.line()?.to_owned(),
+        host: st.host()?.to_owned(),
+        pid: None,
+        last_login: st.time().into(),
+        machine: None,
     })
 }
 
 // read lastlog for a given user uid and map to record object
-fn read_lastlog(f: &mut File, name: &str, uid: usize) -> Result<Record> {
-    // seek lastlog db based on uid and read RStruct object size
+fn read_lastlog<R: Read + Seek>(f: &mut R, name: &str, uid: usize) -> Result<Record> {
+    // seek lastlog db based on uid and read a single entry
     let mut buffer = vec![0; ST_SIZE];
-    f.seek(SeekFrom::Start((uid * ST_SIZE) as u64))?;
-    f.read_exact(&mut buffer)?;
-    // parse value into rstruct bytes
-    let st = read_struct::<RStruct, _>(&buffer[..])?;
+    f.seek(SeekFrom::Start(uid as u64 * ST_SIZE as u64))?;
+    // entries past the end of the (sparse) database have never logged in
+    match f.read_exact(&mut buffer) {
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+            return Ok(new_record(uid as u32, name.to_owned()));
+        }
+        res => res?,
+    }
+    // parse value into raw entry
+    let st = LastlogEntry::decode(&buffer)?;
     map_record(name, uid as u32, st)
 }
 
+// read the lastlog entries of every given account ordered by user-id
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip(users)))]
+pub fn read_users(
+    fname: &str,
+    users: &HashMap<u32, String>,
+    stats: &mut ReadStats,
+) -> Result<Vec<Record>> {
+    debug_event!(accounts = users.len(), "opened database");
+    let mut f = File::open(fname)?;
+    timed(stats, |stats| read_users_from(&mut f, users, stats))
+}
+
+// read the lastlog entries of every given account from any seekable reader
+pub fn read_users_from<R>(
+    f: &mut R,
+    users: &HashMap<u32, String>,
+    stats: &mut ReadStats,
+) -> Result<Vec<Record>>
+where
+    R: Read + Seek,
+{
+    let mut records = vec![];
+    // sort map of user accounts by user-id to ensure nobacktracking on seek action
+    let mut users: Vec<_> = users.iter().collect();
+    users.sort_by_key(|(uid, _)| **uid);
+    // read the entries of every account within the bulk limit in a single read
+    let len = f.seek(SeekFrom::End(0))?;
+    let wanted = users
+        .iter()
+        .map(|(uid, _)| (**uid as u64 + 1) * ST_SIZE as u64)
+        .filter(|end| *end <= BULK_LIMIT)
+        .max()
+        .unwrap_or_default();
+    let mut prefix = vec![];
+    f.seek(SeekFrom::Start(0))?;
+    (&mut *f).take(len.min(wanted)).read_to_end(&mut prefix)?;
+    stats.bytes_read += prefix.len() as u64;
+    debug_event!(bytes = prefix.len(), "read database prefix");
+    for (uid, name) in users.into_iter() {
+        let start = *uid as u64 * ST_SIZE as u64;
+        let end = start + ST_SIZE as u64;
+        let record = if end <= prefix.len() as u64 {
+            let buffer = &prefix[start as usize..end as usize];
+            map_record(name, *uid, LastlogEntry::decode(buffer)?)?
+        } else if end <= len {
+            stats.bytes_read += ST_SIZE as u64;
+            read_lastlog(f, name, *uid as usize)?
+        } else {
+            // entries past the end of the (sparse) database have never logged in
+            new_record(*uid, name.to_owned())
+        };
+        stats.records_parsed += 1;
+        records.push(record);
+    }
+    Ok(records)
+}
+
+// read every entry that has logged in from a lastlog formatted stream
+//
+// the user-id of each entry is its index, resolved to a name where known
+pub fn read_logged_in<R: Read>(mut reader: R, users: &HashMap<u32, String>) -> Result<Vec<Record>> {
+    let mut records = vec![];
+    let mut buffer = vec![0; ST_SIZE];
+    for uid in 0.. {
+        match reader.read_exact(&mut buffer) {
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+            res => res?,
+        }
+        let st = LastlogEntry::decode(&buffer)?;
+        if st.time().is_never() {
+            continue;
+        }
+        let name = users.get(&uid).map(|n| n.as_str()).unwrap_or_default();
+        records.push(map_record(name, uid, st)?);
+    }
+    Ok(records)
+}
+
 /* Implementation */
 
 /// Lastlog Database Reader Implementation
@@ -59,14 +148,103 @@ fn read_lastlog(f: &mut File, name: &str, uid: usize) -> Result<Record> {
 /// ```
 pub struct LastLog {}
 
+impl LastLog {
+    /// Clear the lastlog entry of the given user-id
+    ///
+    /// The entry is zeroed so the user is reported as having never logged
+    /// in. This requires write access to the database (usually root).
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```no_run
+    /// let llog = lastlog::LastLog {};
+    /// llog.clear(1000, "/var/log/lastlog").unwrap();
+    /// ```
+    pub fn clear(&self, uid: u32, fname: &str) -> Result<()> {
+        let mut f = OpenOptions::new().write(true).open(fname)?;
+        f.seek(SeekFrom::Start(uid as u64 * ST_SIZE as u64))?;
+        f.write_all(&vec![0; ST_SIZE])
+    }
+
+    /// Overwrite the lastlog entry of the record's user-id with the record
+    ///
+    /// The login-time, tty and host of the record are stored, growing the
+    /// (sparse) database when needed. Records whose stored fields fail
+    /// [`Record::validate`] are rejected. This requires write access to the
+    /// database (usually root).
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```no_run
+    /// use std::time::SystemTime;
+    /// use lastlog::Record;
+    ///
+    /// let record = Record::builder().uid(1000).tty("pts/3").last_login(SystemTime::now()).build();
+    /// let llog = lastlog::LastLog {};
+    /// llog.write(&record, "/var/log/lastlog").unwrap();
+    /// ```
+    pub fn write(&self, record: &Record, fname: &str) -> Result<()> {
+        let uid = record
+            .uid
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "record has no uid"))?;
+        // lastlog only stores the login-time, tty and host of the record
+        if let Err(violations) = record.validate(&GLIBC_TIME32) {
+            let violations: Vec<_> = violations
+                .into_iter()
+                .filter(|v| matches!(v.field(), "tty" | "host" | "last_login"))
+                .filter(|v| {
+                    !matches!(
+                        v,
+                        Violation::Missing { .. } | Violation::LineMismatch { .. }
+                    )
+                })
+                .collect();
+            if !violations.is_empty() {
+                return Err(invalid_record(violations));
+            }
+        }
+        let time = record.last_login.unix_seconds().unwrap_or_default();
+        let tty = record.tty.strip_prefix("/dev/").unwrap_or(&record.tty);
+        let st = LastlogEntry {
+            time: time as u32,
+            line: fixed_bytes(tty),
+            host: fixed_bytes(&record.host),
+        };
+        let mut f = OpenOptions::new().write(true).open(fname)?;
+        f.seek(SeekFrom::Start(uid as u64 * ST_SIZE as u64))?;
+        f.write_all(&st.encode())
+    }
+}
+
 impl LoginDB for LastLog {
     fn is_valid(&self, f: &mut File) -> bool {
-        let uid = guess_uid();
-        read_lastlog(f, "", uid as usize).is_ok()
+        // only non-empty databases made of whole entries are accepted
+        let Ok(len) = f.seek(SeekFrom::End(0)) else {
+            return false;
+        };
+        if len == 0 || len % ST_SIZE as u64 != 0 {
+            return false;
+        }
+        // decode a real entry, preferring the one of the current user
+        let uid = match guess_uid() as u64 {
+            uid if uid < len / ST_SIZE as u64 => uid,
+            _ => 0,
+        };
+        let mut buffer = vec![0; ST_SIZE];
+        f.seek(SeekFrom::Start(uid * ST_SIZE as u64)).is_ok()
+            && f.read_exact(&mut buffer).is_ok()
+            && LastlogEntry::decode(&buffer)
+                .ok()
+                .and_then(|st| map_record("", uid as u32, st).ok())
+                .is_some()
     }
 
     fn primary_file(&self) -> Result<&'static str> {
-        for fpath in vec!["/var/log/lastlog"].iter() {
+        for fpath in ["/var/log/lastlog"].iter() {
             let Ok(meta) = metadata(fpath) else { continue };
             if meta.is_file() {
                 return Ok(fpath);
@@ -79,33 +257,45 @@ impl LoginDB for LastLog {
     }
 
     fn iter_accounts(&self, fname: &str) -> Result<Vec<Record>> {
-        let mut records = vec![];
-        let mut f = File::open(fname)?;
-        // sort map of user accounts by user-id to ensure nobacktracking on seek action
-        let mut users: Vec<_> = read_passwd_idmap().into_iter().collect();
-        users.sort_by_key(|(uid, _)| *uid);
-        for (uid, name) in users.into_iter() {
-            let record = read_lastlog(&mut f, &name, uid as usize)?;
-            records.push(record);
-        }
-        Ok(records)
+        Ok(self.iter_accounts_with_stats(fname)?.0)
+    }
+
+    fn iter_accounts_with_users(&self, fname: &str, users: &UserMap) -> Result<Vec<Record>> {
+        read_users(fname, users.ids(), &mut ReadStats::default())
+    }
+
+    fn iter_accounts_with_stats(&self, fname: &str) -> Result<(Vec<Record>, ReadStats)> {
+        let mut stats = ReadStats::default();
+        let records = read_users(fname, &read_passwd_idmap(), &mut stats)?;
+        Ok((records, stats))
     }
 
     fn search_uid(&self, uid: u32, fname: &str) -> Result<Record> {
-        let users = read_passwd_idmap();
+        self.search_uid_with_users(uid, fname, &UserMap::system())
+    }
+
+    fn search_uid_with_users(&self, uid: u32, fname: &str, users: &UserMap) -> Result<Record> {
         let name = users
-            .get(&uid)
+            .name(uid)
             .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "no such user"))?;
         let mut f = File::open(fname)?;
         read_lastlog(&mut f, name, uid as usize)
     }
 
     fn search_username(&self, username: &str, fname: &str) -> Result<Record> {
-        let users = read_passwd_nmap();
+        self.search_username_with_users(username, fname, &UserMap::system())
+    }
+
+    fn search_username_with_users(
+        &self,
+        username: &str,
+        fname: &str,
+        users: &UserMap,
+    ) -> Result<Record> {
         let uid = users
-            .get(username)
+            .uid(username)
             .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "no such user"))?;
         let mut f = File::open(fname)?;
-        read_lastlog(&mut f, username, *uid as usize)
+        read_lastlog(&mut f, username, uid as usize)
     }
 }
@@ -0,0 +1,148 @@
+/*
+ *  Compile-time and startup validation of the raw database layouts
+ */
+use core::fmt;
+use core::mem::offset_of;
+
+use super::raw::{LastlogEntry, UtmpEntry, LASTLOG_SIZE, UTMP_SIZE};
+
+/* Variables */
+
+/// Layout used by glibc wherever `ut_tv` is stored as two 32-bit integers
+/// (`x86_64`, `x86`, `arm` and most other targets), and the one decoded by
+/// [`UtmpEntry`] and [`LastlogEntry`]
+pub const GLIBC_TIME32: LayoutProfile = LayoutProfile {
+    name: "glibc (32-bit ut_tv)",
+    utmp_size: 384,
+    lastlog_size: 292,
+    time_bits: 32,
+};
+
+/// Layout used by glibc on `aarch64`, `s390x` and `loongarch64`, where
+/// `ut_session`, `ut_tv` and `ll_time` are native 64-bit integers
+pub const GLIBC_TIME64: LayoutProfile = LayoutProfile {
+    name: "glibc (64-bit ut_tv)",
+    utmp_size: 400,
+    lastlog_size: 296,
+    time_bits: 64,
+};
+
+// the decoders must match the documented glibc layout byte for byte
+const _: () = {
+    assert!(UTMP_SIZE == GLIBC_TIME32.utmp_size);
+    assert!(offset_of!(UtmpEntry, rtype) == 0);
+    assert!(offset_of!(UtmpEntry, pid) == 4);
+    assert!(offset_of!(UtmpEntry, line) == 8);
+    assert!(offset_of!(UtmpEntry, id) == 40);
+    assert!(offset_of!(UtmpEntry, user) == 44);
+    assert!(offset_of!(UtmpEntry, host) == 76);
+    assert!(offset_of!(UtmpEntry, exit) == 332);
+    assert!(offset_of!(UtmpEntry, session) == 336);
+    assert!(offset_of!(UtmpEntry, sec) == 340);
+    assert!(offset_of!(UtmpEntry, usec) == 344);
+    assert!(offset_of!(UtmpEntry, addr) == 348);
+    assert!(offset_of!(UtmpEntry, unused) == 364);
+    assert!(LASTLOG_SIZE == GLIBC_TIME32.lastlog_size);
+    assert!(offset_of!(LastlogEntry, time) == 0);
+    assert!(offset_of!(LastlogEntry, line) == 4);
+    assert!(offset_of!(LastlogEntry, host) == 36);
+};
+
+/* Types */
+
+/// Sizes of the utmp and lastlog entries written by a platform
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayoutProfile {
+    pub name: &'static str,
+    pub utmp_size: usize,
+    pub lastlog_size: usize,
+    pub time_bits: u32,
+}
+
+/// Mismatch between the decoded layout and the one used by the platform
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayoutError {
+    /// database whose entries mismatch (`utmp` or `lastlog`)
+    pub database: &'static str,
+    /// entry size decoded by this crate
+    pub expected: usize,
+    /// entry size used by the platform
+    pub found: usize,
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unsupported {} layout ({} byte entries, expected {})",
+            self.database, self.found, self.expected
+        )
+    }
+}
+
+/* Functions */
+
+/// Retrieve the known database layout of the compile target, if any
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{native_profile, GLIBC_TIME32};
+///
+/// #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+/// assert_eq!(native_profile(), Some(GLIBC_TIME32));
+/// ```
+pub fn native_profile() -> Option<LayoutProfile> {
+    if cfg!(not(all(target_os = "linux", target_env = "gnu"))) {
+        return None;
+    }
+    match cfg!(any(
+        target_arch = "aarch64",
+        target_arch = "s390x",
+        target_arch = "loongarch64"
+    )) {
+        true => Some(GLIBC_TIME64),
+        false => Some(GLIBC_TIME32),
+    }
+}
+
+// compare a decoded entry size against the one used by the platform
+fn check(database: &'static str, expected: usize, found: usize) -> Result<(), LayoutError> {
+    match expected == found {
+        true => Ok(()),
+        false => Err(LayoutError {
+            database,
+            expected,
+            found,
+        }),
+    }
+}
+
+/// Verify the decoded entry layouts match the databases of this platform
+///
+/// The entry sizes are checked against the [`native_profile`] and, with
+/// the `libc` feature, against the size of libc's own `struct utmpx`, so a
+/// layout drift is reported instead of silently producing garbage records.
+/// The auto-selecting lookups such as [`search_uid`](crate::search_uid)
+/// run this check before reading any database.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// if let Err(err) = lastlog::verify_layout() {
+///     eprintln!("{err}");
+/// }
+/// ```
+pub fn verify_layout() -> Result<(), LayoutError> {
+    if let Some(profile) = native_profile() {
+        check("utmp", UTMP_SIZE, profile.utmp_size)?;
+        check("lastlog", LASTLOG_SIZE, profile.lastlog_size)?;
+    }
+    #[cfg(all(feature = "libc", target_os = "linux"))]
+    check("utmp", UTMP_SIZE, core::mem::size_of::<libc::utmpx>())?;
+    Ok(())
+}
@@ -14,44 +14,333 @@
  ```
 
  NOTE: this functionality is only designed to work with UNIX systems
- that support either utmp/wtmp or lastlog database types.
+ that support either utmp/wtmp or lastlog database types. The crate
+ still compiles on unix-like systems without them, such as Haiku and
+ Redox, where looking up the local databases fails as unsupported.
 */
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
 use std::env;
+#[cfg(feature = "std")]
 use std::fs::File;
+#[cfg(feature = "std")]
 use std::io::{Error, ErrorKind, Result};
 
+#[cfg(feature = "std")]
+use cache::Query;
+
+// must be declared first so the macros are visible to every module
+#[cfg(feature = "std")]
+#[macro_use]
+mod macros;
+
+#[cfg(feature = "std")]
+mod account;
+#[cfg(feature = "archaeology")]
+mod archaeology;
+#[cfg(feature = "std")]
+mod banner;
+#[cfg(feature = "std")]
+mod baseline;
+#[cfg(feature = "std")]
+mod boot;
+#[cfg(feature = "std")]
+mod bruteforce;
+#[cfg(feature = "std")]
+mod bundle;
+#[cfg(feature = "std")]
+mod cache;
+#[cfg(feature = "std")]
+mod cancel;
+#[cfg(feature = "std")]
 mod common;
+#[cfg(feature = "std")]
+mod concurrency;
+#[cfg(feature = "csv")]
+mod csv;
+#[cfg(feature = "dbus")]
+mod dbus;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "std")]
+mod filter;
+#[cfg(feature = "std")]
+mod finding;
+#[cfg(feature = "std")]
+mod format;
+#[cfg(feature = "std")]
+mod handle;
+#[cfg(any(feature = "libc", feature = "rustix"))]
+mod identity;
+#[cfg(feature = "journal")]
+mod journal;
+#[cfg(feature = "json")]
+mod json;
+#[cfg(feature = "std")]
 mod lastlog;
+mod layout;
+#[cfg(feature = "sqlite")]
+mod migrate;
+#[cfg(feature = "std")]
+mod monitor;
+#[cfg(feature = "nats")]
+mod nats;
+#[cfg(feature = "osquery")]
+mod osquery;
+#[cfg(feature = "std")]
+mod page;
+#[cfg(feature = "std")]
+mod pam;
+#[cfg(feature = "std")]
+mod pattern;
+#[cfg(feature = "std")]
+mod pipeline;
+#[cfg(feature = "std")]
+mod policy;
+#[cfg(feature = "python")]
+mod python;
+pub mod raw;
+#[cfg(feature = "redact")]
+mod redact;
+#[cfg(feature = "std")]
+mod redox;
+#[cfg(feature = "report")]
+mod report;
+#[cfg(feature = "std")]
+mod runlevel;
+#[cfg(feature = "std")]
+mod scan;
+#[cfg(feature = "std")]
+mod schema;
+#[cfg(feature = "serde")]
+mod serialize;
+#[cfg(feature = "std")]
+mod session;
+#[cfg(feature = "std")]
+mod sink;
+#[cfg(feature = "json")]
+mod snapshot;
+#[cfg(feature = "std")]
+mod sort;
+#[cfg(feature = "std")]
+mod source;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+#[cfg(feature = "std")]
+mod stats;
+#[cfg(feature = "std")]
+mod suppress;
+#[cfg(feature = "syslog")]
+mod syslog;
+#[cfg(feature = "std")]
+mod tamper;
+#[cfg(feature = "std")]
+mod template;
+#[cfg(feature = "std")]
+mod throttle;
+#[cfg(feature = "std")]
+mod tty;
+#[cfg(feature = "std")]
+mod uidmap;
+#[cfg(feature = "std")]
+mod users;
+#[cfg(feature = "std")]
 mod utmp;
+#[cfg(feature = "std")]
+mod validate;
+#[cfg(feature = "std")]
+mod watch;
+#[cfg(feature = "webhook")]
+mod webhook;
+#[cfg(all(feature = "std", not(feature = "libc")))]
+mod zone;
 
-pub use common::{LoginDB, LoginTime, Record, RecordType};
+#[cfg(feature = "std")]
+pub use account::{accounts, accounts_from, Account, AccountSources};
+#[cfg(feature = "std")]
+pub use banner::{format_banner, format_last_login_banner};
+#[cfg(feature = "std")]
+pub use baseline::LoginBaseline;
+#[cfg(feature = "std")]
+pub use boot::{current_boot_id, BootIds, BootInfo};
+#[cfg(feature = "std")]
+pub use bruteforce::BruteForceDetector;
+#[cfg(feature = "std")]
+pub use bundle::{Bundle, BundlePaths};
+#[cfg(feature = "cached")]
+pub use cache::invalidate;
+#[cfg(feature = "std")]
+pub use cancel::{CancelToken, Partial};
+#[cfg(feature = "std")]
+pub use common::{local_machine, LoginDB, LoginTime, ParseError, Record, RecordBuilder, UserMap};
+#[cfg(feature = "std")]
+pub use concurrency::{peak_concurrency, Concurrency};
+#[cfg(feature = "csv")]
+pub use csv::{CsvSink, ToCsv};
+#[cfg(feature = "dbus")]
+pub use dbus::{DbusLogin, DbusSession, LastLogService, DBUS_NAME, DBUS_PATH};
+#[cfg(feature = "std")]
+pub use filter::Filter;
+#[cfg(feature = "std")]
+pub use finding::{Finding, FindingKind, Severity};
+#[cfg(feature = "std")]
+pub use format::{parse_time, LASTLOG_TIME, LAST_FULL_TIME, LAST_TIME, RFC3339_TIME};
+#[cfg(feature = "std")]
+pub use handle::Lastlog;
+#[cfg(any(feature = "libc", feature = "rustix"))]
+pub use identity::{whoami, Identity, SelfLogin};
+#[cfg(feature = "journal")]
+pub use journal::{read_journal, JournalReader};
+#[cfg(feature = "json")]
+pub use json::{JsonSink, ToJson};
+#[cfg(feature = "std")]
 pub use lastlog::LastLog;
-pub use utmp::Utmp;
+pub use layout::{native_profile, verify_layout, LayoutError, LayoutProfile};
+pub use layout::{GLIBC_TIME32, GLIBC_TIME64};
+#[cfg(feature = "sqlite")]
+pub use migrate::{migrate_lastlog2, migrate_wtmpdb, LASTLOG2_DB, WTMPDB_DB};
+#[cfg(feature = "std")]
+pub use monitor::{EventFilter, EventSink, LoginEvent, LoginMonitor};
+#[cfg(feature = "nats")]
+pub use nats::NatsSink;
+#[cfg(feature = "osquery")]
+pub use osquery::OsqueryTable;
+#[cfg(feature = "std")]
+pub use page::{Page, Paged};
+#[cfg(feature = "std")]
+pub use pam::{
+    note_login, note_login_with, report_previous_login, report_previous_login_with, LoginFiles,
+};
+#[cfg(feature = "std")]
+pub use pattern::Pattern;
+#[cfg(feature = "std")]
+pub use pipeline::Pipeline;
+#[cfg(feature = "std")]
+pub use policy::{AccountClass, DormancyPolicy, DormancyViolation};
+pub use raw::RecordType;
+#[cfg(feature = "redact")]
+pub use redact::{Redaction, Redactor};
+#[cfg(feature = "std")]
+pub use redox::Redox;
+#[cfg(feature = "report")]
+pub use report::{Report, ReportFormat};
+#[cfg(feature = "std")]
+pub use runlevel::{
+    current_runlevel, current_runlevel_from, runlevel_at, runlevel_at_from, Runlevel,
+};
+#[cfg(feature = "std")]
+pub use scan::{plan_since, ScanStep};
+#[cfg(feature = "std")]
+pub use schema::{Endian, FieldKind, RecordSchema, SchemaField, SchemaReader};
+#[cfg(feature = "std")]
+pub use session::sessions_cancellable;
+#[cfg(feature = "std")]
+pub use session::{current_sessions, current_sessions_from, current_sessions_from_reader};
+#[cfg(feature = "std")]
+pub use session::{reboots, reboots_from, Reboot};
+#[cfg(feature = "std")]
+pub use session::{sessions, sessions_from, sessions_from_reader, Session, SessionEnd, Sessions};
+#[cfg(feature = "std")]
+pub use sink::{PrometheusSink, RecordSink};
+#[cfg(feature = "json")]
+pub use snapshot::{export_snapshot, import_snapshot, Snapshot, SnapshotChange, SNAPSHOT_VERSION};
+#[cfg(feature = "std")]
+pub use sort::{sort_records, Order, SortBy};
+#[cfg(feature = "std")]
+pub use source::{DataSource, FileSource, MemorySource, ReadSeek, SourceReader};
+#[cfg(feature = "sqlite")]
+pub use sqlite::{HistoryKind, SqliteArchive, SqliteSink, SCHEMA_VERSION};
+#[cfg(feature = "std")]
+pub use stats::ReadStats;
+#[cfg(feature = "std")]
+pub use suppress::{Suppression, Suppressions};
+#[cfg(feature = "syslog")]
+pub use syslog::SyslogSink;
+#[cfg(feature = "std")]
+pub use tamper::TamperDetector;
+#[cfg(feature = "std")]
+pub use template::{Template, TemplateSource};
+#[cfg(feature = "std")]
+pub use throttle::{Throttle, ThrottleKey};
+#[cfg(feature = "std")]
+pub use tty::{classify_tty, normalize_tty, LoginOrigin, TtyKind};
+#[cfg(feature = "std")]
+pub use uidmap::{MapDirection, MappedUsers, UidMap};
+#[cfg(feature = "libc")]
+pub use users::NssUsers;
+#[cfg(feature = "std")]
+pub use users::{reset_user_source, set_user_source, NisUsers, PasswdFile, UserSource};
+#[cfg(feature = "std")]
+pub use utmp::{rotated_files, Utmp, UtmpReader, UtmpRevReader};
+#[cfg(feature = "std")]
+pub use validate::Violation;
+#[cfg(feature = "std")]
+pub use watch::LoginWatcher;
+#[cfg(feature = "webhook")]
+pub use webhook::{WebhookFormat, WebhookSink};
 
 /* Varaibles */
 
+#[cfg(feature = "std")]
 static ENV: &str = "LASTLOG";
 
+// name of the operating system when it keeps no login databases at all
+#[cfg(feature = "std")]
+static UNSUPPORTED_OS: Option<&str> = if cfg!(target_os = "haiku") {
+    Some("haiku")
+} else if cfg!(target_os = "redox") {
+    Some("redox")
+} else {
+    None
+};
+
 /* Functions */
 
+#[cfg(feature = "std")]
 #[inline]
 fn modules() -> Vec<Box<dyn LoginDB>> {
+    #[cfg(target_os = "redox")]
+    return vec![
+        Box::new(redox::Redox {}),
+        Box::new(utmp::Utmp {}),
+        Box::new(lastlog::LastLog {}),
+    ];
+    #[cfg(not(target_os = "redox"))]
     vec![Box::new(utmp::Utmp {}), Box::new(lastlog::LastLog {})]
 }
 
-// find best suited module to retrieve lastlog data
-fn get_module() -> Result<(Box<dyn LoginDB>, String)> {
+/// Find the best suited module and database file to retrieve lastlog data
+///
+/// The path configured in the `LASTLOG` environment variable is preferred
+/// when valid, otherwise the first module with an existing primary file
+/// is selected. Operating systems that keep no login databases, such as
+/// Haiku and Redox, report [`ErrorKind::Unsupported`] unless a path is
+/// configured.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// if let Ok((module, path)) = lastlog::get_module() {
+///     let accounts = module.iter_accounts(&path);
+/// }
+/// ```
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+pub fn get_module() -> Result<(Box<dyn LoginDB>, String)> {
+    verify_layout()?;
     // check if os-env path is configured
     if let Ok(path) = env::var(ENV) {
         // error if given an invalid env path
-        let Ok(mut f) = File::open(&path) else {
+        if File::open(&path).is_err() {
             return Err(Error::new(ErrorKind::InvalidInput, "invalid env path"));
         };
         // check if the given file is valid for each of the supported modules
-        for module in modules().into_iter() {
-            if module.is_valid(&mut f) {
-                return Ok((module, path));
-            }
+        if let Ok(module) = detect_module(&path) {
+            debug_event!(path, "selected database from environment");
+            return Ok((module, path));
         }
     }
     // iterate modules to attempt to find valid primary-file
@@ -59,15 +348,47 @@ fn get_module() -> Result<(Box<dyn LoginDB>, String)> {
         let Ok(path) = module.primary_file() else {
             continue;
         };
+        debug_event!(path, "selected primary database");
         return Ok((module, path.to_owned()));
     }
     // error if no modules were found to work
+    if let Some(os) = UNSUPPORTED_OS {
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            format!("{os} does not keep utmp or lastlog databases"),
+        ));
+    }
     Err(Error::new(
         ErrorKind::NotFound,
         "no operating lastlog modules found",
     ))
 }
 
+/// Find the module capable of reading the given database file
+///
+/// Each supported module inspects the file contents to decide whether
+/// it understands the format, returning the first module that does.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// if let Ok(module) = lastlog::detect_module("/var/log/wtmp") {
+///     let accounts = module.iter_accounts("/var/log/wtmp");
+/// }
+/// ```
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug"))]
+pub fn detect_module(path: &str) -> Result<Box<dyn LoginDB>> {
+    verify_layout()?;
+    let mut f = File::open(path)?;
+    modules()
+        .into_iter()
+        .find(|module| module.is_valid(&mut f))
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "unsupported database format"))
+}
+
 /// Read UTMP records to discover most recent boot-time record
 ///
 /// This will search for the most recent `BOOT_TIME` record-type
@@ -82,6 +403,7 @@ fn get_module() -> Result<(Box<dyn LoginDB>, String)> {
 /// let boot_record = lastlog::system_boot().unwrap();
 /// println!("boot-time! {:?}", boot_record.last_login);
 /// ```
+#[cfg(feature = "std")]
 pub fn system_boot() -> Result<Record> {
     let utmp = Utmp {};
     let path = utmp.primary_file()?;
@@ -107,9 +429,113 @@ pub fn system_boot() -> Result<Record> {
 ///     println!("{:?}", account);
 /// }
 /// ```
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug"))]
 pub fn iter_accounts() -> Result<Vec<Record>> {
     let (module, path) = get_module()?;
-    module.iter_accounts(&path)
+    let records = cache::latest_logins(&path, Query::Accounts, || module.iter_accounts(&path))?;
+    debug_event!(count = records.len(), "read accounts");
+    Ok(records)
+}
+
+/// Use an auto-selected module to iterate logins for every user account
+/// alongside statistics describing the read
+///
+/// Same as `iter_accounts` but also reports the bytes read, entries parsed
+/// and skipped and the time spent, e.g. to track performance regressions
+/// or notice corrupt databases.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// if let Ok((accounts, stats)) = lastlog::iter_accounts_with_stats() {
+///     println!("{} accounts from {} bytes", accounts.len(), stats.bytes_read);
+/// }
+/// ```
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug"))]
+pub fn iter_accounts_with_stats() -> Result<(Vec<Record>, ReadStats)> {
+    let (module, path) = get_module()?;
+    let (records, stats) = module.iter_accounts_with_stats(&path)?;
+    debug_event!(
+        count = records.len(),
+        bytes = stats.bytes_read,
+        "read accounts"
+    );
+    Ok((records, stats))
+}
+
+/// Use an auto-selected module to iterate logins for every user account until cancelled
+///
+/// Same as `iter_accounts` but the scan stops once the token is cancelled
+/// or its deadline passes, returning the records found so far flagged
+/// as truncated. Answers are never cached as they may be incomplete.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use std::time::Duration;
+/// use lastlog::CancelToken;
+///
+/// let token = CancelToken::with_timeout(Duration::from_millis(500));
+/// if let Ok(accounts) = lastlog::iter_accounts_cancellable(&token) {
+///     if accounts.truncated {
+///         eprintln!("scan timed out, results are incomplete");
+///     }
+/// }
+/// ```
+#[cfg(feature = "std")]
+pub fn iter_accounts_cancellable(cancel: &CancelToken) -> Result<Partial<Vec<Record>>> {
+    let (module, path) = get_module()?;
+    module.iter_accounts_cancellable(&path, cancel)
+}
+
+/// Use an auto-selected module to iterate logins for every user account in order
+///
+/// Same as `iter_accounts` but the returned records are sorted by the
+/// given field and direction rather than in database order.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{Order, SortBy};
+///
+/// let accounts = lastlog::iter_accounts_sorted(SortBy::Uid, Order::Ascending);
+/// ```
+#[cfg(feature = "std")]
+pub fn iter_accounts_sorted(by: SortBy, order: Order) -> Result<Vec<Record>> {
+    let mut records = iter_accounts()?;
+    sort_records(&mut records, by, order);
+    Ok(records)
+}
+
+/// Use an auto-selected module to retrieve a single page of user accounts
+///
+/// Accounts are ordered using the given field and direction before the
+/// page is sliced out so that consecutive pages never overlap.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{Order, Page, SortBy};
+///
+/// let page = Page::new(0, 25);
+/// if let Ok(accounts) = lastlog::iter_accounts_page(SortBy::Uid, Order::Ascending, page) {
+///     println!("showing {} of {}", accounts.items.len(), accounts.total);
+/// }
+/// ```
+#[cfg(feature = "std")]
+pub fn iter_accounts_page(by: SortBy, order: Order, page: Page) -> Result<Paged<Record>> {
+    let records = iter_accounts_sorted(by, order)?;
+    Ok(Paged::new(records, page))
 }
 
 /// Use an auto-selected module to find the last login for a specified user-id
@@ -124,9 +550,14 @@ pub fn iter_accounts() -> Result<Vec<Record>> {
 /// ```
 /// let record = lastlog::search_uid(1000);
 /// ```
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug"))]
 pub fn search_uid(uid: u32) -> Result<Record> {
     let (module, path) = get_module()?;
-    module.search_uid(uid, &path)
+    let mut records = cache::latest_logins(&path, Query::Uid(uid), || {
+        Ok(vec![module.search_uid(uid, &path)?])
+    })?;
+    Ok(records.remove(0))
 }
 
 /// Use an auto-selected module to find the last login for a specified username
@@ -141,15 +572,78 @@ pub fn search_uid(uid: u32) -> Result<Record> {
 /// ```
 /// let record = lastlog::search_username("foo");
 /// ```
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug"))]
 pub fn search_username(username: &str) -> Result<Record> {
     let (module, path) = get_module()?;
-    module.search_username(username, &path)
+    let query = Query::Username(username.to_owned());
+    let mut records = cache::latest_logins(&path, query, || {
+        Ok(vec![module.search_username(username, &path)?])
+    })?;
+    Ok(records.remove(0))
+}
+
+/// Use an auto-selected module to find the last logins for several user-ids
+///
+/// All of the requested user-ids are resolved within a single pass over the
+/// most relevant database file, stopping as soon as every user is found.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// let records = lastlog::search_uids(&[0, 1000]);
+/// ```
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug"))]
+pub fn search_uids(uids: &[u32]) -> Result<Vec<Record>> {
+    let (module, path) = get_module()?;
+    module.search_uids(uids, &path)
+}
+
+/// Use an auto-selected module to find the last logins for several usernames
+///
+/// Similar to `search_uids`, all of the requested usernames are resolved
+/// within a single pass over the most relevant database file.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// let records = lastlog::search_usernames(&["root", "foo"]);
+/// ```
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug"))]
+pub fn search_usernames(usernames: &[&str]) -> Result<Vec<Record>> {
+    let (module, path) = get_module()?;
+    module.search_usernames(usernames, &path)
+}
+
+/// Use an auto-selected module to find the last logins of all matching usernames
+///
+/// Accepts either a glob pattern such as `svc-*` or, with the `regex`
+/// feature, a compiled [`Pattern::Regex`].
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// let records = lastlog::search_matching("svc-*");
+/// ```
+#[cfg(feature = "std")]
+pub fn search_matching(pattern: impl Into<Pattern>) -> Result<Vec<Record>> {
+    let (module, path) = get_module()?;
+    module.search_matching(&pattern.into(), &path)
 }
 
-/// Use libc to retrieve the current user-id and complete a search
+/// Use libc or rustix to retrieve the current user-id and complete a search
 ///
 /// Same as search_uid with but looks up the current user-id
-/// automatically using libc.
+/// automatically using libc (or rustix when only the `rustix` feature
+/// is enabled).
 ///
 /// # Examples
 ///
@@ -158,9 +652,30 @@ pub fn search_username(username: &str) -> Result<Record> {
 /// ```
 /// let record = lastlog::search_self();
 /// ```
-#[cfg(feature = "libc")]
+#[cfg(any(feature = "libc", feature = "rustix"))]
 pub fn search_self() -> Result<Record> {
+    Ok(search_self_login()?.record)
+}
+
+/// Find the last login of the current user alongside the resolved identity
+///
+/// Same as `search_self` but also reports which account the search was
+/// made for, so callers can reliably display "you are X, last login Y".
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// if let Ok(login) = lastlog::search_self_login() {
+///     let name = login.identity.name.as_deref().unwrap_or("unknown");
+///     println!("you are {name}: {}", login.record);
+/// }
+/// ```
+#[cfg(any(feature = "libc", feature = "rustix"))]
+pub fn search_self_login() -> Result<SelfLogin> {
     let (module, path) = get_module()?;
-    let uid = unsafe { libc::getuid() };
-    module.search_uid(uid, &path)
+    let identity = whoami();
+    let record = module.search_uid(identity.uid, &path)?;
+    Ok(SelfLogin { identity, record })
 }
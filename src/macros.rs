@@ -0,0 +1,19 @@
+/*
+ *  Internal diagnostics forwarded to `tracing` when the feature is enabled
+ */
+
+// emit a debug level event, compiled out without the `tracing` feature
+macro_rules! debug_event {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($arg)*);
+    };
+}
+
+// emit a warning level event, compiled out without the `tracing` feature
+macro_rules! warn_event {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::warn!($($arg)*);
+    };
+}
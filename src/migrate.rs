@@ -0,0 +1,179 @@
+/*
+ *  Migration of legacy lastlog and wtmp databases into lastlog2 and wtmpdb
+ */
+use std::io::Result;
+use std::time::SystemTime;
+
+use rusqlite::{params, Connection};
+
+use super::common::*;
+use super::lastlog::LastLog;
+use super::session::sessions_from;
+use super::sqlite::sql_error;
+
+/* Variables */
+
+/// Default location of the util-linux lastlog2 database
+pub static LASTLOG2_DB: &str = "/var/lib/lastlog/lastlog2.db";
+
+/// Default location of the wtmpdb login history database
+pub static WTMPDB_DB: &str = "/var/lib/wtmpdb/wtmp.db";
+
+static LASTLOG2_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS Lastlog2(Name TEXT PRIMARY KEY, \
+    Time INTEGER NOT NULL, TTY TEXT, RemoteHost TEXT, Service TEXT);";
+
+static WTMPDB_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS wtmp(ID INTEGER PRIMARY KEY, \
+    Type INTEGER, User TEXT NOT NULL, Login INTEGER, Logout INTEGER, TTY TEXT, \
+    RemoteHost TEXT, Service TEXT);";
+
+// entry types used by wtmpdb
+static WTMPDB_BOOT_TIME: i32 = 2;
+static WTMPDB_USER_PROCESS: i32 = 3;
+
+/* Functions */
+
+// convert a time into the microseconds stored by wtmpdb
+fn micros(time: SystemTime) -> Option<i64> {
+    LoginTime::Last(time).unix_micros().map(|t| t as i64)
+}
+
+// convert an empty field into `NULL`
+fn optional(field: &str) -> Option<&str> {
+    (!field.is_empty()).then_some(field)
+}
+
+/// Copy the latest login of every account from a legacy lastlog into lastlog2
+///
+/// Logins are stored by username, so only user-ids with a passwd entry are
+/// migrated. Accounts that already logged in more recently according to
+/// lastlog2 are left untouched, making the migration safe to repeat.
+///
+/// Returns the number of accounts that were inserted or updated.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```no_run
+/// let migrated = lastlog::migrate_lastlog2("/var/log/lastlog", lastlog::LASTLOG2_DB).unwrap();
+/// println!("migrated {migrated} accounts");
+/// ```
+pub fn migrate_lastlog2(lastlog: &str, db: &str) -> Result<usize> {
+    let records = LastLog {}.iter_accounts(lastlog)?;
+    let mut conn = Connection::open(db).map_err(sql_error)?;
+    conn.execute_batch(LASTLOG2_SCHEMA).map_err(sql_error)?;
+    let tx = conn.transaction().map_err(sql_error)?;
+    let mut migrated = 0;
+    {
+        let mut upsert = tx
+            .prepare(
+                "INSERT INTO Lastlog2 (Name, Time, TTY, RemoteHost) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(Name) DO UPDATE SET
+                    Time = excluded.Time, TTY = excluded.TTY, RemoteHost = excluded.RemoteHost
+                 WHERE excluded.Time > Lastlog2.Time",
+            )
+            .map_err(sql_error)?;
+        for record in records.iter().filter(|r| !r.name.is_empty()) {
+            let Some(time) = record.last_login.unix_seconds() else {
+                continue;
+            };
+            migrated += upsert
+                .execute(params![
+                    record.name,
+                    time as i64,
+                    optional(&record.tty),
+                    optional(&record.host),
+                ])
+                .map_err(sql_error)?;
+        }
+    }
+    tx.commit().map_err(sql_error)?;
+    debug_event!(lastlog, db, migrated, "migrated lastlog");
+    Ok(migrated)
+}
+
+/// Copy the login sessions and boots of a legacy wtmp into wtmpdb
+///
+/// Logins are paired with their logouts like `last(1)` so every session
+/// becomes a single wtmpdb entry, while shutdowns and runlevel changes
+/// have no equivalent and are skipped. Entries already present in wtmpdb
+/// are not inserted twice, and sessions that were still open during an
+/// earlier run get their logout filled in, making the migration safe to
+/// repeat.
+///
+/// Returns the number of entries that were inserted or completed.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```no_run
+/// let migrated = lastlog::migrate_wtmpdb("/var/log/wtmp", lastlog::WTMPDB_DB).unwrap();
+/// println!("migrated {migrated} sessions");
+/// ```
+pub fn migrate_wtmpdb(wtmp: &str, db: &str) -> Result<usize> {
+    let mut sessions: Vec<_> = sessions_from(wtmp)?.collect();
+    // sessions are paired newest first, but wtmpdb ids grow over time
+    sessions.reverse();
+    let mut conn = Connection::open(db).map_err(sql_error)?;
+    conn.execute_batch(WTMPDB_SCHEMA).map_err(sql_error)?;
+    let tx = conn.transaction().map_err(sql_error)?;
+    let mut migrated = 0;
+    {
+        // wtmpdb has no unique key to upsert on, so open entries are
+        // completed first and only inserted when missing entirely
+        let mut complete = tx
+            .prepare(
+                "UPDATE wtmp SET Logout = ?4
+                 WHERE Type = ?1 AND User = ?2 AND Login = ?3 AND TTY IS ?5
+                    AND Logout IS NULL AND ?4 IS NOT NULL",
+            )
+            .map_err(sql_error)?;
+        let mut insert = tx
+            .prepare(
+                "INSERT INTO wtmp (Type, User, Login, Logout, TTY, RemoteHost)
+                 SELECT ?1, ?2, ?3, ?4, ?5, ?6 WHERE NOT EXISTS (
+                    SELECT 1 FROM wtmp WHERE Type = ?1 AND User = ?2 AND Login = ?3 AND TTY IS ?5
+                 )",
+            )
+            .map_err(sql_error)?;
+        for session in sessions {
+            let rtype = match session.record.rtype {
+                RecordType::User => WTMPDB_USER_PROCESS,
+                RecordType::BootTime => WTMPDB_BOOT_TIME,
+                _ => continue,
+            };
+            let Some(login) = session.start().and_then(micros) else {
+                continue;
+            };
+            let record = &session.record;
+            let logout = session.end.time().and_then(micros);
+            let completed = complete
+                .execute(params![
+                    rtype,
+                    record.name,
+                    login,
+                    logout,
+                    optional(&record.tty)
+                ])
+                .map_err(sql_error)?;
+            if completed > 0 {
+                migrated += completed;
+                continue;
+            }
+            migrated += insert
+                .execute(params![
+                    rtype,
+                    record.name,
+                    login,
+                    logout,
+                    optional(&record.tty),
+                    optional(&record.host),
+                ])
+                .map_err(sql_error)?;
+        }
+    }
+    tx.commit().map_err(sql_error)?;
+    debug_event!(wtmp, db, migrated, "migrated wtmp");
+    Ok(migrated)
+}
@@ -0,0 +1,239 @@
+/*
+ *  Callback based notifications for new and failed logins
+ */
+use std::io::Result;
+use std::sync::{Arc, Mutex};
+use std::thread::sleep;
+use std::time::Duration;
+
+use super::common::*;
+use super::pattern::Pattern;
+use super::watch::LoginWatcher;
+
+/* Variables */
+
+static WTMP: &str = "/var/log/wtmp";
+static BTMP: &str = "/var/log/btmp";
+
+/* Types */
+
+type Handler = Box<dyn FnMut(&Record) + Send>;
+
+/// Kind of event observed by a [`LoginMonitor`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LoginEvent {
+    /// successful login appended to wtmp
+    Login,
+    /// failed login attempt appended to btmp
+    FailedLogin,
+}
+
+/// Destination receiving login events forwarded by a [`LoginMonitor`]
+///
+/// Implemented by the notification sinks, such as syslog, so every new
+/// login and failed login can be forwarded with a single call to
+/// [`LoginMonitor::forward`].
+pub trait EventSink: Send {
+    /// Deliver a single event and the record it was observed in
+    fn send(&mut self, event: LoginEvent, record: &Record) -> Result<()>;
+
+    /// Deliver an event that repeated the given number of times since the
+    /// last delivery, such as after being held back by a [`crate::Throttle`]
+    fn send_repeated(&mut self, event: LoginEvent, record: &Record, repeats: usize) -> Result<()> {
+        let _ = repeats;
+        self.send(event, record)
+    }
+}
+
+impl<F> EventSink for F
+where
+    F: FnMut(LoginEvent, &Record) -> Result<()> + Send,
+{
+    fn send(&mut self, event: LoginEvent, record: &Record) -> Result<()> {
+        self(event, record)
+    }
+}
+
+/// Filter deciding which login events are delivered to a handler
+///
+/// An empty filter matches every event. When both users and hosts are
+/// given, an event must match at least one of each.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{EventFilter, Record};
+///
+/// let filter = EventFilter::new().user("root").host("10.0.*");
+/// let record = Record::builder().name("root").host("10.0.0.5").build();
+/// assert!(filter.matches(&record));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    users: Vec<Pattern>,
+    hosts: Vec<Pattern>,
+}
+
+impl EventFilter {
+    /// Generate a new filter matching every event
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only match events for usernames matching the given pattern
+    pub fn user(mut self, pattern: impl Into<Pattern>) -> Self {
+        self.users.push(pattern.into());
+        self
+    }
+
+    /// Only match events originating from hosts matching the given pattern
+    pub fn host(mut self, pattern: impl Into<Pattern>) -> Self {
+        self.hosts.push(pattern.into());
+        self
+    }
+
+    /// Check if the given record passes the filter
+    pub fn matches(&self, record: &Record) -> bool {
+        let user = self.users.is_empty() || self.users.iter().any(|p| p.matches(&record.name));
+        let host = self.hosts.is_empty() || self.hosts.iter().any(|p| p.matches(&record.host));
+        user && host
+    }
+}
+
+/// Subscription based event-loop for login notifications
+///
+/// The monitor watches wtmp for successful logins and (when readable)
+/// btmp for failed logins, invoking every registered handler whose
+/// filter matches a newly appended entry. Entries that fail to parse
+/// are skipped.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```no_run
+/// use lastlog::{EventFilter, LoginMonitor};
+///
+/// LoginMonitor::system()
+///     .unwrap()
+///     .on_login(EventFilter::new().user("root"), |r| {
+///         println!("root login from {}", r.host);
+///     })
+///     .on_failed_login(EventFilter::new(), |r| {
+///         println!("failed login for {}", r.name);
+///     })
+///     .run()
+///     .unwrap();
+/// ```
+pub struct LoginMonitor {
+    interval: Duration,
+    logins: LoginWatcher,
+    failed: Option<LoginWatcher>,
+    login_handlers: Vec<(EventFilter, Handler)>,
+    failed_handlers: Vec<(EventFilter, Handler)>,
+}
+
+impl LoginMonitor {
+    /// Monitor the given wtmp database and optional btmp database
+    pub fn new(wtmp: &str, btmp: Option<&str>) -> Result<Self> {
+        Ok(Self {
+            interval: Duration::from_secs(1),
+            logins: LoginWatcher::new(wtmp)?,
+            failed: btmp.map(LoginWatcher::new).transpose()?,
+            login_handlers: vec![],
+            failed_handlers: vec![],
+        })
+    }
+
+    /// Monitor the default system databases
+    ///
+    /// Failed logins are only reported if `/var/log/btmp` is readable,
+    /// which usually requires root privileges.
+    pub fn system() -> Result<Self> {
+        let mut monitor = Self::new(WTMP, None)?;
+        monitor.failed = LoginWatcher::new(BTMP).ok();
+        Ok(monitor)
+    }
+
+    /// Configure how often the databases are checked for new entries
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Register a handler for successful logins matching the filter
+    pub fn on_login<F>(mut self, filter: EventFilter, handler: F) -> Self
+    where
+        F: FnMut(&Record) + Send + 'static,
+    {
+        self.login_handlers.push((filter, Box::new(handler)));
+        self
+    }
+
+    /// Register a handler for failed logins matching the filter
+    pub fn on_failed_login<F>(mut self, filter: EventFilter, handler: F) -> Self
+    where
+        F: FnMut(&Record) + Send + 'static,
+    {
+        self.failed_handlers.push((filter, Box::new(handler)));
+        self
+    }
+
+    /// Forward every login and failed login matching the filter to the sink
+    ///
+    /// Delivery failures are skipped so a missing collector never stops
+    /// the monitor.
+    pub fn forward<S>(self, filter: EventFilter, sink: S) -> Self
+    where
+        S: EventSink + 'static,
+    {
+        let sink = Arc::new(Mutex::new(sink));
+        let failed = Arc::clone(&sink);
+        let send = |sink: &Mutex<S>, event: LoginEvent, record: &Record| {
+            let mut sink = sink.lock().unwrap_or_else(|e| e.into_inner());
+            if let Err(_e) = sink.send(event, record) {
+                warn_event!(error = %_e, user = record.name, "failed to forward login event");
+            }
+        };
+        self.on_login(filter.clone(), move |record| {
+            send(&sink, LoginEvent::Login, record)
+        })
+        .on_failed_login(filter, move |record| {
+            send(&failed, LoginEvent::FailedLogin, record)
+        })
+    }
+
+    // deliver a single record to all matching handlers
+    fn dispatch(handlers: &mut [(EventFilter, Handler)], record: &Record) {
+        for (filter, handler) in handlers.iter_mut() {
+            if filter.matches(record) {
+                handler(record);
+            }
+        }
+    }
+
+    /// Check the databases once and dispatch any new events without blocking
+    pub fn poll(&mut self) -> Result<()> {
+        for record in self.logins.poll()?.into_iter().filter_map(|r| r.ok()) {
+            if record.rtype == RecordType::User {
+                Self::dispatch(&mut self.login_handlers, &record);
+            }
+        }
+        if let Some(failed) = self.failed.as_mut() {
+            for record in failed.poll()?.into_iter().filter_map(|r| r.ok()) {
+                Self::dispatch(&mut self.failed_handlers, &record);
+            }
+        }
+        Ok(())
+    }
+
+    /// Dispatch events forever, only returning if the databases become unreadable
+    pub fn run(&mut self) -> Result<()> {
+        loop {
+            self.poll()?;
+            sleep(self.interval);
+        }
+    }
+}
@@ -0,0 +1,181 @@
+/*
+ *  NATS publishing of login and failed-login events as JSON
+ */
+use std::io::{BufRead, BufReader, Error, ErrorKind, Read, Result, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+
+use serde_json::Value;
+
+use super::common::*;
+use super::monitor::{EventSink, LoginEvent};
+use super::sink::RecordSink;
+
+/* Variables */
+
+static DEFAULT_SUBJECT: &str = "lastlog";
+
+/* Types */
+
+/// Sink publishing login events to a NATS server as JSON messages
+///
+/// Logins are published to `<subject>.login` and failed logins to
+/// `<subject>.failed`, where the subject prefix defaults to `lastlog`.
+/// Each message is the JSON encoded record with an additional `event`
+/// field. Only plain TCP connections are supported, so TLS terminating
+/// servers need a local leaf node or proxy.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```no_run
+/// use lastlog::{EventFilter, LoginMonitor, NatsSink};
+///
+/// let sink = NatsSink::connect("nats.example.com:4222").unwrap().subject("logins.web01");
+/// LoginMonitor::system()
+///     .unwrap()
+///     .forward(EventFilter::new(), sink)
+///     .run()
+///     .unwrap();
+/// ```
+#[derive(Debug)]
+pub struct NatsSink {
+    reader: BufReader<TcpStream>,
+    stream: TcpStream,
+    subject: String,
+}
+
+/* Functions */
+
+// build the error reported for protocol violations of the server
+fn protocol_error(message: impl Into<String>) -> Error {
+    Error::new(ErrorKind::InvalidData, message.into())
+}
+
+// encode the record as a json object tagged with its event
+fn event_payload(event: LoginEvent, record: &Record, repeats: usize) -> Result<Vec<u8>> {
+    let mut value =
+        serde_json::to_value(record).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    if let Value::Object(map) = &mut value {
+        let name = match event {
+            LoginEvent::Login => "login",
+            LoginEvent::FailedLogin => "failed",
+        };
+        map.insert("event".to_owned(), Value::String(name.to_owned()));
+        if repeats > 0 {
+            map.insert("repeats".to_owned(), Value::from(repeats));
+        }
+    }
+    serde_json::to_vec(&value).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+/* Implementation */
+
+impl NatsSink {
+    /// Connect to the NATS server at the given address without authentication
+    pub fn connect(addr: impl ToSocketAddrs) -> Result<Self> {
+        Self::handshake(addr, None)
+    }
+
+    /// Connect to the NATS server at the given address using a token
+    pub fn connect_with_token(addr: impl ToSocketAddrs, token: &str) -> Result<Self> {
+        Self::handshake(addr, Some(token))
+    }
+
+    // exchange the server info and client options, confirming with a ping
+    fn handshake(addr: impl ToSocketAddrs, token: Option<&str>) -> Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        let mut sink = Self {
+            reader: BufReader::new(stream.try_clone()?),
+            stream,
+            subject: DEFAULT_SUBJECT.to_owned(),
+        };
+        let info = sink.read_line()?;
+        if !info.starts_with("INFO ") {
+            return Err(protocol_error(format!("unexpected greeting: {info}")));
+        }
+        let mut options = serde_json::json!({
+            "verbose": false,
+            "pedantic": false,
+            "name": "lastlog",
+            "lang": "rust",
+            "version": env!("CARGO_PKG_VERSION"),
+            "protocol": 0,
+        });
+        if let Some(token) = token {
+            options["auth_token"] = Value::String(token.to_owned());
+        }
+        write!(sink.stream, "CONNECT {options}\r\nPING\r\n")?;
+        sink.stream.flush()?;
+        loop {
+            match sink.read_line()?.as_str() {
+                "PONG" => return Ok(sink),
+                "PING" => sink.stream.write_all(b"PONG\r\n")?,
+                "+OK" => {}
+                line if line.starts_with("INFO ") => {}
+                line => return Err(Error::new(ErrorKind::PermissionDenied, line.to_owned())),
+            }
+        }
+    }
+
+    /// Set the subject prefix events are published under
+    pub fn subject(mut self, subject: impl Into<String>) -> Self {
+        self.subject = subject.into();
+        self
+    }
+
+    // read a single protocol line without its line ending
+    fn read_line(&mut self) -> Result<String> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "connection closed"));
+        }
+        Ok(line.trim_end().to_owned())
+    }
+
+    // answer any pings received since the last publish, reporting errors
+    fn drain(&mut self) -> Result<()> {
+        self.stream.set_nonblocking(true)?;
+        let mut pending = String::new();
+        let result = self.reader.read_to_string(&mut pending);
+        self.stream.set_nonblocking(false)?;
+        match result {
+            Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+            Err(err) => return Err(err),
+            Ok(_) => return Err(Error::new(ErrorKind::UnexpectedEof, "connection closed")),
+        }
+        for line in pending.lines() {
+            if line == "PING" {
+                self.stream.write_all(b"PONG\r\n")?;
+            } else if let Some(err) = line.strip_prefix("-ERR") {
+                return Err(protocol_error(err.trim()));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl EventSink for NatsSink {
+    fn send(&mut self, event: LoginEvent, record: &Record) -> Result<()> {
+        self.send_repeated(event, record, 0)
+    }
+
+    fn send_repeated(&mut self, event: LoginEvent, record: &Record, repeats: usize) -> Result<()> {
+        self.drain()?;
+        let payload = event_payload(event, record, repeats)?;
+        let subject = match event {
+            LoginEvent::Login => format!("{}.login", self.subject),
+            LoginEvent::FailedLogin => format!("{}.failed", self.subject),
+        };
+        write!(self.stream, "PUB {subject} {}\r\n", payload.len())?;
+        self.stream.write_all(&payload)?;
+        self.stream.write_all(b"\r\n")?;
+        self.stream.flush()
+    }
+}
+
+impl RecordSink for NatsSink {
+    fn write(&mut self, record: &Record) -> Result<()> {
+        self.send(LoginEvent::Login, record)
+    }
+}
@@ -0,0 +1,276 @@
+/*
+ *  osquery compatible tables of login history and current sessions
+ */
+use std::io::{Error, ErrorKind, Result, Write};
+use std::str::FromStr;
+
+use serde_json::{json, Map, Value};
+
+use super::common::*;
+
+/* Variables */
+
+static LAST_COLUMNS: [(&str, &str); 7] = [
+    ("username", "TEXT"),
+    ("tty", "TEXT"),
+    ("pid", "INTEGER"),
+    ("type", "INTEGER"),
+    ("type_name", "TEXT"),
+    ("time", "INTEGER"),
+    ("host", "TEXT"),
+];
+
+static LASTLOG_COLUMNS: [(&str, &str); 5] = [
+    ("uid", "BIGINT"),
+    ("username", "TEXT"),
+    ("tty", "TEXT"),
+    ("host", "TEXT"),
+    ("time", "BIGINT"),
+];
+
+static LOGGED_IN_USERS_COLUMNS: [(&str, &str); 6] = [
+    ("type", "TEXT"),
+    ("user", "TEXT"),
+    ("tty", "TEXT"),
+    ("host", "TEXT"),
+    ("time", "BIGINT"),
+    ("pid", "INTEGER"),
+];
+
+// sqlite expression naming the record-type like osquery does
+static TYPE_NAME_SQL: &str = "CASE type WHEN 0 THEN 'empty' WHEN 1 THEN 'runlevel' \
+    WHEN 2 THEN 'boot_time' WHEN 3 THEN 'new_time' WHEN 4 THEN 'old_time' \
+    WHEN 5 THEN 'init' WHEN 6 THEN 'login' WHEN 7 THEN 'user' WHEN 8 THEN 'dead' \
+    WHEN 9 THEN 'accounting' ELSE 'unknown' END";
+
+/* Types */
+
+/// Table shaped like one of osquery's login related tables
+///
+/// Rows carry the same columns as osquery's `last` and `logged_in_users`
+/// tables, along with a `lastlog` table of the latest login per user-id
+/// that osquery lacks, so existing queries and dashboards work unchanged
+/// on hosts, containers or support bundles osquery cannot read itself.
+///
+/// Rows are either written in the `osqueryi --json` format, or served to
+/// `osqueryd` through automatic table construction (ATC) over a
+/// [`crate::SqliteArchive`] using [`OsqueryTable::atc_config`].
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{OsqueryTable, Record, RecordType};
+///
+/// let record = Record::builder().rtype(RecordType::User).name("root").tty("pts/0").pid(42).build();
+/// let json = OsqueryTable::Last.to_json(&[record]).unwrap();
+/// assert!(json.starts_with(r#"[{"host":"","pid":"42","time":"","tty":"pts/0","type":"7""#));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OsqueryTable {
+    /// login and logout entries of wtmp as osquery's `last`
+    Last,
+    /// latest login of every user-id from lastlog
+    Lastlog,
+    /// entries of utmp as osquery's `logged_in_users`
+    LoggedInUsers,
+}
+
+/* Functions */
+
+// name the record-type like osquery does
+fn type_name(rtype: RecordType) -> &'static str {
+    match rtype {
+        RecordType::Empty => "empty",
+        RecordType::RunLvl => "runlevel",
+        RecordType::BootTime => "boot_time",
+        RecordType::NewTime => "new_time",
+        RecordType::OldTime => "old_time",
+        RecordType::InitProc => "init",
+        RecordType::LoginProc => "login",
+        RecordType::User => "user",
+        RecordType::DeadProc => "dead",
+        RecordType::Accounting => "accounting",
+        RecordType::Unknown(_) => "unknown",
+    }
+}
+
+// render an optional number, leaving missing values empty
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+/* Implementation */
+
+impl OsqueryTable {
+    /// Name of the table within osquery
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Last => "last",
+            Self::Lastlog => "lastlog",
+            Self::LoggedInUsers => "logged_in_users",
+        }
+    }
+
+    /// Column names and osquery types of the table in schema order
+    pub fn columns(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Self::Last => &LAST_COLUMNS,
+            Self::Lastlog => &LASTLOG_COLUMNS,
+            Self::LoggedInUsers => &LOGGED_IN_USERS_COLUMNS,
+        }
+    }
+
+    /// Render the record as the column values of the table
+    ///
+    /// Returns `None` for records osquery excludes from the table, such as
+    /// wtmp entries other than logins and logouts for `last`.
+    pub fn row(&self, record: &Record) -> Option<Vec<String>> {
+        let time = optional(record.last_login.unix_seconds());
+        match self {
+            Self::Last => {
+                if !matches!(record.rtype, RecordType::User | RecordType::DeadProc) {
+                    return None;
+                }
+                Some(vec![
+                    record.name.clone(),
+                    record.tty.clone(),
+                    optional(record.pid),
+                    i32::from(record.rtype).to_string(),
+                    type_name(record.rtype).to_owned(),
+                    time,
+                    record.host.clone(),
+                ])
+            }
+            Self::Lastlog => Some(vec![
+                optional(record.uid),
+                record.name.clone(),
+                record.tty.clone(),
+                record.host.clone(),
+                time,
+            ]),
+            Self::LoggedInUsers => {
+                if record.pid == Some(1) {
+                    return None;
+                }
+                Some(vec![
+                    type_name(record.rtype).to_owned(),
+                    record.name.clone(),
+                    record.tty.clone(),
+                    record.host.clone(),
+                    time,
+                    optional(record.pid),
+                ])
+            }
+        }
+    }
+
+    // render the records as json objects of string values like osqueryi
+    fn objects<'a>(&self, records: &'a [Record]) -> impl Iterator<Item = Value> + 'a {
+        let columns = self.columns();
+        let table = *self;
+        records.iter().filter_map(move |record| {
+            let row = table.row(record)?;
+            let map: Map<String, Value> = columns
+                .iter()
+                .zip(row)
+                .map(|((name, _), value)| (name.to_string(), Value::String(value)))
+                .collect();
+            Some(Value::Object(map))
+        })
+    }
+
+    /// Render the records as a JSON array matching `osqueryi --json`
+    pub fn to_json(&self, records: &[Record]) -> Result<String> {
+        let rows: Vec<Value> = self.objects(records).collect();
+        serde_json::to_string(&rows).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+    }
+
+    /// Write the records as JSON-lines, one row object per line
+    pub fn write_jsonl<W: Write>(&self, records: &[Record], mut writer: W) -> Result<()> {
+        for row in self.objects(records) {
+            writeln!(writer, "{row}")?;
+        }
+        writer.flush()
+    }
+
+    // query building the table from the records of a sqlite archive
+    fn atc_query(&self) -> String {
+        match self {
+            Self::Last => format!(
+                "SELECT user AS username, tty, pid, type, {TYPE_NAME_SQL} AS type_name, \
+                 time_us / 1000000 AS time, host FROM records \
+                 WHERE kind = 'wtmp' AND type IN (7, 8)"
+            ),
+            Self::Lastlog => "SELECT uid, user AS username, tty, host, \
+                 time_us / 1000000 AS time FROM records r \
+                 WHERE kind = 'lastlog' AND NOT EXISTS (\
+                 SELECT 1 FROM records n WHERE n.kind = 'lastlog' AND n.uid IS r.uid \
+                 AND n.time_us > r.time_us)"
+                .to_owned(),
+            Self::LoggedInUsers => format!(
+                "SELECT {TYPE_NAME_SQL} AS type, user, tty, host, \
+                 time_us / 1000000 AS time, pid FROM records r \
+                 WHERE kind = 'utmp' AND ifnull(pid, 0) != 1 AND NOT EXISTS (\
+                 SELECT 1 FROM records n WHERE n.kind = 'utmp' AND n.tty = r.tty \
+                 AND n.time_us > r.time_us)"
+            ),
+        }
+    }
+
+    /// Build an osquery ATC configuration serving the tables from an archive
+    ///
+    /// The tables are named `lastlog_<name>` since osquery refuses to
+    /// shadow its own tables, and read the [`crate::SqliteArchive`] at the
+    /// given path, which should be updated regularly by a scheduled job.
+    /// Since the archive keeps every entry ever seen, only the latest entry
+    /// of every user-id and of every utmp line is reported by the
+    /// `lastlog_lastlog` and `lastlog_logged_in_users` tables.
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use lastlog::OsqueryTable;
+    ///
+    /// let tables = [OsqueryTable::Last, OsqueryTable::LoggedInUsers];
+    /// let config = OsqueryTable::atc_config(&tables, "/var/lib/lastlog/archive.sqlite");
+    /// let last = &config["auto_table_construction"]["lastlog_last"];
+    /// assert_eq!(last["path"], "/var/lib/lastlog/archive.sqlite");
+    /// assert_eq!(last["columns"][0], "username");
+    /// ```
+    pub fn atc_config(tables: &[Self], archive: &str) -> Value {
+        let atc: Map<String, Value> = tables
+            .iter()
+            .map(|table| {
+                let columns: Vec<&str> = table.columns().iter().map(|(name, _)| *name).collect();
+                let config = json!({
+                    "query": table.atc_query(),
+                    "path": archive,
+                    "columns": columns,
+                    "platform": "posix",
+                });
+                (format!("lastlog_{}", table.name()), config)
+            })
+            .collect();
+        json!({ "auto_table_construction": atc })
+    }
+}
+
+impl FromStr for OsqueryTable {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "last" => Ok(Self::Last),
+            "lastlog" => Ok(Self::Lastlog),
+            "logged_in_users" => Ok(Self::LoggedInUsers),
+            _ => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("unknown osquery table: {s}"),
+            )),
+        }
+    }
+}
@@ -0,0 +1,67 @@
+/*
+ *  Offset/limit pagination for large result sets
+ */
+
+/* Types */
+
+/// Window of results to return from a larger collection
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::Page;
+///
+/// let page = Page::new(0, 50);
+/// let items: Vec<_> = page.apply(0..200).collect();
+/// assert_eq!(items.len(), 50);
+/// assert_eq!(page.next().offset, 50);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Page {
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl Page {
+    /// Generate a page starting at the given offset with at most `limit` items
+    pub fn new(offset: usize, limit: usize) -> Self {
+        Self { offset, limit }
+    }
+
+    /// Retrieve the page directly following this one
+    pub fn next(&self) -> Self {
+        Self::new(self.offset.saturating_add(self.limit), self.limit)
+    }
+
+    /// Apply the page window to an iterator of items
+    ///
+    /// Only the items within the window are ever yielded, allowing large
+    /// streams to be paged without collecting them first.
+    pub fn apply<I: IntoIterator>(&self, items: I) -> impl Iterator<Item = I::Item> {
+        items.into_iter().skip(self.offset).take(self.limit)
+    }
+}
+
+/// Single page of results alongside the size of the complete collection
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Paged<T> {
+    pub items: Vec<T>,
+    pub page: Page,
+    pub total: usize,
+}
+
+impl<T> Paged<T> {
+    /// Slice a page of results out of a complete collection
+    pub fn new(items: Vec<T>, page: Page) -> Self {
+        let total = items.len();
+        let items = page.apply(items).collect();
+        Self { items, page, total }
+    }
+
+    /// Check if there are more results available after this page
+    pub fn has_more(&self) -> bool {
+        self.page.offset.saturating_add(self.items.len()) < self.total
+    }
+}
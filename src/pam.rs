@@ -0,0 +1,123 @@
+/*
+ *  Login bookkeeping helpers for pam modules and custom login services
+ */
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+use std::time::SystemTime;
+
+use super::banner::format_banner;
+use super::common::*;
+use super::lastlog::LastLog;
+use super::utmp::{Utmp, UtmpReader};
+
+/* Variables */
+
+static LASTLOG: &str = "/var/log/lastlog";
+static WTMP: &str = "/var/log/wtmp";
+static BTMP: &str = "/var/log/btmp";
+
+/* Types */
+
+/// Databases updated and reported on by the pam helpers
+///
+/// Databases that do not exist are skipped rather than created, matching
+/// `pam_lastlog` on systems where lastlog has been disabled.
+#[derive(Debug, Clone)]
+pub struct LoginFiles {
+    pub lastlog: String,
+    pub wtmp: String,
+    pub btmp: String,
+}
+
+impl Default for LoginFiles {
+    fn default() -> Self {
+        Self {
+            lastlog: LASTLOG.to_owned(),
+            wtmp: WTMP.to_owned(),
+            btmp: BTMP.to_owned(),
+        }
+    }
+}
+
+/* Functions */
+
+/// Record a new login of the user within lastlog and wtmp
+///
+/// Writes the lastlog entry of the user and appends a `USER_PROCESS` entry
+/// for the current process to wtmp, just like `pam_lastlog` does when a
+/// session is opened. Requires write access to the databases (usually root).
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```no_run
+/// lastlog::note_login("root", "pts/3", "10.0.0.5").unwrap();
+/// ```
+pub fn note_login(user: &str, tty: &str, host: &str) -> Result<()> {
+    note_login_with(&LoginFiles::default(), user, tty, host)
+}
+
+/// Record a new login of the user within the given databases
+pub fn note_login_with(files: &LoginFiles, user: &str, tty: &str, host: &str) -> Result<()> {
+    let uid = read_passwd_nmap()
+        .get(user)
+        .copied()
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, format!("no such user {user:?}")))?;
+    let record = Record::builder()
+        .rtype(RecordType::User)
+        .uid(uid)
+        .name(user)
+        .tty(tty)
+        .host(host)
+        .pid(std::process::id() as i32)
+        .last_login(SystemTime::now())
+        .build();
+    if Path::new(&files.lastlog).exists() {
+        LastLog {}.write(&record, &files.lastlog)?;
+    }
+    if Path::new(&files.wtmp).exists() {
+        Utmp {}.append(&record, &files.wtmp)?;
+    }
+    Ok(())
+}
+
+/// Format the previous login banner of the user before noting a new login
+///
+/// The previous login is read from lastlog (falling back to the newest wtmp
+/// entry when lastlog is missing) and failed attempts since then are
+/// counted from btmp when readable. Returns `None` for a first login.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// if let Ok(Some(banner)) = lastlog::report_previous_login("root") {
+///     println!("{banner}");
+/// }
+/// ```
+pub fn report_previous_login(user: &str) -> Result<Option<String>> {
+    report_previous_login_with(&LoginFiles::default(), user)
+}
+
+/// Format the previous login banner of the user using the given databases
+pub fn report_previous_login_with(files: &LoginFiles, user: &str) -> Result<Option<String>> {
+    let record = match Path::new(&files.lastlog).exists() {
+        true => LastLog {}.search_username(user, &files.lastlog)?,
+        false => match (Utmp {}).search_username(user, &files.wtmp) {
+            Ok(record) => record,
+            // wtmp holds no entry at all for users that never logged in
+            Err(err) if err.kind() == ErrorKind::InvalidInput => return Ok(None),
+            Err(err) => return Err(err),
+        },
+    };
+    let failed: Vec<Record> = match UtmpReader::open(&files.btmp) {
+        Ok(reader) => reader
+            .filter_map(|r| r.ok())
+            .filter(|r| r.name == user)
+            .collect(),
+        Err(_) => vec![],
+    };
+    Ok(format_banner(&record, &failed))
+}
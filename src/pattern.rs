@@ -0,0 +1,177 @@
+/*
+ *  Username pattern matching for account searches
+ */
+#[cfg(feature = "regex")]
+use std::io::{Error, ErrorKind, Result};
+
+/* Types */
+
+/// Pattern used to match usernames when searching for several accounts
+///
+/// Globs support `*`, `?` and bracketed character classes such as
+/// `[a-z]` or `[!0-9]`. Regular expressions are available when the
+/// `regex` feature is enabled.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::Pattern;
+///
+/// let pattern = Pattern::glob("svc-*");
+/// assert!(pattern.matches("svc-backup"));
+/// assert!(!pattern.matches("admin"));
+/// ```
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Glob(String),
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+}
+
+impl Pattern {
+    /// Generate a new glob pattern
+    pub fn glob(pattern: impl Into<String>) -> Self {
+        Self::Glob(pattern.into())
+    }
+
+    /// Compile a new regular-expression pattern
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use lastlog::Pattern;
+    ///
+    /// let pattern = Pattern::regex("^admin").unwrap();
+    /// assert!(pattern.matches("admin2"));
+    /// ```
+    #[cfg(feature = "regex")]
+    pub fn regex(pattern: &str) -> Result<Self> {
+        regex::Regex::new(pattern)
+            .map(Self::Regex)
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e))
+    }
+
+    /// Check if the given username matches the pattern
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            Self::Glob(glob) => glob_match(glob.as_bytes(), name.as_bytes()),
+            #[cfg(feature = "regex")]
+            Self::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
+
+impl From<&str> for Pattern {
+    fn from(value: &str) -> Self {
+        Self::glob(value)
+    }
+}
+
+impl From<String> for Pattern {
+    fn from(value: String) -> Self {
+        Self::Glob(value)
+    }
+}
+
+/// Regular expressions compare equal when compiled from the same source.
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Glob(a), Self::Glob(b)) => a == b,
+            #[cfg(feature = "regex")]
+            (Self::Regex(a), Self::Regex(b)) => a.as_str() == b.as_str(),
+            #[cfg(feature = "regex")]
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Pattern {}
+
+#[cfg(feature = "regex")]
+impl From<regex::Regex> for Pattern {
+    fn from(value: regex::Regex) -> Self {
+        Self::Regex(value)
+    }
+}
+
+/* Functions */
+
+// match a bracketed character class starting after the `[`
+// returning whether it matched and the length of the class
+fn class_match(class: &[u8], c: u8) -> Option<(bool, usize)> {
+    let mut i = 0;
+    let negate = matches!(class.first(), Some(b'!') | Some(b'^'));
+    if negate {
+        i += 1;
+    }
+    let mut matched = false;
+    let mut first = true;
+    while i < class.len() {
+        if class[i] == b']' && !first {
+            return Some((matched != negate, i + 1));
+        }
+        first = false;
+        if i + 2 < class.len() && class[i + 1] == b'-' && class[i + 2] != b']' {
+            matched |= class[i] <= c && c <= class[i + 2];
+            i += 3;
+            continue;
+        }
+        matched |= class[i] == c;
+        i += 1;
+    }
+    None
+}
+
+// iterative glob matcher with single-star backtracking
+fn glob_match(pattern: &[u8], name: &[u8]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                star = Some((p, n));
+                p += 1;
+                continue;
+            }
+            Some(b'?') => {
+                p += 1;
+                n += 1;
+                continue;
+            }
+            Some(b'[') => {
+                // unterminated classes are treated as a literal `[`
+                match class_match(&pattern[p + 1..], name[n]) {
+                    Some((true, len)) => {
+                        p += len + 1;
+                        n += 1;
+                        continue;
+                    }
+                    Some((false, _)) => {}
+                    None if name[n] == b'[' => {
+                        p += 1;
+                        n += 1;
+                        continue;
+                    }
+                    None => {}
+                }
+            }
+            Some(c) if *c == name[n] => {
+                p += 1;
+                n += 1;
+                continue;
+            }
+            _ => {}
+        }
+        // backtrack to the last star and consume one more character
+        let Some((sp, sn)) = star else { return false };
+        star = Some((sp, sn + 1));
+        p = sp + 1;
+        n = sn + 1;
+    }
+    pattern[p..].iter().all(|c| *c == b'*')
+}
@@ -0,0 +1,195 @@
+/*
+ *  Composable record stream pipeline: source, filter, dedupe, enrich, sink
+ */
+use std::collections::HashMap;
+use std::io::Result;
+
+use super::common::*;
+use super::filter::Filter;
+#[cfg(feature = "redact")]
+use super::redact::Redactor;
+use super::sink::RecordSink;
+use super::sort::{sort_records, Order, SortBy};
+use super::utmp::UtmpReader;
+
+/* Types */
+
+/// Lazily evaluated chain of stages applied to a stream of records
+///
+/// A pipeline starts from any source of records, such as a streamed utmp
+/// database, passes every record through the configured stages in order
+/// and finally hands the results to a sink. Stages only run while the
+/// pipeline is consumed, so streamed sources are never fully loaded
+/// unless a stage such as [`Pipeline::latest`] needs every record.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{Filter, Pipeline};
+///
+/// if let Ok(pipeline) = Pipeline::open("/var/log/wtmp") {
+///     let count = pipeline
+///         .filter(Filter::parse("type == USER_PROCESS && uid >= 1000").unwrap())
+///         .latest()
+///         .enrich(|record| record.host.make_ascii_lowercase())
+///         .sink(|record| {
+///             println!("{} {}", record.name, record.host);
+///             Ok(())
+///         })
+///         .unwrap();
+///     println!("{count} users");
+/// }
+/// ```
+pub struct Pipeline<'a> {
+    records: Box<dyn Iterator<Item = Record> + 'a>,
+}
+
+/* Implementation */
+
+impl<'a> Pipeline<'a> {
+    /// Start a pipeline from any collection or iterator of records
+    pub fn new<I>(records: I) -> Self
+    where
+        I: IntoIterator<Item = Record>,
+        I::IntoIter: 'a,
+    {
+        Self {
+            records: Box::new(records.into_iter()),
+        }
+    }
+
+    /// Start a pipeline streaming every entry of a utmp/wtmp/btmp database
+    ///
+    /// Entries that cannot be parsed are skipped.
+    pub fn open(fname: &str) -> Result<Self> {
+        let reader = UtmpReader::open(fname)?;
+        Ok(Self::new(reader.filter_map(|r| r.ok())))
+    }
+
+    /// Start a pipeline from the latest login of every account on the system
+    pub fn accounts() -> Result<Self> {
+        Ok(Self::new(super::iter_accounts()?))
+    }
+
+    /// Append the records of another pipeline after this one
+    ///
+    /// Combined with [`Pipeline::latest`] this merges several databases,
+    /// such as rotated wtmp files or the databases of several machines.
+    pub fn chain(self, other: Pipeline<'a>) -> Self {
+        Self::new(self.records.chain(other.records))
+    }
+
+    /// Keep only the records matching the filter expression
+    pub fn filter(self, filter: Filter) -> Self {
+        self.retain(move |record| filter.matches(record))
+    }
+
+    /// Keep only the records the predicate returns `true` for
+    pub fn retain<F>(self, mut predicate: F) -> Self
+    where
+        F: FnMut(&Record) -> bool + 'a,
+    {
+        Self::new(self.records.filter(move |record| predicate(record)))
+    }
+
+    /// Keep only the latest record of every username
+    ///
+    /// When two records share the same login-time the later one in the
+    /// stream wins. Every record is read before the next stage runs.
+    pub fn latest(self) -> Self {
+        let mut latest: HashMap<String, Record> = HashMap::new();
+        for record in self.records {
+            match latest.get(&record.name) {
+                Some(old) if old.last_login > record.last_login => {}
+                _ => {
+                    latest.insert(record.name.clone(), record);
+                }
+            }
+        }
+        Self::new(latest.into_values())
+    }
+
+    /// Modify every record passing through the pipeline
+    pub fn enrich<F>(self, mut enrich: F) -> Self
+    where
+        F: FnMut(&mut Record) + 'a,
+    {
+        Self::new(self.records.map(move |mut record| {
+            enrich(&mut record);
+            record
+        }))
+    }
+
+    /// Fill in the missing user-ids of records using the given users
+    pub fn resolve_users(self, users: &'a UserMap) -> Self {
+        self.enrich(move |record| {
+            if record.uid.is_none() {
+                record.uid = users.uid(&record.name);
+            }
+        })
+    }
+
+    /// Tag every record without a machine with the given hostname
+    pub fn machine(self, machine: impl Into<String>) -> Self {
+        let machine = machine.into();
+        self.enrich(move |record| {
+            record.machine.get_or_insert_with(|| machine.clone());
+        })
+    }
+
+    /// Anonymize every record using the given redaction rules
+    #[cfg(feature = "redact")]
+    pub fn redact(self, redactor: Redactor) -> Self {
+        Self::new(self.records.map(move |record| redactor.redact(&record)))
+    }
+
+    /// Sort the records, reading every record before the next stage runs
+    pub fn sorted(self, by: SortBy, order: Order) -> Self {
+        let mut records: Vec<_> = self.records.collect();
+        sort_records(&mut records, by, order);
+        Self::new(records)
+    }
+
+    /// Stop after the given number of records
+    pub fn limit(self, limit: usize) -> Self {
+        Self::new(self.records.take(limit))
+    }
+
+    /// Pass every resulting record to the sink, returning how many were written
+    ///
+    /// The pipeline stops at the first error returned by the sink.
+    pub fn sink<F>(self, mut sink: F) -> Result<usize>
+    where
+        F: FnMut(Record) -> Result<()>,
+    {
+        let mut count = 0;
+        for record in self.records {
+            sink(record)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Stream every resulting record into the sink and finish it
+    ///
+    /// Returns how many records were written, stopping at the first error.
+    pub fn write_to<S: RecordSink + ?Sized>(self, sink: &mut S) -> Result<usize> {
+        let mut count = 0;
+        for record in self.records {
+            sink.write(&record)?;
+            count += 1;
+        }
+        sink.finish()?;
+        Ok(count)
+    }
+}
+
+impl Iterator for Pipeline<'_> {
+    type Item = Record;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
@@ -0,0 +1,268 @@
+/*
+ *  Dormant account policies evaluated against the combined account report
+ */
+use std::collections::HashMap;
+use std::io::Result;
+use std::time::{Duration, SystemTime};
+
+use super::account::{accounts, Account};
+use super::common::*;
+use super::finding::{Finding, FindingKind, Severity};
+use super::suppress::Suppressions;
+
+/* Variables */
+
+// first and last uid of regular user accounts (see login.defs(5))
+static UID_MIN: u32 = 1000;
+static UID_MAX: u32 = 60000;
+
+static DAY: u64 = 24 * 60 * 60;
+
+// shells that refuse interactive logins
+static NO_LOGIN_SHELLS: [&str; 3] = ["nologin", "false", "sync"];
+
+/* Types */
+
+/// Kind of account a dormancy rule applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AccountClass {
+    /// regular account of a person, within the login.defs uid range
+    Human,
+    /// system or daemon account outside of the uid range or without a shell
+    Service,
+}
+
+/// Rules describing how long accounts may go without logging in
+///
+/// By default human accounts must log in every 90 days and service
+/// accounts are exempt, matching the most common audit requirement.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use std::time::Duration;
+/// use lastlog::DormancyPolicy;
+///
+/// let policy = DormancyPolicy::new()
+///     .human(Some(Duration::from_secs(90 * 24 * 60 * 60)))
+///     .exempt("backup")
+///     .ignore_locked(true);
+/// if let Ok(violations) = policy.check() {
+///     for violation in violations {
+///         println!("{} idle for {:?}", violation.account.name, violation.idle);
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DormancyPolicy {
+    human: Option<Duration>,
+    service: Option<Duration>,
+    uid_range: (u32, u32),
+    exempt: Vec<String>,
+    ignore_locked: bool,
+    suppress: Suppressions,
+}
+
+/// Account breaking a [`DormancyPolicy`] alongside the evidence for it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DormancyViolation {
+    /// account that has been dormant for too long
+    pub account: Account,
+    /// class of the account the rule was selected for
+    pub class: AccountClass,
+    /// longest time the account was allowed to go without a login
+    pub max_idle: Duration,
+    /// time since the last login, `None` when it never logged in
+    pub idle: Option<Duration>,
+    /// latest login record of the account, `None` when it never logged in
+    pub evidence: Option<Record>,
+}
+
+/* Implementation */
+
+impl AccountClass {
+    /// Classify an account by its user-id and login shell
+    pub fn of(account: &Account, uid_range: (u32, u32)) -> Self {
+        let shell = account.shell.rsplit('/').next().unwrap_or_default();
+        let human = (uid_range.0..=uid_range.1).contains(&account.uid)
+            && !shell.is_empty()
+            && !NO_LOGIN_SHELLS.contains(&shell);
+        match human {
+            true => Self::Human,
+            false => Self::Service,
+        }
+    }
+}
+
+impl DormancyPolicy {
+    /// Require human accounts to log in every 90 days, exempting services
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how long human accounts may stay idle, `None` exempts them
+    pub fn human(mut self, max_idle: Option<Duration>) -> Self {
+        self.human = max_idle;
+        self
+    }
+
+    /// Set how long service accounts may stay idle, `None` exempts them
+    pub fn service(mut self, max_idle: Option<Duration>) -> Self {
+        self.service = max_idle;
+        self
+    }
+
+    /// Set the user-id range of human accounts (inclusive)
+    pub fn uid_range(mut self, min: u32, max: u32) -> Self {
+        self.uid_range = (min, max);
+        self
+    }
+
+    /// Exempt the given username from every rule
+    pub fn exempt(mut self, name: impl Into<String>) -> Self {
+        self.exempt.push(name.into());
+        self
+    }
+
+    /// Skip accounts whose password is locked, since they cannot log in
+    pub fn ignore_locked(mut self, ignore: bool) -> Self {
+        self.ignore_locked = ignore;
+        self
+    }
+
+    /// Skip violations whose [`Finding`] is suppressed by any of the rules
+    pub fn suppress(mut self, rules: Suppressions) -> Self {
+        self.suppress = rules;
+        self
+    }
+
+    /// Evaluate the policy against the accounts of this system
+    pub fn check(&self) -> Result<Vec<DormancyViolation>> {
+        let records = super::iter_accounts().unwrap_or_default();
+        Ok(self.evaluate(&accounts()?, &records, SystemTime::now()))
+    }
+
+    /// Evaluate the policy against the accounts of this system as findings
+    pub fn findings(&self) -> Result<Vec<Finding>> {
+        Ok(self.check()?.into_iter().map(Finding::from).collect())
+    }
+
+    /// Evaluate the policy against the given accounts at the given time
+    ///
+    /// The latest login records (such as from `iter_accounts`) are matched
+    /// by user-id and attached as evidence of each violation.
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use std::time::SystemTime;
+    /// use lastlog::DormancyPolicy;
+    ///
+    /// let accounts = lastlog::accounts().unwrap_or_default();
+    /// let records = lastlog::iter_accounts().unwrap_or_default();
+    /// let violations = DormancyPolicy::new().evaluate(&accounts, &records, SystemTime::now());
+    /// assert!(violations.len() <= accounts.len());
+    /// ```
+    pub fn evaluate(
+        &self,
+        accounts: &[Account],
+        records: &[Record],
+        now: SystemTime,
+    ) -> Vec<DormancyViolation> {
+        let evidence: HashMap<u32, &Record> = records
+            .iter()
+            .filter(|r| !r.last_login.is_never())
+            .filter_map(|r| Some((r.uid?, r)))
+            .collect();
+        let mut violations = vec![];
+        for account in accounts {
+            if self.exempt.contains(&account.name)
+                || (self.ignore_locked && account.locked == Some(true))
+            {
+                continue;
+            }
+            let class = AccountClass::of(account, self.uid_range);
+            let max_idle = match class {
+                AccountClass::Human => self.human,
+                AccountClass::Service => self.service,
+            };
+            let Some(max_idle) = max_idle else { continue };
+            let idle = match &account.last_login {
+                LoginTime::Never => None,
+                LoginTime::Last(time) => Some(now.duration_since(*time).unwrap_or_default()),
+            };
+            if idle.is_some_and(|idle| idle <= max_idle) {
+                continue;
+            }
+            let violation = DormancyViolation {
+                account: account.clone(),
+                class,
+                max_idle,
+                idle,
+                evidence: evidence.get(&account.uid).map(|r| (*r).clone()),
+            };
+            if self.suppress.matches(&Finding::from(violation.clone())) {
+                continue;
+            }
+            violations.push(violation);
+        }
+        violations
+    }
+}
+
+impl Default for DormancyPolicy {
+    fn default() -> Self {
+        Self {
+            human: Some(Duration::from_secs(90 * DAY)),
+            service: None,
+            uid_range: (UID_MIN, UID_MAX),
+            exempt: vec![],
+            ignore_locked: false,
+            suppress: Suppressions::new(),
+        }
+    }
+}
+
+/// Dormant human accounts are reported with medium severity and service
+/// accounts with low severity, carrying the latest login as evidence.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use std::time::{Duration, SystemTime};
+/// use lastlog::{DormancyPolicy, Finding, FindingKind};
+///
+/// let accounts = lastlog::accounts().unwrap_or_default();
+/// let records = lastlog::iter_accounts().unwrap_or_default();
+/// let findings: Vec<Finding> = DormancyPolicy::new()
+///     .evaluate(&accounts, &records, SystemTime::now())
+///     .into_iter()
+///     .map(Finding::from)
+///     .collect();
+/// assert!(findings.iter().all(|f| f.kind == FindingKind::Dormant));
+/// ```
+impl From<DormancyViolation> for Finding {
+    fn from(violation: DormancyViolation) -> Self {
+        let severity = match violation.class {
+            AccountClass::Human => Severity::Medium,
+            AccountClass::Service => Severity::Low,
+        };
+        let allowed = violation.max_idle.as_secs() / DAY;
+        let description = match violation.idle {
+            Some(idle) => format!(
+                "not logged in for {} days (at most {allowed} allowed)",
+                idle.as_secs() / DAY
+            ),
+            None => format!("never logged in (at most {allowed} idle days allowed)"),
+        };
+        Finding::new(FindingKind::Dormant, severity, violation.account.name)
+            .description(description)
+            .evidence(violation.evidence.into_iter().collect())
+    }
+}
@@ -0,0 +1,176 @@
+/*
+ *  Python bindings exposing the lookups as the `lastlog` module
+ */
+use std::time::SystemTime;
+
+use pyo3::prelude::*;
+
+use super::common::*;
+use super::format::RFC3339_TIME;
+use super::session::{sessions_from, Session};
+use super::{iter_accounts, search_uid, search_username};
+
+/* Variables */
+
+static WTMP: &str = "/var/log/wtmp";
+
+/* Types */
+
+/// Python view of a login record
+///
+/// Exposes `type`, `uid`, `user`, `tty`, `host`, `pid`, `last_login`
+/// (a UTC `datetime`, `None` if never logged in) and `machine` as
+/// read-only attributes.
+#[pyclass(
+    name = "Record",
+    module = "lastlog",
+    frozen,
+    get_all,
+    eq,
+    skip_from_py_object
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PyRecord {
+    #[pyo3(name = "type")]
+    rtype: String,
+    uid: Option<u32>,
+    user: String,
+    tty: String,
+    host: String,
+    pid: Option<i32>,
+    last_login: Option<SystemTime>,
+    machine: Option<String>,
+}
+
+/// Python view of a login session paired from wtmp history
+///
+/// Exposes the starting `record`, how the session ended as `end`
+/// (`active`, `logout`, `down` or `crash`) and the matching `logout` time.
+#[pyclass(
+    name = "Session",
+    module = "lastlog",
+    frozen,
+    get_all,
+    eq,
+    skip_from_py_object
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PySession {
+    record: PyRecord,
+    end: String,
+    logout: Option<SystemTime>,
+}
+
+/* Functions */
+
+// render an optional value the way python would repr it
+fn repr<T: ToString>(value: Option<T>) -> String {
+    value.map_or("None".to_owned(), |v| v.to_string())
+}
+
+// render an optional time as an RFC3339 timestamp within a repr
+fn repr_time(time: Option<SystemTime>) -> String {
+    repr(
+        LoginTime::from(time)
+            .format_utc(RFC3339_TIME)
+            .map(|t| format!("{t:?}")),
+    )
+}
+
+/* Implementation */
+
+impl From<Record> for PyRecord {
+    fn from(record: Record) -> Self {
+        Self {
+            rtype: match record.rtype.name() {
+                Some(name) => name.to_owned(),
+                None => i32::from(record.rtype).to_string(),
+            },
+            uid: record.uid,
+            user: record.name,
+            tty: record.tty,
+            host: record.host,
+            pid: record.pid,
+            last_login: record.last_login.into(),
+            machine: record.machine,
+        }
+    }
+}
+
+impl From<Session> for PySession {
+    fn from(session: Session) -> Self {
+        Self {
+            end: session.end.kind().to_owned(),
+            logout: session.end.time(),
+            record: session.record.into(),
+        }
+    }
+}
+
+#[pymethods]
+impl PyRecord {
+    fn __repr__(&self) -> String {
+        format!(
+            "Record(type={:?}, uid={}, user={:?}, tty={:?}, host={:?}, pid={}, last_login={})",
+            self.rtype,
+            repr(self.uid),
+            self.user,
+            self.tty,
+            self.host,
+            repr(self.pid),
+            repr_time(self.last_login)
+        )
+    }
+}
+
+#[pymethods]
+impl PySession {
+    fn __repr__(&self) -> String {
+        format!(
+            "Session(record={}, end={:?}, logout={})",
+            self.record.__repr__(),
+            self.end,
+            repr_time(self.logout)
+        )
+    }
+}
+
+/// Find the latest login of the given user-id
+#[pyfunction(name = "search_uid")]
+fn py_search_uid(uid: u32) -> PyResult<PyRecord> {
+    Ok(search_uid(uid)?.into())
+}
+
+/// Find the latest login of the given username
+#[pyfunction(name = "search_username")]
+fn py_search_username(username: &str) -> PyResult<PyRecord> {
+    Ok(search_username(username)?.into())
+}
+
+/// Collect the latest login of every user
+#[pyfunction(name = "iter_accounts")]
+fn py_iter_accounts() -> PyResult<Vec<PyRecord>> {
+    Ok(iter_accounts()?.into_iter().map(PyRecord::from).collect())
+}
+
+/// Pair the login sessions of a wtmp database, newest first
+#[pyfunction(name = "login_history", signature = (path = None, limit = None))]
+fn py_login_history(path: Option<&str>, limit: Option<usize>) -> PyResult<Vec<PySession>> {
+    let sessions = sessions_from(path.unwrap_or(WTMP))?;
+    Ok(sessions
+        .take(limit.unwrap_or(usize::MAX))
+        .map(PySession::from)
+        .collect())
+}
+
+/// Python module exposing login lookups of the `lastlog` crate
+#[pymodule]
+fn lastlog(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyRecord>()?;
+    m.add_class::<PySession>()?;
+    m.add_function(wrap_pyfunction!(py_search_uid, m)?)?;
+    m.add_function(wrap_pyfunction!(py_search_username, m)?)?;
+    m.add_function(wrap_pyfunction!(py_iter_accounts, m)?)?;
+    m.add_function(wrap_pyfunction!(py_login_history, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,337 @@
+/*
+ *  `no_std` decoding of raw utmp and lastlog database entries
+ */
+use core::fmt;
+use core::mem::size_of;
+use core::str;
+
+use bytemuck::{Pod, Zeroable};
+
+/* Variables */
+
+/// Size in bytes of a single utmp/wtmp/btmp entry
+pub const UTMP_SIZE: usize = size_of::<UtmpEntry>();
+
+/// Size in bytes of a single lastlog entry
+pub const LASTLOG_SIZE: usize = size_of::<LastlogEntry>();
+
+/* Types */
+
+/// Utmp RecordType
+/// (https://man7.org/linux/man-pages/man5/utmp.5.html)
+///
+/// Record-types not covered by the man-page are preserved as `Unknown`
+/// alongside their raw `ut_type` value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub enum RecordType {
+    Empty,
+    RunLvl,
+    BootTime,
+    NewTime,
+    OldTime,
+    InitProc,
+    LoginProc,
+    User,
+    DeadProc,
+    Accounting,
+    Unknown(i32),
+}
+
+impl RecordType {
+    /// Check if the record-type is one of the documented utmp types
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use lastlog::RecordType;
+    ///
+    /// assert!(RecordType::from(7).is_known());
+    /// assert_eq!(RecordType::from(42), RecordType::Unknown(42));
+    /// ```
+    pub fn is_known(&self) -> bool {
+        !matches!(self, Self::Unknown(_))
+    }
+
+    /// Retrieve the utmp(5) constant name of a known record-type
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use lastlog::RecordType;
+    ///
+    /// assert_eq!(RecordType::User.name(), Some("USER_PROCESS"));
+    /// assert_eq!(RecordType::Unknown(42).name(), None);
+    /// ```
+    pub fn name(&self) -> Option<&'static str> {
+        Some(match self {
+            Self::Empty => "EMPTY",
+            Self::RunLvl => "RUN_LVL",
+            Self::BootTime => "BOOT_TIME",
+            Self::NewTime => "NEW_TIME",
+            Self::OldTime => "OLD_TIME",
+            Self::InitProc => "INIT_PROCESS",
+            Self::LoginProc => "LOGIN_PROCESS",
+            Self::User => "USER_PROCESS",
+            Self::DeadProc => "DEAD_PROCESS",
+            Self::Accounting => "ACCOUNTING",
+            Self::Unknown(_) => return None,
+        })
+    }
+}
+
+impl From<i32> for RecordType {
+    fn from(value: i32) -> Self {
+        match value {
+            0 => Self::Empty,
+            1 => Self::RunLvl,
+            2 => Self::BootTime,
+            3 => Self::NewTime,
+            4 => Self::OldTime,
+            5 => Self::InitProc,
+            6 => Self::LoginProc,
+            7 => Self::User,
+            8 => Self::DeadProc,
+            9 => Self::Accounting,
+            _ => Self::Unknown(value),
+        }
+    }
+}
+
+impl From<RecordType> for i32 {
+    fn from(value: RecordType) -> Self {
+        match value {
+            RecordType::Empty => 0,
+            RecordType::RunLvl => 1,
+            RecordType::BootTime => 2,
+            RecordType::NewTime => 3,
+            RecordType::OldTime => 4,
+            RecordType::InitProc => 5,
+            RecordType::LoginProc => 6,
+            RecordType::User => 7,
+            RecordType::DeadProc => 8,
+            RecordType::Accounting => 9,
+            RecordType::Unknown(value) => value,
+        }
+    }
+}
+
+/// Raw seconds and microseconds since the unix epoch as stored on disk
+///
+/// A zero timestamp means the entry was never written (never logged in).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub struct Timestamp {
+    pub sec: u32,
+    pub usec: u32,
+}
+
+impl Timestamp {
+    /// Check if the timestamp represents a login that never happened
+    pub fn is_never(&self) -> bool {
+        self.sec == 0
+    }
+}
+
+/// Failure to decode a raw database entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// Fewer bytes than a complete entry were given
+    Truncated { expected: usize, found: usize },
+    /// The named text field is not valid utf-8
+    InvalidString(&'static str),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated { expected, found } => {
+                write!(f, "truncated entry ({found} of {expected} bytes)")
+            }
+            Self::InvalidString(name) => write!(f, "invalid {name}"),
+        }
+    }
+}
+
+/// Single raw utmp(5) entry as laid out by glibc on Linux
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::raw::{UtmpEntry, UTMP_SIZE};
+/// use lastlog::RecordType;
+///
+/// let mut bytes = [0u8; UTMP_SIZE];
+/// bytes[0] = 7;
+/// bytes[44..47].copy_from_slice(b"foo");
+/// let entry = UtmpEntry::decode(&bytes).unwrap();
+/// assert_eq!(entry.record_type(), RecordType::User);
+/// assert_eq!(entry.user().unwrap(), "foo");
+/// assert!(entry.time().is_never());
+/// ```
+#[repr(C, packed)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub struct UtmpEntry {
+    //NOTE: rtype size by all recorded documentation should i16
+    // yet for some reason it's actually an i32 and i have no idea why
+    pub rtype: i32,
+    pub pid: i32,
+    pub line: [u8; 32],
+    pub id: [u8; 4],
+    pub user: [u8; 32],
+    pub host: [u8; 256],
+    pub exit: [i16; 2],
+    pub session: i32,
+    pub sec: i32,
+    pub usec: i32,
+    pub addr: [i32; 4],
+    pub unused: [u8; 20],
+}
+
+/// Single raw lastlog(8) entry, stored at the offset of its user-id
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::raw::{LastlogEntry, LASTLOG_SIZE};
+///
+/// let entry = LastlogEntry::decode(&[0u8; LASTLOG_SIZE]).unwrap();
+/// assert!(entry.time().is_never());
+/// assert!(LastlogEntry::decode(&[0u8; 16]).is_err());
+/// ```
+#[repr(C, packed)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub struct LastlogEntry {
+    pub time: u32,
+    pub line: [u8; 32],
+    pub host: [u8; 256],
+}
+
+/* Functions */
+
+// decode a plain-old-data entry from the start of the given bytes
+fn decode<T: Pod>(bytes: &[u8]) -> Result<T, DecodeError> {
+    let expected = size_of::<T>();
+    match bytes.get(..expected) {
+        Some(bytes) => Ok(bytemuck::pod_read_unaligned(bytes)),
+        None => Err(DecodeError::Truncated {
+            expected,
+            found: bytes.len(),
+        }),
+    }
+}
+
+// view a nul terminated C string field as text
+//
+// bytes following the terminator are left-overs of older entries and ignored
+fn text<'a>(name: &'static str, bytes: &'a [u8]) -> Result<&'a str, DecodeError> {
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    str::from_utf8(&bytes[..end]).map_err(|_| DecodeError::InvalidString(name))
+}
+
+/// Copy a string into a fixed-size nul padded C array, truncating if needed
+///
+/// Truncation never splits a multi-byte character, so the stored field
+/// always decodes again.
+pub fn fixed_bytes<const N: usize>(value: &str) -> [u8; N] {
+    let mut buffer = [0; N];
+    let mut len = value.len().min(N);
+    while !value.is_char_boundary(len) {
+        len -= 1;
+    }
+    buffer[..len].copy_from_slice(&value.as_bytes()[..len]);
+    buffer
+}
+
+/* Implementation */
+
+impl UtmpEntry {
+    /// Decode an entry from the first [`UTMP_SIZE`] bytes
+    ///
+    /// Fails if too few bytes are given or a text field is not utf-8.
+    pub fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let entry: Self = decode(bytes)?;
+        entry.line()?;
+        entry.user()?;
+        entry.host()?;
+        Ok(entry)
+    }
+
+    /// Encode the entry into its raw on-disk form
+    pub fn encode(&self) -> [u8; UTMP_SIZE] {
+        bytemuck::cast(*self)
+    }
+
+    /// Retrieve the record-type (`ut_type`) of the entry
+    pub fn record_type(&self) -> RecordType {
+        RecordType::from(self.rtype)
+    }
+
+    /// Retrieve the tty of the entry (`ut_line`)
+    pub fn line(&self) -> Result<&str, DecodeError> {
+        text("tty", &self.line)
+    }
+
+    /// Retrieve the username of the entry (`ut_user`)
+    pub fn user(&self) -> Result<&str, DecodeError> {
+        text("username", &self.user)
+    }
+
+    /// Retrieve the remote host of the entry (`ut_host`)
+    pub fn host(&self) -> Result<&str, DecodeError> {
+        text("host", &self.host)
+    }
+
+    /// Retrieve the time the entry was written
+    pub fn time(&self) -> Timestamp {
+        Timestamp {
+            sec: self.sec as u32,
+            usec: self.usec as u32,
+        }
+    }
+}
+
+impl LastlogEntry {
+    /// Decode an entry from the first [`LASTLOG_SIZE`] bytes
+    ///
+    /// Fails if too few bytes are given or a text field is not utf-8.
+    pub fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let entry: Self = decode(bytes)?;
+        entry.line()?;
+        entry.host()?;
+        Ok(entry)
+    }
+
+    /// Encode the entry into its raw on-disk form
+    pub fn encode(&self) -> [u8; LASTLOG_SIZE] {
+        bytemuck::cast(*self)
+    }
+
+    /// Retrieve the tty of the last login
+    pub fn line(&self) -> Result<&str, DecodeError> {
+        text("tty", &self.line)
+    }
+
+    /// Retrieve the remote host of the last login
+    pub fn host(&self) -> Result<&str, DecodeError> {
+        text("host", &self.host)
+    }
+
+    /// Retrieve the time of the last login
+    pub fn time(&self) -> Timestamp {
+        Timestamp {
+            sec: self.time,
+            usec: 0,
+        }
+    }
+}
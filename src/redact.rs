@@ -0,0 +1,241 @@
+/*
+ *  Anonymization of usernames, hosts and addresses before sharing records
+ */
+use std::fmt::Write;
+use std::net::IpAddr;
+
+use sha2::{Digest, Sha256};
+
+use super::common::*;
+
+/* Variables */
+
+// number of hex characters kept from every keyed digest
+static HASH_LENGTH: usize = 16;
+
+/* Types */
+
+/// How a single field of a record is rewritten by a [`Redactor`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Redaction {
+    /// leave the field untouched
+    #[default]
+    Keep,
+    /// replace the field with a salted SHA-256 pseudonym
+    ///
+    /// Equal values map to the same pseudonym for the same salt, so logins
+    /// of an account can still be correlated without revealing it.
+    Hash,
+    /// hide most of the field while keeping its shape
+    ///
+    /// Text keeps only its first character (`alice` becomes `a****`),
+    /// while addresses keep only their network (`192.0.2.10` becomes
+    /// `192.0.2.0` and IPv6 addresses keep their first 48 bits).
+    Mask,
+    /// empty the field completely
+    Remove,
+}
+
+/// Transform rewriting the personal fields of records before export
+///
+/// Every field is configured separately, and the remote host is treated
+/// as an address when it parses as an IPv4 or IPv6 address and as a
+/// hostname otherwise. Fields default to [`Redaction::Keep`], including
+/// the user-id, which can re-identify a redacted username by itself (uid
+/// `0` is root and `1000` usually the first user) unless it is redacted
+/// as well.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{Record, Redaction, Redactor};
+///
+/// let redactor = Redactor::new("s3cret")
+///     .name(Redaction::Hash)
+///     .uid(Redaction::Remove)
+///     .host(Redaction::Remove)
+///     .ip(Redaction::Mask);
+///
+/// let record = Record::builder().uid(1000).name("alice").host("192.0.2.10").build();
+/// let shared = redactor.redact(&record);
+/// assert_ne!(shared.name, "alice");
+/// assert_eq!(shared.uid, None);
+/// assert_eq!(shared.name, redactor.redact(&record).name);
+/// assert_eq!(shared.host, "192.0.2.0");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Redactor {
+    salt: Vec<u8>,
+    name: Redaction,
+    uid: Redaction,
+    host: Redaction,
+    ip: Redaction,
+    tty: Redaction,
+    machine: Redaction,
+}
+
+/* Functions */
+
+// mask every character but the first one
+fn mask_text(value: &str) -> String {
+    let mut chars = value.chars();
+    match chars.next() {
+        Some(first) => std::iter::once(first).chain(chars.map(|_| '*')).collect(),
+        None => String::new(),
+    }
+}
+
+// keep only the network portion of an address
+fn mask_ip(addr: IpAddr) -> String {
+    match addr {
+        IpAddr::V4(v4) => {
+            let [a, b, c, _] = v4.octets();
+            IpAddr::from([a, b, c, 0]).to_string()
+        }
+        IpAddr::V6(v6) => {
+            let mut segments = v6.segments();
+            segments[3..].fill(0);
+            IpAddr::from(segments).to_string()
+        }
+    }
+}
+
+/* Implementation */
+
+impl Redactor {
+    /// Build a redactor keeping every field, hashing with the given salt
+    ///
+    /// The salt should be kept secret, otherwise hashed values of common
+    /// usernames can be recovered by hashing guesses.
+    pub fn new(salt: impl AsRef<[u8]>) -> Self {
+        Self {
+            salt: salt.as_ref().to_vec(),
+            ..Self::default()
+        }
+    }
+
+    /// Configure how usernames are rewritten
+    pub fn name(mut self, redaction: Redaction) -> Self {
+        self.name = redaction;
+        self
+    }
+
+    /// Configure how user-ids are rewritten
+    ///
+    /// Both [`Redaction::Hash`] and [`Redaction::Mask`] replace the uid with
+    /// a salted pseudonym above `2^31`, so it never collides with a real
+    /// account, while [`Redaction::Remove`] clears it.
+    pub fn uid(mut self, redaction: Redaction) -> Self {
+        self.uid = redaction;
+        self
+    }
+
+    /// Configure how remote hostnames are rewritten
+    pub fn host(mut self, redaction: Redaction) -> Self {
+        self.host = redaction;
+        self
+    }
+
+    /// Configure how remote IPv4 and IPv6 addresses are rewritten
+    pub fn ip(mut self, redaction: Redaction) -> Self {
+        self.ip = redaction;
+        self
+    }
+
+    /// Configure how terminal lines are rewritten
+    pub fn tty(mut self, redaction: Redaction) -> Self {
+        self.tty = redaction;
+        self
+    }
+
+    /// Configure how machine tags are rewritten
+    pub fn machine(mut self, redaction: Redaction) -> Self {
+        self.machine = redaction;
+        self
+    }
+
+    // generate the salted pseudonym of a value
+    fn hash(&self, value: &str) -> String {
+        let digest = Sha256::new()
+            .chain_update(&self.salt)
+            .chain_update(value.as_bytes())
+            .finalize();
+        let mut hex = String::with_capacity(HASH_LENGTH);
+        for byte in digest.iter().take(HASH_LENGTH / 2) {
+            let _ = write!(hex, "{byte:02x}");
+        }
+        hex
+    }
+
+    // rewrite the user-id according to its redaction
+    fn apply_uid(&self, uid: Option<u32>) -> Option<u32> {
+        let uid = uid?;
+        match self.uid {
+            Redaction::Keep => Some(uid),
+            Redaction::Hash | Redaction::Mask => {
+                let digest = Sha256::new()
+                    .chain_update(&self.salt)
+                    .chain_update(uid.to_be_bytes())
+                    .finalize();
+                let value = u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]);
+                Some(value | 1 << 31)
+            }
+            Redaction::Remove => None,
+        }
+    }
+
+    // rewrite a single text field according to its redaction
+    fn apply(&self, redaction: Redaction, value: &str) -> String {
+        match redaction {
+            _ if value.is_empty() => String::new(),
+            Redaction::Keep => value.to_owned(),
+            Redaction::Hash => self.hash(value),
+            Redaction::Mask => mask_text(value),
+            Redaction::Remove => String::new(),
+        }
+    }
+
+    // rewrite the remote host depending on whether it holds an address
+    fn apply_host(&self, host: &str) -> String {
+        match host.parse::<IpAddr>() {
+            Ok(addr) if self.ip == Redaction::Mask => mask_ip(addr),
+            Ok(_) => self.apply(self.ip, host),
+            Err(_) => self.apply(self.host, host),
+        }
+    }
+
+    /// Produce an anonymized copy of the given record
+    pub fn redact(&self, record: &Record) -> Record {
+        Record {
+            name: self.apply(self.name, &record.name),
+            uid: self.apply_uid(record.uid),
+            tty: self.apply(self.tty, &record.tty),
+            host: self.apply_host(&record.host),
+            machine: match self.machine {
+                Redaction::Remove => None,
+                redaction => record.machine.as_deref().map(|m| self.apply(redaction, m)),
+            },
+            ..record.clone()
+        }
+    }
+
+    /// Anonymize every record of a collection before export
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use lastlog::{Redaction, Redactor};
+    ///
+    /// let redactor = Redactor::new("s3cret").name(Redaction::Mask);
+    /// let users = lastlog::iter_accounts().unwrap_or_default();
+    /// let shared = redactor.redact_all(&users);
+    /// assert_eq!(shared.len(), users.len());
+    /// ```
+    pub fn redact_all<'a>(&self, records: impl IntoIterator<Item = &'a Record>) -> Vec<Record> {
+        records.into_iter().map(|r| self.redact(r)).collect()
+    }
+}
@@ -0,0 +1,65 @@
+/*
+ *  Redox OS login database placeholder
+ */
+use std::fs::File;
+use std::io::{Error, ErrorKind, Result};
+
+use super::common::*;
+
+/* Types */
+
+/// Redox OS Login Database Implementation
+///
+/// Redox does not record logins in a utmp or lastlog database, and its
+/// `login` program keeps no history at all. This module is the extension
+/// point for reading them once Redox does: until then it recognizes no
+/// files and every lookup reports [`ErrorKind::Unsupported`]. It is only
+/// consulted by the automatic module lookup when compiled for Redox, while
+/// utmp and lastlog files copied from other systems are still read there.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::LoginDB;
+///
+/// let redox  = lastlog::Redox {};
+/// let record = redox.search_uid(1000, "/var/log/wtmp");
+/// assert!(record.is_err());
+/// ```
+pub struct Redox {}
+
+/* Functions */
+
+// build the error reported by every lookup
+fn unsupported() -> Error {
+    Error::new(
+        ErrorKind::Unsupported,
+        "redox does not keep a login database",
+    )
+}
+
+/* Implementation */
+
+impl LoginDB for Redox {
+    fn is_valid(&self, _f: &mut File) -> bool {
+        false
+    }
+
+    fn primary_file(&self) -> Result<&'static str> {
+        Err(unsupported())
+    }
+
+    fn iter_accounts(&self, _fname: &str) -> Result<Vec<Record>> {
+        Err(unsupported())
+    }
+
+    fn search_uid(&self, _uid: u32, _fname: &str) -> Result<Record> {
+        Err(unsupported())
+    }
+
+    fn search_username(&self, _username: &str, _fname: &str) -> Result<Record> {
+        Err(unsupported())
+    }
+}
@@ -0,0 +1,269 @@
+/*
+ *  Markdown and HTML audit reports of accounts, logins and failed logins
+ */
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+use std::time::SystemTime;
+
+use super::account::Account;
+use super::common::*;
+use super::format::RFC3339_TIME;
+
+/* Variables */
+
+static ACCOUNT_COLUMNS: [&str; 6] = [
+    "User",
+    "UID",
+    "Last Login",
+    "Last Failed",
+    "Failures",
+    "Locked",
+];
+
+static LOGIN_COLUMNS: [&str; 5] = ["User", "UID", "TTY", "Host", "Last Login"];
+
+static FAILED_COLUMNS: [&str; 4] = ["User", "Attempts", "Last Attempt", "Hosts"];
+
+/* Types */
+
+/// Output format of a rendered [`Report`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReportFormat {
+    /// GitHub flavored Markdown tables
+    Markdown,
+    /// standalone HTML document with plain tables
+    Html,
+}
+
+/// Audit report rendered into Markdown or HTML tables
+///
+/// Every added summary becomes its own section in the order it was added.
+/// Login-times are written as RFC 3339 UTC timestamps, or `never`.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{Record, Report, ReportFormat};
+///
+/// let logins = vec![Record::builder().name("foo").tty("pts/1").build()];
+/// let report = Report::new("Weekly Audit").logins(&logins);
+/// let markdown = report.render(ReportFormat::Markdown);
+/// assert!(markdown.starts_with("# Weekly Audit\n"));
+/// assert!(markdown.contains("| foo |  | pts/1 |  | never |"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    title: String,
+    generated: Option<SystemTime>,
+    sections: Vec<Section>,
+}
+
+// single titled table of a report
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Section {
+    title: String,
+    columns: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+}
+
+/* Functions */
+
+// render a login-time cell
+#[inline]
+fn time_cell(time: &LoginTime) -> String {
+    match time {
+        LoginTime::Never => "never".to_owned(),
+        _ => time.format_utc(RFC3339_TIME).unwrap_or_default(),
+    }
+}
+
+// render an optional cell, leaving it empty when missing
+#[inline]
+fn cell<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+// escape text so it cannot break out of a markdown table cell
+fn escape_markdown(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace(['\r', '\n'], " ")
+}
+
+// escape text for html element content
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/* Implementation */
+
+impl Report {
+    /// Start an empty report with the given title
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Note the time the report was generated below its title
+    pub fn generated(mut self, time: SystemTime) -> Self {
+        self.generated = Some(time);
+        self
+    }
+
+    /// Add a summary of every account, such as from [`crate::accounts`]
+    pub fn accounts(self, accounts: &[Account]) -> Self {
+        let rows = accounts
+            .iter()
+            .map(|account| {
+                vec![
+                    account.name.clone(),
+                    account.uid.to_string(),
+                    time_cell(&account.last_login),
+                    time_cell(&account.last_failed),
+                    account.failed_count.to_string(),
+                    cell(account.locked.map(|l| if l { "yes" } else { "no" })),
+                ]
+            })
+            .collect();
+        self.section("Accounts", &ACCOUNT_COLUMNS, rows)
+    }
+
+    /// Add a summary of the latest login of each user, such as from `iter_accounts`
+    pub fn logins(self, records: &[Record]) -> Self {
+        let rows = records
+            .iter()
+            .map(|record| {
+                vec![
+                    record.name.clone(),
+                    cell(record.uid),
+                    record.tty.clone(),
+                    record.host.clone(),
+                    time_cell(&record.last_login),
+                ]
+            })
+            .collect();
+        self.section("Last Logins", &LOGIN_COLUMNS, rows)
+    }
+
+    /// Add a per-user summary of failed login attempts read from btmp
+    ///
+    /// Users are listed with the most attempts first.
+    pub fn failed(self, records: &[Record]) -> Self {
+        let mut users: HashMap<&str, (usize, LoginTime, BTreeSet<&str>)> = HashMap::new();
+        for record in records.iter().filter(|r| !r.name.is_empty()) {
+            let entry = users
+                .entry(record.name.as_str())
+                .or_insert_with(|| (0, LoginTime::Never, BTreeSet::new()));
+            entry.0 += 1;
+            entry.1 = entry.1.clone().max(record.last_login.clone());
+            if !record.host.is_empty() {
+                entry.2.insert(record.host.as_str());
+            }
+        }
+        let mut users: Vec<_> = users.into_iter().collect();
+        users.sort_by(|a, b| b.1 .0.cmp(&a.1 .0).then(a.0.cmp(b.0)));
+        let rows = users
+            .into_iter()
+            .map(|(name, (count, last, hosts))| {
+                vec![
+                    name.to_owned(),
+                    count.to_string(),
+                    time_cell(&last),
+                    hosts.into_iter().collect::<Vec<_>>().join(", "),
+                ]
+            })
+            .collect();
+        self.section("Failed Logins", &FAILED_COLUMNS, rows)
+    }
+
+    // append a new table section to the report
+    fn section(mut self, title: &str, columns: &[&'static str], rows: Vec<Vec<String>>) -> Self {
+        self.sections.push(Section {
+            title: title.to_owned(),
+            columns: columns.to_vec(),
+            rows,
+        });
+        self
+    }
+
+    /// Render the report in the given format
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Markdown => self.to_markdown(),
+            ReportFormat::Html => self.to_html(),
+        }
+    }
+
+    /// Render the report as Markdown
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# {}", escape_markdown(&self.title));
+        if let Some(time) = self.generated {
+            let _ = writeln!(out, "\nGenerated {}", time_cell(&LoginTime::from(time)));
+        }
+        for section in self.sections.iter() {
+            let _ = writeln!(out, "\n## {}\n", section.title);
+            if section.rows.is_empty() {
+                let _ = writeln!(out, "_No entries._");
+                continue;
+            }
+            let _ = writeln!(out, "| {} |", section.columns.join(" | "));
+            let _ = writeln!(out, "|{}", " --- |".repeat(section.columns.len()));
+            for row in section.rows.iter() {
+                let row: Vec<_> = row.iter().map(|c| escape_markdown(c)).collect();
+                let _ = writeln!(out, "| {} |", row.join(" | "));
+            }
+        }
+        out
+    }
+
+    /// Render the report as a standalone HTML document
+    pub fn to_html(&self) -> String {
+        let title = escape_html(&self.title);
+        let mut out = String::new();
+        let _ = writeln!(out, "<!DOCTYPE html>\n<html>\n<head>");
+        let _ = writeln!(out, "<meta charset=\"utf-8\">\n<title>{title}</title>");
+        let _ = writeln!(out, "</head>\n<body>\n<h1>{title}</h1>");
+        if let Some(time) = self.generated {
+            let time = time_cell(&LoginTime::from(time));
+            let _ = writeln!(out, "<p>Generated {time}</p>");
+        }
+        for section in self.sections.iter() {
+            let _ = writeln!(out, "<h2>{}</h2>", section.title);
+            if section.rows.is_empty() {
+                let _ = writeln!(out, "<p><em>No entries.</em></p>");
+                continue;
+            }
+            let _ = writeln!(out, "<table border=\"1\">\n<tr>");
+            for column in section.columns.iter() {
+                let _ = writeln!(out, "<th>{column}</th>");
+            }
+            let _ = writeln!(out, "</tr>");
+            for row in section.rows.iter() {
+                let _ = write!(out, "<tr>");
+                for value in row {
+                    let _ = write!(out, "<td>{}</td>", escape_html(value));
+                }
+                let _ = writeln!(out, "</tr>");
+            }
+            let _ = writeln!(out, "</table>");
+        }
+        let _ = writeln!(out, "</body>\n</html>");
+        out
+    }
+}
@@ -0,0 +1,154 @@
+/*
+ *  runlevel(8) style queries derived from `RunLvl` entries
+ */
+use std::fs::File;
+use std::io::{BufReader, Result};
+use std::time::SystemTime;
+
+use super::common::*;
+use super::utmp::UtmpReader;
+
+/* Variables */
+
+static UTMP: &str = "/var/run/utmp";
+static WTMP: &str = "/var/log/wtmp";
+
+// previous runlevel stored by init when there was none
+static NO_LEVEL: u8 = b'N';
+
+/* Types */
+
+/// Runlevel the system switched to, decoded from a `RunLvl` entry
+///
+/// Both SysV init and systemd (through `systemd-update-utmp`) store the
+/// new runlevel in the low byte of the entry pid and the previous one in
+/// the byte above it, while shutdowns are stored as a switch to level `0`.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{Record, RecordType, Runlevel};
+///
+/// let record = Record::builder().rtype(RecordType::RunLvl).name("runlevel").pid(b'5' as i32).build();
+/// let level = Runlevel::from_record(&record).unwrap();
+/// assert_eq!(level.level, '5');
+/// assert_eq!(level.previous, None);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Runlevel {
+    /// runlevel switched to, such as `3`, `5` or `0` when shutting down
+    pub level: char,
+    /// runlevel switched from, `None` when switching right after boot
+    pub previous: Option<char>,
+    /// time of the switch
+    pub time: LoginTime,
+}
+
+/* Functions */
+
+// decode a single runlevel byte, ignoring unset and unprintable values
+fn level_char(byte: u8) -> Option<char> {
+    (byte.is_ascii_graphic() && byte != NO_LEVEL).then_some(byte as char)
+}
+
+/// Retrieve the current runlevel of the system from utmp
+///
+/// Equivalent to `runlevel(8)`, returning `None` when no runlevel was
+/// recorded since boot (such as in most containers).
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// if let Ok(Some(level)) = lastlog::current_runlevel() {
+///     println!("N {}", level.level);
+/// }
+/// ```
+pub fn current_runlevel() -> Result<Option<Runlevel>> {
+    current_runlevel_from(UTMP)
+}
+
+/// Retrieve the latest runlevel recorded within the given utmp database
+pub fn current_runlevel_from(fname: &str) -> Result<Option<Runlevel>> {
+    let reader = UtmpReader::new(BufReader::new(File::open(fname)?));
+    Ok(reader
+        .filter_map(|r| r.ok())
+        .filter_map(|r| Runlevel::from_record(&r))
+        .max_by(|a, b| a.time.cmp(&b.time)))
+}
+
+/// Find the runlevel the system was running in at the given time
+///
+/// The system wtmp history is replayed up to the given time, so boots
+/// reset the runlevel until the next switch is recorded. Level `0` is
+/// returned between a shutdown and the following boot, and `None` when
+/// the level is unknown.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use std::time::{Duration, SystemTime};
+///
+/// let yesterday = SystemTime::now() - Duration::from_secs(24 * 60 * 60);
+/// if let Ok(Some(level)) = lastlog::runlevel_at(yesterday) {
+///     println!("runlevel {} since {:?}", level.level, level.time);
+/// }
+/// ```
+pub fn runlevel_at(time: SystemTime) -> Result<Option<Runlevel>> {
+    runlevel_at_from(WTMP, time)
+}
+
+/// Find the runlevel at the given time within the given wtmp database
+pub fn runlevel_at_from(fname: &str, time: SystemTime) -> Result<Option<Runlevel>> {
+    let reader = UtmpReader::new(BufReader::new(File::open(fname)?));
+    let time = LoginTime::Last(time);
+    let mut current: Option<Runlevel> = None;
+    let mut boot = LoginTime::Never;
+    for record in reader.filter_map(|r| r.ok()) {
+        if record.last_login > time {
+            continue;
+        }
+        match record.rtype {
+            RecordType::BootTime if record.last_login >= boot => {
+                boot = record.last_login.clone();
+            }
+            _ => {
+                let Some(level) = Runlevel::from_record(&record) else {
+                    continue;
+                };
+                if current.as_ref().is_none_or(|c| c.time <= level.time) {
+                    current = Some(level);
+                }
+            }
+        }
+    }
+    // a later boot means the system restarted without a switch since
+    Ok(current.filter(|c| c.time >= boot))
+}
+
+/* Implementation */
+
+impl Runlevel {
+    /// Decode the runlevel switch stored by a `RunLvl` entry
+    pub fn from_record(record: &Record) -> Option<Self> {
+        if record.rtype != RecordType::RunLvl {
+            return None;
+        }
+        let pid = record.pid?;
+        Some(Self {
+            level: level_char((pid % 256) as u8)?,
+            previous: level_char((pid / 256 % 256) as u8),
+            time: record.last_login.clone(),
+        })
+    }
+
+    /// Check if the switch shut the system down
+    pub fn is_shutdown(&self) -> bool {
+        self.level == '0'
+    }
+}
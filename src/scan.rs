@@ -0,0 +1,156 @@
+/*
+ *  Start offsets of forward scans reading utmp history since a time
+ */
+use std::fs::File;
+use std::io::{Read, Result, Seek, SeekFrom};
+use std::time::SystemTime;
+
+use super::common::*;
+use super::raw::{UtmpEntry, UTMP_SIZE};
+
+/* Types */
+
+/// Forward scan of a single database file chosen by [`plan_since`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanStep {
+    /// database file to read
+    pub path: String,
+    /// byte offset the forward scan starts from
+    pub offset: u64,
+}
+
+// open database file alongside its first and last stamped entry times
+// (`Never` when no entry is stamped)
+struct FileSpan {
+    path: String,
+    file: File,
+    entries: u64,
+    first: LoginTime,
+    last: LoginTime,
+}
+
+/* Functions */
+
+// read the login-time of the entry at the given index, treating entries
+// that fail to decode as unstamped
+fn entry_time(f: &mut File, index: u64) -> Result<LoginTime> {
+    let mut buffer = [0; UTMP_SIZE];
+    f.seek(SeekFrom::Start(index * UTMP_SIZE as u64))?;
+    f.read_exact(&mut buffer)?;
+    Ok(UtmpEntry::decode(&buffer).map_or(LoginTime::Never, |e| e.time().into()))
+}
+
+// find the first stamped entry in the given order of indexes
+fn stamped(
+    f: &mut File,
+    mut indexes: impl Iterator<Item = u64>,
+) -> Result<Option<(u64, LoginTime)>> {
+    indexes.try_fold(None, |found, index| match found {
+        Some(found) => Ok(Some(found)),
+        None => {
+            let time = entry_time(f, index)?;
+            Ok((!time.is_never()).then_some((index, time)))
+        }
+    })
+}
+
+// index of the first entry written at or after the given time, skipping
+// over unstamped entries
+fn first_since(f: &mut File, entries: u64, since: &LoginTime) -> Result<u64> {
+    let (mut low, mut high) = (0, entries);
+    while low < high {
+        let middle = low + (high - low) / 2;
+        match stamped(f, middle..high)? {
+            Some((index, time)) if time < *since => low = index + 1,
+            _ => high = middle,
+        }
+    }
+    Ok(low)
+}
+
+// open a database file and find its first and last stamped entries
+fn read_span(fname: &str) -> Result<Option<FileSpan>> {
+    let mut f = File::open(fname)?;
+    let entries = f.metadata()?.len() / UTMP_SIZE as u64;
+    if entries == 0 {
+        return Ok(None);
+    }
+    let first = stamped(&mut f, 0..entries)?;
+    let last = match first {
+        Some((index, _)) => stamped(&mut f, (index..entries).rev())?,
+        None => None,
+    };
+    let time = |found: Option<(u64, LoginTime)>| found.map_or(LoginTime::Never, |(_, t)| t);
+    Ok(Some(FileSpan {
+        path: fname.to_owned(),
+        file: f,
+        entries,
+        first: time(first),
+        last: time(last),
+    }))
+}
+
+/// Find where forward scans start reading history since the given time
+/// from utmp files and their archives, as done by [`Utmp::read_since`]
+///
+/// The offset of the first entry written at or after the time is found
+/// with a binary search, which assumes entries are appended in
+/// chronological order as they are in wtmp, and files that end before the
+/// time are skipped entirely. Entries without a login-time (or that fail
+/// to decode) are passed over, files without any are scanned in full and
+/// files that cannot be read are skipped. Steps are ordered oldest file
+/// first, regardless of the archive names.
+///
+/// [`Utmp::read_since`]: crate::Utmp::read_since
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use std::time::{Duration, SystemTime};
+/// use lastlog::{plan_since, rotated_files};
+///
+/// let week = SystemTime::now() - Duration::from_secs(7 * 24 * 60 * 60);
+/// let files = rotated_files("/var/log/wtmp").unwrap_or_default();
+/// if let Ok(steps) = plan_since(&files, week) {
+///     for step in steps {
+///         println!("{} from {}", step.path, step.offset);
+///     }
+/// }
+/// ```
+pub fn plan_since<S: AsRef<str>>(fnames: &[S], since: SystemTime) -> Result<Vec<ScanStep>> {
+    let mut spans = vec![];
+    let mut steps = vec![];
+    for fname in fnames.iter().map(|f| f.as_ref()) {
+        match read_span(fname) {
+            Ok(Some(span)) => spans.push(span),
+            Ok(None) => {}
+            Err(_e) => {
+                warn_event!(path = fname, error = %_e, "skipping unreadable file");
+            }
+        }
+    }
+    let since = LoginTime::Last(since);
+    spans.sort_by_key(|span| span.first.clone());
+    for mut span in spans {
+        let start = match &span.last {
+            LoginTime::Never => 0,
+            last if *last < since => continue,
+            _ if span.first >= since => 0,
+            _ => match first_since(&mut span.file, span.entries, &since) {
+                Ok(start) => start,
+                Err(_e) => {
+                    warn_event!(path = span.path, error = %_e, "skipping unreadable file");
+                    continue;
+                }
+            },
+        };
+        debug_event!(path = span.path, entry = start, "planned forward scan");
+        steps.push(ScanStep {
+            path: span.path,
+            offset: start * UTMP_SIZE as u64,
+        });
+    }
+    Ok(steps)
+}
@@ -0,0 +1,517 @@
+/*
+ *  Runtime described record layouts for patched or foreign utmp formats
+ */
+use std::fs::File;
+use std::io::{BufReader, Error, ErrorKind, Read, Result};
+use std::time::{Duration, UNIX_EPOCH};
+
+use super::common::*;
+use super::layout::{LayoutProfile, GLIBC_TIME32, GLIBC_TIME64};
+
+/* Variables */
+
+static FIELD_KINDS: [FieldKind; 7] = [
+    FieldKind::Type,
+    FieldKind::Pid,
+    FieldKind::Line,
+    FieldKind::User,
+    FieldKind::Host,
+    FieldKind::Time,
+    FieldKind::Usec,
+];
+
+/* Types */
+
+/// Byte order of the integers within an entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Endian {
+    /// least significant byte first, such as `x86_64` and `aarch64`
+    Little,
+    /// most significant byte first, such as `s390x`, SPARC and MIPS
+    Big,
+}
+
+/// Field of an entry that a [`RecordSchema`] can locate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FieldKind {
+    /// utmp(5) record-type integer (`ut_type`)
+    Type,
+    /// process-id integer (`ut_pid`)
+    Pid,
+    /// nul padded terminal line (`ut_line`)
+    Line,
+    /// nul padded username (`ut_user`)
+    User,
+    /// nul padded remote host (`ut_host`)
+    Host,
+    /// login-time in seconds since the unix epoch, 32 or 64 bits wide
+    Time,
+    /// microseconds of the login-time (`tv_usec`)
+    Usec,
+}
+
+/// Position of a single field within an entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SchemaField {
+    /// byte offset from the start of the entry
+    pub offset: usize,
+    /// size of the field in bytes
+    pub size: usize,
+}
+
+/// Description of a fixed-size record layout parsed at runtime
+///
+/// Embedded vendors and historical systems often patch `struct utmp`,
+/// moving, resizing or dropping fields. A schema describes the size of
+/// every entry, the byte order of its integers and where each known field
+/// is stored, so such files parse without a dedicated backend. Fields that
+/// are not described are left empty in the returned records.
+///
+/// Databases laid out like lastlog(8), where the entry offset is the
+/// user-id and no username is stored, are described with
+/// [`RecordSchema::uid_index`].
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{Endian, FieldKind, RecordSchema, RecordType};
+///
+/// let schema = RecordSchema::new("vendor", 48)
+///     .endian(Endian::Big)
+///     .field(FieldKind::Type, 0, 2)
+///     .field(FieldKind::User, 2, 8)
+///     .field(FieldKind::Line, 10, 12)
+///     .field(FieldKind::Time, 40, 8);
+/// let mut entry = [0u8; 48];
+/// entry[1] = 7;
+/// entry[2..6].copy_from_slice(b"root");
+/// entry[10..15].copy_from_slice(b"ttyS0");
+/// entry[44..48].copy_from_slice(&1700000000u32.to_be_bytes());
+///
+/// let records: Vec<_> = schema.reader(&entry[..]).unwrap().filter_map(|r| r.ok()).collect();
+/// assert_eq!(records[0].rtype, RecordType::User);
+/// assert_eq!(records[0].name, "root");
+/// assert_eq!(records[0].last_login.unix_seconds(), Some(1700000000));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordSchema {
+    /// name of the layout used within error messages
+    pub name: String,
+    /// size of every entry in bytes
+    pub size: usize,
+    /// byte order of the integer fields
+    pub endian: Endian,
+    /// whether entries are stored at the offset of their user-id
+    pub indexed: bool,
+    fields: [Option<SchemaField>; 7],
+}
+
+/// Streaming reader decoding entries described by a [`RecordSchema`]
+pub struct SchemaReader<R: Read> {
+    reader: R,
+    schema: RecordSchema,
+    users: UserMap,
+    buffer: Vec<u8>,
+    offset: u64,
+    done: bool,
+}
+
+/* Functions */
+
+// build the error reported for an inconsistent schema
+fn invalid(schema: &str, message: impl Into<String>) -> Error {
+    Error::new(
+        ErrorKind::InvalidInput,
+        format!("invalid schema {schema:?}: {}", message.into()),
+    )
+}
+
+/* Implementation */
+
+impl Endian {
+    /// Byte order of the compile target
+    pub fn native() -> Self {
+        match cfg!(target_endian = "big") {
+            true => Self::Big,
+            false => Self::Little,
+        }
+    }
+}
+
+impl FieldKind {
+    /// Lowercase name of the field as used in TOML descriptions
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Type => "type",
+            Self::Pid => "pid",
+            Self::Line => "line",
+            Self::User => "user",
+            Self::Host => "host",
+            Self::Time => "time",
+            Self::Usec => "usec",
+        }
+    }
+
+    // check if the field stores an integer rather than text
+    fn is_integer(&self) -> bool {
+        !matches!(self, Self::Line | Self::User | Self::Host)
+    }
+}
+
+impl RecordSchema {
+    /// Describe entries of the given size without any known fields
+    pub fn new(name: impl Into<String>, size: usize) -> Self {
+        Self {
+            name: name.into(),
+            size,
+            endian: Endian::native(),
+            indexed: false,
+            fields: [None; 7],
+        }
+    }
+
+    /// Describe the utmp entries of one of the known glibc layouts
+    ///
+    /// Allows reading databases copied from a platform with a different
+    /// layout, such as the 64-bit `ut_tv` entries of an `aarch64` host.
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use lastlog::{FieldKind, RecordSchema, GLIBC_TIME64};
+    ///
+    /// let schema = RecordSchema::from_profile(&GLIBC_TIME64).unwrap();
+    /// assert_eq!(schema.size, 400);
+    /// assert_eq!(schema.time_bits(), Some(64));
+    /// ```
+    pub fn from_profile(profile: &LayoutProfile) -> Option<Self> {
+        let schema = Self::new(profile.name, profile.utmp_size)
+            .field(FieldKind::Type, 0, 2)
+            .field(FieldKind::Pid, 4, 4)
+            .field(FieldKind::Line, 8, 32)
+            .field(FieldKind::User, 44, 32)
+            .field(FieldKind::Host, 76, 256);
+        if *profile == GLIBC_TIME32 {
+            return Some(
+                schema
+                    .field(FieldKind::Time, 340, 4)
+                    .field(FieldKind::Usec, 344, 4),
+            );
+        }
+        if *profile == GLIBC_TIME64 {
+            return Some(
+                schema
+                    .field(FieldKind::Time, 344, 8)
+                    .field(FieldKind::Usec, 352, 8),
+            );
+        }
+        None
+    }
+
+    /// Set the byte order of the integer fields, native by default
+    pub fn endian(mut self, endian: Endian) -> Self {
+        self.endian = endian;
+        self
+    }
+
+    /// Locate a field at the given byte offset and size within an entry
+    pub fn field(mut self, kind: FieldKind, offset: usize, size: usize) -> Self {
+        self.fields[kind as usize] = Some(SchemaField { offset, size });
+        self
+    }
+
+    /// Treat the position of every entry as its user-id like lastlog(8)
+    pub fn uid_index(mut self, indexed: bool) -> Self {
+        self.indexed = indexed;
+        self
+    }
+
+    /// Retrieve the position of the given field, if described
+    pub fn get(&self, kind: FieldKind) -> Option<SchemaField> {
+        self.fields[kind as usize]
+    }
+
+    /// Width of the login-time in bits, if described
+    pub fn time_bits(&self) -> Option<u32> {
+        self.get(FieldKind::Time).map(|f| f.size as u32 * 8)
+    }
+
+    /// Check every field fits the entries and has a decodable size
+    pub fn check(&self) -> Result<()> {
+        if self.size == 0 {
+            return Err(invalid(&self.name, "entry size must not be zero"));
+        }
+        for kind in FIELD_KINDS {
+            let Some(field) = self.get(kind) else {
+                continue;
+            };
+            let name = kind.name();
+            if field.offset + field.size > self.size {
+                return Err(invalid(&self.name, format!("{name} exceeds the entry")));
+            }
+            let sizes: &[usize] = match kind {
+                FieldKind::Time => &[4, 8],
+                _ if kind.is_integer() => &[1, 2, 4, 8],
+                _ => &[],
+            };
+            if !sizes.is_empty() && !sizes.contains(&field.size) {
+                return Err(invalid(
+                    &self.name,
+                    format!("{name} cannot be {} bytes wide", field.size),
+                ));
+            }
+            if field.size == 0 {
+                return Err(invalid(&self.name, format!("{name} is empty")));
+            }
+        }
+        if self.get(FieldKind::Time).is_none() {
+            return Err(invalid(&self.name, "missing time field"));
+        }
+        if self.get(FieldKind::User).is_none() && !self.indexed {
+            return Err(invalid(&self.name, "missing user field or uid index"));
+        }
+        Ok(())
+    }
+
+    // decode a signed integer field of the entry
+    fn integer(&self, entry: &[u8], kind: FieldKind) -> Option<i64> {
+        let field = self.get(kind)?;
+        let bytes = &entry[field.offset..field.offset + field.size];
+        let mut buffer = [0u8; 8];
+        let value = match self.endian {
+            Endian::Little => {
+                buffer[..bytes.len()].copy_from_slice(bytes);
+                i64::from_le_bytes(buffer)
+            }
+            Endian::Big => {
+                buffer[8 - bytes.len()..].copy_from_slice(bytes);
+                i64::from_be_bytes(buffer)
+            }
+        };
+        // sign extend values narrower than 64 bits
+        let shift = 64 - 8 * field.size as u32;
+        Some(value.wrapping_shl(shift).wrapping_shr(shift))
+    }
+
+    // decode a nul padded text field of the entry
+    fn text(&self, entry: &[u8], kind: FieldKind) -> Result<String> {
+        let Some(field) = self.get(kind) else {
+            return Ok(String::new());
+        };
+        let bytes = &entry[field.offset..field.offset + field.size];
+        let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+        match std::str::from_utf8(&bytes[..end]) {
+            Ok(text) => Ok(text.to_owned()),
+            Err(_) => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("invalid {}", kind.name()),
+            )),
+        }
+    }
+
+    /// Decode a single entry found at the given position of the database
+    pub fn decode(&self, entry: &[u8], index: u64, users: &UserMap) -> Result<Record> {
+        if entry.len() < self.size {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                format!("truncated entry ({} of {} bytes)", entry.len(), self.size),
+            ));
+        }
+        let mut name = self.text(entry, FieldKind::User)?;
+        let uid = match self.indexed {
+            true => u32::try_from(index).ok(),
+            false => users.uid(&name),
+        };
+        if name.is_empty() {
+            name = uid
+                .and_then(|uid| users.name(uid))
+                .unwrap_or_default()
+                .to_owned();
+        }
+        let seconds = self.integer(entry, FieldKind::Time).unwrap_or_default();
+        let micros = self.integer(entry, FieldKind::Usec).unwrap_or_default();
+        let last_login = match seconds {
+            s if s <= 0 => LoginTime::Never,
+            s => {
+                let micros = if (0..1_000_000).contains(&micros) {
+                    micros
+                } else {
+                    0
+                };
+                let since = Duration::from_secs(s as u64) + Duration::from_micros(micros as u64);
+                LoginTime::Last(UNIX_EPOCH + since)
+            }
+        };
+        let mut record = Record::builder()
+            .uid(uid)
+            .name(name)
+            .tty(self.text(entry, FieldKind::Line)?)
+            .host(self.text(entry, FieldKind::Host)?)
+            .pid(self.integer(entry, FieldKind::Pid).map(|pid| pid as i32))
+            .build();
+        if let Some(rtype) = self.integer(entry, FieldKind::Type) {
+            record.rtype = RecordType::from(rtype as i32);
+        }
+        record.last_login = last_login;
+        Ok(record)
+    }
+
+    /// Read the entries of any byte stream using the system passwd database
+    pub fn reader<R: Read>(&self, reader: R) -> Result<SchemaReader<R>> {
+        self.reader_with_users(reader, UserMap::system())
+    }
+
+    /// Read the entries of any byte stream using the given users
+    pub fn reader_with_users<R: Read>(&self, reader: R, users: UserMap) -> Result<SchemaReader<R>> {
+        self.check()?;
+        Ok(SchemaReader {
+            reader,
+            schema: self.clone(),
+            users,
+            buffer: vec![0; self.size],
+            offset: 0,
+            done: false,
+        })
+    }
+
+    /// Open the given database file for reading
+    pub fn open(&self, fname: &str) -> Result<SchemaReader<BufReader<File>>> {
+        self.reader(BufReader::new(File::open(fname)?))
+    }
+
+    /// Parse a schema described in TOML
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use lastlog::{Endian, FieldKind, RecordSchema};
+    ///
+    /// let schema = RecordSchema::from_toml(r#"
+    ///     name = "vendor"
+    ///     size = 48
+    ///     endian = "big"
+    ///
+    ///     [fields]
+    ///     type = { offset = 0, size = 2 }
+    ///     user = { offset = 2, size = 8 }
+    ///     time = { offset = 40, size = 8 }
+    /// "#).unwrap();
+    /// assert_eq!(schema.endian, Endian::Big);
+    /// assert_eq!(schema.get(FieldKind::User).unwrap().size, 8);
+    /// ```
+    #[cfg(feature = "toml")]
+    pub fn from_toml(text: &str) -> Result<Self> {
+        let table: toml::Table = text
+            .parse()
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        let name = match table.get("name") {
+            Some(value) => value
+                .as_str()
+                .ok_or_else(|| invalid("custom", "name must be a string"))?,
+            None => "custom",
+        };
+        let integer = |table: &toml::Table, key: &str| -> Result<usize> {
+            let value = table
+                .get(key)
+                .ok_or_else(|| invalid(name, format!("missing {key}")))?;
+            value
+                .as_integer()
+                .and_then(|v| usize::try_from(v).ok())
+                .ok_or_else(|| invalid(name, format!("{key} must be a positive integer")))
+        };
+        let mut schema = Self::new(name, integer(&table, "size")?);
+        schema.endian = match table.get("endian").map(|e| e.as_str()) {
+            None | Some(Some("native")) => Endian::native(),
+            Some(Some("little")) => Endian::Little,
+            Some(Some("big")) => Endian::Big,
+            Some(_) => return Err(invalid(name, "endian must be little, big or native")),
+        };
+        if let Some(indexed) = table.get("uid_index") {
+            schema.indexed = indexed
+                .as_bool()
+                .ok_or_else(|| invalid(name, "uid_index must be a boolean"))?;
+        }
+        let Some(fields) = table.get("fields") else {
+            return Err(invalid(name, "missing fields"));
+        };
+        let fields = fields
+            .as_table()
+            .ok_or_else(|| invalid(name, "fields must be a table"))?;
+        for (key, value) in fields.iter() {
+            let kind = FIELD_KINDS
+                .into_iter()
+                .find(|k| k.name() == key)
+                .ok_or_else(|| invalid(name, format!("unknown field {key}")))?;
+            let field = value
+                .as_table()
+                .ok_or_else(|| invalid(name, format!("{key} must be a table")))?;
+            schema = schema.field(kind, integer(field, "offset")?, integer(field, "size")?);
+        }
+        schema.check()?;
+        Ok(schema)
+    }
+}
+
+impl<R: Read> SchemaReader<R> {
+    /// Byte offset of the next entry to be read
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    // fill the buffer with the next entry, returning false on a clean eof
+    fn fill(&mut self) -> std::result::Result<bool, ParseError> {
+        let mut read = 0;
+        while read < self.buffer.len() {
+            match self.reader.read(&mut self.buffer[read..]) {
+                Ok(0) if read == 0 => return Ok(false),
+                Ok(0) => {
+                    let reason = format!("truncated entry ({read} of {} bytes)", self.schema.size);
+                    return Err(ParseError::new(
+                        self.offset,
+                        ErrorKind::UnexpectedEof,
+                        reason,
+                    ));
+                }
+                Ok(n) => read += n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(ParseError::from_io(self.offset, e)),
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl<R: Read> Iterator for SchemaReader<R> {
+    type Item = std::result::Result<Record, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.fill() {
+            Ok(true) => {}
+            Ok(false) => {
+                self.done = true;
+                return None;
+            }
+            Err(err) => {
+                self.done = true;
+                return Some(Err(err));
+            }
+        }
+        let offset = self.offset;
+        let index = offset / self.schema.size as u64;
+        self.offset += self.schema.size as u64;
+        Some(
+            self.schema
+                .decode(&self.buffer, index, &self.users)
+                .map_err(|e| ParseError::from_io(offset, e)),
+        )
+    }
+}
@@ -0,0 +1,110 @@
+/*
+ *  Serde serialization of records, sessions and account reports
+ */
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+use super::account::Account;
+use super::common::*;
+use super::finding::{Finding, FindingKind, Severity};
+use super::format::RFC3339_TIME;
+use super::session::Session;
+
+/* Implementation */
+
+/// Known record-types serialize as their utmp(5) constant name
+/// (`"USER_PROCESS"`), unknown types as their raw integer value.
+impl Serialize for RecordType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.name() {
+            Some(name) => serializer.serialize_str(name),
+            None => serializer.serialize_i32(i32::from(*self)),
+        }
+    }
+}
+
+/// Login-times serialize as RFC 3339 UTC timestamps, or `null` when the
+/// user never logged in.
+impl Serialize for LoginTime {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.format_utc(RFC3339_TIME) {
+            Some(time) => serializer.serialize_str(&time),
+            None => serializer.serialize_none(),
+        }
+    }
+}
+
+impl Serialize for Record {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut st = serializer.serialize_struct("Record", 8)?;
+        st.serialize_field("type", &self.rtype)?;
+        st.serialize_field("uid", &self.uid)?;
+        st.serialize_field("user", &self.name)?;
+        st.serialize_field("tty", &self.tty)?;
+        st.serialize_field("host", &self.host)?;
+        st.serialize_field("pid", &self.pid)?;
+        st.serialize_field("last_login", &self.last_login)?;
+        // only tagged records carry a machine so single-host output is unchanged
+        match &self.machine {
+            Some(machine) => st.serialize_field("machine", machine)?,
+            None => st.skip_field("machine")?,
+        }
+        st.end()
+    }
+}
+
+impl Serialize for Session {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let end_time = self.end.time().map(LoginTime::from);
+        let mut st = serializer.serialize_struct("Session", 5)?;
+        st.serialize_field("record", &self.record)?;
+        st.serialize_field("end", self.end.kind())?;
+        st.serialize_field("end_time", &end_time)?;
+        st.serialize_field("duration", &self.duration().map(|d| d.as_secs()))?;
+        match &self.boot_id {
+            Some(boot_id) => st.serialize_field("boot_id", boot_id)?,
+            None => st.skip_field("boot_id")?,
+        }
+        st.end()
+    }
+}
+
+impl Serialize for Account {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut st = serializer.serialize_struct("Account", 10)?;
+        st.serialize_field("uid", &self.uid)?;
+        st.serialize_field("gid", &self.gid)?;
+        st.serialize_field("user", &self.name)?;
+        st.serialize_field("gecos", &self.gecos)?;
+        st.serialize_field("home", &self.home)?;
+        st.serialize_field("shell", &self.shell)?;
+        st.serialize_field("last_login", &self.last_login)?;
+        st.serialize_field("last_failed", &self.last_failed)?;
+        st.serialize_field("failed_count", &self.failed_count)?;
+        st.serialize_field("locked", &self.locked)?;
+        st.end()
+    }
+}
+
+impl Serialize for Severity {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl Serialize for FindingKind {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl Serialize for Finding {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut st = serializer.serialize_struct("Finding", 5)?;
+        st.serialize_field("severity", &self.severity)?;
+        st.serialize_field("kind", &self.kind)?;
+        st.serialize_field("subject", &self.subject)?;
+        st.serialize_field("description", &self.description)?;
+        st.serialize_field("evidence", &self.evidence)?;
+        st.end()
+    }
+}
@@ -0,0 +1,465 @@
+/*
+ *  Login/logout session pairing for wtmp history
+ */
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read, Result, Seek};
+use std::time::{Duration, SystemTime};
+
+use super::cancel::{CancelToken, Partial};
+use super::common::*;
+use super::utmp::{UtmpReader, UtmpRevReader};
+
+/* Variables */
+
+static UTMP: &str = "/var/run/utmp";
+static WTMP: &str = "/var/log/wtmp";
+
+/* Types */
+
+/// How and when a session ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SessionEnd {
+    /// The session is still ongoing (still logged in / still running)
+    Active,
+    /// The user logged out normally
+    Logout(SystemTime),
+    /// The system was shut down cleanly during the session
+    Down(SystemTime),
+    /// The system rebooted without a shutdown during the session
+    Crash(SystemTime),
+}
+
+impl SessionEnd {
+    /// Retrieve the time the session ended (if it has)
+    pub fn time(&self) -> Option<SystemTime> {
+        match self {
+            Self::Active => None,
+            Self::Logout(t) | Self::Down(t) | Self::Crash(t) => Some(*t),
+        }
+    }
+
+    /// Retrieve a short lowercase name for how the session ended
+    /// (`active`, `logout`, `down` or `crash`)
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Active => "active",
+            Self::Logout(_) => "logout",
+            Self::Down(_) => "down",
+            Self::Crash(_) => "crash",
+        }
+    }
+}
+
+/// Single paired session built from wtmp history
+///
+/// The record is the entry that started the session: a user login, a
+/// system boot (`BootTime`), or with system events a shutdown/runlevel
+/// change (`RunLvl`). The boot the session belongs to is only known once
+/// annotated with [`BootIds::annotate`](crate::BootIds::annotate).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Session {
+    pub record: Record,
+    pub end: SessionEnd,
+    pub boot_id: Option<String>,
+}
+
+impl Session {
+    /// Retrieve the time the session started
+    pub fn start(&self) -> Option<SystemTime> {
+        self.record.last_login.clone().into()
+    }
+
+    /// Check if the session is still ongoing
+    pub fn is_active(&self) -> bool {
+        self.end == SessionEnd::Active
+    }
+
+    /// Check if the session represents a system event rather than a user login
+    pub fn is_system(&self) -> bool {
+        self.record.rtype != RecordType::User
+    }
+
+    /// Calculate how long the session lasted (or has lasted so far)
+    pub fn duration(&self) -> Option<Duration> {
+        let start = self.start()?;
+        let end = self.end.time().unwrap_or_else(SystemTime::now);
+        end.duration_since(start).ok()
+    }
+}
+
+/// Single uptime period of the machine paired from wtmp history
+///
+/// The period ends with [`SessionEnd::Down`] at a clean shutdown, with
+/// [`SessionEnd::Crash`] when the machine booted again without one and
+/// is [`SessionEnd::Active`] while still running, just like `last reboot`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Reboot {
+    /// `BootTime` entry that started the uptime period
+    pub record: Record,
+    /// how and when the uptime period ended
+    pub end: SessionEnd,
+}
+
+impl Reboot {
+    /// Retrieve the time the machine booted
+    pub fn start(&self) -> Option<SystemTime> {
+        self.record.last_login.clone().into()
+    }
+
+    /// Check if this is the boot the machine is still running
+    pub fn is_running(&self) -> bool {
+        self.end == SessionEnd::Active
+    }
+
+    /// Calculate how long the machine was up (or has been up so far)
+    pub fn uptime(&self) -> Option<Duration> {
+        let start = self.start()?;
+        let end = self.end.time().unwrap_or_else(SystemTime::now);
+        end.duration_since(start).ok()
+    }
+
+    /// Retrieve the kernel release the machine booted with
+    pub fn kernel(&self) -> &str {
+        &self.record.host
+    }
+}
+
+// system event that bounds sessions which never logged out
+#[derive(Debug, Clone, Copy)]
+enum Boundary {
+    Boot(SystemTime),
+    Shutdown(SystemTime),
+}
+
+/// Iterator pairing login entries with their matching logouts
+///
+/// Operates on records ordered from newest to oldest, yielding sessions
+/// in reverse-chronological order just like `last(1)`. Only the set of
+/// currently unmatched logouts is kept in memory.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{Sessions, UtmpRevReader};
+///
+/// if let Ok(reader) = UtmpRevReader::open("/var/log/wtmp") {
+///     for session in Sessions::new(reader.filter_map(|r| r.ok())) {
+///         println!("{} {:?}", session.record.name, session.duration());
+///     }
+/// }
+/// ```
+pub struct Sessions<I> {
+    records: I,
+    logouts: HashMap<String, SystemTime>,
+    boundary: Option<Boundary>,
+    begins: Option<SystemTime>,
+    cancel: Option<CancelToken>,
+    truncated: bool,
+}
+
+impl<I: Iterator<Item = Record>> Sessions<I> {
+    /// Pair sessions from records ordered from newest to oldest
+    pub fn new(records: I) -> Self {
+        Self {
+            records,
+            logouts: HashMap::new(),
+            boundary: None,
+            begins: None,
+            cancel: None,
+            truncated: false,
+        }
+    }
+
+    /// Stop pairing sessions once the given token is cancelled
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use lastlog::CancelToken;
+    ///
+    /// let token = CancelToken::with_timeout(Duration::from_millis(500));
+    /// if let Ok(sessions) = lastlog::sessions() {
+    ///     let mut sessions = sessions.with_cancel(token);
+    ///     let recent: Vec<_> = sessions.by_ref().collect();
+    ///     if sessions.is_truncated() {
+    ///         println!("history cut short after {} sessions", recent.len());
+    ///     }
+    /// }
+    /// ```
+    pub fn with_cancel(mut self, cancel: CancelToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Check if iteration stopped early because the token was cancelled
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Time of the oldest record processed so far (the beginning of the
+    /// history once the iterator is exhausted)
+    pub fn begins(&self) -> Option<SystemTime> {
+        self.begins
+    }
+
+    // determine the end of a session that has no explicit logout
+    fn unmatched(&self) -> SessionEnd {
+        match self.boundary {
+            None => SessionEnd::Active,
+            Some(Boundary::Shutdown(t)) => SessionEnd::Down(t),
+            Some(Boundary::Boot(t)) => SessionEnd::Crash(t),
+        }
+    }
+}
+
+impl<I: Iterator<Item = Record>> Iterator for Sessions<I> {
+    type Item = Session;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.truncated || self.cancel.as_ref().is_some_and(|c| c.is_cancelled()) {
+                self.truncated = true;
+                return None;
+            }
+            let record = self.records.next()?;
+            let LoginTime::Last(time) = record.last_login else {
+                continue;
+            };
+            self.begins = Some(time);
+            match record.rtype {
+                RecordType::DeadProc if !record.tty.is_empty() => {
+                    self.logouts.insert(record.tty.clone(), time);
+                }
+                RecordType::User if !record.name.is_empty() => {
+                    let end = match self.logouts.remove(&record.tty) {
+                        Some(logout) => SessionEnd::Logout(logout),
+                        None => self.unmatched(),
+                    };
+                    return Some(Session {
+                        record,
+                        end,
+                        boot_id: None,
+                    });
+                }
+                RecordType::BootTime => {
+                    let end = self.unmatched();
+                    self.boundary = Some(Boundary::Boot(time));
+                    self.logouts.clear();
+                    return Some(Session {
+                        record,
+                        end,
+                        boot_id: None,
+                    });
+                }
+                RecordType::RunLvl if record.name == "shutdown" => {
+                    let end = match self.boundary {
+                        Some(Boundary::Boot(t)) => SessionEnd::Logout(t),
+                        _ => self.unmatched(),
+                    };
+                    self.boundary = Some(Boundary::Shutdown(time));
+                    return Some(Session {
+                        record,
+                        end,
+                        boot_id: None,
+                    });
+                }
+                RecordType::RunLvl => {
+                    let end = self.unmatched();
+                    return Some(Session {
+                        record,
+                        end,
+                        boot_id: None,
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/* Functions */
+
+/// Pair login sessions contained within the given wtmp database
+///
+/// Sessions are returned newest first and include system boots as well
+/// as shutdown and runlevel events, see [`Session::is_system`]. Entries
+/// that fail to parse are skipped.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// if let Ok(sessions) = lastlog::sessions_from("/var/log/wtmp") {
+///     let users: Vec<_> = sessions.filter(|s| !s.is_system()).collect();
+/// }
+/// ```
+pub fn sessions_from(fname: &str) -> Result<Sessions<impl Iterator<Item = Record>>> {
+    let reader: UtmpRevReader<File> = UtmpRevReader::open(fname)?;
+    Ok(Sessions::new(reader.filter_map(|r| r.ok())))
+}
+
+/// Pair login sessions contained within any seekable wtmp formatted stream
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use std::io::Cursor;
+///
+/// if let Ok(data) = std::fs::read("/var/log/wtmp") {
+///     let sessions = lastlog::sessions_from_reader(Cursor::new(data)).unwrap();
+///     println!("{} sessions", sessions.count());
+/// }
+/// ```
+pub fn sessions_from_reader<R: Read + Seek>(
+    reader: R,
+) -> Result<Sessions<impl Iterator<Item = Record>>> {
+    let reader = UtmpRevReader::new(reader)?;
+    Ok(Sessions::new(reader.filter_map(|r| r.ok())))
+}
+
+/// Pair login sessions contained within the system wtmp database
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// if let Ok(sessions) = lastlog::sessions() {
+///     for session in sessions.take(10) {
+///         println!("{} {:?}", session.record.name, session.end);
+///     }
+/// }
+/// ```
+pub fn sessions() -> Result<Sessions<impl Iterator<Item = Record>>> {
+    sessions_from(WTMP)
+}
+
+/// Pair every boot within the given wtmp database with its shutdown
+///
+/// Boots are returned newest first, replicating `last reboot` as
+/// structured data.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// if let Ok(reboots) = lastlog::reboots_from("/var/log/wtmp") {
+///     for reboot in reboots {
+///         println!("{} {:?} running={}", reboot.kernel(), reboot.uptime(), reboot.is_running());
+///     }
+/// }
+/// ```
+pub fn reboots_from(fname: &str) -> Result<Vec<Reboot>> {
+    Ok(sessions_from(fname)?
+        .filter(|s| s.record.rtype == RecordType::BootTime)
+        .map(|s| Reboot {
+            record: s.record,
+            end: s.end,
+        })
+        .collect())
+}
+
+/// Pair every boot within the system wtmp database with its shutdown
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// if let Some(boot) = lastlog::reboots().ok().and_then(|r| r.into_iter().next()) {
+///     println!("up for {:?}", boot.uptime());
+/// }
+/// ```
+pub fn reboots() -> Result<Vec<Reboot>> {
+    reboots_from(WTMP)
+}
+
+/// Pair login sessions from `/var/log/wtmp` until the token is cancelled
+///
+/// Sessions are collected newest first, so a truncated result holds the
+/// most recent part of the history.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use std::time::Duration;
+/// use lastlog::CancelToken;
+///
+/// let token = CancelToken::with_timeout(Duration::from_millis(500));
+/// if let Ok(history) = lastlog::sessions_cancellable(&token) {
+///     println!("{} sessions, truncated: {}", history.items.len(), history.truncated);
+/// }
+/// ```
+pub fn sessions_cancellable(cancel: &CancelToken) -> Result<Partial<Vec<Session>>> {
+    let mut sessions = sessions()?.with_cancel(cancel.clone());
+    let items = sessions.by_ref().collect();
+    Ok(Partial {
+        items,
+        truncated: sessions.is_truncated(),
+    })
+}
+
+/// Collect the sessions currently logged in according to the given utmp database
+///
+/// Only `USER_PROCESS` entries are returned, in file order, mirroring the
+/// default output of `who(1)`. Entries that fail to parse are skipped.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// if let Ok(current) = lastlog::current_sessions_from("/var/run/utmp") {
+///     for record in current {
+///         println!("{} on {}", record.name, record.tty);
+///     }
+/// }
+/// ```
+pub fn current_sessions_from(fname: &str) -> Result<Vec<Record>> {
+    current_sessions_from_reader(File::open(fname)?)
+}
+
+/// Collect the sessions currently logged in according to any utmp formatted stream
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// let current = lastlog::current_sessions_from_reader(std::io::empty()).unwrap();
+/// assert!(current.is_empty());
+/// ```
+pub fn current_sessions_from_reader<R: Read>(reader: R) -> Result<Vec<Record>> {
+    let reader = UtmpReader::new(BufReader::new(reader));
+    Ok(reader
+        .filter_map(|r| r.ok())
+        .filter(|r| r.rtype == RecordType::User && !r.name.is_empty())
+        .collect())
+}
+
+/// Collect the sessions currently logged in according to the system utmp database
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// if let Ok(current) = lastlog::current_sessions() {
+///     println!("{} users logged in", current.len());
+/// }
+/// ```
+pub fn current_sessions() -> Result<Vec<Record>> {
+    current_sessions_from(UTMP)
+}
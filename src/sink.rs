@@ -0,0 +1,155 @@
+/*
+ *  Incremental output destinations records can be streamed into
+ */
+use std::collections::BTreeMap;
+use std::io::{Result, Write};
+
+use super::common::*;
+
+/* Types */
+
+/// Destination that records are written into one at a time
+///
+/// Bulk operations such as [`crate::Pipeline::write_to`] hand every record
+/// to the sink as soon as it is read, so exporting a huge history never
+/// needs every record in memory at once. Closures taking a `&Record` are
+/// sinks as well.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{Record, RecordSink};
+///
+/// let mut names = vec![];
+/// let mut sink = |record: &Record| {
+///     names.push(record.name.clone());
+///     Ok(())
+/// };
+/// sink.write(&Record::builder().name("foo").build()).unwrap();
+/// sink.finish().unwrap();
+/// assert_eq!(names, ["foo"]);
+/// ```
+pub trait RecordSink {
+    /// Write a single record into the sink
+    fn write(&mut self, record: &Record) -> Result<()>;
+
+    /// Flush anything still buffered once every record was written
+    fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Sink summarizing records as Prometheus text exposition metrics
+///
+/// Counts the records of every type and tracks the latest login of every
+/// user, writing both metric families into the writer on
+/// [`RecordSink::finish`], such as into a node-exporter textfile.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{PrometheusSink, Record, RecordSink};
+///
+/// let mut metrics = vec![];
+/// let mut sink = PrometheusSink::new(&mut metrics);
+/// sink.write(&Record::builder().name("foo").build()).unwrap();
+/// sink.finish().unwrap();
+/// let metrics = String::from_utf8(metrics).unwrap();
+/// assert!(metrics.contains("lastlog_records_total{type=\"USER_PROCESS\"} 1\n"));
+/// ```
+#[derive(Debug)]
+pub struct PrometheusSink<W: Write> {
+    writer: W,
+    types: BTreeMap<String, u64>,
+    logins: BTreeMap<String, u64>,
+}
+
+/* Functions */
+
+// escape a prometheus label value
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/* Implementation */
+
+impl<F> RecordSink for F
+where
+    F: FnMut(&Record) -> Result<()>,
+{
+    fn write(&mut self, record: &Record) -> Result<()> {
+        self(record)
+    }
+}
+
+impl RecordSink for Vec<Record> {
+    fn write(&mut self, record: &Record) -> Result<()> {
+        self.push(record.clone());
+        Ok(())
+    }
+}
+
+impl<W: Write> PrometheusSink<W> {
+    /// Summarize records into the given writer
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            types: BTreeMap::new(),
+            logins: BTreeMap::new(),
+        }
+    }
+
+    /// Retrieve the underlying writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write> RecordSink for PrometheusSink<W> {
+    fn write(&mut self, record: &Record) -> Result<()> {
+        let rtype = match record.rtype.name() {
+            Some(name) => name.to_owned(),
+            None => i32::from(record.rtype).to_string(),
+        };
+        *self.types.entry(rtype).or_default() += 1;
+        if record.rtype == RecordType::User && !record.name.is_empty() {
+            if let Some(time) = record.last_login.unix_seconds() {
+                let latest = self.logins.entry(record.name.clone()).or_default();
+                *latest = (*latest).max(time);
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        let w = &mut self.writer;
+        writeln!(
+            w,
+            "# HELP lastlog_records_total Number of login records by type"
+        )?;
+        writeln!(w, "# TYPE lastlog_records_total counter")?;
+        for (rtype, count) in self.types.iter() {
+            writeln!(w, "lastlog_records_total{{type=\"{rtype}\"}} {count}")?;
+        }
+        writeln!(
+            w,
+            "# HELP lastlog_last_login_timestamp_seconds Time of the latest login of each user"
+        )?;
+        writeln!(w, "# TYPE lastlog_last_login_timestamp_seconds gauge")?;
+        for (user, time) in self.logins.iter() {
+            let user = escape_label(user);
+            writeln!(
+                w,
+                "lastlog_last_login_timestamp_seconds{{user=\"{user}\"}} {time}"
+            )?;
+        }
+        w.flush()
+    }
+}
@@ -0,0 +1,354 @@
+/*
+ *  Portable, versioned snapshots of login records for fleet aggregation
+ */
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json::{json, Map, Value};
+
+use super::common::*;
+use super::raw::RecordType;
+
+/* Variables */
+
+static FORMAT: &str = "lastlog-snapshot";
+
+/// Version of the snapshot layout written by [`Snapshot::write`]
+pub const SNAPSHOT_VERSION: u64 = 1;
+
+/* Types */
+
+/// Login records of one or many machines at a point in time
+///
+/// Snapshots are written as JSON with a format marker and version, and
+/// store login-times as unix microseconds so records survive a round-trip
+/// unchanged. Every record carries the [`Record::machine`] it was read on,
+/// so snapshots of many hosts can be merged into a single set and queried
+/// together.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{Record, Snapshot};
+///
+/// let alpha = Snapshot::new("alpha", vec![Record::builder().name("root").build()]);
+/// let beta = Snapshot::new("beta", vec![Record::builder().name("root").build()]);
+///
+/// let mut buffer = vec![];
+/// alpha.write(&mut buffer).unwrap();
+/// let alpha = Snapshot::read(buffer.as_slice()).unwrap();
+///
+/// let fleet = Snapshot::merge([alpha, beta]);
+/// assert_eq!(fleet.machines(), vec!["alpha", "beta"]);
+/// assert_eq!(fleet.search_username("root").count(), 2);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    /// time the snapshot was taken
+    pub created: SystemTime,
+    /// records of every machine within the snapshot
+    pub records: Vec<Record>,
+}
+
+/// Change between two snapshots of the same machines found by [`Snapshot::diff`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotChange {
+    /// account that only exists in the newer snapshot
+    NewAccount(Record),
+    /// account that no longer exists in the newer snapshot
+    RemovedAccount(Record),
+    /// account that logged in for the first time between both snapshots
+    FirstLogin { before: Record, after: Record },
+    /// account whose latest login changed between both snapshots
+    LoginChanged { before: Record, after: Record },
+}
+
+/* Functions */
+
+// index the latest record of every machine and username within a snapshot
+fn index(snapshot: &Snapshot) -> BTreeMap<(Option<&str>, &str), &Record> {
+    let mut records: BTreeMap<_, &Record> = BTreeMap::new();
+    for record in snapshot.records.iter() {
+        let key = (record.machine.as_deref(), record.name.as_str());
+        match records.get(&key) {
+            Some(old) if old.last_login >= record.last_login => {}
+            _ => {
+                records.insert(key, record);
+            }
+        }
+    }
+    records
+}
+
+// generate an invalid-data error for a malformed snapshot
+fn invalid(reason: impl Into<String>) -> Error {
+    Error::new(ErrorKind::InvalidData, reason.into())
+}
+
+// convert a login-time into unix microseconds, `null` if never logged in
+fn encode_time(time: &LoginTime) -> Value {
+    time.unix_micros().map_or(Value::Null, |t| json!(t as u64))
+}
+
+// convert unix microseconds back into a login-time
+fn decode_time(value: Option<&Value>) -> Result<LoginTime> {
+    match value {
+        None | Some(Value::Null) => Ok(LoginTime::Never),
+        Some(value) => {
+            let micros = value
+                .as_u64()
+                .ok_or_else(|| invalid("invalid login time"))?;
+            Ok(LoginTime::Last(UNIX_EPOCH + Duration::from_micros(micros)))
+        }
+    }
+}
+
+// encode a single record into its snapshot layout
+fn encode_record(record: &Record) -> Value {
+    json!({
+        "type": i32::from(record.rtype),
+        "uid": record.uid,
+        "user": record.name,
+        "tty": record.tty,
+        "host": record.host,
+        "pid": record.pid,
+        "last_login": encode_time(&record.last_login),
+        "machine": record.machine,
+    })
+}
+
+// decode a single record from its snapshot layout
+fn decode_record(value: &Value) -> Result<Record> {
+    let entry = value.as_object().ok_or_else(|| invalid("invalid record"))?;
+    let text = |name: &str| entry.get(name).and_then(|v| v.as_str()).unwrap_or_default();
+    let rtype = entry
+        .get("type")
+        .and_then(|v| v.as_i64())
+        .ok_or_else(|| invalid("record without a type"))?;
+    Ok(Record::builder()
+        .rtype(RecordType::from(rtype as i32))
+        .uid(entry.get("uid").and_then(|v| v.as_u64()).map(|u| u as u32))
+        .name(text("user"))
+        .tty(text("tty"))
+        .host(text("host"))
+        .pid(entry.get("pid").and_then(|v| v.as_i64()).map(|p| p as i32))
+        .last_login(decode_time(entry.get("last_login"))?)
+        .machine(
+            entry
+                .get("machine")
+                .and_then(|v| v.as_str())
+                .map(|m| m.to_owned()),
+        )
+        .build())
+}
+
+/// Take a snapshot of the latest login of every account on this machine
+///
+/// Records are read from the auto-selected database like `iter_accounts`
+/// and tagged with [`local_machine`].
+///
+/// [`local_machine`]: crate::local_machine
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// if let Ok(snapshot) = lastlog::export_snapshot() {
+///     let path = std::env::temp_dir().join(format!("lastlog-snapshot-{}.json", std::process::id()));
+///     if snapshot.save(path.to_str().unwrap()).is_ok() {
+///         std::fs::remove_file(&path).unwrap();
+///     }
+/// }
+/// ```
+pub fn export_snapshot() -> Result<Snapshot> {
+    let machine = local_machine().unwrap_or_default();
+    Ok(Snapshot::new(machine, super::iter_accounts()?))
+}
+
+/// Load a snapshot previously written with [`Snapshot::save`]
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// let paths = ["/tmp/web01.json", "/tmp/web02.json"];
+/// let fleet = lastlog::Snapshot::merge(
+///     paths.iter().filter_map(|p| lastlog::import_snapshot(p).ok()),
+/// );
+/// ```
+pub fn import_snapshot(fname: &str) -> Result<Snapshot> {
+    Snapshot::read(BufReader::new(File::open(fname)?))
+}
+
+/* Implementation */
+
+impl Snapshot {
+    /// Take a snapshot of the given records read on the given machine
+    ///
+    /// Records that are not yet tagged with a machine are assigned to it.
+    pub fn new(machine: impl Into<String>, mut records: Vec<Record>) -> Self {
+        let machine = machine.into();
+        for record in records.iter_mut().filter(|r| r.machine.is_none()) {
+            record.machine = Some(machine.clone());
+        }
+        Self {
+            created: SystemTime::now(),
+            records,
+        }
+    }
+
+    /// Combine the snapshots of many machines into one set
+    ///
+    /// When several snapshots hold records of the same machine only the
+    /// most recently created one is kept, so re-importing a newer dump of
+    /// a host replaces its previous records.
+    pub fn merge(snapshots: impl IntoIterator<Item = Snapshot>) -> Self {
+        let mut snapshots: Vec<_> = snapshots.into_iter().collect();
+        snapshots.sort_by_key(|s| Reverse(s.created));
+        let mut seen = BTreeSet::new();
+        let mut records = vec![];
+        let created = snapshots.first().map_or(UNIX_EPOCH, |s| s.created);
+        for snapshot in snapshots {
+            let machines: BTreeSet<_> =
+                snapshot.records.iter().map(|r| r.machine.clone()).collect();
+            records.extend(
+                snapshot
+                    .records
+                    .into_iter()
+                    .filter(|r| !seen.contains(&r.machine)),
+            );
+            seen.extend(machines);
+        }
+        Self { created, records }
+    }
+
+    /// Compare two snapshots reporting only what changed between them
+    ///
+    /// Records are matched by machine and username, and changes are
+    /// returned in that order so scheduled audits produce stable reports.
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use std::time::SystemTime;
+    /// use lastlog::{Record, Snapshot, SnapshotChange};
+    ///
+    /// let old = Snapshot::new("web01", vec![Record::builder().name("root").build()]);
+    /// let root = Record::builder().name("root").last_login(SystemTime::now());
+    /// let new = Snapshot::new("web01", vec![root.build()]);
+    ///
+    /// let changes = Snapshot::diff(&old, &new);
+    /// assert!(matches!(changes[0], SnapshotChange::FirstLogin { .. }));
+    /// ```
+    pub fn diff(old: &Snapshot, new: &Snapshot) -> Vec<SnapshotChange> {
+        let (before, after) = (index(old), index(new));
+        let mut changes = vec![];
+        let keys: BTreeSet<_> = before.keys().chain(after.keys()).collect();
+        for key in keys {
+            let change = match (before.get(key), after.get(key)) {
+                (None, Some(added)) => SnapshotChange::NewAccount((*added).clone()),
+                (Some(removed), None) => SnapshotChange::RemovedAccount((*removed).clone()),
+                (Some(b), Some(a)) if b.last_login == a.last_login => continue,
+                (Some(b), Some(a)) => {
+                    let (before, after) = ((*b).clone(), (*a).clone());
+                    match before.last_login.is_never() {
+                        true => SnapshotChange::FirstLogin { before, after },
+                        false => SnapshotChange::LoginChanged { before, after },
+                    }
+                }
+                (None, None) => continue,
+            };
+            changes.push(change);
+        }
+        changes
+    }
+
+    /// List every machine with records in the snapshot
+    pub fn machines(&self) -> Vec<&str> {
+        let machines: BTreeSet<_> = self
+            .records
+            .iter()
+            .filter_map(|r| r.machine.as_deref())
+            .collect();
+        machines.into_iter().collect()
+    }
+
+    /// Iterate the records of a single machine
+    pub fn for_machine<'a>(&'a self, machine: &'a str) -> impl Iterator<Item = &'a Record> {
+        self.records
+            .iter()
+            .filter(move |r| r.machine.as_deref() == Some(machine))
+    }
+
+    /// Iterate the records of the given username across every machine
+    pub fn search_username<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Record> {
+        self.records.iter().filter(move |r| r.name == name)
+    }
+
+    /// Find the most recent login of the given username on any machine
+    pub fn latest_login(&self, name: &str) -> Option<&Record> {
+        self.records
+            .iter()
+            .filter(|r| r.name == name)
+            .max_by_key(|r| &r.last_login)
+    }
+
+    /// Write the snapshot as versioned JSON
+    pub fn write<W: Write>(&self, writer: W) -> Result<()> {
+        let created = LoginTime::Last(self.created);
+        let snapshot = json!({
+            "format": FORMAT,
+            "version": SNAPSHOT_VERSION,
+            "created": encode_time(&created),
+            "records": self.records.iter().map(encode_record).collect::<Vec<_>>(),
+        });
+        serde_json::to_writer(writer, &snapshot).map_err(|e| invalid(e.to_string()))
+    }
+
+    /// Read a snapshot written by [`Snapshot::write`]
+    ///
+    /// Snapshots of a newer, unknown version are rejected instead of being
+    /// misread.
+    pub fn read<R: Read>(reader: R) -> Result<Self> {
+        let snapshot: Map<String, Value> =
+            serde_json::from_reader(reader).map_err(|e| invalid(e.to_string()))?;
+        if snapshot.get("format").and_then(|v| v.as_str()) != Some(FORMAT) {
+            return Err(invalid("not a lastlog snapshot"));
+        }
+        match snapshot.get("version").and_then(|v| v.as_u64()) {
+            Some(version) if version <= SNAPSHOT_VERSION => {}
+            Some(version) => {
+                return Err(invalid(format!("unsupported snapshot version {version}")))
+            }
+            None => return Err(invalid("snapshot without a version")),
+        }
+        let created = match decode_time(snapshot.get("created"))? {
+            LoginTime::Last(created) => created,
+            LoginTime::Never => UNIX_EPOCH,
+        };
+        let records = snapshot
+            .get("records")
+            .and_then(|v| v.as_array())
+            .ok_or_else(|| invalid("snapshot without records"))?
+            .iter()
+            .map(decode_record)
+            .collect::<Result<_>>()?;
+        Ok(Self { created, records })
+    }
+
+    /// Save the snapshot into the given file
+    pub fn save(&self, fname: &str) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(fname)?);
+        self.write(&mut writer)?;
+        writer.flush()
+    }
+}
@@ -0,0 +1,60 @@
+/*
+ *  Deterministic ordering helpers for record collections
+ */
+use std::cmp::Ordering;
+
+use super::common::Record;
+
+/* Types */
+
+/// Record field used to order a collection of records
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SortBy {
+    Uid,
+    Username,
+    LastLogin,
+}
+
+/// Direction a collection of records is ordered in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Order {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+/* Functions */
+
+// compare two records by the given field, falling back on uid/name for ties
+fn compare(a: &Record, b: &Record, by: SortBy) -> Ordering {
+    let primary = match by {
+        SortBy::Uid => a.uid.cmp(&b.uid),
+        SortBy::Username => a.name.cmp(&b.name),
+        SortBy::LastLogin => a.last_login.cmp(&b.last_login),
+    };
+    primary
+        .then_with(|| a.uid.cmp(&b.uid))
+        .then_with(|| a.name.cmp(&b.name))
+}
+
+/// Sort a collection of records by the given field and direction
+///
+/// Ties are broken by uid and then username so the resulting order is
+/// always deterministic between runs.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{sort_records, Order, SortBy};
+///
+/// let mut records = lastlog::iter_accounts().unwrap_or_default();
+/// sort_records(&mut records, SortBy::LastLogin, Order::Descending);
+/// ```
+pub fn sort_records(records: &mut [Record], by: SortBy, order: Order) {
+    records.sort_by(|a, b| match order {
+        Order::Ascending => compare(a, b, by),
+        Order::Descending => compare(b, a, by),
+    });
+}
@@ -0,0 +1,183 @@
+/*
+ *  Pluggable data sources for parsing databases without file access
+ */
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Cursor, Error, ErrorKind, Read, Result, Seek};
+
+use super::account::read_accounts_from;
+use super::common::*;
+use super::lastlog::{read_logged_in, read_users_from};
+use super::session::Sessions;
+use super::stats::ReadStats;
+use super::utmp::{find_latest_from, UtmpReader, UtmpRevReader};
+
+/* Types */
+
+/// Readable and seekable database stream returned by a [`DataSource`]
+pub trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek + ?Sized> ReadSeek for T {}
+
+/// Provider of the raw database contents parsed by a [`SourceReader`]
+///
+/// Abstracts all file access so the parsers can be used where no
+/// filesystem is available, such as a `wasm32` build parsing databases
+/// uploaded into a browser.
+pub trait DataSource {
+    /// Open the database of the given name for reading
+    fn open(&self, name: &str) -> Result<Box<dyn ReadSeek + '_>>;
+}
+
+/// Data source reading databases from the local filesystem
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FileSource;
+
+/// Data source serving databases held in memory
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{DataSource, MemorySource};
+///
+/// let mut source = MemorySource::new();
+/// source.insert("passwd", "root:x:0:0:root:/root:/bin/bash\n");
+/// assert!(source.open("passwd").is_ok());
+/// assert!(source.open("wtmp").is_err());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MemorySource {
+    files: HashMap<String, Vec<u8>>,
+}
+
+/// Login queries against the databases of any [`DataSource`]
+///
+/// Usernames and user-ids are resolved using a passwd file loaded from
+/// the same source with [`SourceReader::with_passwd`], so nothing is read
+/// from the local system.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{MemorySource, SourceReader};
+///
+/// let mut source = MemorySource::new();
+/// source.insert("passwd", "root:x:0:0:root:/root:/bin/bash\n");
+/// source.insert("lastlog", vec![0u8; 292]);
+///
+/// let reader = SourceReader::new(source).with_passwd("passwd").unwrap();
+/// let records = reader.iter_accounts("lastlog").unwrap();
+/// assert_eq!(records[0].name, "root");
+/// assert!(records[0].last_login.is_never());
+/// assert!(reader.logged_in("lastlog").unwrap().is_empty());
+/// ```
+#[derive(Debug, Clone)]
+pub struct SourceReader<S: DataSource> {
+    source: S,
+    users: HashMap<String, u32>,
+}
+
+/* Implementation */
+
+impl DataSource for FileSource {
+    fn open(&self, name: &str) -> Result<Box<dyn ReadSeek + '_>> {
+        Ok(Box::new(File::open(name)?))
+    }
+}
+
+impl MemorySource {
+    /// Create an empty in-memory data source
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or replace the contents of the named database
+    pub fn insert(&mut self, name: impl Into<String>, data: impl Into<Vec<u8>>) {
+        self.files.insert(name.into(), data.into());
+    }
+}
+
+impl DataSource for MemorySource {
+    fn open(&self, name: &str) -> Result<Box<dyn ReadSeek + '_>> {
+        match self.files.get(name) {
+            Some(data) => Ok(Box::new(Cursor::new(data.as_slice()))),
+            None => Err(Error::new(
+                ErrorKind::NotFound,
+                format!("no such database {name:?}"),
+            )),
+        }
+    }
+}
+
+impl<S: DataSource> SourceReader<S> {
+    /// Query the given source without any known accounts
+    pub fn new(source: S) -> Self {
+        Self {
+            source,
+            users: HashMap::new(),
+        }
+    }
+
+    /// Resolve accounts using the named passwd(5) file of the source
+    pub fn with_passwd(mut self, name: &str) -> Result<Self> {
+        self.users = read_accounts_from(self.source.open(name)?)?
+            .into_iter()
+            .map(|a| (a.name, a.uid))
+            .collect();
+        Ok(self)
+    }
+
+    /// Retrieve the underlying data source
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    /// Map every known username to its user-id
+    pub fn users(&self) -> &HashMap<String, u32> {
+        &self.users
+    }
+
+    /// Iterate every entry of the named utmp/wtmp/btmp database in file order
+    pub fn records(&self, name: &str) -> Result<UtmpReader<Box<dyn ReadSeek + '_>>> {
+        Ok(UtmpReader::with_users(
+            self.source.open(name)?,
+            self.users.clone(),
+        ))
+    }
+
+    /// Pair the login sessions of the named wtmp database, newest first
+    pub fn sessions(&self, name: &str) -> Result<Sessions<impl Iterator<Item = Record> + '_>> {
+        let reader = UtmpRevReader::with_users(self.source.open(name)?, self.users.clone())?;
+        Ok(Sessions::new(reader.filter_map(|r| r.ok())))
+    }
+
+    /// Find the latest login of the given username within a utmp/wtmp database
+    pub fn search_username(&self, username: &str, name: &str) -> Result<Record> {
+        let mut f = self.source.open(name)?;
+        let mut stats = ReadStats::default();
+        find_latest_from(&self.users, &mut f, &mut stats, |n| n == username)?
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "no such user"))
+    }
+
+    /// Read the lastlog entry of every known account from a lastlog database
+    pub fn iter_accounts(&self, name: &str) -> Result<Vec<Record>> {
+        let idmap = self.users.iter().map(|(n, u)| (*u, n.to_owned())).collect();
+        read_users_from(
+            &mut self.source.open(name)?,
+            &idmap,
+            &mut ReadStats::default(),
+        )
+    }
+
+    /// Read every lastlog entry that has logged in, known account or not
+    ///
+    /// Entries of user-ids missing from the passwd file have an empty name.
+    pub fn logged_in(&self, name: &str) -> Result<Vec<Record>> {
+        let idmap = self.users.iter().map(|(n, u)| (*u, n.to_owned())).collect();
+        read_logged_in(self.source.open(name)?, &idmap)
+    }
+}
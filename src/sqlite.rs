@@ -0,0 +1,370 @@
+/*
+ *  SQLite archive of parsed wtmp, btmp, utmp and lastlog history
+ */
+use std::fs::{self, Metadata};
+use std::io::{Error, Result};
+
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+
+use super::common::*;
+use super::lastlog::LastLog;
+use super::sink::RecordSink;
+use super::utmp::{rotated_files, UtmpReader};
+
+/* Variables */
+
+/// Version of the archive schema, stored as the database `user_version`
+pub const SCHEMA_VERSION: i64 = 2;
+
+// number of records inserted per transaction by a streaming sink
+static SINK_BATCH: usize = 1000;
+
+static SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS records (
+    id      INTEGER PRIMARY KEY,
+    kind    TEXT NOT NULL,
+    source  TEXT NOT NULL,
+    type    INTEGER NOT NULL,
+    uid     INTEGER,
+    user    TEXT NOT NULL,
+    tty     TEXT NOT NULL,
+    host    TEXT NOT NULL,
+    pid     INTEGER,
+    time_us INTEGER,
+    machine TEXT
+);
+CREATE UNIQUE INDEX IF NOT EXISTS records_entry ON records (
+    kind, type, user, tty, host,
+    ifnull(pid, -1), ifnull(time_us, -1), ifnull(machine, '')
+);
+CREATE INDEX IF NOT EXISTS records_user ON records (user, time_us);
+CREATE TABLE IF NOT EXISTS progress (
+    source TEXT PRIMARY KEY,
+    inode  INTEGER NOT NULL,
+    offset INTEGER NOT NULL
+);
+";
+
+/* Types */
+
+/// Kind of database a set of archived records was read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HistoryKind {
+    /// login history such as `/var/log/wtmp`
+    Wtmp,
+    /// failed login attempts such as `/var/log/btmp`
+    Btmp,
+    /// currently active sessions such as `/var/run/utmp`
+    Utmp,
+    /// latest login per user-id such as `/var/log/lastlog`
+    Lastlog,
+}
+
+/// Local SQLite database collecting parsed login history
+///
+/// Records are stored in a single `records` table with a stable schema:
+///
+/// | Column    | Type      | Description                                      |
+/// | --------- | --------- | ------------------------------------------------ |
+/// | `kind`    | `TEXT`    | `wtmp`, `btmp`, `utmp` or `lastlog`              |
+/// | `source`  | `TEXT`    | path of the file the record was first read from  |
+/// | `type`    | `INTEGER` | utmp(5) record-type                              |
+/// | `uid`     | `INTEGER` | user-id, `NULL` when unknown                     |
+/// | `user`    | `TEXT`    | username                                         |
+/// | `tty`     | `TEXT`    | terminal line                                    |
+/// | `host`    | `TEXT`    | remote host                                      |
+/// | `pid`     | `INTEGER` | process-id, `NULL` when unknown                  |
+/// | `time_us` | `INTEGER` | login-time as unix microseconds, `NULL` if never |
+/// | `machine` | `TEXT`    | machine the record was read on, if tagged        |
+///
+/// Identical entries are only stored once, so re-archiving a database
+/// after it was rotated (e.g. `wtmp` becoming `wtmp.1`) keeps the archive
+/// free of duplicates while it grows beyond the rotation window.
+///
+/// [`SqliteArchive::update`] additionally remembers how far every utmp
+/// formatted file was read within the `progress` table, so scheduled runs
+/// only parse the entries appended since the last one.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{HistoryKind, SqliteArchive};
+///
+/// let path = std::env::temp_dir().join(format!("lastlog-archive-{}.sqlite", std::process::id()));
+/// let mut archive = SqliteArchive::open(path.to_str().unwrap()).unwrap();
+/// let added = archive.archive(HistoryKind::Wtmp, "/var/log/wtmp").unwrap_or_default();
+/// println!("archived {added} new entries");
+/// std::fs::remove_file(&path).unwrap();
+/// ```
+pub struct SqliteArchive {
+    conn: Connection,
+}
+
+/// Sink streaming records into a [`SqliteArchive`] in batches
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{HistoryKind, Pipeline, SqliteArchive};
+///
+/// let path = std::env::temp_dir().join(format!("lastlog-sink-{}.sqlite", std::process::id()));
+/// let mut archive = SqliteArchive::open(path.to_str().unwrap()).unwrap();
+/// if let Ok(pipeline) = Pipeline::open("/var/log/wtmp") {
+///     let mut sink = archive.sink(HistoryKind::Wtmp, "/var/log/wtmp");
+///     pipeline.write_to(&mut sink).unwrap();
+///     println!("archived {} new entries", sink.added());
+/// }
+/// std::fs::remove_file(&path).unwrap();
+/// ```
+pub struct SqliteSink<'a> {
+    archive: &'a mut SqliteArchive,
+    kind: HistoryKind,
+    source: String,
+    buffer: Vec<Record>,
+    added: usize,
+}
+
+/* Functions */
+
+// convert sqlite failures into io errors
+#[inline]
+pub fn sql_error(err: rusqlite::Error) -> Error {
+    Error::other(err)
+}
+
+// unique identity of a file on disk used to detect rotation
+#[cfg(unix)]
+fn file_inode(meta: &Metadata) -> i64 {
+    use std::os::unix::fs::MetadataExt;
+    meta.ino() as i64
+}
+
+#[cfg(not(unix))]
+fn file_inode(_meta: &Metadata) -> i64 {
+    0
+}
+
+// read every complete entry from the given offset, returning the next offset
+fn read_tail(fname: &str, offset: u64) -> Result<(Vec<Record>, u64)> {
+    let mut reader = UtmpReader::open_at(fname, offset)?;
+    let records = reader.by_ref().filter_map(|r| r.ok()).collect();
+    Ok((records, reader.offset()))
+}
+
+// store the given records within an open transaction
+fn insert_records(
+    tx: &Transaction,
+    kind: HistoryKind,
+    source: &str,
+    records: &[Record],
+) -> Result<usize> {
+    let mut insert = tx
+        .prepare_cached(
+            "INSERT OR IGNORE INTO records
+             (kind, source, type, uid, user, tty, host, pid, time_us, machine)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        )
+        .map_err(sql_error)?;
+    let mut added = 0;
+    for record in records {
+        added += insert
+            .execute(params![
+                kind.as_str(),
+                source,
+                i32::from(record.rtype),
+                record.uid,
+                record.name,
+                record.tty,
+                record.host,
+                record.pid,
+                record.last_login.unix_micros().map(|t| t as i64),
+                record.machine,
+            ])
+            .map_err(sql_error)?;
+    }
+    Ok(added)
+}
+
+/* Implementation */
+
+impl HistoryKind {
+    /// Name of the kind as stored in the `kind` column
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Wtmp => "wtmp",
+            Self::Btmp => "btmp",
+            Self::Utmp => "utmp",
+            Self::Lastlog => "lastlog",
+        }
+    }
+}
+
+impl SqliteArchive {
+    /// Open or create the archive at the given path
+    pub fn open(path: &str) -> Result<Self> {
+        Self::with_connection(Connection::open(path).map_err(sql_error)?)
+    }
+
+    /// Open an archive within an existing connection, creating its schema
+    pub fn with_connection(conn: Connection) -> Result<Self> {
+        let version: i64 = conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(sql_error)?;
+        if version > SCHEMA_VERSION {
+            return Err(Error::other(format!(
+                "unsupported archive schema version {version}"
+            )));
+        }
+        conn.execute_batch(SCHEMA).map_err(sql_error)?;
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION)
+            .map_err(sql_error)?;
+        Ok(Self { conn })
+    }
+
+    /// Underlying connection for running ad-hoc queries
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    /// Store the given records, returning how many were not archived yet
+    pub fn insert(&mut self, kind: HistoryKind, source: &str, records: &[Record]) -> Result<usize> {
+        let tx = self.conn.transaction().map_err(sql_error)?;
+        let added = insert_records(&tx, kind, source, records)?;
+        tx.commit().map_err(sql_error)?;
+        debug_event!(kind = kind.as_str(), source, added, "archived records");
+        Ok(added)
+    }
+
+    /// Stream records into the archive, see [`SqliteSink`]
+    pub fn sink(&mut self, kind: HistoryKind, source: &str) -> SqliteSink<'_> {
+        SqliteSink {
+            archive: self,
+            kind,
+            source: source.to_owned(),
+            buffer: Vec::with_capacity(SINK_BATCH),
+            added: 0,
+        }
+    }
+
+    /// Parse the given database and store every record not archived yet
+    ///
+    /// Utmp formatted files are archived entry by entry, skipping entries
+    /// that fail to parse, while lastlog archives the latest login of
+    /// every account.
+    pub fn archive(&mut self, kind: HistoryKind, fname: &str) -> Result<usize> {
+        let records = match kind {
+            HistoryKind::Lastlog => LastLog {}.iter_accounts(fname)?,
+            _ => UtmpReader::open(fname)?.filter_map(|r| r.ok()).collect(),
+        };
+        self.insert(kind, fname, &records)
+    }
+
+    /// Archive only the entries appended to the given database since the last update
+    ///
+    /// The inode and byte offset reached within utmp formatted files are
+    /// stored alongside the records, so a cron job maintaining years of
+    /// history only parses what was written since its previous run. When
+    /// the file was rotated the remaining tail of the previous file is
+    /// first read from its numbered or dated archive, and reading restarts
+    /// from the beginning of the new file. Lastlog has no append-only
+    /// layout and is always archived completely.
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use lastlog::{HistoryKind, SqliteArchive};
+    ///
+    /// let path = std::env::temp_dir().join(format!("lastlog-update-{}.sqlite", std::process::id()));
+    /// let mut archive = SqliteArchive::open(path.to_str().unwrap()).unwrap();
+    /// let added = archive.update(HistoryKind::Wtmp, "/var/log/wtmp").unwrap_or_default();
+    /// println!("archived {added} new entries");
+    /// std::fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn update(&mut self, kind: HistoryKind, fname: &str) -> Result<usize> {
+        if kind == HistoryKind::Lastlog {
+            return self.archive(kind, fname);
+        }
+        let meta = fs::metadata(fname)?;
+        let inode = file_inode(&meta);
+        let progress: Option<(i64, i64)> = self
+            .conn
+            .query_row(
+                "SELECT inode, offset FROM progress WHERE source = ?1",
+                [fname],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(sql_error)?;
+        let mut records = vec![];
+        let start = match progress {
+            Some((old, offset)) if old == inode && offset as u64 <= meta.len() => offset as u64,
+            Some((old, offset)) => {
+                // finish reading the previous file if it was rotated away
+                let renamed = rotated_files(fname)?.into_iter().skip(1).find(|f| {
+                    fs::metadata(f).is_ok_and(|m| file_inode(&m) == old && offset as u64 <= m.len())
+                });
+                if let Some(renamed) = renamed {
+                    debug_event!(from = fname, to = renamed, "database was rotated");
+                    records.extend(read_tail(&renamed, offset as u64)?.0);
+                }
+                0
+            }
+            None => 0,
+        };
+        let (tail, offset) = read_tail(fname, start)?;
+        records.extend(tail);
+
+        let tx = self.conn.transaction().map_err(sql_error)?;
+        let added = insert_records(&tx, kind, fname, &records)?;
+        tx.execute(
+            "INSERT OR REPLACE INTO progress (source, inode, offset) VALUES (?1, ?2, ?3)",
+            params![fname, inode, offset as i64],
+        )
+        .map_err(sql_error)?;
+        tx.commit().map_err(sql_error)?;
+        debug_event!(
+            kind = kind.as_str(),
+            source = fname,
+            start,
+            offset,
+            "updated archive"
+        );
+        Ok(added)
+    }
+}
+
+impl SqliteSink<'_> {
+    /// Number of records that were not archived yet, counted once flushed
+    pub fn added(&self) -> usize {
+        self.added
+    }
+
+    // insert every buffered record within a single transaction
+    fn flush(&mut self) -> Result<()> {
+        if !self.buffer.is_empty() {
+            self.added += self.archive.insert(self.kind, &self.source, &self.buffer)?;
+            self.buffer.clear();
+        }
+        Ok(())
+    }
+}
+
+impl RecordSink for SqliteSink<'_> {
+    fn write(&mut self, record: &Record) -> Result<()> {
+        self.buffer.push(record.clone());
+        if self.buffer.len() >= SINK_BATCH {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.flush()
+    }
+}
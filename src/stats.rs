@@ -0,0 +1,68 @@
+/*
+ *  Statistics collected while reading login databases
+ */
+use std::ops::AddAssign;
+use std::time::{Duration, Instant};
+
+/* Types */
+
+/// Summary of the work performed by a bulk read operation
+///
+/// Entries that could not be parsed are counted as skipped rather than
+/// parsed, so a growing `records_skipped` points at a corrupt database
+/// while a growing `duration` for the same `bytes_read` points at a
+/// performance regression.
+///
+/// The `duration` is only measured by the `*_with_stats` operations that
+/// read files directly. Streaming readers such as [`UtmpReader`] report
+/// it as zero, leaving timing up to the caller.
+///
+/// [`UtmpReader`]: crate::UtmpReader
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// if let Ok((_, stats)) = lastlog::iter_accounts_with_stats() {
+///     if stats.records_skipped > 0 {
+///         eprintln!("skipped {} corrupt entries", stats.records_skipped);
+///     }
+///     println!("{} bytes in {:?}", stats.bytes_read, stats.duration);
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReadStats {
+    /// number of bytes read from the database
+    pub bytes_read: u64,
+    /// number of entries successfully parsed into records
+    pub records_parsed: usize,
+    /// number of entries that failed to parse and were skipped
+    pub records_skipped: usize,
+    /// time spent reading and parsing
+    pub duration: Duration,
+}
+
+/* Functions */
+
+// run the given operation adding the time it took to the statistics
+pub fn timed<T, F>(stats: &mut ReadStats, op: F) -> T
+where
+    F: FnOnce(&mut ReadStats) -> T,
+{
+    let start = Instant::now();
+    let result = op(stats);
+    stats.duration += start.elapsed();
+    result
+}
+
+/* Implementation */
+
+impl AddAssign for ReadStats {
+    fn add_assign(&mut self, rhs: Self) {
+        self.bytes_read += rhs.bytes_read;
+        self.records_parsed += rhs.records_parsed;
+        self.records_skipped += rhs.records_skipped;
+        self.duration += rhs.duration;
+    }
+}
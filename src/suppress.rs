@@ -0,0 +1,286 @@
+/*
+ *  Allowlist rules suppressing known false positives of audit analyses
+ */
+use std::fs::read_to_string;
+use std::io::{Error, ErrorKind, Result};
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use super::common::*;
+use super::finding::{Finding, FindingKind};
+use super::pattern::Pattern;
+
+/* Variables */
+
+static MINUTES_PER_DAY: u64 = 24 * 60;
+
+/* Types */
+
+// remote hosts a suppression applies to
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum HostRule {
+    Pattern(Pattern),
+    Network(IpAddr, u8),
+}
+
+/// Rule suppressing the findings of known and expected activity
+///
+/// A rule matches a finding when every given condition holds for each of
+/// its evidence records: the username and host match, and the login-time
+/// lies within the time window (UTC, wrapping past midnight). Findings
+/// without evidence, such as accounts that never logged in, are matched by
+/// their subject instead and never by a time window.
+///
+/// Rules are either built in code, or parsed from a line of `key=value`
+/// conditions using the keys `kind`, `user`, `host` and `time`.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{Finding, FindingKind, Record, Severity, Suppression};
+///
+/// let rule: Suppression = "user=backup host=10.0.0.0/8 time=01:00-03:00".parse().unwrap();
+/// let login = Record::builder()
+///     .name("backup")
+///     .host("10.20.0.5")
+///     .last_login(std::time::UNIX_EPOCH + std::time::Duration::from_secs(2 * 60 * 60))
+///     .build();
+/// let finding = Finding::new(FindingKind::Anomaly, Severity::Low, "backup").evidence(vec![login]);
+/// assert!(rule.matches(&finding));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Suppression {
+    kind: Option<FindingKind>,
+    user: Option<Pattern>,
+    host: Option<HostRule>,
+    window: Option<(u64, u64)>,
+}
+
+/// Set of [`Suppression`] rules applied to the findings of an analysis
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{DormancyPolicy, Suppressions};
+///
+/// let config = "# nightly backups from the bastion host\n\
+///     user=backup host=bastion.example.com\n\
+///     kind=dormant user=svc-*\n";
+/// let rules = Suppressions::parse(config).unwrap();
+/// let policy = DormancyPolicy::new().suppress(rules);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Suppressions(Vec<Suppression>);
+
+/* Functions */
+
+// build the error reported for an invalid rule
+fn invalid(message: impl Into<String>) -> Error {
+    Error::new(
+        ErrorKind::InvalidInput,
+        format!("invalid suppression: {}", message.into()),
+    )
+}
+
+// parse a time of day such as `01:30` into minutes past midnight
+fn parse_minutes(time: &str) -> Result<u64> {
+    let (hour, minute) = time.split_once(':').unwrap_or((time, "0"));
+    match (hour.parse::<u64>(), minute.parse::<u64>()) {
+        (Ok(hour), Ok(minute)) if hour < 24 && minute < 60 => Ok(hour * 60 + minute),
+        _ => Err(invalid(format!("invalid time of day {time:?}"))),
+    }
+}
+
+// check if the address lies within the network
+fn in_network(addr: IpAddr, network: IpAddr, prefix: u8) -> bool {
+    match (addr, network) {
+        (IpAddr::V4(addr), IpAddr::V4(network)) => {
+            let mask = u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0);
+            u32::from(addr) & mask == u32::from(network) & mask
+        }
+        (IpAddr::V6(addr), IpAddr::V6(network)) => {
+            let mask = u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0);
+            u128::from(addr) & mask == u128::from(network) & mask
+        }
+        _ => false,
+    }
+}
+
+/* Implementation */
+
+impl HostRule {
+    // check if the remote host matches the rule
+    fn matches(&self, host: &str) -> bool {
+        match self {
+            Self::Pattern(pattern) => pattern.matches(host),
+            Self::Network(network, prefix) => host
+                .parse::<IpAddr>()
+                .is_ok_and(|addr| in_network(addr, *network, *prefix)),
+        }
+    }
+}
+
+impl Suppression {
+    /// Build a rule matching every finding until conditions are added
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only match findings reported by the given analysis
+    pub fn kind(mut self, kind: FindingKind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+
+    /// Only match logins of usernames matching the pattern
+    pub fn user(mut self, user: impl Into<Pattern>) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+
+    /// Only match logins from hosts matching a glob or a CIDR network
+    /// such as `10.0.0.0/8`
+    pub fn host(mut self, host: &str) -> Result<Self> {
+        let rule = match host.split_once('/') {
+            Some((network, prefix)) => {
+                let network: IpAddr = network
+                    .parse()
+                    .map_err(|_| invalid(format!("invalid network {host:?}")))?;
+                let max = if network.is_ipv4() { 32 } else { 128 };
+                let prefix = prefix
+                    .parse::<u8>()
+                    .ok()
+                    .filter(|p| *p <= max)
+                    .ok_or_else(|| invalid(format!("invalid prefix length in {host:?}")))?;
+                HostRule::Network(network, prefix)
+            }
+            None => HostRule::Pattern(Pattern::glob(host)),
+        };
+        self.host = Some(rule);
+        Ok(self)
+    }
+
+    /// Only match logins within a daily UTC time window such as
+    /// `22:00-06:00`
+    pub fn window(mut self, window: &str) -> Result<Self> {
+        let (start, end) = window
+            .split_once('-')
+            .ok_or_else(|| invalid(format!("invalid time window {window:?}")))?;
+        self.window = Some((parse_minutes(start)?, parse_minutes(end)?));
+        Ok(self)
+    }
+
+    // check if the login record matches every condition of the rule
+    fn matches_record(&self, record: &Record) -> bool {
+        if self.user.as_ref().is_some_and(|u| !u.matches(&record.name)) {
+            return false;
+        }
+        if self.host.as_ref().is_some_and(|h| !h.matches(&record.host)) {
+            return false;
+        }
+        let Some((start, end)) = self.window else {
+            return true;
+        };
+        let Some(seconds) = record.last_login.unix_seconds() else {
+            return false;
+        };
+        let minute = seconds / 60 % MINUTES_PER_DAY;
+        match start <= end {
+            true => (start..=end).contains(&minute),
+            false => minute >= start || minute <= end,
+        }
+    }
+
+    /// Check if the rule suppresses the finding
+    pub fn matches(&self, finding: &Finding) -> bool {
+        if self.kind.is_some_and(|kind| kind != finding.kind) {
+            return false;
+        }
+        if !finding.evidence.is_empty() {
+            return finding.evidence.iter().all(|r| self.matches_record(r));
+        }
+        self.window.is_none()
+            && self
+                .user
+                .as_ref()
+                .is_none_or(|u| u.matches(&finding.subject))
+            && self
+                .host
+                .as_ref()
+                .is_none_or(|h| h.matches(&finding.subject))
+    }
+}
+
+impl FromStr for Suppression {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut rule = Self::new();
+        for condition in s.split_whitespace() {
+            let (key, value) = condition
+                .split_once('=')
+                .ok_or_else(|| invalid(format!("expected key=value, found {condition:?}")))?;
+            rule = match key {
+                "kind" => rule.kind(value.parse()?),
+                "user" => rule.user(value),
+                "host" => rule.host(value)?,
+                "time" => rule.window(value)?,
+                _ => return Err(invalid(format!("unknown condition {key:?}"))),
+            };
+        }
+        Ok(rule)
+    }
+}
+
+impl Suppressions {
+    /// Start without any rules, suppressing nothing
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse one rule per line, skipping empty lines and `#` comments
+    pub fn parse(rules: &str) -> Result<Self> {
+        let mut suppressions = Self::new();
+        for (n, line) in rules.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let rule = line
+                .parse()
+                .map_err(|e| Error::new(ErrorKind::InvalidInput, format!("line {}: {e}", n + 1)))?;
+            suppressions.0.push(rule);
+        }
+        Ok(suppressions)
+    }
+
+    /// Read the rules of a configuration file
+    pub fn load(path: &str) -> Result<Self> {
+        Self::parse(&read_to_string(path)?)
+    }
+
+    /// Add a rule to the set
+    pub fn push(&mut self, rule: Suppression) {
+        self.0.push(rule);
+    }
+
+    /// Check if any rule suppresses the finding
+    pub fn matches(&self, finding: &Finding) -> bool {
+        self.0.iter().any(|rule| rule.matches(finding))
+    }
+
+    /// Remove every suppressed finding
+    pub fn apply(&self, findings: Vec<Finding>) -> Vec<Finding> {
+        findings.into_iter().filter(|f| !self.matches(f)).collect()
+    }
+}
+
+impl From<Vec<Suppression>> for Suppressions {
+    fn from(rules: Vec<Suppression>) -> Self {
+        Self(rules)
+    }
+}
@@ -0,0 +1,238 @@
+/*
+ *  RFC 5424 syslog forwarding of login and failed-login events
+ */
+use std::io::{Result, Write};
+use std::net::{TcpStream, ToSocketAddrs, UdpSocket};
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+
+use super::common::*;
+use super::format::RFC3339_TIME;
+use super::monitor::{EventSink, LoginEvent};
+use super::sink::RecordSink;
+
+/* Variables */
+
+#[cfg(unix)]
+static DEV_LOG: &str = "/dev/log";
+
+// facility used for security and authorization messages
+static AUTHPRIV: u8 = 10;
+
+static SEVERITY_WARNING: u8 = 4;
+static SEVERITY_NOTICE: u8 = 5;
+
+// structured data id using the documentation enterprise number (RFC 5612)
+static SD_ID: &str = "login@32473";
+
+/* Types */
+
+// connection the formatted messages are delivered over
+#[derive(Debug)]
+enum Transport {
+    #[cfg(unix)]
+    Local(UnixDatagram),
+    Udp(UdpSocket),
+    Tcp(TcpStream),
+}
+
+/// Sink forwarding records to syslog as RFC 5424 messages
+///
+/// Every message carries the record fields as structured data under the
+/// `login@32473` id, so collectors can index them without parsing the
+/// free-form message. Logins are sent with `notice` and failed logins
+/// with `warning` severity using the `authpriv` facility by default.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{LoginEvent, Record, SyslogSink};
+///
+/// let record = Record::builder().name("root").tty("pts/0").host("10.0.0.5").build();
+/// if let Ok(sink) = SyslogSink::udp("127.0.0.1:514") {
+///     let sink = sink.hostname("web01");
+///     let message = sink.format(LoginEvent::Login, &record);
+///     assert!(message.starts_with("<85>1 - web01 lastlog - LOGIN [login@32473 "));
+///     assert!(message.ends_with("] login of root on pts/0 from 10.0.0.5"));
+/// }
+/// ```
+///
+/// Forwarding every new event seen by a monitor:
+///
+/// ```no_run
+/// use lastlog::{EventFilter, LoginMonitor, SyslogSink};
+///
+/// LoginMonitor::system()
+///     .unwrap()
+///     .forward(EventFilter::new(), SyslogSink::tcp("siem.example.com:601").unwrap())
+///     .run()
+///     .unwrap();
+/// ```
+#[derive(Debug)]
+pub struct SyslogSink {
+    transport: Transport,
+    facility: u8,
+    hostname: Option<String>,
+    app_name: String,
+}
+
+/* Functions */
+
+// escape a structured data parameter value
+fn escape_param(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// replace a missing or invalid header field with the nil value
+fn header_field(value: &str, max: usize) -> String {
+    let value: String = value
+        .chars()
+        .filter(|c| c.is_ascii_graphic())
+        .take(max)
+        .collect();
+    match value.is_empty() {
+        true => "-".to_owned(),
+        false => value,
+    }
+}
+
+/* Implementation */
+
+impl SyslogSink {
+    // build a sink delivering over the given transport
+    fn with_transport(transport: Transport) -> Self {
+        Self {
+            transport,
+            facility: AUTHPRIV,
+            hostname: None,
+            app_name: "lastlog".to_owned(),
+        }
+    }
+
+    /// Forward to the local syslog daemon through `/dev/log`
+    #[cfg(unix)]
+    pub fn local() -> Result<Self> {
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(DEV_LOG)?;
+        Ok(Self::with_transport(Transport::Local(socket)))
+    }
+
+    /// Forward to a remote collector over UDP (RFC 5426)
+    pub fn udp(addr: impl ToSocketAddrs) -> Result<Self> {
+        let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+        socket.connect(addr)?;
+        Ok(Self::with_transport(Transport::Udp(socket)))
+    }
+
+    /// Forward to a remote collector over TCP using octet counting (RFC 6587)
+    pub fn tcp(addr: impl ToSocketAddrs) -> Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        Ok(Self::with_transport(Transport::Tcp(stream)))
+    }
+
+    /// Set the syslog facility code, `authpriv` (10) by default
+    pub fn facility(mut self, facility: u8) -> Self {
+        self.facility = facility.min(23);
+        self
+    }
+
+    /// Set the hostname reported when a record has no machine
+    pub fn hostname(mut self, hostname: impl Into<String>) -> Self {
+        self.hostname = Some(hostname.into());
+        self
+    }
+
+    /// Set the application name reported in every message
+    pub fn app_name(mut self, app_name: impl Into<String>) -> Self {
+        self.app_name = app_name.into();
+        self
+    }
+
+    /// Format the event as an RFC 5424 message without any framing
+    pub fn format(&self, event: LoginEvent, record: &Record) -> String {
+        self.format_repeated(event, record, 0)
+    }
+
+    // format the event noting how often it repeated since the last message
+    fn format_repeated(&self, event: LoginEvent, record: &Record, repeats: usize) -> String {
+        let (severity, msgid, message) = match event {
+            LoginEvent::FailedLogin => (SEVERITY_WARNING, "FAILED", "failed login for"),
+            LoginEvent::Login => (SEVERITY_NOTICE, "LOGIN", "login of"),
+        };
+        let time = record
+            .last_login
+            .format_utc(RFC3339_TIME)
+            .unwrap_or_default();
+        let host = record.machine.as_deref().or(self.hostname.as_deref());
+        let pid = record.pid.map(|p| p.to_string()).unwrap_or_default();
+        let rtype = match record.rtype.name() {
+            Some(name) => name.to_owned(),
+            None => i32::from(record.rtype).to_string(),
+        };
+        let mut data = format!("[{SD_ID} user=\"{}\"", escape_param(&record.name));
+        if let Some(uid) = record.uid {
+            data.push_str(&format!(" uid=\"{uid}\""));
+        }
+        data.push_str(&format!(
+            " tty=\"{}\" host=\"{}\" type=\"{rtype}\"",
+            escape_param(&record.tty),
+            escape_param(&record.host)
+        ));
+        if repeats > 0 {
+            data.push_str(&format!(" repeats=\"{repeats}\""));
+        }
+        data.push(']');
+        let mut text = format!("{message} {}", record.name);
+        if !record.tty.is_empty() {
+            text.push_str(&format!(" on {}", record.tty));
+        }
+        if !record.host.is_empty() {
+            text.push_str(&format!(" from {}", record.host));
+        }
+        if repeats > 0 {
+            text.push_str(&format!(" (repeated {repeats} times)"));
+        }
+        format!(
+            "<{}>1 {} {} {} {} {msgid} {data} {text}",
+            self.facility * 8 + severity,
+            header_field(&time, 32),
+            header_field(host.unwrap_or_default(), 255),
+            header_field(&self.app_name, 48),
+            header_field(&pid, 128),
+        )
+    }
+}
+
+impl EventSink for SyslogSink {
+    fn send(&mut self, event: LoginEvent, record: &Record) -> Result<()> {
+        self.send_repeated(event, record, 0)
+    }
+
+    fn send_repeated(&mut self, event: LoginEvent, record: &Record, repeats: usize) -> Result<()> {
+        let message = self.format_repeated(event, record, repeats);
+        match &mut self.transport {
+            #[cfg(unix)]
+            Transport::Local(socket) => socket.send(message.as_bytes()).map(|_| ()),
+            Transport::Udp(socket) => socket.send(message.as_bytes()).map(|_| ()),
+            Transport::Tcp(stream) => {
+                write!(stream, "{} {message}", message.len())?;
+                stream.flush()
+            }
+        }
+    }
+}
+
+impl RecordSink for SyslogSink {
+    fn write(&mut self, record: &Record) -> Result<()> {
+        self.send(LoginEvent::Login, record)
+    }
+}
@@ -0,0 +1,193 @@
+/*
+ *  Detection of removed, altered or forged wtmp entries
+ */
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read, Result};
+use std::time::Duration;
+
+use super::common::*;
+use super::finding::{Finding, FindingKind, Severity};
+use super::raw::UTMP_SIZE;
+use super::suppress::Suppressions;
+use super::utmp::parse_entry;
+
+/* Variables */
+
+// backward clock jumps tolerated before reporting them
+static DEFAULT_TOLERANCE: Duration = Duration::from_secs(60 * 60);
+
+/* Types */
+
+/// Detection of signs that a wtmp database was tampered with
+///
+/// Log cleaners hide logins by zeroing or removing entries, and entries
+/// forged with a past login-time break the chronological order wtmp is
+/// appended in. Zeroed entries are reported as high severity findings,
+/// login-times jumping backwards by more than the tolerance (ignoring
+/// clock changes recorded by `OLD_TIME` and `NEW_TIME` entries), as well
+/// as a trailing partial entry, as medium severity
+/// [`FindingKind::Tampering`] findings. Entries that fail to parse
+/// entirely are reported as low severity findings.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::TamperDetector;
+///
+/// if let Ok(findings) = TamperDetector::new().scan("/var/log/wtmp") {
+///     for finding in findings {
+///         println!("{finding}");
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TamperDetector {
+    tolerance: Duration,
+    suppress: Suppressions,
+}
+
+/* Functions */
+
+// fill the buffer with the next entry, returning how many bytes were read
+fn read_entry<R: Read>(reader: &mut R, buffer: &mut [u8]) -> Result<usize> {
+    let mut len = 0;
+    while len < buffer.len() {
+        match reader.read(&mut buffer[len..])? {
+            0 => break,
+            n => len += n,
+        }
+    }
+    Ok(len)
+}
+
+/* Implementation */
+
+impl TamperDetector {
+    /// Report backward clock jumps of more than an hour
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how far login-times may jump backwards before being reported
+    pub fn tolerance(mut self, tolerance: Duration) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Drop findings suppressed by any of the rules
+    pub fn suppress(mut self, rules: Suppressions) -> Self {
+        self.suppress = rules;
+        self
+    }
+
+    /// Check the entries of a wtmp formatted stream, naming it `subject`
+    /// within the findings
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use lastlog::{Severity, TamperDetector};
+    ///
+    /// // a single entry wiped by a log cleaner
+    /// let wtmp = vec![0u8; 384];
+    /// let findings = TamperDetector::new().check(&wtmp[..], "wtmp").unwrap();
+    /// assert_eq!(findings[0].severity, Severity::High);
+    /// ```
+    pub fn check<R: Read>(&self, mut reader: R, subject: &str) -> Result<Vec<Finding>> {
+        let users = HashMap::new();
+        let mut findings = vec![];
+        let mut zeroed = vec![];
+        let mut previous: Option<Record> = None;
+        let mut buffer = vec![0; UTMP_SIZE];
+        for index in 0u64.. {
+            let offset = index * UTMP_SIZE as u64;
+            let len = read_entry(&mut reader, &mut buffer)?;
+            if len == 0 {
+                break;
+            }
+            if len < UTMP_SIZE {
+                let finding = Finding::new(FindingKind::Tampering, Severity::Medium, subject)
+                    .description(format!("partial entry of {len} bytes at offset {offset}"));
+                findings.push(finding);
+                break;
+            }
+            if buffer.iter().all(|b| *b == 0) {
+                zeroed.push(offset);
+                continue;
+            }
+            let record = match parse_entry(&users, &buffer, offset) {
+                Ok(record) => record,
+                Err(err) => {
+                    let finding = Finding::new(FindingKind::Tampering, Severity::Low, subject)
+                        .description(err.to_string());
+                    findings.push(finding);
+                    continue;
+                }
+            };
+            if let Some(finding) = self.check_order(previous.as_ref(), &record, subject) {
+                findings.push(finding);
+            }
+            if !record.last_login.is_never() {
+                previous = Some(record);
+            }
+        }
+        if !zeroed.is_empty() {
+            let offsets: Vec<String> = zeroed.iter().map(|o| o.to_string()).collect();
+            let finding = Finding::new(FindingKind::Tampering, Severity::High, subject)
+                .description(format!(
+                    "{} zeroed entries at offsets {}",
+                    zeroed.len(),
+                    offsets.join(", ")
+                ));
+            findings.insert(0, finding);
+        }
+        Ok(self.suppress.apply(findings))
+    }
+
+    // report an entry stamped earlier than the one before it
+    fn check_order(
+        &self,
+        previous: Option<&Record>,
+        record: &Record,
+        subject: &str,
+    ) -> Option<Finding> {
+        let previous = previous?;
+        // clock changes legitimately move the time backwards
+        let clock = [RecordType::OldTime, RecordType::NewTime];
+        if clock.contains(&previous.rtype) || clock.contains(&record.rtype) {
+            return None;
+        }
+        let before = previous.last_login.unix_seconds()?;
+        let after = record.last_login.unix_seconds()?;
+        if after + self.tolerance.as_secs() >= before {
+            return None;
+        }
+        let finding = Finding::new(FindingKind::Tampering, Severity::Medium, subject)
+            .description(format!(
+                "entry of {} jumps {} seconds back in time",
+                record.name,
+                before - after
+            ))
+            .evidence(vec![previous.clone(), record.clone()]);
+        Some(finding)
+    }
+
+    /// Check the entries of a wtmp database file
+    pub fn scan(&self, fname: &str) -> Result<Vec<Finding>> {
+        self.check(BufReader::new(File::open(fname)?), fname)
+    }
+}
+
+impl Default for TamperDetector {
+    fn default() -> Self {
+        Self {
+            tolerance: DEFAULT_TOLERANCE,
+            suppress: Suppressions::new(),
+        }
+    }
+}
@@ -0,0 +1,205 @@
+/*
+ *  User defined output templates for records, sessions and accounts
+ */
+use std::io::{Error, ErrorKind, Result};
+
+use super::account::Account;
+use super::common::*;
+use super::format::LASTLOG_TIME;
+use super::session::Session;
+
+/* Types */
+
+// single parsed template segment
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Field(String, Option<String>),
+}
+
+/// Output template such as `"{user}\t{time:%Y-%m-%d}\t{host}"`
+///
+/// Fields are written as `{name}` and time fields additionally accept a
+/// `strftime(3)` format as `{name:format}`, rendered in local time. Literal
+/// braces are written as `{{` and `}}`, and the escapes `\t`, `\n` and `\\`
+/// are expanded so templates can be passed from a shell unchanged.
+///
+/// Available fields depend on the item being rendered:
+///
+/// | Item      | Fields                                                         |
+/// | --------- | -------------------------------------------------------------- |
+/// | `Record`  | `type`, `uid`, `user`, `tty`, `host`, `pid`, `time`, `machine`, `origin` |
+/// | `Session` | all record fields plus `logout` (time), `end`, `duration` (seconds), `boot_id` |
+/// | `Account` | `uid`, `gid`, `user`, `gecos`, `home`, `shell`, `time`, `failed` (time), `failed_count`, `locked` |
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{Record, RecordType, Template};
+///
+/// let template = Template::new("{user}\\t{tty}|{time:%Y}").unwrap();
+/// let record = Record::builder().name("foo").tty("pts/0").build();
+/// assert_eq!(template.render(&record).unwrap(), "foo\tpts/0|");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+/// Item that can be rendered using a [`Template`]
+pub trait TemplateSource {
+    /// Retrieve the value of the given field, or `None` if unsupported
+    fn field(&self, name: &str, spec: Option<&str>) -> Option<String>;
+}
+
+/* Functions */
+
+// render a login-time using the given format, empty if never logged in
+fn format_time(time: &LoginTime, spec: Option<&str>) -> String {
+    time.format_local(spec.unwrap_or(LASTLOG_TIME))
+        .unwrap_or_default()
+}
+
+// render an optional value, empty if missing
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+// expand backslash escapes within template text
+fn push_escaped(text: &mut String, c: Option<char>) {
+    match c {
+        Some('t') => text.push('\t'),
+        Some('n') => text.push('\n'),
+        Some('\\') => text.push('\\'),
+        Some(c) => {
+            text.push('\\');
+            text.push(c);
+        }
+        None => text.push('\\'),
+    }
+}
+
+/* Implementation */
+
+impl Template {
+    /// Parse a new template, failing on unbalanced braces
+    pub fn new(template: &str) -> Result<Self> {
+        let invalid = |reason: &str| Error::new(ErrorKind::InvalidInput, reason.to_owned());
+        let mut parts = vec![];
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => push_escaped(&mut text, chars.next()),
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '}' => return Err(invalid("unmatched '}' in template")),
+                '{' => {
+                    let mut field = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => field.push(c),
+                            None => return Err(invalid("unterminated field in template")),
+                        }
+                    }
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    let (name, spec) = match field.split_once(':') {
+                        Some((name, spec)) => (name, Some(spec.to_owned())),
+                        None => (field.as_str(), None),
+                    };
+                    if name.is_empty() {
+                        return Err(invalid("empty field name in template"));
+                    }
+                    parts.push(Part::Field(name.to_owned(), spec));
+                }
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Self { parts })
+    }
+
+    /// Render the template for the given item, failing on unknown fields
+    pub fn render(&self, item: &impl TemplateSource) -> Result<String> {
+        let mut out = String::new();
+        for part in self.parts.iter() {
+            match part {
+                Part::Text(text) => out.push_str(text),
+                Part::Field(name, spec) => match item.field(name, spec.as_deref()) {
+                    Some(value) => out.push_str(&value),
+                    None => {
+                        let reason = format!("unknown template field {name:?}");
+                        return Err(Error::new(ErrorKind::InvalidInput, reason));
+                    }
+                },
+            }
+        }
+        Ok(out)
+    }
+}
+
+impl TemplateSource for Record {
+    fn field(&self, name: &str, spec: Option<&str>) -> Option<String> {
+        Some(match name {
+            "type" => match self.rtype.name() {
+                Some(name) => name.to_owned(),
+                None => i32::from(self.rtype).to_string(),
+            },
+            "uid" => optional(self.uid),
+            "user" => self.name.clone(),
+            "tty" => self.tty.clone(),
+            "host" => self.host.clone(),
+            "pid" => optional(self.pid),
+            "time" => format_time(&self.last_login, spec),
+            "machine" => self.machine.clone().unwrap_or_default(),
+            "origin" => self.origin().name().to_owned(),
+            _ => return None,
+        })
+    }
+}
+
+impl TemplateSource for Session {
+    fn field(&self, name: &str, spec: Option<&str>) -> Option<String> {
+        Some(match name {
+            "logout" => {
+                let logout = self.end.time().map_or(LoginTime::Never, LoginTime::from);
+                format_time(&logout, spec)
+            }
+            "end" => self.end.kind().to_owned(),
+            "boot_id" => self.boot_id.clone().unwrap_or_default(),
+            "duration" => optional(self.duration().map(|d| d.as_secs())),
+            _ => return self.record.field(name, spec),
+        })
+    }
+}
+
+impl TemplateSource for Account {
+    fn field(&self, name: &str, spec: Option<&str>) -> Option<String> {
+        Some(match name {
+            "uid" => self.uid.to_string(),
+            "gid" => self.gid.to_string(),
+            "user" => self.name.clone(),
+            "gecos" => self.gecos.clone(),
+            "home" => self.home.clone(),
+            "shell" => self.shell.clone(),
+            "time" => format_time(&self.last_login, spec),
+            "failed" => format_time(&self.last_failed, spec),
+            "failed_count" => self.failed_count.to_string(),
+            "locked" => optional(self.locked),
+            _ => return None,
+        })
+    }
+}
@@ -0,0 +1,162 @@
+/*
+ *  Rate limiting and deduplication of forwarded login events
+ */
+use std::collections::HashMap;
+use std::io::Result;
+use std::time::{Duration, SystemTime};
+
+use super::common::*;
+use super::monitor::{EventSink, LoginEvent};
+
+/* Types */
+
+/// Fields identifying repeated events for a [`Throttle`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ThrottleKey {
+    /// one alert per username
+    User,
+    /// one alert per remote host, collapsing storms against many accounts
+    Host,
+    /// one alert per username and remote host pair
+    #[default]
+    UserHost,
+}
+
+// events seen for a single key within the current window
+#[derive(Debug, Clone)]
+struct Window {
+    until: SystemTime,
+    suppressed: usize,
+    last: Option<Record>,
+}
+
+/// Event sink wrapper delivering at most one event per key and window
+///
+/// The first event of every key is delivered right away, while repeats
+/// within the window are held back. Once the window has passed, the next
+/// event of the same key first delivers the last held back event through
+/// [`EventSink::send_repeated`] with the number of repeats, so a storm of
+/// failed logins collapses into two alerts. Logins and failed logins are
+/// throttled separately, and windows are measured using the login-time of
+/// each record (or the current time when missing), so replayed history is
+/// throttled the same way as live events.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use std::time::{Duration, UNIX_EPOCH};
+/// use lastlog::{EventSink, LoginEvent, Record, Throttle};
+///
+/// let mut delivered = vec![];
+/// let sink = |_: LoginEvent, record: &Record| {
+///     delivered.push(record.last_login.unix_seconds().unwrap());
+///     Ok(())
+/// };
+/// let mut throttle = Throttle::new(sink, Duration::from_secs(3600));
+/// for minute in [0, 1, 2, 90] {
+///     let time = UNIX_EPOCH + Duration::from_secs(minute * 60);
+///     let record = Record::builder().name("root").host("10.0.0.5").last_login(time).build();
+///     throttle.send(LoginEvent::FailedLogin, &record).unwrap();
+/// }
+/// assert_eq!(throttle.suppressed(), 2);
+/// drop(throttle);
+/// // the first attempt, the last repeat of the storm and the next attempt
+/// assert_eq!(delivered, [0, 2 * 60, 90 * 60]);
+/// ```
+#[derive(Debug)]
+pub struct Throttle<S: EventSink> {
+    inner: S,
+    window: Duration,
+    key: ThrottleKey,
+    windows: HashMap<(LoginEvent, String), Window>,
+    suppressed: usize,
+}
+
+/* Implementation */
+
+impl ThrottleKey {
+    // build the key of the record
+    fn key(&self, record: &Record) -> String {
+        match self {
+            Self::User => record.name.clone(),
+            Self::Host => record.host.clone(),
+            Self::UserHost => format!("{}\0{}", record.name, record.host),
+        }
+    }
+}
+
+impl<S: EventSink> Throttle<S> {
+    /// Deliver at most one event per username and host within the window
+    pub fn new(inner: S, window: Duration) -> Self {
+        Self {
+            inner,
+            window,
+            key: ThrottleKey::default(),
+            windows: HashMap::new(),
+            suppressed: 0,
+        }
+    }
+
+    /// Set which fields identify repeated events
+    pub fn key(mut self, key: ThrottleKey) -> Self {
+        self.key = key;
+        self
+    }
+
+    /// Total number of events held back so far
+    pub fn suppressed(&self) -> usize {
+        self.suppressed
+    }
+
+    /// Deliver the held back events of every window that ended before `now`
+    pub fn flush(&mut self, now: SystemTime) -> Result<()> {
+        for ((event, _), window) in self.windows.iter_mut() {
+            if window.until > now {
+                continue;
+            }
+            if let Some(record) = window.last.take() {
+                self.inner
+                    .send_repeated(*event, &record, window.suppressed)?;
+                window.suppressed = 0;
+            }
+        }
+        self.windows.retain(|_, w| w.until > now);
+        Ok(())
+    }
+
+    /// Retrieve the wrapped sink
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: EventSink> EventSink for Throttle<S> {
+    fn send(&mut self, event: LoginEvent, record: &Record) -> Result<()> {
+        let now = match &record.last_login {
+            LoginTime::Last(time) => *time,
+            LoginTime::Never => SystemTime::now(),
+        };
+        let key = (event, self.key.key(record));
+        if let Some(window) = self.windows.get_mut(&key) {
+            if now < window.until {
+                window.suppressed += 1;
+                window.last = Some(record.clone());
+                self.suppressed += 1;
+                return Ok(());
+            }
+            if let Some(last) = window.last.take() {
+                self.inner.send_repeated(event, &last, window.suppressed)?;
+            }
+        }
+        self.inner.send(event, record)?;
+        let window = Window {
+            until: now + self.window,
+            suppressed: 0,
+            last: None,
+        };
+        self.windows.insert(key, window);
+        Ok(())
+    }
+}
@@ -0,0 +1,207 @@
+/*
+ *  Normalization and classification of terminal lines and login origins
+ */
+use super::common::*;
+use super::session::Session;
+
+/* Variables */
+
+// virtual console traditionally running the X display manager
+static DISPLAY_CONSOLE: &str = "tty7";
+
+/* Types */
+
+/// Kind of terminal a login happened on, derived from its line name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TtyKind {
+    /// pseudo terminal such as `pts/3`, used by ssh and terminal emulators
+    Pty,
+    /// virtual console such as `tty1` or the system `console`
+    Console,
+    /// serial line such as `ttyS0`, `ttyUSB0` or a hypervisor `hvc0`
+    Serial,
+    /// X11 display such as `:0`
+    Display,
+    /// ssh session without a terminal, logged as `ssh:notty`
+    Ssh,
+    /// system event without a terminal, logged as `~`
+    System,
+    /// empty or unrecognized line
+    Unknown,
+}
+
+/// Where a login came from, inferred from its terminal line and remote host
+///
+/// | Line                     | Host           | Origin      |
+/// | ------------------------ | -------------- | ----------- |
+/// | `pts/N`, `ssh:notty`     | any remote     | `Ssh`       |
+/// | `:0`, `tty7`             | empty or `:0`  | `Graphical` |
+/// | `pts/N`, `ttyN`, `ttySN` | empty          | `Local`     |
+/// | anything else            | any remote     | `Remote`    |
+/// | `~`                      |                | `System`    |
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LoginOrigin {
+    /// terminal or console on the machine itself
+    Local,
+    /// remote shell over ssh
+    Ssh,
+    /// graphical session started by a display manager
+    Graphical,
+    /// other remote login such as telnet or rlogin
+    Remote,
+    /// system event rather than a login
+    System,
+    /// origin could not be inferred
+    Unknown,
+}
+
+/* Functions */
+
+/// Normalize a terminal line into the form stored by utmp
+///
+/// The `/dev/` prefix and surrounding whitespace are removed and legacy
+/// devfs names such as `vc/1` are mapped to their current names.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::normalize_tty;
+///
+/// assert_eq!(normalize_tty("/dev/pts/3"), "pts/3");
+/// assert_eq!(normalize_tty("pts/3"), "pts/3");
+/// assert_eq!(normalize_tty("/dev/vc/1"), "tty1");
+/// assert_eq!(normalize_tty(":0"), ":0");
+/// ```
+pub fn normalize_tty(line: &str) -> String {
+    let line = line.trim();
+    let line = line.strip_prefix("/dev/").unwrap_or(line);
+    match line.strip_prefix("vc/") {
+        Some(n) => format!("tty{n}"),
+        None => line.to_owned(),
+    }
+}
+
+// check if the name is the given prefix followed by a number
+fn numbered(line: &str, prefix: &str) -> bool {
+    line.strip_prefix(prefix)
+        .is_some_and(|n| !n.is_empty() && n.bytes().all(|c| c.is_ascii_digit()))
+}
+
+/// Classify a terminal line by the kind of terminal it names
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{classify_tty, TtyKind};
+///
+/// assert_eq!(classify_tty("/dev/pts/3"), TtyKind::Pty);
+/// assert_eq!(classify_tty("tty1"), TtyKind::Console);
+/// assert_eq!(classify_tty("ttyS0"), TtyKind::Serial);
+/// assert_eq!(classify_tty(":0"), TtyKind::Display);
+/// assert_eq!(classify_tty("ssh:notty"), TtyKind::Ssh);
+/// ```
+pub fn classify_tty(line: &str) -> TtyKind {
+    let line = normalize_tty(line);
+    let line = line.as_str();
+    match line {
+        "" => TtyKind::Unknown,
+        "~" => TtyKind::System,
+        "console" => TtyKind::Console,
+        _ if line.starts_with("ssh") => TtyKind::Ssh,
+        _ if line.starts_with(':') => TtyKind::Display,
+        _ if numbered(line, "pts/") || line.starts_with("ttyp") || line.starts_with("pty") => {
+            TtyKind::Pty
+        }
+        _ if numbered(line, "tty") => TtyKind::Console,
+        _ if ["ttyS", "ttyUSB", "ttyACM", "ttyAMA", "hvc"]
+            .iter()
+            .any(|p| numbered(line, p)) =>
+        {
+            TtyKind::Serial
+        }
+        _ => TtyKind::Unknown,
+    }
+}
+
+/* Implementation */
+
+impl TtyKind {
+    /// Retrieve a short lowercase name for the kind of terminal
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Pty => "pty",
+            Self::Console => "console",
+            Self::Serial => "serial",
+            Self::Display => "display",
+            Self::Ssh => "ssh",
+            Self::System => "system",
+            Self::Unknown => "unknown",
+        }
+    }
+}
+
+impl LoginOrigin {
+    /// Retrieve a short lowercase name for the origin
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Local => "local",
+            Self::Ssh => "ssh",
+            Self::Graphical => "graphical",
+            Self::Remote => "remote",
+            Self::System => "system",
+            Self::Unknown => "unknown",
+        }
+    }
+}
+
+impl Record {
+    /// Classify the terminal line of the record, see [`classify_tty`]
+    pub fn tty_kind(&self) -> TtyKind {
+        classify_tty(&self.tty)
+    }
+
+    /// Infer where the login came from, see [`LoginOrigin`]
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use lastlog::{LoginOrigin, Record};
+    ///
+    /// let ssh = Record::builder().tty("pts/3").host("10.0.0.5").build();
+    /// let terminal = Record::builder().tty("pts/3").build();
+    /// let desktop = Record::builder().tty(":0").host(":0").build();
+    /// assert_eq!(ssh.origin(), LoginOrigin::Ssh);
+    /// assert_eq!(terminal.origin(), LoginOrigin::Local);
+    /// assert_eq!(desktop.origin(), LoginOrigin::Graphical);
+    /// ```
+    pub fn origin(&self) -> LoginOrigin {
+        let host = self.host.trim();
+        let remote = !host.is_empty() && !host.starts_with(':');
+        match self.tty_kind() {
+            TtyKind::System => LoginOrigin::System,
+            TtyKind::Ssh => LoginOrigin::Ssh,
+            TtyKind::Pty if remote => LoginOrigin::Ssh,
+            _ if remote => LoginOrigin::Remote,
+            TtyKind::Display => LoginOrigin::Graphical,
+            _ if host.starts_with(':') => LoginOrigin::Graphical,
+            TtyKind::Console if normalize_tty(&self.tty) == DISPLAY_CONSOLE => {
+                LoginOrigin::Graphical
+            }
+            TtyKind::Pty | TtyKind::Console | TtyKind::Serial => LoginOrigin::Local,
+            TtyKind::Unknown => LoginOrigin::Unknown,
+        }
+    }
+}
+
+impl Session {
+    /// Infer where the session came from, see [`Record::origin`]
+    pub fn origin(&self) -> LoginOrigin {
+        self.record.origin()
+    }
+}
@@ -0,0 +1,156 @@
+/*
+ *  User-namespace uid remapping using `/proc/<pid>/uid_map` style ranges
+ */
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::str::FromStr;
+
+use super::common::*;
+use super::users::UserSource;
+
+/* Variables */
+
+static SELF_UID_MAP: &str = "/proc/self/uid_map";
+
+/* Types */
+
+/// Ranges translating user-ids between a user-namespace and its parent
+///
+/// Every line of a `uid_map` holds the first user-id inside the namespace,
+/// the first user-id outside of it and the length of the range, e.g.
+/// `0 100000 65536` for a typical rootless container.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::UidMap;
+///
+/// let map: UidMap = "0 100000 65536".parse().unwrap();
+/// assert_eq!(map.to_outside(1000), Some(101000));
+/// assert_eq!(map.to_inside(101000), Some(1000));
+/// assert_eq!(map.to_inside(1000), None);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UidMap {
+    ranges: Vec<(u32, u32, u32)>,
+}
+
+/// Direction user-ids are translated in by [`MappedUsers`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapDirection {
+    /// from inside the namespace to its parent, e.g. reading a host
+    /// lastlog with the passwd of a container
+    ToOutside,
+    /// from the parent to inside the namespace, e.g. reading a container
+    /// lastlog with the passwd of the host
+    ToInside,
+}
+
+/// User source translating the user-ids of another source with a [`UidMap`]
+///
+/// Accounts whose user-id falls outside of every range are dropped, just
+/// like the kernel reports them as the overflow user.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{set_user_source, MapDirection, MappedUsers, PasswdFile, UidMap};
+///
+/// if let Ok(map) = UidMap::current() {
+///     let users = PasswdFile::new("/etc/passwd");
+///     set_user_source(MappedUsers::new(users, map, MapDirection::ToOutside));
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct MappedUsers<S: UserSource> {
+    source: S,
+    map: UidMap,
+    direction: MapDirection,
+}
+
+/* Implementation */
+
+impl UidMap {
+    /// Read the uid map of the current process
+    pub fn current() -> Result<Self> {
+        Self::read(SELF_UID_MAP)
+    }
+
+    /// Read a uid map from the given file
+    pub fn read(fname: &str) -> Result<Self> {
+        fs::read_to_string(fname)?.parse()
+    }
+
+    /// Build a map from `(inside, outside, count)` ranges
+    pub fn from_ranges(ranges: Vec<(u32, u32, u32)>) -> Self {
+        Self { ranges }
+    }
+
+    /// Translate a user-id inside the namespace to its parent
+    pub fn to_outside(&self, uid: u32) -> Option<u32> {
+        self.ranges
+            .iter()
+            .find(|(inside, _, count)| uid >= *inside && uid - inside < *count)
+            .and_then(|(inside, outside, _)| outside.checked_add(uid - inside))
+    }
+
+    /// Translate a user-id of the parent namespace to inside of it
+    pub fn to_inside(&self, uid: u32) -> Option<u32> {
+        self.ranges
+            .iter()
+            .find(|(_, outside, count)| uid >= *outside && uid - outside < *count)
+            .and_then(|(inside, outside, _)| inside.checked_add(uid - outside))
+    }
+}
+
+impl FromStr for UidMap {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut ranges = vec![];
+        for line in s.lines().filter(|l| !l.trim().is_empty()) {
+            let fields: Vec<_> = line.split_whitespace().map(|f| f.parse::<u32>()).collect();
+            let [Ok(inside), Ok(outside), Ok(count)] = fields[..] else {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("invalid uid map range: {line:?}"),
+                ));
+            };
+            ranges.push((inside, outside, count));
+        }
+        Ok(Self { ranges })
+    }
+}
+
+impl<S: UserSource> MappedUsers<S> {
+    /// Translate the user-ids of the given source in the given direction
+    pub fn new(source: S, map: UidMap, direction: MapDirection) -> Self {
+        Self {
+            source,
+            map,
+            direction,
+        }
+    }
+}
+
+impl<S: UserSource> UserSource for MappedUsers<S> {
+    fn users(&self) -> Result<UserMap> {
+        let users = self.source.users()?;
+        let names = users
+            .names()
+            .iter()
+            .filter_map(|(name, uid)| {
+                let uid = match self.direction {
+                    MapDirection::ToOutside => self.map.to_outside(*uid),
+                    MapDirection::ToInside => self.map.to_inside(*uid),
+                }?;
+                Some((name.to_owned(), uid))
+            })
+            .collect();
+        Ok(UserMap::from_names(names))
+    }
+}
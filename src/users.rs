@@ -0,0 +1,231 @@
+/*
+ *  Pluggable sources for the username and user-id mappings of every backend
+ */
+#[cfg(feature = "libc")]
+use std::collections::HashMap;
+#[cfg(feature = "libc")]
+use std::ffi::CStr;
+use std::io::{Error, Result};
+use std::process::Command;
+#[cfg(feature = "libc")]
+use std::sync::Mutex;
+use std::sync::{Arc, RwLock};
+
+use super::account::{read_accounts, read_accounts_from};
+use super::common::*;
+
+/* Variables */
+
+static SOURCE: RwLock<Option<Arc<dyn UserSource>>> = RwLock::new(None);
+
+// the getpwent cursor is process-wide so enumerations are serialized
+#[cfg(feature = "libc")]
+static NSS_LOCK: Mutex<()> = Mutex::new(());
+
+/* Types */
+
+/// Provider of the username and user-id mappings used by every backend
+///
+/// By default accounts are parsed from `/etc/passwd`. Installing another
+/// source with [`set_user_source`] lets tests, containers and directory
+/// backed systems (NSS, LDAP, ...) supply users their own way, and every
+/// query that resolves usernames uses it from then on.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use std::io::Result;
+/// use lastlog::{UserMap, UserSource};
+///
+/// struct Directory;
+///
+/// impl UserSource for Directory {
+///     fn users(&self) -> Result<UserMap> {
+///         let names = [("root".to_owned(), 0), ("alice".to_owned(), 1000)];
+///         Ok(UserMap::from_names(names.into_iter().collect()))
+///     }
+/// }
+///
+/// let users = Directory.users().unwrap();
+/// assert_eq!(users.uid("alice"), Some(1000));
+/// ```
+pub trait UserSource: Send + Sync {
+    /// Retrieve every account known to the source
+    fn users(&self) -> Result<UserMap>;
+}
+
+/// User source parsing a passwd(5) formatted file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PasswdFile {
+    path: String,
+}
+
+/// User source enumerating accounts through the system NSS configuration
+///
+/// Unlike [`PasswdFile`] this includes accounts served by NSS modules such
+/// as `sssd` or `ldap`, exactly as `getent passwd` lists them.
+#[cfg(feature = "libc")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NssUsers;
+
+/// User source combining the local passwd file with the NIS `passwd` map
+///
+/// The map is listed with `ypcat passwd`, so accounts that only exist in
+/// NIS are resolved on legacy networks. Like a `files nis` entry in
+/// nsswitch.conf, local accounts take precedence when both define the
+/// same username.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{set_user_source, NisUsers};
+///
+/// set_user_source(NisUsers::new().domain("corp"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NisUsers {
+    local: Option<String>,
+    domain: Option<String>,
+}
+
+/* Functions */
+
+/// Replace the user source used by every backend
+///
+/// Answers cached by the `cached` feature are dropped, while existing
+/// [`Lastlog`] handles should be invalidated by the caller.
+///
+/// [`Lastlog`]: crate::Lastlog
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::{set_user_source, reset_user_source, PasswdFile};
+///
+/// set_user_source(PasswdFile::new("/etc/passwd"));
+/// let records = lastlog::iter_accounts();
+/// reset_user_source();
+/// ```
+pub fn set_user_source(source: impl UserSource + 'static) {
+    *SOURCE.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(source));
+    #[cfg(feature = "cached")]
+    super::cache::invalidate();
+}
+
+/// Restore the default `/etc/passwd` user source
+pub fn reset_user_source() {
+    *SOURCE.write().unwrap_or_else(|e| e.into_inner()) = None;
+    #[cfg(feature = "cached")]
+    super::cache::invalidate();
+}
+
+// retrieve the installed user source, `None` when passwd is used directly
+pub fn user_source() -> Option<Arc<dyn UserSource>> {
+    SOURCE.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/* Implementation */
+
+impl PasswdFile {
+    /// Read users from the given passwd formatted file
+    pub fn new(path: impl Into<String>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl UserSource for PasswdFile {
+    fn users(&self) -> Result<UserMap> {
+        let accounts = read_accounts(&self.path)?;
+        Ok(UserMap::from_names(
+            accounts.into_iter().map(|a| (a.name, a.uid)).collect(),
+        ))
+    }
+}
+
+impl NisUsers {
+    /// Read NIS accounts of the default domain alongside `/etc/passwd`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read the map of the given NIS domain instead of the default one
+    pub fn domain(mut self, domain: impl Into<String>) -> Self {
+        self.domain = Some(domain.into());
+        self
+    }
+
+    /// Merge the NIS accounts with another passwd file, or none at all
+    pub fn local(mut self, path: Option<String>) -> Self {
+        self.local = path;
+        self
+    }
+
+    // list the passwd map of the configured domain with ypcat
+    fn read_map(&self) -> Result<Vec<u8>> {
+        let mut command = Command::new("ypcat");
+        if let Some(domain) = &self.domain {
+            command.args(["-d", domain]);
+        }
+        let output = command.arg("passwd").output()?;
+        if !output.status.success() {
+            let reason = String::from_utf8_lossy(&output.stderr);
+            return Err(Error::other(format!("ypcat failed: {}", reason.trim())));
+        }
+        Ok(output.stdout)
+    }
+}
+
+impl Default for NisUsers {
+    fn default() -> Self {
+        Self {
+            local: Some(PASSWD.to_owned()),
+            domain: None,
+        }
+    }
+}
+
+impl UserSource for NisUsers {
+    fn users(&self) -> Result<UserMap> {
+        let mut accounts = read_accounts_from(self.read_map()?.as_slice())?;
+        if let Some(local) = &self.local {
+            // local accounts are inserted last so they replace nis entries
+            accounts.extend(read_accounts(local)?);
+        }
+        Ok(UserMap::from_names(
+            accounts.into_iter().map(|a| (a.name, a.uid)).collect(),
+        ))
+    }
+}
+
+#[cfg(feature = "libc")]
+impl UserSource for NssUsers {
+    fn users(&self) -> Result<UserMap> {
+        let _guard = NSS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut names = HashMap::new();
+        unsafe {
+            libc::setpwent();
+            loop {
+                let pw = libc::getpwent();
+                if pw.is_null() {
+                    break;
+                }
+                let name = CStr::from_ptr((*pw).pw_name).to_string_lossy();
+                names.insert(name.into_owned(), (*pw).pw_uid);
+            }
+            libc::endpwent();
+        }
+        Ok(UserMap::from_names(names))
+    }
+}
+
+impl UserSource for UserMap {
+    fn users(&self) -> Result<UserMap> {
+        Ok(self.clone())
+    }
+}
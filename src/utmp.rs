@@ -1,118 +1,684 @@
-/*!
-
-
-*/
-use std::collections::HashMap;
-use std::fs::{metadata, File};
-use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom};
+/*
+ *  Linux `/var/run/utmp` and `/var/log/wtmp` db reader
+ */
+use std::collections::{HashMap, HashSet};
+use std::fs::{metadata, read_dir, rename, File, OpenOptions};
+use std::io::{BufReader, Error, ErrorKind, Read, Result, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
 
+use super::cancel::{CancelToken, Partial};
 use super::common::*;
+use super::layout::GLIBC_TIME32;
+use super::raw::{UtmpEntry, UTMP_SIZE};
+use super::scan::plan_since;
+use super::stats::{timed, ReadStats};
+use super::validate::invalid_record;
 
 /* Variables */
 
-static ST_SIZE: usize = std::mem::size_of::<RStruct>();
-
-/* Type */
-
-#[repr(C, packed)]
-#[derive(Debug, Copy, Clone)]
-struct RStruct {
-    //NOTE: rtype size by all recorded documentation should i16
-    // yet for some reason it's actually an i32 and i have no idea why
-    rtype: i32,
-    pid: i32,
-    line: [u8; 32],
-    id: [u8; 4],
-    user: [u8; 32],
-    host: [u8; 256],
-    exit: [i16; 2],
-    session: i32,
-    sec: i32,
-    usec: i32,
-    addr: [i32; 4],
-    unused: [u8; 20],
-}
+pub static ST_SIZE: usize = UTMP_SIZE;
+
+// suffix counter keeping temporary files of concurrent prunes apart
+static PRUNE_COUNT: AtomicU64 = AtomicU64::new(0);
 
 /* Functions */
 
-#[inline]
-fn stringify<'a>(name: &str, string: &'a [u8]) -> Result<&'a str> {
-    Ok(std::str::from_utf8(string)
-        .map_err(|_| Error::new(ErrorKind::InvalidData, format!("invalid {name}")))?
-        .trim_matches('\0'))
-}
-
-// map rstruct object into public record object
-fn map_record(umap: &HashMap<String, u32>, st: RStruct) -> Result<Record> {
-    let tty = stringify("tty", &st.line)?;
-    let name = stringify("username", &st.user)?;
-    let rtype =
-        RecordType::try_from(st.rtype).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+// map raw entry into public record object
+fn map_record(umap: &HashMap<String, u32>, st: UtmpEntry) -> Result<Record> {
+    let name = st.user()?;
     Ok(Record {
-        rtype,
-        uid: umap.get(name).map(|uid| *uid),
+        rtype: st.record_type(),
+        uid: umap.get(name).copied(),
         name: name.to_owned(),
-        tty: tty.trim_matches('\0').to_owned(),
-        last_login: unix_timestamp(st.sec as u32),
+        tty: st.line()?.to_owned(),
+        host: st.host()?.to_owned(),
+        pid: Some(st.pid),
+        last_login: st.time().into(),
+        machine: None,
     })
 }
 
-// replace hashmap entry if login was newer than current record
-fn set_latest(all: &mut HashMap<String, Record>, new: Record) {
-    if let Some(rec) = all.get(&new.name) {
-        if let LoginTime::Last(old) = rec.last_login {
-            if let LoginTime::Last(new) = new.last_login {
-                if old > new {
-                    return;
-                }
-            }
-        }
+// convert a public record object into its raw entry form
+fn unmap_record(record: &Record) -> UtmpEntry {
+    let (sec, usec) = match record.last_login.unix_micros() {
+        Some(micros) => ((micros / 1_000_000) as i32, (micros % 1_000_000) as i32),
+        None => (0, 0),
+    };
+    // the entry id is conventionally the last four characters of the line
+    let tty = record.tty.strip_prefix("/dev/").unwrap_or(&record.tty);
+    let start = tty.char_indices().rev().nth(3).map_or(0, |(i, _)| i);
+    let id = &tty[start..];
+    UtmpEntry {
+        rtype: i32::from(record.rtype),
+        pid: record.pid.unwrap_or_default(),
+        line: fixed_bytes(tty),
+        id: fixed_bytes(id),
+        user: fixed_bytes(&record.name),
+        host: fixed_bytes(&record.host),
+        exit: [0; 2],
+        session: 0,
+        sec,
+        usec,
+        addr: [0; 4],
+        unused: [0; 20],
+    }
+}
+
+// update the latest record of the entry's username, skipping older entries
+// before any strings are allocated and reusing the buffers of replaced records
+fn set_latest_entry(
+    all: &mut HashMap<String, Record>,
+    umap: &HashMap<String, u32>,
+    st: UtmpEntry,
+) -> Result<()> {
+    let time = LoginTime::from(st.time());
+    let Some(rec) = all.get_mut(st.user()?) else {
+        let rec = map_record(umap, st)?;
+        all.insert(rec.name.clone(), rec);
+        return Ok(());
+    };
+    if rec.last_login > time {
+        return Ok(());
     }
-    all.insert(new.name.to_owned(), new);
+    rec.rtype = st.record_type();
+    rec.tty.clear();
+    rec.tty.push_str(st.line()?);
+    rec.host.clear();
+    rec.host.push_str(st.host()?);
+    rec.pid = Some(st.pid);
+    rec.last_login = time;
+    Ok(())
 }
 
 // read single entry from utmp file
 #[inline]
-fn read_utmp(f: &mut File, buf: &mut Vec<u8>) -> Result<RStruct> {
-    f.read(buf)?;
-    let st = read_struct::<RStruct, _>(&buf[..])?;
-    if st.rtype < 0 || st.rtype > 10 || st.sec == 0 {
+fn read_utmp<R: Read>(f: &mut R, buf: &mut [u8]) -> Result<UtmpEntry> {
+    f.read_exact(buf)?;
+    let st = UtmpEntry::decode(buf)?;
+    if st.sec == 0 {
         return Err(Error::new(ErrorKind::InvalidData, "read invalid struct"));
     }
     Ok(st)
 }
 
 // dynamic read-until manager for reading utmp/wtmp/btmp file object
-fn read_until<F>(umap: &HashMap<String, u32>, fname: &str, until: F) -> Result<Vec<Record>>
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip(umap, until))
+)]
+fn read_until<F>(
+    umap: &HashMap<String, u32>,
+    fname: &str,
+    stats: &mut ReadStats,
+    until: F,
+) -> Result<Vec<Record>>
 where
-    F: Fn(&Record) -> bool,
+    F: FnMut(&str) -> bool,
 {
+    debug_event!("opened database");
     let mut f = File::open(fname)?;
-    let mut seek = f.seek(SeekFrom::End(0))?;
+    timed(stats, |stats| read_until_from(umap, &mut f, stats, until))
+}
+
+// read-until manager reading backwards from any seekable utmp formatted reader
+//
+// the predicate is given the username of every entry, stopping once it matches,
+// and accounts without any login are only filled in when the whole file was read
+pub fn read_until_from<R, F>(
+    umap: &HashMap<String, u32>,
+    f: &mut R,
+    stats: &mut ReadStats,
+    mut until: F,
+) -> Result<Vec<Record>>
+where
+    R: Read + Seek,
+    F: FnMut(&str) -> bool,
+{
+    // ignore any trailing partial entry of a truncated database
+    let len = f.seek(SeekFrom::End(0))?;
+    let mut seek = len - len % ST_SIZE as u64;
     let mut buffer = vec![0; ST_SIZE];
     let mut records = HashMap::new();
+    debug_event!(entries = seek / ST_SIZE as u64, "reading entries backwards");
     while seek > 0 {
         // read raw struct from buffer and update seek position
         seek -= ST_SIZE as u64;
         f.seek(SeekFrom::Start(seek))?;
-        let st = read_utmp(&mut f, &mut buffer)?;
-        // convert into standard record object
-        let rec = map_record(&umap, st)?;
-        if until(&rec) {
-            set_latest(&mut records, rec);
-            break;
+        let st = read_utmp(f, &mut buffer)?;
+        stats.bytes_read += ST_SIZE as u64;
+        stats.records_parsed += 1;
+        let done = until(st.user()?);
+        // convert into standard record object when newer than the current one
+        set_latest_entry(&mut records, umap, st)?;
+        if done {
+            return Ok(records.into_values().collect());
         }
-        set_latest(&mut records, rec);
     }
-    // assign empty records for accounts that have never logged-in
+    fill_missing(&mut records, umap);
+    Ok(records.into_values().collect())
+}
+
+// find the newest entry of a username accepted by the filter within the file
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip(umap, accept))
+)]
+fn find_latest<F>(
+    umap: &HashMap<String, u32>,
+    fname: &str,
+    stats: &mut ReadStats,
+    accept: F,
+) -> Result<Option<Record>>
+where
+    F: FnMut(&str) -> bool,
+{
+    debug_event!("opened database");
+    let mut f = File::open(fname)?;
+    timed(stats, |stats| find_latest_from(umap, &mut f, stats, accept))
+}
+
+// read backwards from any seekable utmp formatted reader until the filter
+// accepts the username of an entry, only mapping that entry into a record
+pub fn find_latest_from<R, F>(
+    umap: &HashMap<String, u32>,
+    f: &mut R,
+    stats: &mut ReadStats,
+    mut accept: F,
+) -> Result<Option<Record>>
+where
+    R: Read + Seek,
+    F: FnMut(&str) -> bool,
+{
+    let len = f.seek(SeekFrom::End(0))?;
+    let mut seek = len - len % ST_SIZE as u64;
+    let mut buffer = vec![0; ST_SIZE];
+    while seek > 0 {
+        seek -= ST_SIZE as u64;
+        f.seek(SeekFrom::Start(seek))?;
+        let st = read_utmp(f, &mut buffer)?;
+        stats.bytes_read += ST_SIZE as u64;
+        stats.records_parsed += 1;
+        if accept(st.user()?) {
+            debug_event!(offset = seek, "found latest entry");
+            return map_record(umap, st).map(Some);
+        }
+    }
+    Ok(None)
+}
+
+// build the empty record of an existing account that never logged-in
+fn never_logged_in(
+    umap: &HashMap<String, u32>,
+    uid: Option<u32>,
+    name: Option<&str>,
+) -> Option<Record> {
+    umap.iter()
+        .find(|(n, u)| uid.is_none_or(|uid| **u == uid) && name.is_none_or(|name| *n == name))
+        .map(|(n, u)| new_record(*u, n.to_owned()))
+}
+
+// assign empty records for accounts that have never logged-in
+fn fill_missing(records: &mut HashMap<String, Record>, umap: &HashMap<String, u32>) {
     for (user, uid) in umap.iter() {
         if !records.contains_key(user) {
             records.insert(user.to_owned(), new_record(*uid, user.to_owned()));
         }
     }
+}
+
+// read the latest record per username within a single utmp file
+fn read_latest(
+    umap: &HashMap<String, u32>,
+    fname: &str,
+) -> Result<(HashMap<String, Record>, ReadStats)> {
+    debug_event!(path = fname, "opened database");
+    let f = BufReader::new(File::open(fname)?);
+    let mut reader = UtmpReader::with_users(f, HashMap::new());
+    let mut records = HashMap::new();
+    while let Some(result) = reader.next_entry() {
+        let st = result?;
+        if !st.time().is_never() {
+            set_latest_entry(&mut records, umap, st)?;
+        }
+    }
+    Ok((records, reader.stats()))
+}
+
+// stream several utmp files keeping only the latest record per username,
+// scanning the files in parallel and merging the results in the
+// given order so ties are still won by the later file
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip(umap)))]
+pub fn read_merged(
+    umap: &HashMap<String, u32>,
+    fnames: &[String],
+    stats: &mut ReadStats,
+) -> Result<Vec<Record>> {
+    let scans = timed(stats, |_| {
+        parallel_map(fnames, |fname| read_latest(umap, fname))
+    });
+    let mut records: HashMap<String, Record> = HashMap::new();
+    for scan in scans {
+        let (latest, scanned) = scan?;
+        *stats += scanned;
+        for (name, rec) in latest {
+            match records.get(&name) {
+                Some(old) if old.last_login > rec.last_login => {}
+                _ => {
+                    records.insert(name, rec);
+                }
+            }
+        }
+    }
+    debug_event!(users = records.len(), "merged latest logins");
+    fill_missing(&mut records, umap);
     Ok(records.into_values().collect())
 }
 
+// keep the user login records of known accounts, one per user-id
+fn latest_per_uid(records: Vec<Record>) -> Vec<Record> {
+    let mut results = HashMap::new();
+    for rec in records
+        .into_iter()
+        .filter(|r| r.rtype == RecordType::User)
+        .filter(|r| r.uid.is_some())
+    {
+        results.insert(rec.uid, rec);
+    }
+    results.into_values().collect()
+}
+
+// update the statistics of a streaming reader with a single entry
+fn count_entry(stats: &mut ReadStats, result: &std::result::Result<Record, ParseError>) {
+    stats.bytes_read += ST_SIZE as u64;
+    match result {
+        Ok(_) => stats.records_parsed += 1,
+        Err(_) => stats.records_skipped += 1,
+    }
+}
+
+// parse a single raw utmp entry located at the given file offset
+pub fn parse_entry(
+    umap: &HashMap<String, u32>,
+    buf: &[u8],
+    offset: u64,
+) -> std::result::Result<Record, ParseError> {
+    UtmpEntry::decode(buf)
+        .map_err(Error::from)
+        .and_then(|st| map_record(umap, st))
+        .map_err(|e| {
+            warn_event!(offset, error = %e, "failed to parse entry");
+            ParseError::from_io(offset, e)
+        })
+}
+
+// directory containing the given file, the working directory for bare names
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    }
+}
+
+// flush the directory entry of a renamed file so the rename survives a crash
+#[cfg(unix)]
+fn sync_parent(fname: &str) -> Result<()> {
+    File::open(parent_dir(Path::new(fname)))?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_parent(_fname: &str) -> Result<()> {
+    Ok(())
+}
+
+// check the open file is still the one linked at the given path
+#[cfg(unix)]
+fn is_linked(f: &File, fname: &str) -> Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+    let (open, linked) = (f.metadata()?, metadata(fname)?);
+    Ok(open.dev() == linked.dev() && open.ino() == linked.ino())
+}
+
+#[cfg(not(unix))]
+fn is_linked(_f: &File, _fname: &str) -> Result<bool> {
+    Ok(true)
+}
+
+// open a database exclusively locked, reopening it when it was replaced
+// (e.g. by another prune) while waiting for the lock
+fn lock_linked(fname: &str) -> Result<File> {
+    loop {
+        let f = OpenOptions::new().read(true).write(true).open(fname)?;
+        f.lock()?;
+        if is_linked(&f, fname)? {
+            return Ok(f);
+        }
+    }
+}
+
+// create a temporary file next to the database that no other writer uses
+fn create_temp(fname: &str) -> Result<(String, File)> {
+    loop {
+        let count = PRUNE_COUNT.fetch_add(1, Ordering::Relaxed);
+        let temp = format!("{fname}.prune-{}-{count}", std::process::id());
+        match OpenOptions::new().write(true).create_new(true).open(&temp) {
+            Ok(f) => return Ok((temp, f)),
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+// order of a rotated archive from its name suffix (`.2`, `-20240301` or
+// `.2.gz`), newest first: numbered archives by their rotation count, then
+// dated archives by their date
+fn rotation_key(suffix: &str) -> Option<(u8, u64)> {
+    let suffix = suffix.strip_suffix(".gz").unwrap_or(suffix);
+    let (dated, digits) = match suffix.strip_prefix('.') {
+        Some(digits) => (false, digits),
+        None => (true, suffix.strip_prefix('-')?),
+    };
+    if digits.is_empty() || !digits.bytes().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let value: u64 = digits.parse().ok()?;
+    match dated {
+        false => Some((0, value)),
+        true => Some((1, u64::MAX - value)),
+    }
+}
+
+/// Discover a database file alongside its rotated archives
+///
+/// Both numbered (`wtmp.1`) and dated (`wtmp-20240301`) archives located
+/// in the same directory are returned after the original file, newest
+/// first: numbered archives by their rotation count (`wtmp.2` before
+/// `wtmp.10`), followed by dated archives by their date. Compressed
+/// archives are skipped since they cannot be read directly.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// let files = lastlog::rotated_files("/var/log/wtmp").unwrap_or_default();
+/// ```
+pub fn rotated_files(fname: &str) -> Result<Vec<String>> {
+    let path = Path::new(fname);
+    let Some(base) = path.file_name().and_then(|n| n.to_str()) else {
+        return Err(Error::new(ErrorKind::InvalidInput, "invalid database path"));
+    };
+    let dir = parent_dir(path);
+    let mut archives = vec![];
+    for entry in read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else { continue };
+        let Some(suffix) = name.strip_prefix(base) else {
+            continue;
+        };
+        if name.ends_with(".gz") {
+            continue;
+        }
+        let Some(key) = rotation_key(suffix) else {
+            continue;
+        };
+        if entry.file_type()?.is_file() {
+            archives.push((key, entry.path().to_string_lossy().into_owned()));
+        }
+    }
+    archives.sort();
+    let archives = archives.into_iter().map(|(_, path)| path);
+    Ok(std::iter::once(fname.to_owned()).chain(archives).collect())
+}
+
+/// Streaming reader yielding every entry of a utmp/wtmp/btmp database
+///
+/// Entries are read in file order (oldest first for wtmp) and each one is
+/// reported individually, so a single corrupt entry does not prevent the
+/// remaining records from being read. Reading stops after an I/O error
+/// or a truncated trailing entry.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::UtmpReader;
+///
+/// if let Ok(reader) = UtmpReader::open("/var/log/wtmp") {
+///     for result in reader {
+///         match result {
+///             Ok(record) => println!("{record}"),
+///             Err(err) => eprintln!("skipping entry: {err}"),
+///         }
+///     }
+/// }
+/// ```
+pub struct UtmpReader<R: Read> {
+    reader: R,
+    users: HashMap<String, u32>,
+    buffer: Vec<u8>,
+    offset: u64,
+    stats: ReadStats,
+    done: bool,
+}
+
+impl UtmpReader<BufReader<File>> {
+    /// Open the given database file for reading
+    pub fn open(fname: &str) -> Result<Self> {
+        let f = File::open(fname)?;
+        Ok(Self::new(BufReader::new(f)))
+    }
+
+    /// Open the given database file for reading from the given byte offset
+    pub fn open_at(fname: &str, offset: u64) -> Result<Self> {
+        let mut f = File::open(fname)?;
+        f.seek(SeekFrom::Start(offset))?;
+        let mut reader = Self::new(BufReader::new(f));
+        reader.offset = offset;
+        Ok(reader)
+    }
+}
+
+impl<R: Read> UtmpReader<R> {
+    /// Read records from any byte stream using the system passwd database
+    pub fn new(reader: R) -> Self {
+        Self::with_users(reader, read_passwd_nmap())
+    }
+
+    /// Read records from any byte stream using a pre-built username to uid map
+    pub fn with_users(reader: R, users: HashMap<String, u32>) -> Self {
+        Self {
+            reader,
+            users,
+            buffer: vec![0; ST_SIZE],
+            offset: 0,
+            stats: ReadStats::default(),
+            done: false,
+        }
+    }
+
+    /// Byte offset of the next entry to be read
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Statistics of the entries read so far
+    pub fn stats(&self) -> ReadStats {
+        self.stats
+    }
+
+    // read and decode the next raw entry without mapping it into a record
+    fn next_entry(&mut self) -> Option<std::result::Result<UtmpEntry, ParseError>> {
+        if self.done {
+            return None;
+        }
+        match self.fill() {
+            Ok(true) => {}
+            Ok(false) => {
+                self.done = true;
+                return None;
+            }
+            Err(err) => {
+                self.done = true;
+                return Some(Err(err));
+            }
+        }
+        let offset = self.offset;
+        self.offset += ST_SIZE as u64;
+        self.stats.bytes_read += ST_SIZE as u64;
+        match UtmpEntry::decode(&self.buffer) {
+            Ok(st) => {
+                self.stats.records_parsed += 1;
+                Some(Ok(st))
+            }
+            Err(e) => {
+                warn_event!(offset, error = %e, "failed to parse entry");
+                self.stats.records_skipped += 1;
+                Some(Err(ParseError::from_io(offset, e.into())))
+            }
+        }
+    }
+
+    // fill the buffer with the next entry, returning false on a clean eof
+    fn fill(&mut self) -> std::result::Result<bool, ParseError> {
+        let mut read = 0;
+        while read < ST_SIZE {
+            match self.reader.read(&mut self.buffer[read..]) {
+                Ok(0) if read == 0 => return Ok(false),
+                Ok(0) => {
+                    let reason = format!("truncated entry ({read} of {ST_SIZE} bytes)");
+                    warn_event!(offset = self.offset, "{reason}");
+                    return Err(ParseError::new(
+                        self.offset,
+                        ErrorKind::UnexpectedEof,
+                        reason,
+                    ));
+                }
+                Ok(n) => read += n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(ParseError::from_io(self.offset, e)),
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl<R: Read> Iterator for UtmpReader<R> {
+    type Item = std::result::Result<Record, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let offset = self.offset;
+        Some(
+            self.next_entry()?.and_then(|st| {
+                map_record(&self.users, st).map_err(|e| ParseError::from_io(offset, e))
+            }),
+        )
+    }
+}
+
+/// Streaming reader yielding every entry of a utmp/wtmp/btmp database
+/// from newest to oldest
+///
+/// Entries are read backwards from the end of the file in small chunks,
+/// which is the natural order for reporting recent sessions. Any trailing
+/// partial entry is ignored.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```
+/// use lastlog::UtmpRevReader;
+///
+/// if let Ok(reader) = UtmpRevReader::open("/var/log/wtmp") {
+///     let recent: Vec<_> = reader.filter_map(|r| r.ok()).take(10).collect();
+/// }
+/// ```
+pub struct UtmpRevReader<R: Read + Seek> {
+    reader: R,
+    users: HashMap<String, u32>,
+    chunk: Vec<u8>,
+    chunk_offset: u64,
+    position: u64,
+    stats: ReadStats,
+    done: bool,
+}
+
+impl UtmpRevReader<File> {
+    /// Open the given database file for reading
+    pub fn open(fname: &str) -> Result<Self> {
+        Self::new(File::open(fname)?)
+    }
+}
+
+impl<R: Read + Seek> UtmpRevReader<R> {
+    /// Number of entries read from the underlying reader at once
+    const CHUNK_ENTRIES: u64 = 64;
+
+    /// Read records backwards from any seekable stream using the system passwd database
+    pub fn new(reader: R) -> Result<Self> {
+        Self::with_users(reader, read_passwd_nmap())
+    }
+
+    /// Read records backwards from any seekable stream using a pre-built username to uid map
+    pub fn with_users(mut reader: R, users: HashMap<String, u32>) -> Result<Self> {
+        let len = reader.seek(SeekFrom::End(0))?;
+        let end = len - len % ST_SIZE as u64;
+        Ok(Self {
+            reader,
+            users,
+            chunk: vec![],
+            chunk_offset: end,
+            position: end,
+            stats: ReadStats::default(),
+            done: false,
+        })
+    }
+
+    // load the chunk of entries directly preceding the current chunk
+    fn load(&mut self) -> std::result::Result<(), ParseError> {
+        let size = (Self::CHUNK_ENTRIES * ST_SIZE as u64).min(self.chunk_offset);
+        let start = self.chunk_offset - size;
+        self.chunk.resize(size as usize, 0);
+        self.reader
+            .seek(SeekFrom::Start(start))
+            .and_then(|_| self.reader.read_exact(&mut self.chunk))
+            .map_err(|e| ParseError::from_io(start, e))?;
+        self.chunk_offset = start;
+        Ok(())
+    }
+
+    /// Statistics of the entries read so far
+    pub fn stats(&self) -> ReadStats {
+        self.stats
+    }
+}
+
+impl<R: Read + Seek> Iterator for UtmpRevReader<R> {
+    type Item = std::result::Result<Record, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || self.position == 0 {
+            return None;
+        }
+        if self.position == self.chunk_offset {
+            if let Err(err) = self.load() {
+                self.done = true;
+                return Some(Err(err));
+            }
+        }
+        self.position -= ST_SIZE as u64;
+        let start = (self.position - self.chunk_offset) as usize;
+        let entry = &self.chunk[start..start + ST_SIZE];
+        let result = parse_entry(&self.users, entry, self.position);
+        count_entry(&mut self.stats, &result);
+        Some(result)
+    }
+}
+
 /* Implementation */
 
 /// UTMP/WTMP Database Reader Implementation
@@ -120,6 +686,14 @@ where
 /// This module allows for reading the [utmp](https://linux.die.net/man/5/utmp)
 /// database format.
 ///
+/// Memory usage of every bulk operation is bounded by the number of
+/// distinct usernames rather than the number of entries, so computing
+/// latest logins over years of (rotated) wtmp history is safe for
+/// memory-constrained processes.
+/// Entries are filtered before their strings are copied and replaced
+/// records reuse their buffers, so bulk reads allocate per username
+/// rather than per entry as well.
+///
 /// # Examples
 ///
 /// Basic Usage:
@@ -148,19 +722,337 @@ impl Utmp {
     /// let records = utmp.read_all("/var/run/utmp");
     /// ```
     pub fn read_all(&self, fname: &str) -> Result<Vec<Record>> {
+        Ok(self.read_all_with_stats(fname)?.0)
+    }
+
+    /// Read all records contained within a Utmp file alongside read statistics
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// let utmp = lastlog::Utmp {};
+    /// if let Ok((records, stats)) = utmp.read_all_with_stats("/var/log/wtmp") {
+    ///     println!("parsed {} entries in {:?}", stats.records_parsed, stats.duration);
+    /// }
+    /// ```
+    pub fn read_all_with_stats(&self, fname: &str) -> Result<(Vec<Record>, ReadStats)> {
         let users = read_passwd_nmap();
-        read_until(&users, fname, |_| false)
+        let mut stats = ReadStats::default();
+        let records = read_until(&users, fname, &mut stats, |_| false)?;
+        Ok((records, stats))
+    }
+
+    /// Read all records contained within a Utmp file until cancelled
+    ///
+    /// Entries are read newest first, so a truncated result holds the most
+    /// recent records of the file. Accounts without a login among them are
+    /// left out of a truncated result rather than reported as never having
+    /// logged in.
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use lastlog::CancelToken;
+    ///
+    /// let utmp = lastlog::Utmp {};
+    /// let token = CancelToken::with_timeout(Duration::from_millis(500));
+    /// if let Ok(partial) = utmp.read_all_cancellable("/var/log/wtmp", &token) {
+    ///     if partial.truncated {
+    ///         println!("showing the latest {} records only", partial.items.len());
+    ///     }
+    /// }
+    /// ```
+    pub fn read_all_cancellable(
+        &self,
+        fname: &str,
+        cancel: &CancelToken,
+    ) -> Result<Partial<Vec<Record>>> {
+        let users = read_passwd_nmap();
+        let mut truncated = false;
+        let items = read_until(&users, fname, &mut ReadStats::default(), |_| {
+            truncated = cancel.is_cancelled();
+            truncated
+        })?;
+        Ok(Partial { items, truncated })
+    }
+
+    /// Read every record written at or after the given time from a Utmp
+    /// file and its rotated archives, oldest first
+    ///
+    /// Instead of walking every file from the end, the start offsets are
+    /// found with [`plan_since`] so archives ending before the given time
+    /// are skipped and each remaining file is read forwards from its first
+    /// matching entry. Entries that fail to parse are skipped.
+    ///
+    /// [`plan_since`]: crate::plan_since
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use std::time::{Duration, SystemTime};
+    ///
+    /// let utmp = lastlog::Utmp {};
+    /// let day = SystemTime::now() - Duration::from_secs(24 * 60 * 60);
+    /// if let Ok(records) = utmp.read_since("/var/log/wtmp", day) {
+    ///     println!("{} entries in the last day", records.len());
+    /// }
+    /// ```
+    pub fn read_since(&self, fname: &str, since: SystemTime) -> Result<Vec<Record>> {
+        let mut records = vec![];
+        for step in plan_since(&rotated_files(fname)?, since)? {
+            let reader = UtmpReader::open_at(&step.path, step.offset)?;
+            records.extend(
+                reader
+                    .filter_map(|r| r.ok())
+                    .filter(|r| r.last_login >= LoginTime::Last(since)),
+            );
+        }
+        Ok(records)
+    }
+
+    /// Read the latest record per username across several Utmp files
+    ///
+    /// Files are streamed one entry at a time and only the most recent
+    /// record for each username is ever kept in memory. Files are scanned
+    /// in parallel on multi-core systems, and when two files hold a login
+    /// at the same time the one listed later wins.
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// let utmp = lastlog::Utmp {};
+    /// let records = utmp.read_merged(&["/var/log/wtmp", "/var/log/wtmp.1"]);
+    /// ```
+    pub fn read_merged<S: AsRef<str>>(&self, fnames: &[S]) -> Result<Vec<Record>> {
+        Ok(self.read_merged_with_stats(fnames)?.0)
+    }
+
+    /// Read the latest record per username across several Utmp files
+    /// alongside the read statistics summed over every file
+    ///
+    /// Entries that fail to parse abort the read, so `records_skipped`
+    /// stays zero on success.
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// let utmp = lastlog::Utmp {};
+    /// if let Ok((_, stats)) = utmp.read_merged_with_stats(&["/var/log/wtmp"]) {
+    ///     println!("read {} bytes", stats.bytes_read);
+    /// }
+    /// ```
+    pub fn read_merged_with_stats<S: AsRef<str>>(
+        &self,
+        fnames: &[S],
+    ) -> Result<(Vec<Record>, ReadStats)> {
+        let users = read_passwd_nmap();
+        let fnames: Vec<_> = fnames.iter().map(|f| f.as_ref().to_owned()).collect();
+        let mut stats = ReadStats::default();
+        let records = read_merged(&users, &fnames, &mut stats)?;
+        Ok((records, stats))
+    }
+
+    /// Append a new entry to the end of a wtmp/btmp database
+    ///
+    /// The record is written in a single call with the file opened in append
+    /// mode, so concurrent writers never interleave partial entries. Records
+    /// failing [`Record::validate`] are rejected. This requires write access
+    /// to the database (usually root).
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```no_run
+    /// use std::time::SystemTime;
+    /// use lastlog::Record;
+    ///
+    /// let record = Record::builder().name("foo").tty("pts/3").last_login(SystemTime::now()).build();
+    /// let utmp = lastlog::Utmp {};
+    /// utmp.append(&record, "/var/log/wtmp").unwrap();
+    /// ```
+    pub fn append(&self, record: &Record, fname: &str) -> Result<()> {
+        record.validate(&GLIBC_TIME32).map_err(invalid_record)?;
+        let mut f = OpenOptions::new().append(true).open(fname)?;
+        f.write_all(&unmap_record(record).encode())
+    }
+
+    /// Rewrite a wtmp/btmp database keeping only entries newer than the cutoff
+    ///
+    /// The kept entries are written into a temporary file next to the
+    /// database which then atomically replaces it, so readers never see a
+    /// half written file and history is never lost when the rewrite fails.
+    /// The database is exclusively locked (advisory `flock`) while it is
+    /// rewritten, and entries appended meanwhile are carried over. A prune
+    /// waiting for the lock while another replaces the database locks the
+    /// replacement instead. Entries that fail to parse or were never
+    /// timestamped are kept. This requires write access to the database and
+    /// its directory (usually root).
+    ///
+    /// Returns the number of entries that were removed.
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```no_run
+    /// use std::time::{Duration, SystemTime};
+    ///
+    /// let year = Duration::from_secs(365 * 24 * 60 * 60);
+    /// let utmp = lastlog::Utmp {};
+    /// let removed = utmp.prune("/var/log/wtmp", SystemTime::now() - year).unwrap();
+    /// println!("removed {removed} entries");
+    /// ```
+    pub fn prune(&self, fname: &str, cutoff: SystemTime) -> Result<usize> {
+        let mut f = lock_linked(fname)?;
+        let mut data = vec![];
+        f.read_to_end(&mut data)?;
+
+        let cutoff = LoginTime::Last(cutoff);
+        let mut kept = Vec::with_capacity(data.len());
+        let mut removed = 0;
+        let mut chunks = data.chunks_exact(ST_SIZE);
+        for chunk in chunks.by_ref() {
+            match UtmpEntry::decode(chunk) {
+                // entries that were never stamped carry no age to compare
+                Ok(st) if !st.time().is_never() && LoginTime::from(st.time()) < cutoff => {
+                    removed += 1
+                }
+                _ => kept.extend_from_slice(chunk),
+            }
+        }
+        // a trailing partial entry may still be completed by a writer
+        kept.extend_from_slice(chunks.remainder());
+        if removed == 0 {
+            return Ok(0);
+        }
+
+        let (temp, mut out) = create_temp(fname)?;
+        let result = (|| {
+            let meta = f.metadata()?;
+            out.set_permissions(meta.permissions())?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::MetadataExt;
+                std::os::unix::fs::fchown(&out, Some(meta.uid()), Some(meta.gid()))?;
+            }
+            out.write_all(&kept)?;
+            // carry over entries of writers not honoring the lock
+            let mut appended = vec![];
+            f.read_to_end(&mut appended)?;
+            out.write_all(&appended)?;
+            out.sync_all()?;
+            rename(&temp, fname)?;
+            sync_parent(fname)
+        })();
+        if result.is_err() {
+            let _ = std::fs::remove_file(&temp);
+        }
+        result?;
+        debug_event!(fname, removed, "pruned database");
+        Ok(removed)
+    }
+
+    /// Read the latest record per username from any Utmp formatted byte stream
+    ///
+    /// Useful for databases that are not regular files such as decompressed
+    /// archives or data piped through stdin.
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// let utmp = lastlog::Utmp {};
+    /// if let Ok(data) = std::fs::read("/var/log/wtmp.1") {
+    ///     let records = utmp.read_stream(&data[..]);
+    /// }
+    /// ```
+    pub fn read_stream<R: Read>(&self, reader: R) -> Result<Vec<Record>> {
+        let users = read_passwd_nmap();
+        let mut records = HashMap::new();
+        let mut reader = UtmpReader::with_users(reader, HashMap::new());
+        while let Some(result) = reader.next_entry() {
+            let st = result?;
+            if !st.time().is_never() {
+                set_latest_entry(&mut records, &users, st)?;
+            }
+        }
+        fill_missing(&mut records, &users);
+        Ok(records.into_values().collect())
+    }
+
+    /// Read the latest record per username across a file and its rotated archives
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// let utmp = lastlog::Utmp {};
+    /// let records = utmp.read_rotated("/var/log/wtmp");
+    /// ```
+    pub fn read_rotated(&self, fname: &str) -> Result<Vec<Record>> {
+        let users = read_passwd_nmap();
+        read_merged(&users, &rotated_files(fname)?, &mut ReadStats::default())
+    }
+
+    /// Iterate every entry contained within a Utmp file in file order
+    ///
+    /// Unlike `read_all`, entries are not deduplicated by username and
+    /// parse failures are reported per-entry rather than for the whole call.
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// let utmp = lastlog::Utmp {};
+    /// if let Ok(records) = utmp.records("/var/log/wtmp") {
+    ///     let valid: Vec<_> = records.filter_map(|r| r.ok()).collect();
+    /// }
+    /// ```
+    pub fn records(&self, fname: &str) -> Result<UtmpReader<BufReader<File>>> {
+        UtmpReader::open(fname)
+    }
+
+    /// Iterate every entry contained within a Utmp file from newest to oldest
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// let utmp = lastlog::Utmp {};
+    /// if let Ok(records) = utmp.records_rev("/var/log/wtmp") {
+    ///     let latest = records.filter_map(|r| r.ok()).next();
+    /// }
+    /// ```
+    pub fn records_rev(&self, fname: &str) -> Result<UtmpRevReader<File>> {
+        UtmpRevReader::open(fname)
     }
 }
 
 impl LoginDB for Utmp {
     fn is_valid(&self, f: &mut File) -> bool {
+        // only documented record-types are accepted when guessing the format
         let mut buffer = vec![0; ST_SIZE];
-        read_utmp(f, &mut buffer).is_ok()
+        read_utmp(f, &mut buffer).is_ok_and(|st| st.record_type().is_known())
     }
 
     fn primary_file(&self) -> Result<&'static str> {
-        for fpath in vec!["/var/run/utmp", "/var/log/utmp", "/var/log/wtmp"].iter() {
+        for fpath in ["/var/run/utmp", "/var/log/utmp", "/var/log/wtmp"].iter() {
             let Ok(meta) = metadata(fpath) else { continue };
             if meta.is_file() {
                 return Ok(fpath);
@@ -174,39 +1066,100 @@ impl LoginDB for Utmp {
 
     // iterate all accounts in /etc/passwd and generate relevant records
     fn iter_accounts(&self, fname: &str) -> Result<Vec<Record>> {
-        let mut results = HashMap::new();
-        let records = self.read_all(fname)?;
-        for rec in records
-            .into_iter()
-            .filter(|r| r.rtype == RecordType::User)
-            .filter(|r| r.uid.is_some())
-        {
-            results.insert(rec.uid, rec);
-        }
-        Ok(results.into_values().collect())
+        self.iter_accounts_with_users(fname, &UserMap::system())
+    }
+
+    fn iter_accounts_with_users(&self, fname: &str, users: &UserMap) -> Result<Vec<Record>> {
+        let records = read_until(users.names(), fname, &mut ReadStats::default(), |_| false)?;
+        Ok(latest_per_uid(records))
+    }
+
+    fn iter_accounts_with_stats(&self, fname: &str) -> Result<(Vec<Record>, ReadStats)> {
+        let (records, stats) = self.read_all_with_stats(fname)?;
+        Ok((latest_per_uid(records), stats))
+    }
+
+    fn iter_accounts_cancellable(
+        &self,
+        fname: &str,
+        cancel: &CancelToken,
+    ) -> Result<Partial<Vec<Record>>> {
+        let partial = self.read_all_cancellable(fname, cancel)?;
+        Ok(Partial {
+            items: latest_per_uid(partial.items),
+            truncated: partial.truncated,
+        })
     }
 
     // search for latest login for a given uid
     fn search_uid(&self, uid: u32, fname: &str) -> Result<Record> {
-        let users = read_passwd_nmap();
-        let records = read_until(&users, fname, |r| r.uid == Some(uid))?;
-        for record in records.into_iter() {
-            if record.uid == Some(uid) {
-                return Ok(record);
-            }
-        }
-        Err(Error::new(ErrorKind::InvalidInput, "no such user"))
+        self.search_uid_with_users(uid, fname, &UserMap::system())
+    }
+
+    fn search_uid_with_users(&self, uid: u32, fname: &str, users: &UserMap) -> Result<Record> {
+        let users = users.names();
+        let accept = |name: &str| users.get(name) == Some(&uid);
+        find_latest(users, fname, &mut ReadStats::default(), accept)?
+            .or_else(|| never_logged_in(users, Some(uid), None))
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "no such user"))
     }
 
     // search for latest login for a given username
     fn search_username(&self, username: &str, fname: &str) -> Result<Record> {
+        self.search_username_with_users(username, fname, &UserMap::system())
+    }
+
+    fn search_username_with_users(
+        &self,
+        username: &str,
+        fname: &str,
+        users: &UserMap,
+    ) -> Result<Record> {
+        let users = users.names();
+        let accept = |name: &str| name == username;
+        find_latest(users, fname, &mut ReadStats::default(), accept)?
+            .or_else(|| never_logged_in(users, None, Some(username)))
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "no such user"))
+    }
+
+    // search for latest logins of several uids in a single pass
+    fn search_uids(&self, uids: &[u32], fname: &str) -> Result<Vec<Record>> {
         let users = read_passwd_nmap();
-        let records = read_until(&users, fname, |r| r.name == username)?;
-        for record in records.into_iter() {
-            if record.name == username {
-                return Ok(record);
+        let mut missing: HashSet<u32> = uids.iter().copied().collect();
+        let records = read_until(&users, fname, &mut ReadStats::default(), |name| {
+            if let Some(uid) = users.get(name) {
+                missing.remove(uid);
             }
-        }
-        Err(Error::new(ErrorKind::InvalidInput, "no such user"))
+            missing.is_empty()
+        })?;
+        let found: HashMap<_, _> = records.into_iter().map(|r| (r.uid, r)).collect();
+        uids.iter()
+            .map(|uid| {
+                found
+                    .get(&Some(*uid))
+                    .cloned()
+                    .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "no such user"))
+            })
+            .collect()
+    }
+
+    // search for latest logins of several usernames in a single pass
+    fn search_usernames(&self, usernames: &[&str], fname: &str) -> Result<Vec<Record>> {
+        let users = read_passwd_nmap();
+        let mut missing: HashSet<&str> = usernames.iter().copied().collect();
+        let records = read_until(&users, fname, &mut ReadStats::default(), |name| {
+            missing.remove(name);
+            missing.is_empty()
+        })?;
+        let found: HashMap<_, _> = records.into_iter().map(|r| (r.name.clone(), r)).collect();
+        usernames
+            .iter()
+            .map(|name| {
+                found
+                    .get(*name)
+                    .cloned()
+                    .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "no such user"))
+            })
+            .collect()
     }
 }
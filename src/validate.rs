@@ -0,0 +1,169 @@
+/*
+ *  Validation of records before they are written into a database
+ */
+use std::fmt;
+use std::io::{Error, ErrorKind};
+
+use super::common::*;
+use super::layout::LayoutProfile;
+
+/* Variables */
+
+// sizes of the fixed text fields shared by every glibc layout
+static LINE_SIZE: usize = 32;
+static USER_SIZE: usize = 32;
+static HOST_SIZE: usize = 256;
+
+// terminal line glibc stores for system events without a terminal
+static SYSTEM_LINE: &str = "~";
+
+/* Types */
+
+/// Problem found by [`Record::validate`] that would corrupt a written entry
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Violation {
+    /// text field longer than the fixed-size field storing it
+    TooLong {
+        field: &'static str,
+        len: usize,
+        max: usize,
+    },
+    /// text field holding a nul byte, which would truncate it when read
+    NulByte { field: &'static str },
+    /// field required by the record-type is empty
+    Missing { field: &'static str },
+    /// terminal line differs from the one used for the record-type
+    LineMismatch {
+        expected: &'static str,
+        found: String,
+    },
+    /// login-time before the unix epoch
+    TimeBeforeEpoch,
+    /// login-time past the last second the layout can store
+    TimeOverflow { max: u64 },
+    /// record-type not documented by utmp(5)
+    UnknownType(i32),
+}
+
+/* Functions */
+
+// check a text field fits its fixed-size field without hidden truncation
+fn check_text(violations: &mut Vec<Violation>, field: &'static str, value: &str, max: usize) {
+    if value.len() > max {
+        violations.push(Violation::TooLong {
+            field,
+            len: value.len(),
+            max,
+        });
+    }
+    if value.contains('\0') {
+        violations.push(Violation::NulByte { field });
+    }
+}
+
+// convert violations into an error rejecting the write
+pub fn invalid_record(violations: Vec<Violation>) -> Error {
+    let reasons: Vec<_> = violations.iter().map(|v| v.to_string()).collect();
+    Error::new(
+        ErrorKind::InvalidInput,
+        format!("invalid record: {}", reasons.join(", ")),
+    )
+}
+
+/* Implementation */
+
+impl Violation {
+    /// Name of the record field the violation concerns
+    pub fn field(&self) -> &'static str {
+        match self {
+            Self::TooLong { field, .. } | Self::NulByte { field } | Self::Missing { field } => {
+                field
+            }
+            Self::LineMismatch { .. } => "tty",
+            Self::TimeBeforeEpoch | Self::TimeOverflow { .. } => "last_login",
+            Self::UnknownType(_) => "rtype",
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooLong { field, len, max } => {
+                write!(f, "{field} is {len} bytes long (at most {max})")
+            }
+            Self::NulByte { field } => write!(f, "{field} contains a nul byte"),
+            Self::Missing { field } => write!(f, "{field} is required"),
+            Self::LineMismatch { expected, found } => {
+                write!(f, "tty is {found:?} (expected {expected:?})")
+            }
+            Self::TimeBeforeEpoch => write!(f, "login time is before the unix epoch"),
+            Self::TimeOverflow { max } => write!(f, "login time is past {max} seconds"),
+            Self::UnknownType(rtype) => write!(f, "unknown record type {rtype}"),
+        }
+    }
+}
+
+impl Record {
+    /// Check the record can be written losslessly with the given layout
+    ///
+    /// Field lengths, the login-time range of the layout and the fields
+    /// required by the record-type are checked, returning every violation
+    /// found rather than silently truncating or wrapping values. The write
+    /// APIs such as [`Utmp::append`](crate::Utmp::append) run this check
+    /// before persisting anything.
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use lastlog::{Record, Violation, GLIBC_TIME32};
+    ///
+    /// let record = Record::builder().name("a-very-long-username-over-32-bytes").build();
+    /// let violations = record.validate(&GLIBC_TIME32).unwrap_err();
+    /// assert!(violations.contains(&Violation::Missing { field: "tty" }));
+    /// assert_eq!(violations[0].field(), "user");
+    /// ```
+    pub fn validate(&self, layout: &LayoutProfile) -> Result<(), Vec<Violation>> {
+        let mut violations = vec![];
+        check_text(&mut violations, "user", &self.name, USER_SIZE);
+        check_text(&mut violations, "tty", &self.tty, LINE_SIZE);
+        check_text(&mut violations, "host", &self.host, HOST_SIZE);
+
+        if let LoginTime::Last(_) = self.last_login {
+            let max = (1u64 << (layout.time_bits - 1)) - 1;
+            match self.last_login.unix_seconds() {
+                None => violations.push(Violation::TimeBeforeEpoch),
+                Some(secs) if secs > max => violations.push(Violation::TimeOverflow { max }),
+                Some(_) => {}
+            }
+        }
+
+        match self.rtype {
+            RecordType::User => {
+                if self.name.is_empty() {
+                    violations.push(Violation::Missing { field: "user" });
+                }
+                if self.tty.is_empty() {
+                    violations.push(Violation::Missing { field: "tty" });
+                }
+            }
+            RecordType::LoginProc if self.tty.is_empty() => {
+                violations.push(Violation::Missing { field: "tty" });
+            }
+            RecordType::BootTime | RecordType::RunLvl if self.tty != SYSTEM_LINE => {
+                violations.push(Violation::LineMismatch {
+                    expected: SYSTEM_LINE,
+                    found: self.tty.clone(),
+                });
+            }
+            RecordType::Unknown(rtype) => violations.push(Violation::UnknownType(rtype)),
+            _ => {}
+        }
+        match violations.is_empty() {
+            true => Ok(()),
+            false => Err(violations),
+        }
+    }
+}
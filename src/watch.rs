@@ -0,0 +1,186 @@
+/*
+ *  Rotation-aware tailing of utmp/wtmp/btmp databases
+ */
+use std::collections::{HashMap, VecDeque};
+use std::fs::{metadata, File, Metadata};
+use std::io::{Read, Result, Seek, SeekFrom};
+use std::thread::sleep;
+use std::time::Duration;
+
+use super::common::*;
+use super::utmp::{parse_entry, ST_SIZE};
+
+/* Variables */
+
+static DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+
+/* Types */
+
+// unique identity of a file on disk used to detect rotation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileId(u64, u64);
+
+impl FileId {
+    #[cfg(unix)]
+    fn new(meta: &Metadata) -> Self {
+        use std::os::unix::fs::MetadataExt;
+        Self(meta.dev(), meta.ino())
+    }
+
+    #[cfg(not(unix))]
+    fn new(_meta: &Metadata) -> Self {
+        Self(0, 0)
+    }
+}
+
+// currently opened database file and read position
+struct Tail {
+    file: File,
+    id: FileId,
+    offset: u64,
+}
+
+/// Long-lived reader delivering new records as they are appended to a database
+///
+/// The watcher survives log rotation: when the watched path is replaced
+/// (different inode) or truncated, any remaining entries of the old file
+/// are delivered before reading the new file from the start.
+///
+/// Iterating the watcher blocks forever, polling for new entries at the
+/// configured interval. Use [`LoginWatcher::poll`] for non-blocking checks.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```no_run
+/// use lastlog::LoginWatcher;
+///
+/// let watcher = LoginWatcher::new("/var/log/wtmp").unwrap();
+/// for record in watcher.filter_map(|r| r.ok()) {
+///     println!("{record}");
+/// }
+/// ```
+pub struct LoginWatcher {
+    path: String,
+    interval: Duration,
+    users: HashMap<String, u32>,
+    tail: Option<Tail>,
+    pending: VecDeque<Result<Record>>,
+}
+
+impl LoginWatcher {
+    /// Watch the given database for entries appended after this call
+    pub fn new(path: &str) -> Result<Self> {
+        let mut watcher = Self::from_start(path)?;
+        if let Some(tail) = watcher.tail.as_mut() {
+            let len = tail.file.seek(SeekFrom::End(0))?;
+            tail.offset = len - len % ST_SIZE as u64;
+        }
+        Ok(watcher)
+    }
+
+    /// Watch the given database delivering all existing entries first
+    pub fn from_start(path: &str) -> Result<Self> {
+        let mut watcher = Self {
+            path: path.to_owned(),
+            interval: DEFAULT_INTERVAL,
+            users: read_passwd_nmap(),
+            tail: None,
+            pending: VecDeque::new(),
+        };
+        watcher.tail = Some(watcher.open()?);
+        Ok(watcher)
+    }
+
+    /// Configure how often the database is checked for new entries
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Path of the database being watched
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    // open the watched path from the beginning
+    fn open(&self) -> Result<Tail> {
+        let file = File::open(&self.path)?;
+        let id = FileId::new(&file.metadata()?);
+        Ok(Tail {
+            file,
+            id,
+            offset: 0,
+        })
+    }
+
+    // read all complete entries past the current offset of the tail
+    fn drain(users: &HashMap<String, u32>, tail: &mut Tail) -> Result<Vec<Result<Record>>> {
+        let len = tail.file.metadata()?.len();
+        let available = len.saturating_sub(tail.offset);
+        let complete = available - available % ST_SIZE as u64;
+        if complete == 0 {
+            return Ok(vec![]);
+        }
+        let mut buffer = vec![0; complete as usize];
+        tail.file.seek(SeekFrom::Start(tail.offset))?;
+        tail.file.read_exact(&mut buffer)?;
+        let records = buffer
+            .chunks_exact(ST_SIZE)
+            .enumerate()
+            .map(|(n, chunk)| {
+                let offset = tail.offset + (n * ST_SIZE) as u64;
+                parse_entry(users, chunk, offset).map_err(|e| e.into())
+            })
+            .collect();
+        tail.offset += complete;
+        Ok(records)
+    }
+
+    /// Check the database once for new entries without blocking
+    ///
+    /// Returns an empty list if nothing new was appended. A missing file
+    /// (for example mid-rotation) is not considered an error.
+    pub fn poll(&mut self) -> Result<Vec<Result<Record>>> {
+        let mut records = vec![];
+        // detect replacement or truncation of the watched path
+        let current: Option<Metadata> = metadata(&self.path).ok();
+        let rotated = match (&self.tail, &current) {
+            (None, Some(_)) => true,
+            (Some(tail), Some(meta)) => FileId::new(meta) != tail.id || meta.len() < tail.offset,
+            _ => false,
+        };
+        if let Some(tail) = self.tail.as_mut() {
+            records.extend(Self::drain(&self.users, tail)?);
+        }
+        if rotated {
+            self.users = read_passwd_nmap();
+            self.tail = self.open().ok();
+            if let Some(tail) = self.tail.as_mut() {
+                records.extend(Self::drain(&self.users, tail)?);
+            }
+        }
+        Ok(records)
+    }
+}
+
+impl Iterator for LoginWatcher {
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.pending.pop_front() {
+                return Some(item);
+            }
+            match self.poll() {
+                Ok(records) if records.is_empty() => sleep(self.interval),
+                Ok(records) => self.pending.extend(records),
+                Err(err) => {
+                    sleep(self.interval);
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
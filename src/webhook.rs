@@ -0,0 +1,242 @@
+/*
+ *  Webhook notifications of login and failed-login events
+ */
+use std::io::{Error, ErrorKind, Result};
+use std::thread::sleep;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use super::common::*;
+use super::filter::Filter;
+use super::monitor::{EventSink, LoginEvent};
+use super::sink::RecordSink;
+
+/* Variables */
+
+static DEFAULT_RETRIES: u32 = 3;
+static DEFAULT_BACKOFF: Duration = Duration::from_secs(1);
+static DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/* Types */
+
+/// Payload layout expected by the receiving webhook
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum WebhookFormat {
+    /// `{"event": ..., "record": {...}}` with the JSON encoded record
+    #[default]
+    Generic,
+    /// Slack incoming webhook message with a readable `text`
+    Slack,
+    /// Microsoft Teams incoming webhook message card
+    Teams,
+}
+
+/// Sink posting JSON to a webhook whenever a matching event occurs
+///
+/// Requests failing with a connection error, a timeout, `429` or a `5xx`
+/// status are retried with an exponential backoff, while any other status
+/// is reported immediately. An optional [`Filter`] restricts which events
+/// are posted, such as only root logins.
+///
+/// # Examples
+///
+/// Basic Usage:
+///
+/// ```no_run
+/// use lastlog::{EventFilter, Filter, LoginMonitor, WebhookFormat, WebhookSink};
+///
+/// let sink = WebhookSink::new("https://hooks.slack.com/services/T000/B000/XXXX")
+///     .format(WebhookFormat::Slack)
+///     .filter(Filter::parse("user == root").unwrap());
+/// LoginMonitor::system()
+///     .unwrap()
+///     .forward(EventFilter::new(), sink)
+///     .run()
+///     .unwrap();
+/// ```
+#[derive(Debug)]
+pub struct WebhookSink {
+    url: String,
+    format: WebhookFormat,
+    filter: Option<Filter>,
+    retries: u32,
+    backoff: Duration,
+    agent: ureq::Agent,
+}
+
+/* Functions */
+
+// build the agent used for every request
+fn build_agent(timeout: Duration) -> ureq::Agent {
+    ureq::Agent::config_builder()
+        .timeout_global(Some(timeout))
+        .build()
+        .into()
+}
+
+// check if the failure may be resolved by sending the request again
+fn is_transient(err: &ureq::Error) -> bool {
+    match err {
+        ureq::Error::StatusCode(code) => *code == 429 || *code >= 500,
+        ureq::Error::Io(_) | ureq::Error::Timeout(_) | ureq::Error::ConnectionFailed => true,
+        ureq::Error::HostNotFound => true,
+        _ => false,
+    }
+}
+
+// describe the event as a single line of text
+fn event_text(event: LoginEvent, record: &Record, repeats: usize) -> String {
+    let mut text = match event {
+        LoginEvent::Login => format!("Login of {}", record.name),
+        LoginEvent::FailedLogin => format!("Failed login for {}", record.name),
+    };
+    if !record.tty.is_empty() {
+        text.push_str(&format!(" on {}", record.tty));
+    }
+    if !record.host.is_empty() {
+        text.push_str(&format!(" from {}", record.host));
+    }
+    if let Some(machine) = record.machine.as_deref() {
+        text.push_str(&format!(" ({machine})"));
+    }
+    if repeats > 0 {
+        text.push_str(&format!(", repeated {repeats} times"));
+    }
+    text
+}
+
+/* Implementation */
+
+impl WebhookSink {
+    /// Post every event to the given url using the generic format
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            format: WebhookFormat::default(),
+            filter: None,
+            retries: DEFAULT_RETRIES,
+            backoff: DEFAULT_BACKOFF,
+            agent: build_agent(DEFAULT_TIMEOUT),
+        }
+    }
+
+    /// Set the payload layout expected by the webhook
+    pub fn format(mut self, format: WebhookFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Only post events whose record matches the filter expression
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Set how often a failed request is retried, 3 by default
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Set the delay before the first retry, doubled for every further one
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Set how long a single request may take, 10 seconds by default
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.agent = build_agent(timeout);
+        self
+    }
+
+    /// Build the JSON payload posted for the event
+    ///
+    /// # Examples
+    ///
+    /// Basic Usage:
+    ///
+    /// ```
+    /// use lastlog::{LoginEvent, Record, WebhookFormat, WebhookSink};
+    ///
+    /// let sink = WebhookSink::new("http://localhost/hook").format(WebhookFormat::Slack);
+    /// let record = Record::builder().name("root").tty("pts/0").host("10.0.0.5").build();
+    /// let payload = sink.payload(LoginEvent::Login, &record).unwrap();
+    /// assert_eq!(payload["text"], "Login of root on pts/0 from 10.0.0.5");
+    /// ```
+    pub fn payload(&self, event: LoginEvent, record: &Record) -> Result<Value> {
+        self.payload_repeated(event, record, 0)
+    }
+
+    // build the payload noting how often the event repeated since the last post
+    fn payload_repeated(
+        &self,
+        event: LoginEvent,
+        record: &Record,
+        repeats: usize,
+    ) -> Result<Value> {
+        let text = event_text(event, record, repeats);
+        Ok(match self.format {
+            WebhookFormat::Generic => {
+                let record = serde_json::to_value(record)
+                    .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+                let event = match event {
+                    LoginEvent::Login => "login",
+                    LoginEvent::FailedLogin => "failed",
+                };
+                json!({ "event": event, "text": text, "repeats": repeats, "record": record })
+            }
+            WebhookFormat::Slack => json!({ "text": text }),
+            WebhookFormat::Teams => json!({
+                "@type": "MessageCard",
+                "@context": "https://schema.org/extensions",
+                "summary": text,
+                "text": text,
+            }),
+        })
+    }
+
+    // post the payload, retrying transient failures with a backoff
+    fn post(&self, payload: &Value) -> Result<()> {
+        let body = payload.to_string();
+        let mut delay = self.backoff;
+        let mut attempt = 0;
+        loop {
+            let result = self
+                .agent
+                .post(&self.url)
+                .header("Content-Type", "application/json")
+                .send(body.as_str());
+            match result {
+                Ok(_) => return Ok(()),
+                Err(err) if attempt < self.retries && is_transient(&err) => {
+                    debug_event!(attempt, error = %err, "retrying webhook");
+                    sleep(delay);
+                    delay = delay.saturating_mul(2);
+                    attempt += 1;
+                }
+                Err(err) => return Err(Error::other(format!("webhook failed: {err}"))),
+            }
+        }
+    }
+}
+
+impl EventSink for WebhookSink {
+    fn send(&mut self, event: LoginEvent, record: &Record) -> Result<()> {
+        self.send_repeated(event, record, 0)
+    }
+
+    fn send_repeated(&mut self, event: LoginEvent, record: &Record, repeats: usize) -> Result<()> {
+        if self.filter.as_ref().is_some_and(|f| !f.matches(record)) {
+            return Ok(());
+        }
+        self.post(&self.payload_repeated(event, record, repeats)?)
+    }
+}
+
+impl RecordSink for WebhookSink {
+    fn write(&mut self, record: &Record) -> Result<()> {
+        self.send(LoginEvent::Login, record)
+    }
+}
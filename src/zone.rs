@@ -0,0 +1,370 @@
+/*
+ *  Local timezone resolution from `TZ` and the system tzdata
+ */
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+use super::format::{civil_from_days, days_from_civil};
+
+/* Variables */
+
+static LOCALTIME: &str = "/etc/localtime";
+
+static ZONEINFO: &str = "/usr/share/zoneinfo";
+
+// transition time used when a POSIX rule leaves it out (02:00)
+static DEFAULT_TIME: i64 = 2 * 3600;
+
+// size of a TZif header, including the magic and version
+static HEADER_SIZE: usize = 44;
+
+// zone last resolved, along with the `TZ` value it was resolved from
+static ZONE: Mutex<Option<(Option<String>, Zone)>> = Mutex::new(None);
+
+/* Types */
+
+// day of the year a POSIX rule transition takes place on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RuleDate {
+    // `Jn`: day 1-365, never counting February 29th
+    Julian(u32),
+    // `n`: day 0-365, counting February 29th
+    Day(u32),
+    // `Mm.w.d`: weekday `d` of week `w` (5 being the last) of month `m`
+    Month(u32, u32, u32),
+}
+
+// transition date along with the local time-of-day it takes place at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Transition {
+    date: RuleDate,
+    time: i64,
+}
+
+// POSIX `TZ` rule such as `EST5EDT,M3.2.0,M11.1.0` (offsets east of utc)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Rule {
+    std: i32,
+    dst: Option<(i32, Transition, Transition)>,
+}
+
+// utc-offsets of a timezone, as listed by a TZif file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Zone {
+    initial: i32,
+    transitions: Vec<(i64, i32)>,
+    rule: Option<Rule>,
+}
+
+// cursor over the characters of a POSIX `TZ` rule
+struct RuleParser<'a> {
+    text: &'a [u8],
+    pos: usize,
+}
+
+/* Functions */
+
+// read a big-endian signed integer of `len` bytes at the given position
+fn read_int(data: &[u8], at: usize, len: usize) -> Option<i64> {
+    let bytes = data.get(at..at.checked_add(len)?)?;
+    let value = bytes.iter().fold(0u64, |n, b| n << 8 | *b as u64);
+    let shift = 64 - 8 * len as u32;
+    Some(((value << shift) as i64) >> shift)
+}
+
+// parse the utc-offsets of a TZif (`tzfile(5)`) formatted timezone
+fn parse_tzif(data: &[u8]) -> Option<Zone> {
+    if data.get(..4)? != b"TZif" {
+        return None;
+    }
+    let version = *data.get(4)?;
+    let counts = |at: usize| -> Option<[usize; 6]> {
+        let mut counts = [0; 6];
+        for (n, count) in counts.iter_mut().enumerate() {
+            *count = read_int(data, at + 20 + n * 4, 4)? as u32 as usize;
+        }
+        Some(counts)
+    };
+    // version 1 data is followed by the same data using 64-bit times
+    let (mut at, mut size) = (0, 4);
+    let [isut, isstd, leap, times, types, chars] = counts(at)?;
+    if version >= b'2' {
+        at += HEADER_SIZE + times * 5 + types * 6 + chars + leap * 8 + isstd + isut;
+        size = 8;
+        if data.get(at..at + 4)? != b"TZif" {
+            return None;
+        }
+    }
+    let [isut, isstd, leap, times, types, chars] = counts(at)?;
+    let at = at + HEADER_SIZE;
+    let kinds = at + times * size;
+    let infos = kinds + times;
+    let offset = |kind: usize| -> Option<i32> {
+        match kind < types {
+            true => Some(read_int(data, infos + kind * 6, 4)? as i32),
+            false => None,
+        }
+    };
+    let mut zone = Zone {
+        initial: offset(0)?,
+        ..Zone::default()
+    };
+    for n in 0..times {
+        let time = read_int(data, at + n * size, size)?;
+        let kind = *data.get(kinds + n)? as usize;
+        zone.transitions.push((time, offset(kind)?));
+    }
+    // the footer holds the rule for times after the last transition
+    let footer = infos + types * 6 + chars + leap * (size + 4) + isstd + isut;
+    if version >= b'2' {
+        let footer = data.get(footer + 1..).unwrap_or_default();
+        let end = footer.iter().position(|b| *b == b'\n').unwrap_or(0);
+        zone.rule = std::str::from_utf8(&footer[..end])
+            .ok()
+            .and_then(parse_rule);
+    }
+    Some(zone)
+}
+
+// parse a POSIX `TZ` rule, as described by `tzset(3)`
+fn parse_rule(text: &str) -> Option<Rule> {
+    let mut parser = RuleParser {
+        text: text.as_bytes(),
+        pos: 0,
+    };
+    parser.name()?;
+    let std = -parser.clock()? as i32;
+    if parser.done() {
+        return Some(Rule { std, dst: None });
+    }
+    parser.name()?;
+    let dst = match parser.peek() {
+        None | Some(b',') => std + 3600,
+        Some(_) => -parser.clock()? as i32,
+    };
+    // rules without transitions follow the united states
+    let (start, end) = match parser.eat(b',') {
+        true => {
+            let start = parser.transition()?;
+            if !parser.eat(b',') {
+                return None;
+            }
+            (start, parser.transition()?)
+        }
+        false => (
+            Transition {
+                date: RuleDate::Month(3, 2, 0),
+                time: DEFAULT_TIME,
+            },
+            Transition {
+                date: RuleDate::Month(11, 1, 0),
+                time: DEFAULT_TIME,
+            },
+        ),
+    };
+    match parser.done() {
+        true => Some(Rule {
+            std,
+            dst: Some((dst, start, end)),
+        }),
+        false => None,
+    }
+}
+
+// retrieve the days since unix-epoch a rule date falls on within a year
+fn rule_day(year: i64, date: RuleDate) -> i64 {
+    let first = days_from_civil(year, 1, 1);
+    let leap = days_from_civil(year + 1, 1, 1) - first == 366;
+    match date {
+        RuleDate::Julian(day) => first + day as i64 - 1 + (leap && day >= 60) as i64,
+        RuleDate::Day(day) => first + day as i64,
+        RuleDate::Month(month, week, weekday) => {
+            let start = days_from_civil(year, month, 1);
+            let (next_year, next_month) = match month {
+                12 => (year + 1, 1),
+                m => (year, m + 1),
+            };
+            let length = days_from_civil(next_year, next_month, 1) - start;
+            let skip = (weekday as i64 - (start + 4)).rem_euclid(7);
+            let mut day = start + skip + (week as i64 - 1) * 7;
+            while day >= start + length {
+                day -= 7;
+            }
+            day
+        }
+    }
+}
+
+// resolve the zone named by the given `TZ` value (or the system default)
+fn load_zone(tz: Option<&str>) -> Zone {
+    let read = |path: &Path| fs::read(path).ok().and_then(|data| parse_tzif(&data));
+    let Some(tz) = tz else {
+        return read(Path::new(LOCALTIME)).unwrap_or_default();
+    };
+    let name = tz.strip_prefix(':').unwrap_or(tz);
+    if name.is_empty() {
+        return Zone::default();
+    }
+    let zone = match name.starts_with('/') {
+        true => read(Path::new(name)),
+        false if name.split('/').any(|part| part == "..") => None,
+        false => {
+            let tzdir = env::var("TZDIR").unwrap_or_else(|_| ZONEINFO.to_owned());
+            read(&Path::new(&tzdir).join(name))
+        }
+    };
+    zone.or_else(|| {
+        let rule = parse_rule(name)?;
+        Some(Zone {
+            rule: Some(rule),
+            ..Zone::default()
+        })
+    })
+    .unwrap_or_default()
+}
+
+/// Retrieve the utc-offset (in seconds) of the local timezone at the given
+/// seconds since unix-epoch
+///
+/// The timezone is named by the `TZ` environment variable, either as a
+/// tzdata zone (such as `Europe/Berlin`, found within `$TZDIR` or
+/// `/usr/share/zoneinfo`), an absolute path to a TZif file or a POSIX rule
+/// (such as `EST5EDT,M3.2.0,M11.1.0`), and defaults to `/etc/localtime`.
+/// Timezones that cannot be resolved are treated as UTC.
+pub fn local_offset(timestamp: i64) -> i32 {
+    let tz = env::var("TZ").ok();
+    let mut cache = ZONE.lock().unwrap_or_else(|err| err.into_inner());
+    let zone = match cache.take() {
+        Some((key, zone)) if key == tz => zone,
+        _ => load_zone(tz.as_deref()),
+    };
+    let offset = zone.offset(timestamp);
+    *cache = Some((tz, zone));
+    offset
+}
+
+/* Implementation */
+
+impl<'a> RuleParser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.text.get(self.pos).copied()
+    }
+
+    fn done(&self) -> bool {
+        self.pos >= self.text.len()
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        let matched = self.peek() == Some(c);
+        self.pos += matched as usize;
+        matched
+    }
+
+    // parse a number of at most `digits` digits
+    fn number(&mut self, digits: usize) -> Option<i64> {
+        let start = self.pos;
+        while self.pos - start < digits && self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.text[start..self.pos])
+            .ok()?
+            .parse()
+            .ok()
+    }
+
+    // parse a zone abbreviation, either alphabetic or quoted as `<...>`
+    fn name(&mut self) -> Option<()> {
+        let start = self.pos;
+        if self.eat(b'<') {
+            while !self.eat(b'>') {
+                self.peek()?;
+                self.pos += 1;
+            }
+            return Some(());
+        }
+        while self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+            self.pos += 1;
+        }
+        (self.pos - start >= 3).then_some(())
+    }
+
+    // parse a signed `hh[:mm[:ss]]` length of time (in seconds)
+    fn clock(&mut self) -> Option<i64> {
+        let sign = match self.peek() {
+            Some(b'-') => -1,
+            _ => 1,
+        };
+        if !self.eat(b'-') {
+            self.eat(b'+');
+        }
+        let mut secs = self.number(3)? * 3600;
+        if self.eat(b':') {
+            secs += self.number(2)? * 60;
+            if self.eat(b':') {
+                secs += self.number(2)?;
+            }
+        }
+        Some(sign * secs)
+    }
+
+    // parse a `date[/time]` rule transition
+    fn transition(&mut self) -> Option<Transition> {
+        let date = match self.peek()? {
+            b'J' => {
+                self.pos += 1;
+                RuleDate::Julian(self.number(3)?.clamp(1, 365) as u32)
+            }
+            b'M' => {
+                self.pos += 1;
+                let month = self.number(2)?;
+                self.eat(b'.').then_some(())?;
+                let week = self.number(1)?;
+                self.eat(b'.').then_some(())?;
+                let weekday = self.number(1)?;
+                if !(1..=12).contains(&month) || !(1..=5).contains(&week) || weekday > 6 {
+                    return None;
+                }
+                RuleDate::Month(month as u32, week as u32, weekday as u32)
+            }
+            _ => RuleDate::Day(self.number(3)?.min(365) as u32),
+        };
+        let time = match self.eat(b'/') {
+            true => self.clock()?,
+            false => DEFAULT_TIME,
+        };
+        Some(Transition { date, time })
+    }
+}
+
+impl Rule {
+    fn offset(&self, timestamp: i64) -> i32 {
+        let Some((dst, start, end)) = self.dst else {
+            return self.std;
+        };
+        let (year, _, _) = civil_from_days((timestamp + self.std as i64).div_euclid(86400));
+        // transitions take place at the local time still in effect
+        let start = rule_day(year, start.date) * 86400 + start.time - self.std as i64;
+        let end = rule_day(year, end.date) * 86400 + end.time - dst as i64;
+        let summer = match start < end {
+            true => start <= timestamp && timestamp < end,
+            false => timestamp < end || start <= timestamp,
+        };
+        match summer {
+            true => dst,
+            false => self.std,
+        }
+    }
+}
+
+impl Zone {
+    fn offset(&self, timestamp: i64) -> i32 {
+        let passed = self.transitions.partition_point(|(at, _)| *at <= timestamp);
+        match (passed, self.rule) {
+            (n, Some(rule)) if n == self.transitions.len() => rule.offset(timestamp),
+            (0, _) => self.initial,
+            (n, _) => self.transitions[n - 1].1,
+        }
+    }
+}
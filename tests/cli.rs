@@ -0,0 +1,78 @@
+/*
+ *  Checks of the times printed by the lastlog-rs binary
+ */
+use std::fs::{remove_file, File};
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::{Duration, UNIX_EPOCH};
+
+use lastlog::{Record, RecordType, Utmp};
+
+/* Variables */
+
+// new york as a POSIX rule, so the checks do not depend on installed tzdata
+static NEW_YORK: &str = "EST5EDT,M3.2.0,M11.1.0";
+
+// Mon Jan 15 18:00:00 UTC 2024 (13:00 EST)
+static WINTER: u64 = 1705341600;
+
+// Mon Jul 15 14:00:00 UTC 2024 (10:00 EDT)
+static SUMMER: u64 = 1721052000;
+
+/* Functions */
+
+// write a wtmp database holding a single login of root at the given time
+fn wtmp(name: &str, secs: u64) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("lastlog-cli-{name}-{}", std::process::id()));
+    File::create(&path).unwrap();
+    let record = Record::builder()
+        .rtype(RecordType::User)
+        .name("root")
+        .tty("pts/0")
+        .host("10.0.0.5")
+        .pid(100)
+        .last_login(UNIX_EPOCH + Duration::from_secs(secs))
+        .build();
+    Utmp {}.append(&record, path.to_str().unwrap()).unwrap();
+    path
+}
+
+// run the binary within the given timezone, returning what it printed
+fn run(tz: &str, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_lastlog-rs"))
+        .env("TZ", tz)
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn last_prints_local_times() {
+    let path = wtmp("last", WINTER);
+    let fname = path.to_str().unwrap();
+    let local = run(NEW_YORK, &["last", "-f", fname]);
+    let utc = run("UTC0", &["last", "-f", fname]);
+    remove_file(&path).unwrap();
+    assert!(local.contains("Mon Jan 15 13:00"), "{local}");
+    assert!(utc.contains("Mon Jan 15 18:00"), "{utc}");
+}
+
+#[test]
+fn lastlog_prints_local_offsets() {
+    let winter = wtmp("winter", WINTER);
+    let summer = wtmp("summer", SUMMER);
+    let winter_out = run(NEW_YORK, &["lastlog", "-f", winter.to_str().unwrap()]);
+    let summer_out = run(NEW_YORK, &["lastlog", "-f", summer.to_str().unwrap()]);
+    remove_file(&winter).unwrap();
+    remove_file(&summer).unwrap();
+    assert!(
+        winter_out.contains("Mon Jan 15 13:00:00 -0500 2024"),
+        "{winter_out}"
+    );
+    assert!(
+        summer_out.contains("Mon Jul 15 10:00:00 -0400 2024"),
+        "{summer_out}"
+    );
+}